@@ -344,7 +344,7 @@ pub async fn studio_install() -> Result<()> {
 
         let label = OPTIONS[selection];
         match selection {
-            0 => run_task(label, || install_plugin()),
+            0 => run_task(label, install_plugin),
             1 => run_task(label, || {
                 let exe = get_exe_path()?;
                 install_claude(&exe).map(|_| ())
@@ -518,6 +518,7 @@ mod tests {
         let mut permissions = fs::metadata(&config_path)
             .expect("metadata should be available for cleanup")
             .permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
         permissions.set_readonly(false);
         fs::set_permissions(&config_path, permissions)
             .expect("should be able to clear read-only permissions");
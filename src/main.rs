@@ -5,6 +5,7 @@ use rbx_studio_server::*;
 use rmcp::ServiceExt;
 use std::io;
 use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing_subscriber::{self, EnvFilter};
@@ -33,10 +34,121 @@ struct Args {
 enum Command {
     /// Run the MCP server using stdio transport
     #[command(alias = "stdio")]
-    Server,
+    Server(ServerArgs),
     /// Launch the interactive Roblox Studio installer
     #[command(name = "studio-install")]
     StudioInstall,
+    /// Compare two place or model files and report instance, property, and script changes
+    #[command(name = "diff-places")]
+    DiffPlaces(DiffPlacesArgs),
+}
+
+#[derive(clap::Args)]
+struct DiffPlacesArgs {
+    /// Baseline .rbxl/.rbxlx/.rbxm/.rbxmx file
+    before: PathBuf,
+
+    /// File to compare against the baseline
+    after: PathBuf,
+
+    /// Print the diff as JSON instead of a text report
+    #[arg(long)]
+    json: bool,
+
+    /// Lines of context around each change in script source diffs
+    #[arg(long, value_name = "LINES", default_value_t = DEFAULT_DIFF_CONTEXT_LINES)]
+    context: usize,
+}
+
+#[derive(clap::Args, Default)]
+struct ServerArgs {
+    /// Ask the MCP client to confirm instance batches that delete at least this many instances
+    #[arg(long, value_name = "COUNT")]
+    confirm_delete_threshold: Option<usize>,
+
+    /// Never ask the MCP client to confirm destructive operations
+    #[arg(long)]
+    no_confirm: bool,
+
+    /// Serve tools from a .rbxl/.rbxlx/.rbxm/.rbxmx file instead of a running Studio session
+    #[arg(long, value_name = "FILE")]
+    place: Option<PathBuf>,
+
+    /// Rojo project file, or a directory containing default.project.json, used by the
+    /// sync_from_filesystem and sync_to_filesystem tools
+    #[arg(long, value_name = "PATH")]
+    project: Option<PathBuf>,
+
+    /// selene executable used to lint sources sent through manage_scripts. selene.toml is read
+    /// from the --project directory when one is given
+    #[arg(long, value_name = "PATH")]
+    selene: Option<PathBuf>,
+
+    /// luau-lsp or luau-analyze executable used by the analyze_scripts tool
+    #[arg(long, value_name = "PATH")]
+    luau_analyzer: Option<PathBuf>,
+
+    /// Roblox API type definitions (globalTypes.d.luau) passed to luau-lsp with --definitions
+    #[arg(long, value_name = "PATH", requires = "luau_analyzer")]
+    luau_definitions: Option<PathBuf>,
+
+    /// Directory where script sources read or written by the server are kept for
+    /// list_script_versions and restore_script_version. Defaults to the local data directory
+    #[arg(long, value_name = "DIR", conflicts_with = "no_history")]
+    history_dir: Option<PathBuf>,
+
+    /// Versions of each script kept in the history before the oldest are dropped (default 50)
+    #[arg(long, value_name = "COUNT", conflicts_with = "no_history")]
+    history_max_versions: Option<usize>,
+
+    /// Do not keep a history of script sources
+    #[arg(long)]
+    no_history: bool,
+
+    /// Directory where manage_templates saves instance templates. Defaults to the local data
+    /// directory
+    #[arg(long, value_name = "DIR")]
+    templates_dir: Option<PathBuf>,
+}
+
+impl ServerArgs {
+    /// Applies the project and external tools shared by the Studio and place file servers.
+    fn configure_tools(&self, mut service: RBXStudioServer) -> RBXStudioServer {
+        if let Some(project) = &self.project {
+            service = service.with_project(project.clone());
+        }
+        if let Some(selene) = &self.selene {
+            service = service.with_selene(selene.clone());
+        }
+        if let Some(analyzer) = &self.luau_analyzer {
+            service = service.with_luau_analyzer(analyzer.clone(), self.luau_definitions.clone());
+        }
+        if !self.no_history {
+            if let Some(dir) = self.history_dir.clone().or_else(default_history_dir) {
+                service = service.with_script_history(
+                    dir,
+                    self.history_max_versions.unwrap_or(DEFAULT_MAX_VERSIONS),
+                );
+            }
+        }
+        if let Some(dir) = self.templates_dir.clone().or_else(default_templates_dir) {
+            service = service.with_templates(dir);
+        }
+        service
+    }
+
+    fn confirmation_policy(&self) -> ConfirmationPolicy {
+        if self.no_confirm {
+            return ConfirmationPolicy::disabled();
+        }
+        ConfirmationPolicy {
+            delete_threshold: Some(
+                self.confirm_delete_threshold
+                    .unwrap_or(DEFAULT_DELETE_CONFIRMATION_THRESHOLD),
+            ),
+            ..ConfirmationPolicy::default()
+        }
+    }
 }
 
 #[tokio::main]
@@ -53,21 +165,26 @@ async fn main() -> Result<()> {
     let command = if args.legacy_studio_install {
         Some(Command::StudioInstall)
     } else if args.legacy_stdio {
-        Some(Command::Server)
+        Some(Command::Server(ServerArgs::default()))
     } else {
         args.command
     };
 
     match command {
-        Some(Command::Server) => run_server().await,
+        Some(Command::Server(server_args)) => run_server(server_args).await,
         Some(Command::StudioInstall) => install::studio_install().await,
+        Some(Command::DiffPlaces(diff_args)) => diff_places(diff_args),
         None => install::install().await,
     }
 }
 
-async fn run_server() -> Result<()> {
+async fn run_server(args: ServerArgs) -> Result<()> {
     tracing::debug!("Debug MCP tracing enabled");
 
+    if let Some(path) = &args.place {
+        return run_place_server(path, &args).await;
+    }
+
     let server_state = Arc::new(Mutex::new(AppState::new()));
 
     let (close_tx, close_rx) = tokio::sync::oneshot::channel();
@@ -85,6 +202,7 @@ async fn run_server() -> Result<()> {
             let app = axum::Router::new()
                 .route("/request", get(request_handler))
                 .route("/response", post(response_handler))
+                .route("/events", post(events_handler))
                 .route("/proxy", post(proxy_handler))
                 .with_state(server_state_clone);
             tracing::info!("This MCP instance is HTTP server listening on {STUDIO_PLUGIN_PORT}");
@@ -117,7 +235,9 @@ async fn run_server() -> Result<()> {
     };
 
     // Create an instance of our counter router
-    let service = RBXStudioServer::new(Arc::clone(&server_state))
+    let service = RBXStudioServer::new(Arc::clone(&server_state), args.confirmation_policy());
+    let service = args
+        .configure_tools(service)
         .serve(rmcp::transport::stdio())
         .await
         .inspect_err(|e| {
@@ -144,6 +264,44 @@ async fn run_server() -> Result<()> {
     }
 }
 
+/// Serves the MCP tools from a place file on disk. No HTTP listener is started because there is no
+/// Studio plugin to talk to.
+async fn run_place_server(path: &Path, args: &ServerArgs) -> Result<()> {
+    let place = PlaceFile::open(path)?;
+    tracing::info!("Serving {} without Roblox Studio", place.path().display());
+
+    let service = RBXStudioServer::new(
+        Arc::new(Mutex::new(AppState::new())),
+        args.confirmation_policy(),
+    )
+    .with_place_file(place);
+    let service = args
+        .configure_tools(service)
+        .serve(rmcp::transport::stdio())
+        .await
+        .inspect_err(|e| {
+            tracing::error!("serving error: {:?}", e);
+        })?;
+    service.waiting().await?;
+    Ok(())
+}
+
+/// Prints the diff between two files. Exits with status 1 when they differ, like `diff`.
+fn diff_places(args: DiffPlacesArgs) -> Result<()> {
+    let before = PlaceFile::open(&args.before)?;
+    let after = PlaceFile::open(&args.after)?;
+    let diff = before.diff(&after, args.context);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        println!("{diff}");
+    }
+    if !diff.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 enum BindOutcome {
     Listener(tokio::net::TcpListener),
     AddrInUse,
@@ -228,12 +386,8 @@ mod tests {
     async fn spawn_http_server_logs_and_signals_on_error() {
         let (close_tx, close_rx) = tokio::sync::oneshot::channel();
         let close_signal: CloseSignal = Arc::new(Mutex::new(Some(close_tx)));
-        let failing_future = async {
-            Err::<(), ServerError>(ServerError::from(io::Error::new(
-                io::ErrorKind::Other,
-                "boom",
-            )))
-        };
+        let failing_future =
+            async { Err::<(), ServerError>(ServerError::from(io::Error::other("boom"))) };
 
         let handle = spawn_http_server(failing_future, Arc::clone(&close_signal));
 
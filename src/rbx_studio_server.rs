@@ -2,31 +2,88 @@ use crate::error::Result;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{extract::State, Json};
+use base64::Engine;
 use color_eyre::eyre::{eyre, Error, OptionExt};
 use rmcp::{
-    handler::server::tool::Parameters,
+    handler::server::{tool::ToolCallContext, wrapper::Parameters},
     model::{
-        CallToolResult, Content, Implementation, ProtocolVersion, ServerCapabilities, ServerInfo,
+        AnnotateAble, CallToolRequestParam, CallToolResult, Content, Implementation,
+        ListResourcesResult, ListToolsResult, PaginatedRequestParam, ProtocolVersion, RawResource,
+        ReadResourceRequestParam, ReadResourceResult, ResourceContents,
+        ResourceUpdatedNotificationParam, ServerCapabilities, ServerInfo, SubscribeRequestParam,
+        UnsubscribeRequestParam,
     },
-    schemars, tool, tool_handler, tool_router, ErrorData, ServerHandler,
+    schemars,
+    service::RequestContext,
+    tool, tool_router, ErrorData, Peer, RoleServer, ServerHandler,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::{HashMap, VecDeque};
-use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::oneshot::{error::TryRecvError, Receiver};
 use tokio::sync::{mpsc, watch, Mutex};
 use tokio::time::Duration;
 use uuid::Uuid;
 
+mod change_feed;
+mod change_history;
+mod confirmation;
+mod dry_run;
+mod export;
+mod instance_ids;
+mod place_file;
+mod project;
+mod roblox_value;
+mod script_analysis;
+mod script_format;
+mod script_graph;
+mod script_history;
+mod script_outline;
+mod script_patch;
+mod script_search;
+mod script_typecheck;
+mod selector;
+mod snapshot_cache;
+mod templates;
+
+use change_feed::{
+    ChangeBatch, ChangeFeed, WatchChangesPayload, WatchChangesRequest, WatchStatus, CHANGES_URI,
+};
+use change_history::{ChangeHistoryRequest, RequestLog};
+pub use confirmation::{ConfirmationPolicy, DEFAULT_DELETE_CONFIRMATION_THRESHOLD};
+use dry_run::PlannedChange;
+use instance_ids::{InstanceIds, PathOrId, ResolvedId, STALE_ID_ERROR};
+pub use place_file::{PlaceDiff, PlaceFile, DEFAULT_DIFF_CONTEXT_LINES};
+use project::{ProjectSyncAction, ProjectSyncRequest, RojoProject};
+use roblox_value::RobloxValue;
+use script_analysis::ScriptBatch;
+use script_history::ScriptHistory;
+pub use script_history::{default_history_dir, DEFAULT_MAX_VERSIONS};
+use script_search::ReplacePlan;
+use script_typecheck::LuauAnalyzer;
+use selector::InstanceTarget;
+use snapshot_cache::SnapshotCache;
+pub use templates::default_templates_dir;
+use templates::{
+    InstantiateTemplatePayload, InstantiateTemplateRequest, ManageTemplatesRequest, TemplateAction,
+    TemplateStore,
+};
+
 pub const STUDIO_PLUGIN_PORT: u16 = 44755;
 const LONG_POLL_DURATION: Duration = Duration::from_secs(15);
 
+/// Description of the `atomic` flag shared by the batch tools that support it.
+const ATOMIC_DESCRIPTION: &str = "Stop at the first failed operation and undo every operation before it, so the batch applies completely or not at all";
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ToolArguments {
     args: ToolArgumentValues,
     id: Option<Uuid>,
+    /// Checked by the plugin before it runs the request (see `instance_ids.rs`).
+    #[serde(default, rename = "resolvedIds", skip_serializing_if = "Vec::is_empty")]
+    resolved_ids: Vec<ResolvedId>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -40,6 +97,7 @@ pub struct AppState {
     output_map: HashMap<Uuid, mpsc::UnboundedSender<Result<String>>>,
     waiter: watch::Receiver<()>,
     trigger: watch::Sender<()>,
+    changes: ChangeFeed,
 }
 pub type PackedState = Arc<Mutex<AppState>>;
 
@@ -51,20 +109,26 @@ impl AppState {
             output_map: HashMap::new(),
             waiter,
             trigger,
+            changes: ChangeFeed::new(),
         }
     }
 }
 
 impl ToolArguments {
     fn new(args: ToolArgumentValues) -> (Self, Uuid) {
-        Self { args, id: None }.with_id()
+        Self {
+            args,
+            id: None,
+            resolved_ids: Vec::new(),
+        }
+        .with_id()
     }
     fn with_id(self) -> (Self, Uuid) {
         let id = Uuid::new_v4();
         (
             Self {
-                args: self.args,
                 id: Some(id),
+                ..self
             },
             id,
         )
@@ -73,22 +137,151 @@ impl ToolArguments {
 #[derive(Clone)]
 pub struct RBXStudioServer {
     state: PackedState,
+    confirmation: ConfirmationPolicy,
+    place: Option<Arc<Mutex<PlaceFile>>>,
+    project: Option<PathBuf>,
+    selene: Option<PathBuf>,
+    luau_analyzer: Option<LuauAnalyzer>,
+    history: Option<Arc<ScriptHistory>>,
+    templates: Option<Arc<TemplateStore>>,
+    instance_ids: Arc<InstanceIds>,
+    requests: Arc<RequestLog>,
+    snapshots: Arc<SnapshotCache>,
+    change_subscription: Arc<std::sync::Mutex<Option<tokio::task::AbortHandle>>>,
     tool_router: rmcp::handler::server::tool::ToolRouter<Self>,
 }
 
-#[tool_handler]
 impl ServerHandler for RBXStudioServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            protocol_version: ProtocolVersion::V_2025_03_26,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            protocol_version: ProtocolVersion::V_2025_06_18,
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some(
-                "Use tools like run_code, insert_model, inspect_environment, environment_control, apply_instance_operations, manage_scripts, test_and_play_control, editor_session_control, terrain_operations, asset_pipeline, collection_and_attributes, physics_and_navigation, diagnostics_and_metrics, and data_model_snapshot. See the README for the complete catalog and usage notes."
+                "Use tools like run_code, insert_model, inspect_environment, environment_control, apply_instance_operations, manage_scripts, test_and_play_control, editor_session_control, terrain_operations, asset_pipeline, collection_and_attributes, physics_and_navigation, diagnostics_and_metrics, data_model_snapshot, watch_changes, change_history, manage_templates, instantiate_template, sync_from_filesystem, and sync_to_filesystem. See the README for the complete catalog and usage notes."
                     .to_string(),
             ),
         }
     }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    /// Routes a tool call, letting its path arguments look up `{ "id": ... }` instance ids.
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let context = ToolCallContext::new(self, request, context);
+        Arc::clone(&self.instance_ids)
+            .scope(self.tool_router.call(context))
+            .await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let mut changes = RawResource::new(CHANGES_URI, "changes");
+        changes.title = Some("Studio change feed".to_string());
+        changes.description = Some(
+            "Recent changes under the roots watched by watch_changes. Subscribe to be notified when new ones arrive."
+                .to_string(),
+        );
+        changes.mime_type = Some("application/json".to_string());
+        Ok(ListResourcesResult::with_all_items(vec![
+            changes.no_annotation()
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        if request.uri != CHANGES_URI {
+            return Err(ErrorData::resource_not_found(
+                format!("Unknown resource {}", request.uri),
+                None,
+            ));
+        }
+        let recent = self.state.lock().await.changes.recent();
+        let text = serde_json::to_string(&recent)
+            .map_err(|err| ErrorData::internal_error(err.to_string(), None))?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: CHANGES_URI.to_string(),
+                mime_type: Some("application/json".to_string()),
+                text,
+                meta: None,
+            }],
+        })
+    }
+
+    /// Sends `notifications/resources/updated` for the change feed whenever the plugin posts
+    /// events, until the client unsubscribes or goes away.
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        if request.uri != CHANGES_URI {
+            return Err(ErrorData::resource_not_found(
+                format!("Unknown resource {}", request.uri),
+                None,
+            ));
+        }
+        let mut updates = self.state.lock().await.changes.subscribe();
+        let peer = context.peer;
+        let task = tokio::spawn(async move {
+            while updates.changed().await.is_ok() {
+                let notification = ResourceUpdatedNotificationParam {
+                    uri: CHANGES_URI.to_string(),
+                };
+                if peer.notify_resource_updated(notification).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let previous = self
+            .change_subscription
+            .lock()
+            .expect("change subscription poisoned")
+            .replace(task.abort_handle());
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        if request.uri == CHANGES_URI {
+            if let Some(subscription) = self
+                .change_subscription
+                .lock()
+                .expect("change subscription poisoned")
+                .take()
+            {
+                subscription.abort();
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -262,9 +455,15 @@ enum InstanceOperationAction {
     Reparent,
     Clone,
     BulkSetProperties,
+    Group,
+    Ungroup,
+    PivotTo,
+    MoveBy,
+    SetPrimaryPart,
+    Weld,
 }
 
-type InstancePropertyMap = std::collections::HashMap<String, JsonValue>;
+type InstancePropertyMap = std::collections::HashMap<String, RobloxValue>;
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -272,6 +471,8 @@ struct CreateInstanceOperation {
     #[schemars(
         description = "Ordered list of instance names to resolve the destination parent path"
     )]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
     #[schemars(description = "Class name for the instance that should be created")]
     class_name: String,
@@ -290,6 +491,8 @@ struct CreateInstanceOperation {
 #[serde(default, rename_all = "camelCase")]
 struct UpdateInstanceOperation {
     #[schemars(description = "Ordered list of instance names to resolve the target path")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
     #[serde(default)]
     #[schemars(description = "Property bag applied to the resolved instance")]
@@ -305,6 +508,8 @@ struct UpdateInstanceOperation {
 #[serde(rename_all = "camelCase")]
 struct DeleteInstanceOperation {
     #[schemars(description = "Ordered list of instance names to resolve the target path")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
 }
 
@@ -312,8 +517,12 @@ struct DeleteInstanceOperation {
 #[serde(rename_all = "camelCase")]
 struct ReparentInstanceOperation {
     #[schemars(description = "Ordered list of instance names to resolve the target path")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
     #[schemars(description = "Ordered list of instance names describing the new parent location")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     new_parent_path: Vec<String>,
     #[serde(default)]
     #[schemars(description = "Optional explicit instance name override applied after reparenting")]
@@ -332,11 +541,15 @@ struct CloneInstanceOperation {
     #[schemars(
         description = "Ordered list of instance names to resolve the target that should be cloned"
     )]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
     #[schemars(description = "Number of clones that should be produced (defaults to 1)")]
     clone_count: Option<u32>,
     #[serde(default)]
     #[schemars(description = "Optional destination parent path for the new clones")]
+    #[serde(deserialize_with = "instance_ids::optional_path")]
+    #[schemars(with = "Option<PathOrId>")]
     new_parent_path: Option<Vec<String>>,
     #[serde(default)]
     #[schemars(description = "Optional base name applied to the generated clones")]
@@ -353,15 +566,122 @@ struct CloneInstanceOperation {
 #[serde(default, rename_all = "camelCase")]
 struct BulkSetPropertiesOperation {
     #[schemars(
-        description = "Ordered list of instance paths that should receive the property updates"
+        description = "Ordered list of instance paths or selectors that should receive the property updates"
     )]
-    target_paths: Vec<Vec<String>>,
+    target_paths: Vec<InstanceTarget>,
     #[schemars(description = "Property bag applied to each resolved target instance")]
     properties: InstancePropertyMap,
     #[schemars(description = "Attributes applied to each resolved target instance")]
     attributes: InstancePropertyMap,
 }
 
+#[derive(
+    Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, Default, PartialEq, Eq,
+)]
+enum GroupClass {
+    #[default]
+    Model,
+    Folder,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct GroupInstanceOperation {
+    #[schemars(
+        description = "Path of the group to create; the last segment is its name and the rest its parent"
+    )]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[schemars(description = "Instance paths or selectors that are moved into the group")]
+    target_paths: Vec<InstanceTarget>,
+    #[serde(default)]
+    #[schemars(description = "Class of the group (defaults to Model)")]
+    class_name: GroupClass,
+    #[serde(default)]
+    #[schemars(
+        description = "Path of a grouped part to use as the Model's PrimaryPart and pivot. Without it the pivot is the centre of the grouped parts' bounding box."
+    )]
+    #[serde(deserialize_with = "instance_ids::optional_path")]
+    #[schemars(with = "Option<PathOrId>")]
+    primary_part_path: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct UngroupInstanceOperation {
+    #[schemars(
+        description = "Path of the Model or Folder whose children are moved to its parent before it is destroyed"
+    )]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PivotToInstanceOperation {
+    #[schemars(description = "Path of the part or Model to move")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "New pivot as 12 CFrame components (position then rotation rows), as passed to PVInstance:PivotTo"
+    )]
+    cframe: Option<[f64; 12]>,
+    #[serde(default)]
+    #[schemars(description = "New pivot position, keeping the current rotation")]
+    position: Option<[f64; 3]>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MoveByInstanceOperation {
+    #[schemars(description = "Instance paths or selectors of the parts and Models to move")]
+    target_paths: Vec<InstanceTarget>,
+    #[serde(default)]
+    #[schemars(description = "World-space offset in studs added to each pivot")]
+    offset: Option<[f64; 3]>,
+    #[serde(default)]
+    #[schemars(
+        description = "Rotation in degrees (X, Y, Z as in CFrame.fromOrientation) applied about each pivot in world axes"
+    )]
+    rotation: Option<[f64; 3]>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SetPrimaryPartOperation {
+    #[schemars(description = "Path of the Model")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Path of a BasePart inside the Model; omit or pass null to clear PrimaryPart"
+    )]
+    #[serde(deserialize_with = "instance_ids::optional_path")]
+    #[schemars(with = "Option<PathOrId>")]
+    primary_part_path: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WeldInstanceOperation {
+    #[schemars(description = "Path of the part every target is welded to (Part0)")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[schemars(
+        description = "Instance paths or selectors of the parts welded to it (Part1); each gets a WeldConstraint parented under it"
+    )]
+    target_paths: Vec<InstanceTarget>,
+    #[serde(default)]
+    #[schemars(description = "Name of the created WeldConstraints (defaults to WeldConstraint)")]
+    name: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 #[serde(tag = "action", rename_all = "snake_case")]
 enum InstanceOperation {
@@ -371,6 +691,12 @@ enum InstanceOperation {
     Reparent(ReparentInstanceOperation),
     Clone(CloneInstanceOperation),
     BulkSetProperties(BulkSetPropertiesOperation),
+    Group(GroupInstanceOperation),
+    Ungroup(UngroupInstanceOperation),
+    PivotTo(PivotToInstanceOperation),
+    MoveBy(MoveByInstanceOperation),
+    SetPrimaryPart(SetPrimaryPartOperation),
+    Weld(WeldInstanceOperation),
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -378,6 +704,12 @@ enum InstanceOperation {
 struct ApplyInstanceOperationsRequest {
     #[schemars(description = "Batch of instance operations that will be processed sequentially")]
     operations: Vec<InstanceOperation>,
+    #[serde(default)]
+    #[schemars(description = ATOMIC_DESCRIPTION)]
+    atomic: bool,
+    #[serde(default)]
+    #[schemars(description = dry_run::DESCRIPTION)]
+    dry_run: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -390,8 +722,18 @@ struct InstanceOperationResult {
     #[schemars(description = "Path that was processed for this result")]
     path: Vec<String>,
     #[serde(default)]
+    #[schemars(
+        description = "Stable id of the instance at `path` when the operation ran; pass {\"id\": ...} wherever an instance path is accepted"
+    )]
+    id: Option<String>,
+    #[serde(default)]
     #[schemars(description = "Optional collection of instance paths affected by the operation")]
     paths: Vec<Vec<String>>,
+    #[serde(default)]
+    #[schemars(
+        description = "Stable ids of the instances at `paths` after the operation, in the same order; empty for paths that no longer resolve"
+    )]
+    ids: Vec<String>,
     #[schemars(description = "True if the operation succeeded, false otherwise")]
     success: bool,
     #[serde(default)]
@@ -410,6 +752,14 @@ struct ApplyInstanceOperationsResponse {
     #[serde(default)]
     #[schemars(description = "True when at least one operation mutated the DataModel")]
     write_occurred: bool,
+    #[serde(default)]
+    #[schemars(
+        description = "True when an atomic batch failed and the operations before the failure were undone"
+    )]
+    rolled_back: bool,
+    #[serde(default)]
+    #[schemars(description = "Why an atomic batch was rolled back")]
+    rollback_reason: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -516,6 +866,12 @@ struct ManageScriptsRequest {
     operations: Vec<ScriptOperation>,
     #[schemars(description = "Metadata selection applied when operations omit an override")]
     default_metadata: Option<ScriptMetadataSelection>,
+    #[serde(default)]
+    #[schemars(description = ATOMIC_DESCRIPTION)]
+    atomic: bool,
+    #[serde(default)]
+    #[schemars(description = dry_run::DESCRIPTION)]
+    dry_run: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -526,6 +882,20 @@ struct ManageScriptsResponse {
     #[serde(default)]
     #[schemars(description = "High level summary string describing the batch outcome")]
     summary: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(description = "True when the batch was a dry run and nothing was changed")]
+    dry_run: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(description = "Changes a dry run would have made")]
+    changes: Vec<PlannedChange>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(
+        description = "True when an atomic batch failed and the operations before the failure were undone"
+    )]
+    rolled_back: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Why an atomic batch was rolled back")]
+    rollback_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -544,8 +914,10 @@ enum EditorSessionControlAction {
         description = "Replace the current Studio selection with the provided instance paths"
     )]
     SetSelection {
-        #[schemars(description = "Array of instance paths that will be selected in Studio")]
-        paths: Vec<Vec<String>>,
+        #[schemars(
+            description = "Array of instance paths or selectors that will be selected in Studio"
+        )]
+        paths: Vec<InstanceTarget>,
     },
     #[schemars(description = "Apply camera transforms such as CFrame, Focus, or FieldOfView")]
     FocusCamera {
@@ -561,8 +933,10 @@ enum EditorSessionControlAction {
     },
     #[schemars(description = "Frame one or more instances within the viewport camera")]
     FrameInstances {
-        #[schemars(description = "Instance paths that should be framed by the camera")]
-        paths: Vec<Vec<String>>,
+        #[schemars(
+            description = "Instance paths or selectors that should be framed by the camera"
+        )]
+        paths: Vec<InstanceTarget>,
         #[serde(default)]
         #[schemars(description = "Optional tween duration in seconds when moving the camera")]
         tween_seconds: Option<f64>,
@@ -574,6 +948,8 @@ enum EditorSessionControlAction {
         #[schemars(
             description = "Instance path resolving to the target Script/LocalScript/ModuleScript"
         )]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
         path: Vec<String>,
         #[serde(default)]
         #[schemars(description = "Optional 1-indexed line number to focus in the opened script")]
@@ -587,21 +963,6 @@ enum EditorSessionControlAction {
     },
 }
 
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
-struct EditorSessionControlResponse {
-    #[schemars(description = "Identifier of the executed action")]
-    action: String,
-    #[schemars(description = "True when the editor session control action succeeded")]
-    success: bool,
-    #[serde(default)]
-    #[schemars(description = "Optional message that summarises the action outcome")]
-    message: Option<String>,
-    #[serde(default)]
-    #[schemars(description = "Number of instances affected by the action, when applicable")]
-    affected_instances: Option<usize>,
-}
-
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 enum TerrainPivotMode {
@@ -696,8 +1057,10 @@ struct TerrainClearRegionOperation {
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 #[serde(rename_all = "camelCase")]
 struct TerrainConvertToTerrainOperation {
-    #[schemars(description = "Paths to BasePart instances that should be converted to terrain")]
-    paths: Vec<Vec<String>>,
+    #[schemars(
+        description = "Paths or selectors of BasePart instances that should be converted to terrain"
+    )]
+    paths: Vec<InstanceTarget>,
     #[serde(default)]
     #[schemars(description = "Resolution to use when converting parts to terrain")]
     resolution: Option<u32>,
@@ -725,35 +1088,9 @@ struct TerrainOperationsRequest {
         description = "Optional placement pivot resolved before applying relative operations"
     )]
     pivot: Option<TerrainPivotPlacement>,
-}
-
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
-struct TerrainOperationResult {
-    #[schemars(description = "Index of the processed terrain operation")]
-    index: usize,
-    #[schemars(description = "Operation identifier that was attempted")]
-    operation: String,
-    #[schemars(description = "True when the operation completed successfully")]
-    success: bool,
-    #[serde(default)]
-    #[schemars(description = "Optional details describing the outcome")]
-    message: Option<String>,
-    #[serde(default)]
-    #[schemars(description = "Structured data returned for the processed operation")]
-    details: Option<JsonValue>,
-}
-
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
-struct TerrainOperationsResponse {
-    #[schemars(description = "Results emitted for each processed terrain operation")]
-    results: Vec<TerrainOperationResult>,
     #[serde(default)]
-    #[schemars(description = "Optional human readable summary of the batch")]
-    summary: Option<String>,
-    #[schemars(description = "True when at least one operation wrote to terrain")]
-    write_occurred: bool,
+    #[schemars(description = dry_run::DESCRIPTION)]
+    dry_run: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -813,13 +1150,14 @@ struct PackagePublishRequest {
     tags: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 enum AssetPipelineOperationKind {
     SearchMarketplace,
     InsertAssetVersion,
     ImportRbxm,
     PublishPackage,
+    ExportInstances,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -877,6 +1215,8 @@ enum AssetPipelineOperation {
         desired_name: Option<String>,
         #[serde(default)]
         #[schemars(description = "Parent path where the inserted instance should be placed")]
+        #[serde(deserialize_with = "instance_ids::optional_path")]
+        #[schemars(with = "Option<PathOrId>")]
         target_parent_path: Option<Vec<String>>,
         #[serde(default)]
         #[schemars(description = "Collision handling strategy for this operation")]
@@ -897,6 +1237,8 @@ enum AssetPipelineOperation {
         desired_name: Option<String>,
         #[serde(default)]
         #[schemars(description = "Parent path where the imported instance should be placed")]
+        #[serde(deserialize_with = "instance_ids::optional_path")]
+        #[schemars(with = "Option<PathOrId>")]
         target_parent_path: Option<Vec<String>>,
         #[serde(default)]
         #[schemars(description = "Collision handling strategy for this operation")]
@@ -911,10 +1253,29 @@ enum AssetPipelineOperation {
     #[schemars(description = "Publish an existing instance in the place as a package")]
     PublishPackage {
         #[schemars(description = "Path pointing to the instance that should be published")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
         instance_path: Vec<String>,
         #[schemars(description = "Package publishing configuration")]
         publish: PackagePublishRequest,
     },
+    #[schemars(
+        description = "Serialize instances from Studio into an RBXM or RBXMX file written by the MCP server"
+    )]
+    #[serde(rename_all = "camelCase")]
+    ExportInstances {
+        #[schemars(
+            description = "Paths or selectors of the instances to export as the file's top-level roots"
+        )]
+        instance_paths: Vec<InstanceTarget>,
+        #[schemars(
+            description = "Absolute filesystem path ending in .rbxm (binary) or .rbxmx (XML)"
+        )]
+        file_path: String,
+        #[serde(default)]
+        #[schemars(description = "Replace the file when it already exists")]
+        overwrite: bool,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
@@ -924,6 +1285,8 @@ struct AssetPipelineRequest {
     operations: Vec<AssetPipelineOperation>,
     #[serde(default)]
     #[schemars(description = "Fallback parent path applied when operations omit a destination")]
+    #[serde(deserialize_with = "instance_ids::optional_path")]
+    #[schemars(with = "Option<PathOrId>")]
     default_parent_path: Option<Vec<String>>,
     #[serde(default)]
     #[schemars(description = "Default collision strategy when not supplied per operation")]
@@ -940,6 +1303,11 @@ struct ScriptOperationResult {
     action: ScriptOperationKind,
     #[schemars(description = "Normalised path that was targeted for this operation")]
     path: Vec<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Instance id of the script after a successful operation, usable in place of its path"
+    )]
+    id: Option<String>,
     #[schemars(description = "True if the operation succeeded, false if it failed")]
     success: bool,
     #[serde(default)]
@@ -967,13 +1335,19 @@ struct ScriptDiagnostic {
     #[schemars(description = "Diagnostic category, e.g. syntax or lint")]
     #[serde(default)]
     kind: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Severity reported by the analyzer, e.g. error or warning")]
+    severity: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Lint rule that produced the diagnostic")]
+    code: Option<String>,
     #[schemars(description = "Human readable diagnostic message")]
     message: String,
     #[serde(default)]
-    #[schemars(description = "1-indexed line number if provided by Studio")]
+    #[schemars(description = "1-indexed line number, when known")]
     line: Option<u32>,
     #[serde(default)]
-    #[schemars(description = "1-indexed column number if provided by Studio")]
+    #[schemars(description = "1-indexed column number, when known")]
     column: Option<u32>,
 }
 
@@ -984,23 +1358,45 @@ enum ScriptOperationKind {
     Create,
     #[schemars(description = "Fetch the source for an existing script")]
     GetSource,
+    #[schemars(description = "List the symbols an existing script declares at its top level")]
+    Outline,
     #[schemars(description = "Replace the source on an existing script")]
     SetSource,
+    #[schemars(description = "Format the source of an existing script with StyLua")]
+    FormatScript,
+    #[schemars(description = "Apply a unified diff to the source of an existing script")]
+    ApplyPatch,
+    #[schemars(
+        description = "Replace a line and column range in the source of an existing script"
+    )]
+    ReplaceRange,
     #[schemars(description = "Rename an existing script instance")]
     Rename,
+    #[schemars(description = "Delete an existing script instance")]
+    Delete,
+    #[schemars(description = "Move an existing script under a new parent")]
+    Move,
+    #[schemars(description = "Copy an existing script, including its children")]
+    Duplicate,
+    #[schemars(description = "Replace a script with one of another class, keeping its contents")]
+    ConvertType,
+    #[schemars(description = "Enable or disable an existing Script or LocalScript")]
+    SetEnabled,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(
+    tag = "action",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
 enum ScriptOperation {
-    #[serde(rename = "create")]
     Create {
         #[schemars(description = "Target path for the script, including the desired script name")]
         path: Vec<String>,
         #[schemars(
             description = "Roblox class of the script to create (Script, LocalScript, ModuleScript)"
         )]
-        #[serde(rename = "scriptType")]
         script_type: ScriptType,
         #[serde(default)]
         #[schemars(description = "Optional source assigned to the script upon creation")]
@@ -1009,35 +1405,140 @@ enum ScriptOperation {
         #[schemars(description = "Optional run context, e.g. Server, Client, or Legacy")]
         run_context: Option<String>,
         #[serde(default)]
-        #[schemars(description = "Attributes applied via Instance:SetAttribute")]
-        attributes: HashMap<String, JsonValue>,
+        #[schemars(
+            description = "Attributes applied via Instance:SetAttribute, as typed RobloxValues"
+        )]
+        attributes: InstancePropertyMap,
+        #[serde(default)]
+        #[schemars(description = "Apply the source even when it fails to parse")]
+        force: bool,
+        #[serde(default)]
+        #[schemars(
+            description = "Format the source with StyLua using the project's stylua.toml before writing it"
+        )]
+        format: bool,
         #[serde(default)]
         #[schemars(description = "Metadata selection override for this operation")]
         metadata: Option<ScriptMetadataSelection>,
     },
-    #[serde(rename = "get_source")]
     GetSource {
         #[schemars(description = "Path to the existing script to inspect")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
         path: Vec<String>,
         #[serde(default)]
+        #[schemars(
+            description = "1-indexed first line to return; the whole source is returned when neither line is set"
+        )]
+        start_line: Option<u32>,
+        #[serde(default)]
+        #[schemars(
+            description = "1-indexed last line to return (inclusive); defaults to the end of the script"
+        )]
+        end_line: Option<u32>,
+        #[serde(default)]
         #[schemars(description = "Metadata selection override for this operation")]
         metadata: Option<ScriptMetadataSelection>,
     },
-    #[serde(rename = "set_source")]
     SetSource {
         #[schemars(description = "Path to the existing script to update")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
         path: Vec<String>,
         #[schemars(
             description = "New source code that should replace the current script contents"
         )]
         source: String,
         #[serde(default)]
+        #[schemars(
+            description = "Only apply when the script's current source equals this, so edits based on a stale read fail instead of overwriting newer changes"
+        )]
+        expected_source: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Apply the source even when it fails to parse")]
+        force: bool,
+        #[serde(default)]
+        #[schemars(
+            description = "Format the source with StyLua using the project's stylua.toml before writing it"
+        )]
+        format: bool,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    Outline {
+        #[schemars(
+            description = "Path to the existing script whose functions, locals, table fields, and types should be listed with their line numbers"
+        )]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    FormatScript {
+        #[schemars(description = "Path to the existing script to format in place")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    ApplyPatch {
+        #[schemars(description = "Path to the existing script to patch")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[schemars(
+            description = "Unified diff against the current source. Hunks are located by their context lines, so line numbers may be approximate; the patch fails with the conflicting lines when the context does not match"
+        )]
+        patch: String,
+        #[serde(default)]
+        #[schemars(description = "Apply the patched source even when it fails to parse")]
+        force: bool,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    ReplaceRange {
+        #[schemars(description = "Path to the existing script to edit")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[schemars(description = "1-indexed line where the replaced range starts")]
+        start_line: u32,
+        #[serde(default)]
+        #[schemars(
+            description = "1-indexed column where the replaced range starts; defaults to the start of the line"
+        )]
+        start_column: Option<u32>,
+        #[schemars(description = "1-indexed line where the replaced range ends (inclusive)")]
+        end_line: u32,
+        #[serde(default)]
+        #[schemars(
+            description = "1-indexed column just past the replaced range; defaults to the end of the line"
+        )]
+        end_column: Option<u32>,
+        #[schemars(description = "Text that replaces the range")]
+        text: String,
+        #[serde(default)]
+        #[schemars(
+            description = "Text the range is expected to contain; the edit fails when the script has changed"
+        )]
+        expected_text: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Apply the edited source even when it fails to parse")]
+        force: bool,
+        #[serde(default)]
         #[schemars(description = "Metadata selection override for this operation")]
         metadata: Option<ScriptMetadataSelection>,
     },
-    #[serde(rename = "rename")]
     Rename {
         #[schemars(description = "Path to the existing script to rename")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
         path: Vec<String>,
         #[schemars(description = "Replacement name for the script instance")]
         new_name: String,
@@ -1045,10 +1546,134 @@ enum ScriptOperation {
         #[schemars(description = "Metadata selection override for this operation")]
         metadata: Option<ScriptMetadataSelection>,
     },
+    Delete {
+        #[schemars(description = "Path to the existing script to delete, along with its children")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[serde(default)]
+        #[schemars(
+            description = "Metadata selection override for this operation, read before the script is deleted"
+        )]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    Move {
+        #[schemars(description = "Path to the existing script to move")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[schemars(description = "Path to the instance the script should be parented to")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        new_parent: Vec<String>,
+        #[serde(default)]
+        #[schemars(description = "Optional new name; defaults to the script's current name")]
+        new_name: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    Duplicate {
+        #[schemars(description = "Path to the existing script to copy")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[serde(default)]
+        #[schemars(
+            description = "Path to the parent of the copy; defaults to the parent of the original"
+        )]
+        #[serde(deserialize_with = "instance_ids::optional_path")]
+        #[schemars(with = "Option<PathOrId>")]
+        new_parent: Option<Vec<String>>,
+        #[serde(default)]
+        #[schemars(
+            description = "Name of the copy; defaults to the original name, with a Copy suffix when the copy shares its parent"
+        )]
+        new_name: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    ConvertType {
+        #[schemars(
+            description = "Path to the existing script to convert. Its name, source, attributes, tags, enabled state, and children are kept"
+        )]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[schemars(
+            description = "Roblox class to convert the script to (Script, LocalScript, ModuleScript)"
+        )]
+        script_type: ScriptType,
+        #[serde(default)]
+        #[schemars(description = "Optional run context for the converted script")]
+        run_context: Option<String>,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
+    SetEnabled {
+        #[schemars(description = "Path to the existing Script or LocalScript to toggle")]
+        #[serde(deserialize_with = "instance_ids::path")]
+        #[schemars(with = "PathOrId")]
+        path: Vec<String>,
+        #[schemars(description = "True to enable the script, false to disable it")]
+        enabled: bool,
+        #[serde(default)]
+        #[schemars(description = "Metadata selection override for this operation")]
+        metadata: Option<ScriptMetadataSelection>,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
+impl ScriptOperation {
+    fn kind(&self) -> ScriptOperationKind {
+        match self {
+            ScriptOperation::Create { .. } => ScriptOperationKind::Create,
+            ScriptOperation::GetSource { .. } => ScriptOperationKind::GetSource,
+            ScriptOperation::Outline { .. } => ScriptOperationKind::Outline,
+            ScriptOperation::SetSource { .. } => ScriptOperationKind::SetSource,
+            ScriptOperation::FormatScript { .. } => ScriptOperationKind::FormatScript,
+            ScriptOperation::ApplyPatch { .. } => ScriptOperationKind::ApplyPatch,
+            ScriptOperation::ReplaceRange { .. } => ScriptOperationKind::ReplaceRange,
+            ScriptOperation::Rename { .. } => ScriptOperationKind::Rename,
+            ScriptOperation::Delete { .. } => ScriptOperationKind::Delete,
+            ScriptOperation::Move { .. } => ScriptOperationKind::Move,
+            ScriptOperation::Duplicate { .. } => ScriptOperationKind::Duplicate,
+            ScriptOperation::ConvertType { .. } => ScriptOperationKind::ConvertType,
+            ScriptOperation::SetEnabled { .. } => ScriptOperationKind::SetEnabled,
+        }
+    }
+
+    fn path(&self) -> &[String] {
+        match self {
+            ScriptOperation::Create { path, .. }
+            | ScriptOperation::GetSource { path, .. }
+            | ScriptOperation::Outline { path, .. }
+            | ScriptOperation::SetSource { path, .. }
+            | ScriptOperation::FormatScript { path, .. }
+            | ScriptOperation::ApplyPatch { path, .. }
+            | ScriptOperation::ReplaceRange { path, .. }
+            | ScriptOperation::Rename { path, .. }
+            | ScriptOperation::Delete { path, .. }
+            | ScriptOperation::Move { path, .. }
+            | ScriptOperation::Duplicate { path, .. }
+            | ScriptOperation::ConvertType { path, .. }
+            | ScriptOperation::SetEnabled { path, .. } => path,
+        }
+    }
+
+    /// Replaces the source written by a `create` or `set_source` operation.
+    fn set_source(&mut self, new_source: String) {
+        match self {
+            ScriptOperation::Create { source, .. } => *source = Some(new_source),
+            ScriptOperation::SetSource { source, .. } => *source = new_source,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
 enum ScriptType {
     #[serde(rename = "Script")]
     Script,
@@ -1148,6 +1773,9 @@ impl Default for DiagnosticsAndMetricsRequest {
 struct CollectionAndAttributesRequest {
     #[schemars(description = "Ordered set of tag or attribute operations to execute")]
     operations: Vec<CollectionAndAttributesOperation>,
+    #[serde(default)]
+    #[schemars(description = dry_run::DESCRIPTION)]
+    dry_run: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -1157,32 +1785,38 @@ enum CollectionAndAttributesOperation {
         description = "Return CollectionService tags (and optional attributes) for specific instances"
     )]
     ListTags {
-        #[schemars(description = "Instance paths to inspect for tag metadata")]
-        paths: Vec<Vec<String>>,
+        #[schemars(description = "Instance paths or selectors to inspect for tag metadata")]
+        paths: Vec<InstanceTarget>,
         #[serde(default)]
         #[schemars(description = "Include Instance:GetAttributes() output for each path")]
         include_attributes: bool,
     },
     #[schemars(description = "Apply CollectionService tags to one or more instances")]
     AddTags {
-        #[schemars(description = "Instance paths that will receive the provided tags")]
-        paths: Vec<Vec<String>>,
+        #[schemars(
+            description = "Instance paths or selectors that will receive the provided tags"
+        )]
+        paths: Vec<InstanceTarget>,
         #[schemars(description = "Tags that should be added to every resolved instance")]
         tags: Vec<String>,
     },
     #[schemars(description = "Remove CollectionService tags from one or more instances")]
     RemoveTags {
-        #[schemars(description = "Instance paths that will have the provided tags removed")]
-        paths: Vec<Vec<String>>,
+        #[schemars(
+            description = "Instance paths or selectors that will have the provided tags removed"
+        )]
+        paths: Vec<InstanceTarget>,
         #[schemars(description = "Tags that should be removed from every resolved instance")]
         tags: Vec<String>,
     },
     #[schemars(description = "Synchronise Instance attributes with the provided key/value map")]
     SyncAttributes {
-        #[schemars(description = "Instance paths whose attributes will be updated")]
-        paths: Vec<Vec<String>>,
-        #[schemars(description = "Attributes that should be written via Instance:SetAttribute")]
-        attributes: HashMap<String, JsonValue>,
+        #[schemars(description = "Instance paths or selectors whose attributes will be updated")]
+        paths: Vec<InstanceTarget>,
+        #[schemars(
+            description = "Attributes that should be written via Instance:SetAttribute, as typed RobloxValues"
+        )]
+        attributes: InstancePropertyMap,
         #[serde(default)]
         #[schemars(
             description = "Remove existing attributes that are not present in the provided map"
@@ -1300,6 +1934,8 @@ struct PhysicsCollisionPairTuning {
 #[serde(rename_all = "camelCase")]
 struct PhysicsAssignPartRequest {
     #[schemars(description = "Instance path segments that should resolve to a BasePart")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
     #[schemars(description = "Collision group that the resolved BasePart will be assigned to")]
     group_name: String,
@@ -1339,6 +1975,9 @@ enum PhysicsAndNavigationOperation {
 struct PhysicsAndNavigationRequest {
     #[schemars(description = "Batch of physics/pathfinding operations to run sequentially")]
     operations: Vec<PhysicsAndNavigationOperation>,
+    #[serde(default)]
+    #[schemars(description = dry_run::DESCRIPTION)]
+    dry_run: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -1477,6 +2116,8 @@ struct SoundServiceSettings {
 #[serde(rename_all = "camelCase")]
 struct SoundInstanceControl {
     #[schemars(description = "Path to the Sound instance that should be modified")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
     path: Vec<String>,
     #[serde(default)]
     #[schemars(description = "Sound asset id to assign")]
@@ -1612,52 +2253,9 @@ struct EnvironmentControlRequest {
     #[serde(default)]
     #[schemars(description = "Targeted sound instance adjustments")]
     sounds: Vec<SoundInstanceControl>,
-}
-
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
-#[serde(default, rename_all = "camelCase")]
-struct EnvironmentControlResponse {
-    #[schemars(description = "True if all requested adjustments were applied successfully")]
-    success: bool,
-    #[serde(default)]
-    #[schemars(description = "Human readable summary of the applied environment edits")]
-    summary: Option<String>,
-    #[serde(default)]
-    #[schemars(description = "List of granular property changes that were applied")]
-    changes: Vec<String>,
-    #[serde(default)]
-    #[schemars(description = "Validation or runtime issues encountered while applying edits")]
-    errors: Vec<String>,
-}
-
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
-struct PhysicsAndNavigationOperationResult {
-    #[schemars(description = "Index of the processed operation within the batch")]
-    index: usize,
-    #[schemars(description = "Identifier of the executed operation")]
-    operation: String,
-    #[schemars(description = "True when the operation completed successfully")]
-    success: bool,
-    #[serde(default)]
-    #[schemars(description = "Optional human readable status message")]
-    message: Option<String>,
     #[serde(default)]
-    #[schemars(description = "Structured details returned by the operation (such as waypoints)")]
-    details: Option<JsonValue>,
-}
-
-#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
-struct PhysicsAndNavigationResponse {
-    #[schemars(description = "Per-operation results describing the batch execution")]
-    results: Vec<PhysicsAndNavigationOperationResult>,
-    #[serde(default)]
-    #[schemars(description = "Optional summary of the applied operations")]
-    summary: Option<String>,
-    #[serde(default)]
-    #[schemars(description = "True when at least one operation mutated collision data")]
-    write_occurred: bool,
+    #[schemars(description = dry_run::DESCRIPTION)]
+    dry_run: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
@@ -1681,9 +2279,9 @@ struct DataModelSnapshotPropertyPick {
 #[serde(default, rename_all = "camelCase")]
 struct DataModelSnapshotRequest {
     #[schemars(
-        description = "Instance paths to use as traversal roots. Defaults to the DataModel when omitted."
+        description = "Instance paths or selectors to use as traversal roots. Defaults to the DataModel when omitted."
     )]
-    root_paths: Vec<Vec<String>>,
+    root_paths: Vec<InstanceTarget>,
     #[serde(default)]
     #[schemars(
         description = "Maximum traversal depth relative to each root. Depth 0 only returns the root instance."
@@ -1723,6 +2321,11 @@ struct DataModelSnapshotRequest {
     #[serde(default)]
     #[schemars(description = "Seed used when randomising sampled property lists.")]
     random_seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "Handle of an earlier snapshot. The snapshot is retaken with that snapshot's options (the other fields are ignored) and only instances added, removed, or changed since then are returned."
+    )]
+    since: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
@@ -1730,6 +2333,11 @@ struct DataModelSnapshotRequest {
 struct DataModelSnapshotInstance {
     #[schemars(description = "Path of the instance relative to the DataModel.")]
     path: Vec<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Stable id of the instance for this session. Pass {\"id\": ...} wherever an instance path is accepted to keep addressing it after renames and moves."
+    )]
+    id: Option<String>,
     #[schemars(description = "Name of the instance.")]
     name: String,
     #[schemars(description = "Class name of the instance.")]
@@ -1744,10 +2352,10 @@ struct DataModelSnapshotInstance {
     child_count: Option<u32>,
     #[serde(default)]
     #[schemars(description = "Instance attributes captured for the snapshot entry.")]
-    attributes: HashMap<String, JsonValue>,
+    attributes: HashMap<String, RobloxValue>,
     #[serde(default)]
     #[schemars(description = "Property values captured for the snapshot entry.")]
-    properties: HashMap<String, JsonValue>,
+    properties: HashMap<String, RobloxValue>,
     #[serde(default)]
     #[schemars(description = "Errors encountered while reading requested properties.")]
     property_errors: Vec<DataModelSnapshotPropertyError>,
@@ -1788,13 +2396,205 @@ struct DataModelSnapshotResponse {
     metadata: HashMap<String, JsonValue>,
 }
 
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ProjectSyncOptions {
+    #[serde(default)]
+    #[schemars(
+        description = "Rojo project file or directory containing default.project.json. Defaults to the server's --project."
+    )]
+    project: Option<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Remove scripts and folders on the destination side that no longer exist on the source side."
+    )]
+    prune: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DiffPlacesRequest {
+    #[schemars(
+        description = "Path to the .rbxl/.rbxlx/.rbxm/.rbxmx file treated as the baseline."
+    )]
+    before: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Path to the file to compare against. When omitted the baseline is compared with the live session."
+    )]
+    after: Option<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Instance path in the live session that corresponds to the file's root, e.g. [\"Workspace\"] for a model file. Defaults to the DataModel."
+    )]
+    live_root: Vec<String>,
+    #[serde(default)]
+    #[schemars(description = "Lines of context around each change in script source diffs.")]
+    context_lines: Option<u32>,
+}
+
+/// Which scripts `search_scripts` and `replace_in_scripts` look at, and what they match.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ScriptQuery {
+    #[schemars(
+        description = "Text to search for, or a Rust regular expression when regex is set."
+    )]
+    query: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Treat the query as a regular expression (Rust regex syntax, with ^ and $ matching at line boundaries)."
+    )]
+    regex: bool,
+    #[serde(default)]
+    #[schemars(description = "Match case exactly. Defaults to true.")]
+    case_sensitive: Option<bool>,
+    #[serde(default)]
+    #[schemars(
+        description = "Instance paths or selectors to search under, e.g. [[\"ServerScriptService\"]]. Defaults to the whole DataModel."
+    )]
+    roots: Vec<InstanceTarget>,
+    #[serde(default)]
+    #[schemars(
+        description = "Script classes to search. Defaults to Script, LocalScript, and ModuleScript."
+    )]
+    class_names: Vec<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Case-insensitive regular expression matched against each script's dot-separated path, e.g. \"^ReplicatedStorage\\.Shared\\.\"."
+    )]
+    path_filter: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SearchScriptsRequest {
+    #[serde(flatten)]
+    query: ScriptQuery,
+    #[serde(default)]
+    #[schemars(description = "Lines of context returned before and after each match.")]
+    context_lines: Option<u32>,
+    #[serde(default)]
+    #[schemars(description = "Maximum number of matches to return. Defaults to 200.")]
+    max_results: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ReplaceInScriptsRequest {
+    #[serde(flatten)]
+    query: ScriptQuery,
+    #[schemars(
+        description = "Replacement text. With regex set, $1 or ${name} insert capture groups and $$ inserts a literal $."
+    )]
+    replacement: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Return a unified diff of every change without writing anything to Studio."
+    )]
+    dry_run: bool,
+    #[serde(default)]
+    #[schemars(description = "Lines of context around each change in the preview diffs.")]
+    context_lines: Option<u32>,
+    #[serde(default)]
+    #[schemars(
+        description = "Write the changes even when a rewritten script no longer parses. Otherwise nothing is written."
+    )]
+    force: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ListScriptVersionsRequest {
+    #[schemars(description = "Path to the script whose recorded versions should be listed")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[serde(default)]
+    #[schemars(description = "Maximum number of versions to return, newest first (default 20).")]
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DiffScriptVersionsRequest {
+    #[schemars(description = "Path to the script whose versions should be compared")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[schemars(
+        description = "Version id (or a prefix of at least 4 characters) to diff from, or \"current\" for the script's source in Studio"
+    )]
+    from: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Version id to diff to. Defaults to \"current\", the script's source in Studio"
+    )]
+    to: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Lines of context around each change (default 3).")]
+    context_lines: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RestoreScriptVersionRequest {
+    #[schemars(description = "Path to the script to restore")]
+    #[serde(deserialize_with = "instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    path: Vec<String>,
+    #[schemars(description = "Version id (or a prefix of at least 4 characters) to restore")]
+    version: String,
+    #[serde(default)]
+    #[schemars(description = "Restore the source even when it no longer parses")]
+    force: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzeScriptsRequest {
+    #[serde(default)]
+    #[schemars(
+        description = "Instance paths or selectors whose scripts are exported for analysis, so requires between them resolve. Defaults to the whole DataModel."
+    )]
+    roots: Vec<InstanceTarget>,
+    #[serde(default)]
+    #[schemars(
+        description = "Only report diagnostics for scripts at or under these instance paths or selectors. Defaults to every analysed script."
+    )]
+    paths: Vec<InstanceTarget>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum GraphFormat {
+    #[default]
+    #[schemars(description = "Scripts, edges, cycles, and unused modules as JSON")]
+    Json,
+    #[schemars(description = "A Graphviz digraph; edges inside cycles are red")]
+    Dot,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ScriptDependencyGraphRequest {
+    #[serde(default)]
+    #[schemars(
+        description = "Instance paths or selectors whose scripts are analysed. Defaults to the whole DataModel; modules required only from outside the roots are reported as unused."
+    )]
+    roots: Vec<InstanceTarget>,
+    #[serde(default)]
+    #[schemars(description = "Output format. Defaults to json.")]
+    format: Option<GraphFormat>,
+}
+
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 #[serde(tag = "tool", content = "params")]
 enum ToolArgumentValues {
     RunCode(RunCode),
     InsertModel(InsertModel),
     InspectEnvironment(InspectEnvironment),
-    EnvironmentControl(EnvironmentControlRequest),
+    EnvironmentControl(Box<EnvironmentControlRequest>),
     ApplyInstanceOperations(ApplyInstanceOperationsRequest),
     ManageScripts(ManageScriptsRequest),
     TestAndPlayControl(TestAndPlayControl),
@@ -1805,16 +2605,132 @@ enum ToolArgumentValues {
     PhysicsAndNavigation(PhysicsAndNavigationRequest),
     DiagnosticsAndMetrics(DiagnosticsAndMetricsRequest),
     DataModelSnapshot(DataModelSnapshotRequest),
+    ProjectSync(ProjectSyncRequest),
+    ChangeHistory(ChangeHistoryRequest),
+    InstantiateTemplate(InstantiateTemplatePayload),
+    WatchChanges(WatchChangesPayload),
+}
+
+impl ToolArgumentValues {
+    /// True for a request to a mutating tool that asks to preview its changes.
+    fn is_dry_run(&self) -> bool {
+        match self {
+            ToolArgumentValues::ApplyInstanceOperations(request) => request.dry_run,
+            ToolArgumentValues::ManageScripts(request) => request.dry_run,
+            ToolArgumentValues::TerrainOperations(request) => request.dry_run,
+            ToolArgumentValues::CollectionAndAttributes(request) => request.dry_run,
+            ToolArgumentValues::PhysicsAndNavigation(request) => request.dry_run,
+            ToolArgumentValues::EnvironmentControl(request) => request.dry_run,
+            _ => false,
+        }
+    }
+
+    /// Name of the MCP tool that produced these arguments.
+    fn tool_name(&self) -> &'static str {
+        match self {
+            ToolArgumentValues::RunCode(_) => "run_code",
+            ToolArgumentValues::InsertModel(_) => "insert_model",
+            ToolArgumentValues::InspectEnvironment(_) => "inspect_environment",
+            ToolArgumentValues::EnvironmentControl(_) => "environment_control",
+            ToolArgumentValues::ApplyInstanceOperations(_) => "apply_instance_operations",
+            ToolArgumentValues::ManageScripts(_) => "manage_scripts",
+            ToolArgumentValues::TestAndPlayControl(_) => "test_and_play_control",
+            ToolArgumentValues::EditorSessionControl(_) => "editor_session_control",
+            ToolArgumentValues::TerrainOperations(_) => "terrain_operations",
+            ToolArgumentValues::AssetPipeline(_) => "asset_pipeline",
+            ToolArgumentValues::CollectionAndAttributes(_) => "collection_and_attributes",
+            ToolArgumentValues::PhysicsAndNavigation(_) => "physics_and_navigation",
+            ToolArgumentValues::DiagnosticsAndMetrics(_) => "diagnostics_and_metrics",
+            ToolArgumentValues::DataModelSnapshot(_) => "data_model_snapshot",
+            ToolArgumentValues::ProjectSync(request) => match request.action {
+                ProjectSyncAction::Push => "sync_from_filesystem",
+                ProjectSyncAction::Pull => "sync_to_filesystem",
+            },
+            ToolArgumentValues::ChangeHistory(_) => "change_history",
+            ToolArgumentValues::InstantiateTemplate(_) => "instantiate_template",
+            ToolArgumentValues::WatchChanges(_) => "watch_changes",
+        }
+    }
 }
+
 #[tool_router]
 impl RBXStudioServer {
-    pub fn new(state: PackedState) -> Self {
+    pub fn new(state: PackedState, confirmation: ConfirmationPolicy) -> Self {
         Self {
             state,
+            confirmation,
+            place: None,
+            project: None,
+            selene: None,
+            luau_analyzer: None,
+            history: None,
+            templates: None,
+            instance_ids: Arc::default(),
+            requests: Arc::default(),
+            snapshots: Arc::default(),
+            change_subscription: Arc::default(),
             tool_router: Self::tool_router(),
         }
     }
 
+    /// Uses the Rojo project at `path` for the filesystem sync tools.
+    pub fn with_project(self, path: PathBuf) -> Self {
+        Self {
+            project: Some(path),
+            ..self
+        }
+    }
+
+    /// Lints sources sent through `manage_scripts` with the selene executable at `path`.
+    pub fn with_selene(self, path: PathBuf) -> Self {
+        Self {
+            selene: Some(path),
+            ..self
+        }
+    }
+
+    /// Type-checks scripts for `analyze_scripts` with the `luau-lsp` or `luau-analyze` executable
+    /// at `path`. `definitions` are the Roblox API type definitions passed to `luau-lsp`.
+    pub fn with_luau_analyzer(self, path: PathBuf, definitions: Option<PathBuf>) -> Self {
+        Self {
+            luau_analyzer: Some(LuauAnalyzer {
+                executable: path,
+                definitions,
+            }),
+            ..self
+        }
+    }
+
+    /// Keeps every script source read or written through `manage_scripts` in a content-addressed
+    /// store under `dir`, shared with other sessions using the same directory. Only the newest
+    /// `max_versions` of each script are kept.
+    pub fn with_script_history(self, dir: PathBuf, max_versions: usize) -> Self {
+        Self {
+            history: Some(Arc::new(ScriptHistory::new(
+                dir,
+                Uuid::new_v4().to_string(),
+                max_versions,
+            ))),
+            ..self
+        }
+    }
+
+    /// Keeps the templates saved through `manage_templates` under `dir`.
+    pub fn with_templates(self, dir: PathBuf) -> Self {
+        Self {
+            templates: Some(Arc::new(TemplateStore::new(dir))),
+            ..self
+        }
+    }
+
+    /// Serves supported tools from `place` instead of forwarding them to Studio.
+    pub fn with_place_file(self, place: PlaceFile) -> Self {
+        Self {
+            place: Some(Arc::new(Mutex::new(place))),
+            ..self
+        }
+    }
+
     #[tool(
         description = "Runs a command in Roblox Studio and returns the printed output. Can be used to both make changes and retrieve information"
     )]
@@ -1855,30 +2771,40 @@ impl RBXStudioServer {
         &self,
         Parameters(args): Parameters<EnvironmentControlRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::EnvironmentControl(args))
+        self.generic_tool_run(ToolArgumentValues::EnvironmentControl(Box::new(args)))
             .await
     }
 
     #[tool(
-        description = "Applies a batch of create/update/delete operations against instances in the open Studio session."
+        description = "Applies a batch of create/update/delete/reparent/clone/bulk_set_properties operations, plus group/ungroup, pivot_to/move_by, set_primary_part, and weld, against instances in the open Studio session. Property and attribute values use the typed RobloxValue encoding: plain JSON for booleans, numbers, and strings, and objects tagged with \"type\" (Vector3, CFrame, Color3, UDim2, EnumItem, Instance, ...) for other datatypes, as returned by data_model_snapshot."
     )]
     async fn apply_instance_operations(
         &self,
+        peer: Peer<RoleServer>,
         Parameters(args): Parameters<ApplyInstanceOperationsRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::ApplyInstanceOperations(args))
+        if let Err(err) =
+            roblox_value::ensure_valid(roblox_value::operation_value_errors(&args.operations))
+        {
+            return Ok(tool_result(Err(err.into())));
+        }
+        self.confirmed_tool_run(&peer, ToolArgumentValues::ApplyInstanceOperations(args))
             .await
     }
 
     #[tool(
-        description = "Creates, inspects, and edits Script/LocalScript/ModuleScript instances in the current Studio session."
+        description = "Creates, inspects, edits, moves, duplicates, converts, and deletes Script/LocalScript/ModuleScript instances in the current Studio session."
     )]
     async fn manage_scripts(
         &self,
         Parameters(args): Parameters<ManageScriptsRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::ManageScripts(args))
-            .await
+        if let Err(err) =
+            roblox_value::ensure_valid(roblox_value::script_attribute_errors(&args.operations))
+        {
+            return Ok(tool_result(Err(err.into())));
+        }
+        Ok(tool_result(self.run_manage_scripts(args).await?))
     }
 
     #[tool(
@@ -1908,9 +2834,10 @@ impl RBXStudioServer {
     )]
     async fn terrain_operations(
         &self,
+        peer: Peer<RoleServer>,
         Parameters(args): Parameters<TerrainOperationsRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::TerrainOperations(args))
+        self.confirmed_tool_run(&peer, ToolArgumentValues::TerrainOperations(args))
             .await
     }
 
@@ -1919,10 +2846,24 @@ impl RBXStudioServer {
     )]
     async fn asset_pipeline(
         &self,
+        peer: Peer<RoleServer>,
         Parameters(args): Parameters<AssetPipelineRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::AssetPipeline(args))
-            .await
+        if !export::has_exports(&args) {
+            return self
+                .confirmed_tool_run(&peer, ToolArgumentValues::AssetPipeline(args))
+                .await;
+        }
+        let request = args.clone();
+        let args = ToolArgumentValues::AssetPipeline(args);
+        if let Some(aborted) = self.confirm(&peer, &args).await {
+            return Ok(aborted);
+        }
+        let result = self
+            .dispatch(args)
+            .await?
+            .and_then(|response| export::write_exports(&request, &response).map_err(Into::into));
+        Ok(tool_result(result))
     }
 
     #[tool(
@@ -1932,6 +2873,11 @@ impl RBXStudioServer {
         &self,
         Parameters(args): Parameters<CollectionAndAttributesRequest>,
     ) -> Result<CallToolResult, ErrorData> {
+        if let Err(err) =
+            roblox_value::ensure_valid(roblox_value::collection_attribute_errors(&args.operations))
+        {
+            return Ok(tool_result(Err(err.into())));
+        }
         self.generic_tool_run(ToolArgumentValues::CollectionAndAttributes(args))
             .await
     }
@@ -1959,21 +2905,480 @@ impl RBXStudioServer {
     }
 
     #[tool(
-        description = "Collects read-only snapshots of the DataModel with optional class filters, property sampling, and pagination."
+        description = "Collects read-only snapshots of the DataModel with optional class filters, property sampling, and pagination. Unpaginated responses carry a handle; pass it back as since to get only the instances added, removed, or changed since that snapshot."
     )]
     async fn data_model_snapshot(
         &self,
         Parameters(args): Parameters<DataModelSnapshotRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::DataModelSnapshot(args))
-            .await
+        let result = match &args.since {
+            Some(since) => self.snapshot_since(since).await?,
+            None => self
+                .dispatch(ToolArgumentValues::DataModelSnapshot(args.clone()))
+                .await?
+                .map(|response| self.snapshots.annotate(args, response)),
+        };
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Reports what changed in the open Studio session since the last call: instances added under, removed from, or with properties changed under the watched roots, whether by a person in Studio or through MCP (those carry a requestId). The first call starts watching (roots, defaulting to the main services); pass the returned cursor to later calls to get only newer events. Clients can also subscribe to the studio://changes resource to be notified of new events."
+    )]
+    async fn watch_changes(
+        &self,
+        Parameters(args): Parameters<WatchChangesRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Err(err) = args.validate() {
+            return Ok(tool_result(Err(err.into())));
+        }
+        let response = match self
+            .dispatch(ToolArgumentValues::WatchChanges(args.payload()))
+            .await?
+        {
+            Ok(response) => response,
+            Err(err) => return Ok(tool_result(Err(err))),
+        };
+        let status: WatchStatus = match serde_json::from_str(&response) {
+            Ok(status) => status,
+            Err(err) => {
+                return Ok(tool_result(Err(eyre!(
+                    "Unable to parse the change feed status: {err}"
+                )
+                .into())));
+            }
+        };
+        let poll = {
+            let mut state = self.state.lock().await;
+            state.changes.push(status.pending.clone());
+            state.changes.poll(
+                args.cursor,
+                args.limit,
+                args.exclude_mcp_changes.unwrap_or(false),
+            )
+        };
+        let result = serde_json::to_string(&poll.with_status(&status)).map_err(Into::into);
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Undoes and redoes changes made through MCP using Studio's undo history. Actions: undo and redo (count, default 1), list_recent (limit), and undo_until (requestId from list_recent, inclusive). Stops at changes made by hand in Studio rather than undoing them."
+    )]
+    async fn change_history(
+        &self,
+        Parameters(args): Parameters<ChangeHistoryRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Err(err) = args.validate() {
+            return Ok(tool_result(Err(err.into())));
+        }
+        let result = self
+            .dispatch(ToolArgumentValues::ChangeHistory(args))
+            .await?
+            .map(|response| self.requests.annotate(response));
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Saves and manages named instance templates kept by the server. Actions: save (name plus instancePath from the place or filePath of a local .rbxm/.rbxmx with one root), list, get, and delete. Instance names and string attributes in a template may contain {{parameter}} placeholders; get and list report the parameters each template takes."
+    )]
+    async fn manage_templates(
+        &self,
+        Parameters(args): Parameters<ManageTemplatesRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let result = self.run_manage_templates(args).await?;
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Inserts a copy of a template saved with manage_templates under parentPath, filling in its {{parameter}} placeholders from parameters, then optionally renaming it (instanceName), setting attributes, and pivoting it to a cframe or position."
+    )]
+    async fn instantiate_template(
+        &self,
+        Parameters(args): Parameters<InstantiateTemplateRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let payload = args.validate().and_then(|()| {
+            let (_, model) = self.template_store()?.load(&args.name)?;
+            let prepared = place_file::prepare_template(&model, &args)?;
+            Ok(InstantiateTemplatePayload {
+                template: args.name.clone(),
+                parent_path: args.parent_path.clone(),
+                model: base64::engine::general_purpose::STANDARD.encode(prepared),
+            })
+        });
+        match payload {
+            Ok(payload) => {
+                self.generic_tool_run(ToolArgumentValues::InstantiateTemplate(payload))
+                    .await
+            }
+            Err(err) => Ok(tool_result(Err(err.into()))),
+        }
+    }
+
+    #[tool(
+        description = "Pushes the scripts and .model.json files mapped by a Rojo project into Studio, creating or updating instances to match the filesystem."
+    )]
+    async fn sync_from_filesystem(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(args): Parameters<ProjectSyncOptions>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let prune = args.prune.unwrap_or(false);
+        let nodes = match self
+            .load_project(&args)
+            .and_then(|project| project.push_nodes(prune))
+        {
+            Ok(nodes) => nodes,
+            Err(err) => return Ok(tool_result(Err(err.into()))),
+        };
+        let args = ToolArgumentValues::ProjectSync(ProjectSyncRequest {
+            action: ProjectSyncAction::Push,
+            nodes,
+            roots: Vec::new(),
+            prune,
+        });
+        self.confirmed_tool_run(&peer, args).await
+    }
+
+    #[tool(
+        description = "Pulls script edits made in Studio back into the directories mapped by a Rojo project, using Rojo file naming conventions."
+    )]
+    async fn sync_to_filesystem(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(args): Parameters<ProjectSyncOptions>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let prune = args.prune.unwrap_or(false);
+        let project = match self.load_project(&args) {
+            Ok(project) => project,
+            Err(err) => return Ok(tool_result(Err(err.into()))),
+        };
+        let args = ToolArgumentValues::ProjectSync(ProjectSyncRequest {
+            action: ProjectSyncAction::Pull,
+            nodes: Vec::new(),
+            roots: project.pull_roots(),
+            prune,
+        });
+        if let Some(aborted) = self.confirm(&peer, &args).await {
+            return Ok(aborted);
+        }
+        let result = self
+            .dispatch(args)
+            .await?
+            .and_then(|response| project.write_pull(&response, prune).map_err(Into::into));
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Compares a place/model file with another file or the live session and reports added, removed, and modified instances with property, attribute, and script source diffs."
+    )]
+    async fn diff_places(
+        &self,
+        Parameters(args): Parameters<DiffPlacesRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let diff = self.run_diff_places(&args).await?;
+        Ok(tool_result(
+            diff.and_then(|diff| Ok(serde_json::to_string(&diff)?)),
+        ))
+    }
+
+    #[tool(
+        description = "Searches the source of every script in the place for a literal string or regular expression, with optional root, class, and path filters. Returns each match's script path, line, and column with surrounding context lines."
+    )]
+    async fn search_scripts(
+        &self,
+        Parameters(args): Parameters<SearchScriptsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let result = self
+            .snapshot_entries(script_search::snapshot_request(&args.query))
+            .await?
+            .and_then(|entries| script_search::search(&args, &entries).map_err(Into::into));
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Finds and replaces a literal string or regular expression across many scripts. With dry_run it returns a unified diff per script; otherwise every change is written in one atomic batch that a single undo reverts, and nothing is written if any script fails to update."
+    )]
+    async fn replace_in_scripts(
+        &self,
+        Parameters(args): Parameters<ReplaceInScriptsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entries = match self
+            .snapshot_entries(script_search::snapshot_request(&args.query))
+            .await?
+        {
+            Ok(entries) => entries,
+            Err(err) => return Ok(tool_result(Err(err))),
+        };
+        let mut plan = match ReplacePlan::new(&args, &entries) {
+            Ok(plan) => plan,
+            Err(err) => return Ok(tool_result(Err(err.into()))),
+        };
+        if args.dry_run || plan.is_empty() {
+            return Ok(tool_result(plan.preview(args.dry_run).map_err(Into::into)));
+        }
+        if let Some(refusal) = plan.refusal() {
+            return Ok(tool_result(refusal.map_err(Into::into)));
+        }
+        let result = self
+            .run_manage_scripts(plan.write_request())
+            .await?
+            .and_then(|response| plan.report(&response).map_err(Into::into));
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Resolves the require() calls of every script (script.Parent chains, GetService, FindFirstChild/WaitForChild, and locals holding them) into a module dependency graph, returned as JSON or Graphviz DOT with circular requires and unused modules reported."
+    )]
+    async fn script_dependency_graph(
+        &self,
+        Parameters(args): Parameters<ScriptDependencyGraphRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let result = self
+            .snapshot_entries(script_graph::snapshot_request(&args))
+            .await?
+            .and_then(|entries| {
+                script_graph::dependency_graph(&args, &entries).map_err(Into::into)
+            });
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Type-checks scripts with the luau-lsp or luau-analyze executable configured on the server. Scripts are exported to a temporary tree with a generated sourcemap, and type errors and lint warnings are returned per instance path with line and column."
+    )]
+    async fn analyze_scripts(
+        &self,
+        Parameters(args): Parameters<AnalyzeScriptsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let Some(analyzer) = &self.luau_analyzer else {
+            return Ok(tool_result(Err(eyre!(
+                "analyze_scripts requires the server to be started with --luau-analyzer"
+            )
+            .into())));
+        };
+        let entries = match self
+            .snapshot_entries(script_search::script_sources_request(
+                &args.roots,
+                script_search::SCRIPT_CLASSES,
+            ))
+            .await?
+        {
+            Ok(entries) => entries,
+            Err(err) => return Ok(tool_result(Err(err))),
+        };
+        // Selectors only resolve in Studio, so the scripts to report are listed by a snapshot too.
+        let reported = if args.paths.is_empty() {
+            None
+        } else {
+            match self
+                .snapshot_entries(script_search::script_paths_request(
+                    &args.paths,
+                    script_search::SCRIPT_CLASSES,
+                ))
+                .await?
+            {
+                Ok(reported) => Some(reported.into_iter().map(|entry| entry.path).collect()),
+                Err(err) => return Ok(tool_result(Err(err))),
+            }
+        };
+        let result = script_typecheck::analyze(analyzer, &entries, reported.as_ref()).await;
+        Ok(tool_result(result.map_err(Into::into)))
+    }
+
+    #[tool(
+        description = "Lists the versions of a script's source recorded by the server whenever it read or wrote the script, newest first, across server sessions. Use the version ids with diff_script_versions and restore_script_version."
+    )]
+    async fn list_script_versions(
+        &self,
+        Parameters(args): Parameters<ListScriptVersionsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let result = self.script_history().and_then(|history| {
+            history
+                .list(&args.path, args.limit.unwrap_or(20) as usize)
+                .map_err(Into::into)
+        });
+        Ok(tool_result(result))
+    }
+
+    #[tool(
+        description = "Returns a unified diff between two recorded versions of a script, or between a recorded version and the script's current source in Studio."
+    )]
+    async fn diff_script_versions(
+        &self,
+        Parameters(args): Parameters<DiffScriptVersionsRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let history = match self.script_history() {
+            Ok(history) => history,
+            Err(err) => return Ok(tool_result(Err(err))),
+        };
+        let needs_current = script_history::is_current(&args.from)
+            || args.to.as_deref().is_none_or(script_history::is_current);
+        let current = if needs_current {
+            match self.current_source(&args.path).await? {
+                Ok(source) => Some(source),
+                Err(err) => return Ok(tool_result(Err(err))),
+            }
+        } else {
+            None
+        };
+        let result = history.diff(
+            &args.path,
+            &args.from,
+            args.to.as_deref(),
+            current.as_deref(),
+            args.context_lines.unwrap_or(3) as usize,
+        );
+        Ok(tool_result(result.map_err(Into::into)))
+    }
+
+    #[tool(
+        description = "Writes a recorded version of a script's source back to the script. The source being replaced is recorded first, so a restore can itself be undone with another restore."
+    )]
+    async fn restore_script_version(
+        &self,
+        Parameters(args): Parameters<RestoreScriptVersionRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let (version, source) = match self.script_history().and_then(|history| {
+            history
+                .source_of(&args.path, &args.version)
+                .map_err(Into::into)
+        }) {
+            Ok(found) => found,
+            Err(err) => return Ok(tool_result(Err(err))),
+        };
+        let request = ManageScriptsRequest {
+            operations: vec![ScriptOperation::SetSource {
+                path: args.path.clone(),
+                source,
+                expected_source: None,
+                force: args.force,
+                format: false,
+                metadata: None,
+            }],
+            default_metadata: None,
+            atomic: false,
+            dry_run: false,
+        };
+        let result = self
+            .run_manage_scripts(request)
+            .await?
+            .and_then(|response| {
+                let mut response: ManageScriptsResponse =
+                    serde_json::from_str(&response).map_err(|_| eyre!("{response}"))?;
+                let result = response
+                    .results
+                    .pop()
+                    .ok_or_eyre("Studio returned no result for the restore")?;
+                Ok(serde_json::to_string(&serde_json::json!({
+                    "restoredVersion": version,
+                    "result": result,
+                }))?)
+            });
+        Ok(tool_result(result))
+    }
+
+    /// Directory of the server's `--project`, where tool configuration files are looked up.
+    fn project_dir(&self) -> Option<PathBuf> {
+        let project = self.project.as_ref()?;
+        if project.is_dir() {
+            Some(project.clone())
+        } else {
+            project.parent().map(PathBuf::from)
+        }
+    }
+
+    /// Loads the project named in `args`, falling back to the server's `--project`.
+    fn load_project(&self, args: &ProjectSyncOptions) -> color_eyre::Result<RojoProject> {
+        let path = args
+            .project
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| self.project.clone())
+            .ok_or_eyre(
+                "No Rojo project configured; pass `project` or start the server with --project",
+            )?;
+        RojoProject::load(&path)
+    }
+
+    /// Runs `args` after asking the client to confirm any destructive changes it contains.
+    async fn confirmed_tool_run(
+        &self,
+        peer: &Peer<RoleServer>,
+        args: ToolArgumentValues,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Some(aborted) = self.confirm(peer, &args).await {
+            return Ok(aborted);
+        }
+        self.generic_tool_run(args).await
+    }
+
+    /// Asks the client to confirm any destructive changes in `args`, returning the error result to
+    /// send back when the human declines. Clients without elicitation cannot ask the human, so their
+    /// destructive requests are refused. Dry runs change nothing, so they are never confirmed.
+    async fn confirm(
+        &self,
+        peer: &Peer<RoleServer>,
+        args: &ToolArgumentValues,
+    ) -> Option<CallToolResult> {
+        if args.is_dry_run() {
+            return None;
+        }
+        let summary = self.confirmation.summarize(args)?;
+        match confirmation::request_confirmation(peer, &summary).await {
+            confirmation::ConfirmationOutcome::Confirmed => None,
+            confirmation::ConfirmationOutcome::Unsupported => {
+                tracing::info!("Destructive request refused: the client cannot confirm it");
+                Some(CallToolResult::error(vec![Content::text(format!(
+                    "{summary}\n\nThe MCP client cannot show a confirmation prompt, so nothing was sent to Studio. Ask the user to run these changes from a client that supports confirmation prompts, or to restart the server with --no-confirm."
+                ))]))
+            }
+            confirmation::ConfirmationOutcome::Declined(reason) => {
+                tracing::info!("Destructive request aborted: {reason}");
+                Some(CallToolResult::error(vec![Content::text(format!(
+                    "Operation aborted before reaching Studio: {reason}. No changes were made."
+                ))]))
+            }
+        }
     }
 
     async fn generic_tool_run(
         &self,
         args: ToolArgumentValues,
     ) -> Result<CallToolResult, ErrorData> {
-        let (command, id) = ToolArguments::new(args);
+        let result = self.dispatch(args).await?;
+        Ok(tool_result(result))
+    }
+
+    /// Runs `args` against the served place file or the Studio plugin and returns its raw response,
+    /// recording the instance ids it reports. Dry runs report ids of instances that were never
+    /// kept, so they are not recorded.
+    async fn dispatch(&self, args: ToolArgumentValues) -> Result<Result<String>, ErrorData> {
+        let dry_run = args.is_dry_run();
+        let result = self.dispatch_unrecorded(args).await?;
+        Ok(result.map(|response| {
+            if dry_run {
+                dry_run::present_source_changes(response)
+            } else {
+                self.instance_ids.record(&response);
+                response
+            }
+        }))
+    }
+
+    /// Requests sent to Studio are kept in the request log that `change_history` reads.
+    async fn dispatch_unrecorded(
+        &self,
+        args: ToolArgumentValues,
+    ) -> Result<Result<String>, ErrorData> {
+        let resolved_ids = instance_ids::take_resolved();
+        if let Some(place) = &self.place {
+            let mut place = place.lock().await;
+            if let Err(err) = place.check_resolved_ids(&resolved_ids) {
+                return Ok(Err(err.into()));
+            }
+            return Ok(place.run(&args).map_err(Into::into));
+        }
+        let tool = args.tool_name();
+        let (mut command, id) = ToolArguments::new(args);
+        command.resolved_ids = resolved_ids;
         tracing::debug!("Running command: {:?}", command);
         let (tx, mut rx) = mpsc::unbounded_channel::<Result<String>>();
         let trigger = {
@@ -1993,11 +3398,297 @@ impl RBXStudioServer {
             let mut state = self.state.lock().await;
             state.output_map.remove_entry(&id);
         }
-        tracing::debug!("Sending to MCP: {result:?}");
-        match result {
-            Ok(result) => Ok(CallToolResult::success(vec![Content::text(result)])),
-            Err(err) => Ok(CallToolResult::error(vec![Content::text(err.to_string())])),
+        let result = result.and_then(|response| {
+            if response.starts_with(STALE_ID_ERROR) {
+                return Err(eyre!(response).into());
+            }
+            self.requests.record(id, tool, &response);
+            Ok(response)
+        });
+        Ok(result)
+    }
+
+    /// Compares `args.before` with another file, the served place file, or the live session.
+    async fn run_diff_places(
+        &self,
+        args: &DiffPlacesRequest,
+    ) -> Result<Result<PlaceDiff>, ErrorData> {
+        let context_lines = args
+            .context_lines
+            .map_or(DEFAULT_DIFF_CONTEXT_LINES, |lines| lines as usize);
+        let before = match PlaceFile::open(&args.before) {
+            Ok(before) => before,
+            Err(err) => return Ok(Err(err.into())),
+        };
+        if let Some(after) = &args.after {
+            return Ok(PlaceFile::open(after)
+                .map(|after| before.diff(&after, context_lines))
+                .map_err(Into::into));
+        }
+        if let Some(place) = &self.place {
+            return Ok(Ok(before.diff(&*place.lock().await, context_lines)));
         }
+
+        let entries = match self
+            .snapshot_entries(before.live_snapshot_request(&args.live_root))
+            .await?
+        {
+            Ok(entries) => entries,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(Ok(before.diff_live(
+            &entries,
+            &args.live_root,
+            context_lines,
+        )))
+    }
+
+    fn script_history(&self) -> Result<&ScriptHistory> {
+        self.history.as_deref().ok_or_else(|| {
+            eyre!("Script history is disabled; start the server without --no-history").into()
+        })
+    }
+
+    fn template_store(&self) -> color_eyre::Result<&TemplateStore> {
+        self.templates.as_deref().ok_or_else(|| {
+            eyre!("No template directory is available; start the server with --templates-dir")
+        })
+    }
+
+    /// Runs a `manage_templates` request. Saving from the place fetches the model from the place
+    /// file or through the plugin's `export_instances` operation.
+    async fn run_manage_templates(
+        &self,
+        args: ManageTemplatesRequest,
+    ) -> Result<Result<String>, ErrorData> {
+        let store = match args.validate().and_then(|()| self.template_store()) {
+            Ok(store) => store,
+            Err(err) => return Ok(Err(err.into())),
+        };
+        let name = args.name.as_deref().unwrap_or_default();
+        let result = match args.action {
+            TemplateAction::List => store.list().map(|templates| {
+                serde_json::json!({
+                    "action": args.action,
+                    "summary": format!("{} saved template{}", templates.len(), if templates.len() == 1 { "" } else { "s" }),
+                    "templates": templates,
+                })
+            }),
+            TemplateAction::Get => store.get(name).map(|template| {
+                serde_json::json!({ "action": args.action, "template": template })
+            }),
+            TemplateAction::Delete => store.delete(name).map(|template| {
+                serde_json::json!({
+                    "action": args.action,
+                    "summary": format!("Deleted template '{name}'"),
+                    "template": template,
+                })
+            }),
+            TemplateAction::Save => {
+                let model = match (&args.instance_path, &args.file_path) {
+                    (Some(path), _) => match store.check_save(name, args.overwrite) {
+                        Ok(()) => self.template_model(path).await?,
+                        Err(err) => Err(err),
+                    },
+                    (None, Some(file_path)) => templates::read_model_file(Path::new(file_path)),
+                    (None, None) => unreachable!("validated above"),
+                };
+                let source = match (&args.instance_path, &args.file_path) {
+                    (Some(path), _) => path.join("."),
+                    (None, file_path) => file_path.clone().unwrap_or_default(),
+                };
+                model
+                    .and_then(|model| {
+                        store.save(name, args.description.clone(), source, &model, args.overwrite)
+                    })
+                    .map(|template| {
+                        serde_json::json!({
+                            "action": args.action,
+                            "summary": format!(
+                                "Saved template '{name}' ({} instances, parameters: {})",
+                                template.instance_count,
+                                if template.parameters.is_empty() { "none".to_string() } else { template.parameters.join(", ") }
+                            ),
+                            "template": template,
+                        })
+                    })
+            }
+        };
+        Ok(result
+            .map(|response| response.to_string())
+            .map_err(Into::into))
+    }
+
+    /// Serialises the instance at `path` as a template model.
+    async fn template_model(
+        &self,
+        path: &[String],
+    ) -> Result<color_eyre::Result<Vec<u8>>, ErrorData> {
+        if let Some(place) = &self.place {
+            return Ok(place.lock().await.export_template(path));
+        }
+        let request = AssetPipelineRequest {
+            operations: vec![AssetPipelineOperation::ExportInstances {
+                instance_paths: vec![InstanceTarget::Path(path.to_vec())],
+                file_path: String::new(),
+                overwrite: false,
+            }],
+            ..AssetPipelineRequest::default()
+        };
+        Ok(self
+            .dispatch(ToolArgumentValues::AssetPipeline(request))
+            .await?
+            .map_err(|err| eyre!("{err}"))
+            .and_then(|response| export::exported_model(&response)))
+    }
+
+    /// Fetches the full source of the script at `path` through `manage_scripts`.
+    async fn current_source(&self, path: &[String]) -> Result<Result<String>, ErrorData> {
+        let request = ManageScriptsRequest {
+            operations: vec![ScriptOperation::GetSource {
+                path: path.to_vec(),
+                start_line: None,
+                end_line: None,
+                metadata: None,
+            }],
+            default_metadata: None,
+            atomic: false,
+            dry_run: false,
+        };
+        Ok(self.dispatch_scripts(request).await?.and_then(|response| {
+            let response: ManageScriptsResponse =
+                serde_json::from_str(&response).map_err(|_| eyre!("{response}"))?;
+            match response.results.into_iter().next() {
+                Some(result) if result.success => Ok(result.source.unwrap_or_default()),
+                Some(result) => Err(eyre!(result.message.unwrap_or_default()).into()),
+                None => Err(eyre!("Studio returned no result for the source").into()),
+            }
+        }))
+    }
+
+    /// Dispatches a `manage_scripts` request and records the sources it read or wrote in the
+    /// script history.
+    async fn dispatch_scripts(
+        &self,
+        request: ManageScriptsRequest,
+    ) -> Result<Result<String>, ErrorData> {
+        let response = self
+            .dispatch(ToolArgumentValues::ManageScripts(request.clone()))
+            .await?;
+        if request.dry_run {
+            return Ok(response);
+        }
+        if let (Some(history), Ok(response)) = (&self.history, &response) {
+            if let Ok(parsed) = serde_json::from_str::<ManageScriptsResponse>(response) {
+                if let Err(err) = history.record_batch(&request, &parsed) {
+                    tracing::warn!("Failed to record script history: {err:#}");
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    /// Runs a `manage_scripts` batch: fetches the sources it edits, formats, parses, and lints
+    /// them, and sends what remains to the place file or Studio.
+    async fn run_manage_scripts(
+        &self,
+        args: ManageScriptsRequest,
+    ) -> Result<Result<String>, ErrorData> {
+        if let Some(prior) = self
+            .history
+            .as_ref()
+            .filter(|_| !args.dry_run)
+            .and_then(|_| script_history::prior_sources_request(&args))
+        {
+            // Only recorded; scripts that do not exist yet simply fail to read.
+            self.dispatch_scripts(prior).await?.ok();
+        }
+        let fetched = match script_analysis::source_fetch_request(&args) {
+            Some(fetch) => match self.dispatch_scripts(fetch).await? {
+                Ok(response) => Some(response),
+                Err(err) => return Ok(Err(err)),
+            },
+            None => None,
+        };
+        let config_dir = self.project_dir();
+        let batch = match ScriptBatch::prepare(
+            &args,
+            fetched.as_deref(),
+            self.selene.as_deref(),
+            config_dir.as_deref(),
+        )
+        .await
+        {
+            Ok(batch) => batch,
+            Err(err) => return Ok(Err(err.into())),
+        };
+        if batch.is_passthrough() {
+            return self.dispatch_scripts(args).await;
+        }
+        let response = match batch.forwarded(&args) {
+            Some(forwarded) => match self.dispatch_scripts(forwarded).await? {
+                Ok(response) => Some(response),
+                Err(err) => return Ok(Err(err)),
+            },
+            None => None,
+        };
+        Ok(batch.merge(response.as_deref()).map_err(Into::into))
+    }
+
+    /// Retakes the snapshot cached under `since` and compares the two, caching the new one.
+    async fn snapshot_since(&self, since: &str) -> Result<Result<String>, ErrorData> {
+        let (request, before) = match self.snapshots.get(since) {
+            Ok(snapshot) => snapshot,
+            Err(err) => return Ok(Err(err.into())),
+        };
+        let after = match self.snapshot_entries(request.clone()).await? {
+            Ok(entries) => entries,
+            Err(err) => return Ok(Err(err)),
+        };
+        let delta = snapshot_cache::delta(
+            since,
+            self.snapshots.store(request, after.clone()),
+            &before,
+            &after,
+        );
+        Ok(serde_json::to_string(&delta).map_err(Into::into))
+    }
+
+    /// Runs a data model snapshot, following page cursors until every entry has been read.
+    async fn snapshot_entries(
+        &self,
+        mut request: DataModelSnapshotRequest,
+    ) -> Result<Result<Vec<DataModelSnapshotInstance>>, ErrorData> {
+        let mut entries = Vec::new();
+        loop {
+            let response = match self
+                .dispatch(ToolArgumentValues::DataModelSnapshot(request.clone()))
+                .await?
+            {
+                Ok(response) => response,
+                Err(err) => return Ok(Err(err)),
+            };
+            let page: DataModelSnapshotResponse = match serde_json::from_str(&response) {
+                Ok(page) => page,
+                Err(err) => {
+                    return Ok(Err(eyre!("Unable to parse snapshot: {err}").into()));
+                }
+            };
+            entries.extend(page.entries);
+            match page.next_cursor {
+                Some(cursor) if page.truncated => request.page_cursor = Some(cursor),
+                _ => return Ok(Ok(entries)),
+            }
+        }
+    }
+}
+
+/// Converts a tool's raw response into the result sent to the MCP client.
+fn tool_result(result: Result<String>) -> CallToolResult {
+    tracing::debug!("Sending to MCP: {result:?}");
+    match result {
+        Ok(result) => CallToolResult::success(vec![Content::text(result)]),
+        Err(err) => CallToolResult::error(vec![Content::text(err.to_string())]),
     }
 }
 
@@ -2034,6 +3725,15 @@ pub async fn response_handler(
     Ok(tx.send(Ok(payload.response))?)
 }
 
+/// Receives the events the plugin's change feed posts between `watch_changes` calls.
+pub async fn events_handler(
+    State(state): State<PackedState>,
+    Json(batch): Json<ChangeBatch>,
+) -> Result<impl IntoResponse> {
+    state.lock().await.changes.push(batch);
+    Ok(StatusCode::OK)
+}
+
 pub async fn proxy_handler(
     State(state): State<PackedState>,
     Json(command): Json<ToolArguments>,
@@ -2253,7 +3953,7 @@ mod tests {
                 .expect("proxy command was not enqueued");
             assert_eq!(queued.id, Some(id));
             assert!(
-                locked.output_map.get(&id).is_none(),
+                !locked.output_map.contains_key(&id),
                 "proxy handler did not clean up output sender"
             );
         }
@@ -0,0 +1,348 @@
+//! Live change feed. Once `watch_changes` has started it, the plugin listens for instances being
+//! added, removed, and changed under the watched roots and posts the events to `/events` in
+//! batches. The server numbers them with a cursor and keeps the most recent ones in a ring buffer
+//! that `watch_changes` polls, and tells MCP clients subscribed to `studio://changes` when new
+//! events arrive. Events caused by an MCP request carry its `requestId`, so agents can tell their
+//! own edits apart from the ones a person made in Studio.
+
+use super::InstanceTarget;
+use color_eyre::eyre::{bail, Result};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use tokio::sync::watch;
+
+/// URI of the MCP resource that mirrors the feed.
+pub(super) const CHANGES_URI: &str = "studio://changes";
+/// Events kept for polling; older ones are reported as missed.
+const BUFFERED_EVENTS: usize = 1000;
+/// Events returned by one poll unless `limit` says otherwise.
+const DEFAULT_EVENT_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ChangeEventKind {
+    /// The instance was added under a watched root.
+    Added,
+    /// The instance is being removed from under a watched root.
+    Removed,
+    /// A property of the instance changed; see `property` and `value`.
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+    /// Position of the event in the feed, assigned by the server.
+    #[serde(default)]
+    cursor: u64,
+    kind: ChangeEventKind,
+    path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    class_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    property: Option<String>,
+    /// New value of `property` in the RobloxValue encoding. Omitted for script sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<JsonValue>,
+    /// The MCP request being handled when the change was made, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Unix time of the change, in seconds.
+    #[serde(default)]
+    timestamp: f64,
+}
+
+/// Events posted by the plugin. `dropped` counts the events it discarded because too many
+/// piled up between two posts.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ChangeBatch {
+    events: Vec<ChangeEvent>,
+    dropped: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(super) struct WatchChangesRequest {
+    #[schemars(
+        description = "Instances whose descendants to watch, replacing the current roots. Defaults to the current roots, or Workspace, Lighting, ReplicatedFirst, ReplicatedStorage, ServerScriptService, ServerStorage, StarterGui, StarterPack, and StarterPlayer when the feed is not running yet."
+    )]
+    roots: Option<Vec<InstanceTarget>>,
+    #[schemars(
+        description = "Cursor returned by the previous call; only later events are returned. Omit it to get every buffered event."
+    )]
+    pub(super) cursor: Option<u64>,
+    #[schemars(description = "Maximum number of events to return. Defaults to 200.")]
+    pub(super) limit: Option<u32>,
+    #[schemars(
+        description = "Leave out changes made while handling MCP requests, so only edits made in Studio are returned."
+    )]
+    pub(super) exclude_mcp_changes: Option<bool>,
+    #[schemars(description = "Stop watching. Events already buffered are still returned.")]
+    stop: Option<bool>,
+}
+
+impl WatchChangesRequest {
+    pub(super) fn validate(&self) -> Result<()> {
+        if self.limit == Some(0) {
+            bail!("limit must be at least 1");
+        }
+        if self.stop == Some(true) && self.roots.is_some() {
+            bail!("roots cannot be set when stopping the feed");
+        }
+        Ok(())
+    }
+
+    /// What the plugin's `WatchChanges` tool needs to start, keep, or stop the feed.
+    pub(super) fn payload(&self) -> WatchChangesPayload {
+        WatchChangesPayload {
+            roots: self.roots.clone(),
+            stop: self.stop.unwrap_or(false),
+        }
+    }
+}
+
+/// Request forwarded to the plugin's `WatchChanges` tool.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct WatchChangesPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    roots: Option<Vec<InstanceTarget>>,
+    #[serde(default)]
+    stop: bool,
+}
+
+/// The plugin's answer to `WatchChanges`, including the events it had not posted yet.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub(super) struct WatchStatus {
+    pub(super) roots: Vec<Vec<String>>,
+    pub(super) watching: bool,
+    #[serde(flatten)]
+    pub(super) pending: ChangeBatch,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct WatchChangesResponse {
+    events: Vec<ChangeEvent>,
+    /// Pass as `cursor` to continue after these events.
+    cursor: u64,
+    /// Events after the given cursor that are no longer available, because they fell out of the
+    /// buffer or the plugin dropped them.
+    missed: u64,
+    /// True when `limit` cut the events short; poll again with `cursor` for the rest.
+    more: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<Vec<String>>,
+    watching: bool,
+    summary: String,
+}
+
+/// Numbered events, most recent last.
+#[derive(Debug)]
+pub(super) struct ChangeFeed {
+    events: VecDeque<ChangeEvent>,
+    /// Cursor of the latest event, counting dropped ones.
+    latest: u64,
+    updates: watch::Sender<u64>,
+}
+
+impl ChangeFeed {
+    pub(super) fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+            latest: 0,
+            updates: watch::Sender::new(0),
+        }
+    }
+
+    /// Numbers and buffers a batch of events, notifying subscribers when it held any.
+    pub(super) fn push(&mut self, batch: ChangeBatch) {
+        if batch.events.is_empty() && batch.dropped == 0 {
+            return;
+        }
+        for mut event in batch.events {
+            self.latest += 1;
+            event.cursor = self.latest;
+            if self.events.len() == BUFFERED_EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(event);
+        }
+        self.latest += batch.dropped;
+        self.updates.send_replace(self.latest);
+    }
+
+    /// Changes to the latest cursor, for resource subscriptions.
+    pub(super) fn subscribe(&self) -> watch::Receiver<u64> {
+        self.updates.subscribe()
+    }
+
+    /// Events after `cursor`, up to `limit` of them. A cursor from an earlier server session is
+    /// treated as the start of the feed.
+    pub(super) fn poll(
+        &self,
+        cursor: Option<u64>,
+        limit: Option<u32>,
+        exclude_mcp_changes: bool,
+    ) -> WatchChangesResponse {
+        let since = cursor.filter(|cursor| *cursor <= self.latest).unwrap_or(0);
+        let limit = limit.map_or(DEFAULT_EVENT_LIMIT, |limit| limit as usize);
+        let mut events = Vec::new();
+        let mut skipped = 0;
+        let mut next = self.latest;
+        let mut more = false;
+        for event in self.events.iter().filter(|event| event.cursor > since) {
+            if events.len() == limit {
+                more = true;
+                break;
+            }
+            if exclude_mcp_changes && event.request_id.is_some() {
+                skipped += 1;
+            } else {
+                events.push(event.clone());
+            }
+            next = event.cursor;
+        }
+        if !more {
+            next = self.latest;
+        }
+        let missed = next - since - events.len() as u64 - skipped;
+        let summary = match (events.len(), missed) {
+            (0, 0) => "No changes".to_string(),
+            (count, 0) => format!("{count} change{}", if count == 1 { "" } else { "s" }),
+            (count, missed) => format!(
+                "{count} change{}, {missed} missed",
+                if count == 1 { "" } else { "s" }
+            ),
+        };
+        WatchChangesResponse {
+            events,
+            cursor: next,
+            missed,
+            more,
+            roots: Vec::new(),
+            watching: false,
+            summary,
+        }
+    }
+
+    /// The most recent events, for reading the `studio://changes` resource.
+    pub(super) fn recent(&self) -> WatchChangesResponse {
+        let cursor = self
+            .events
+            .iter()
+            .rev()
+            .nth(DEFAULT_EVENT_LIMIT)
+            .map(|event| event.cursor);
+        self.poll(cursor, None, false)
+    }
+}
+
+impl WatchChangesResponse {
+    /// Adds what the plugin reported about the watched roots.
+    pub(super) fn with_status(self, status: &WatchStatus) -> Self {
+        let summary = if status.watching {
+            format!(
+                "{} under {} root{}",
+                self.summary,
+                status.roots.len(),
+                if status.roots.len() == 1 { "" } else { "s" }
+            )
+        } else {
+            format!("{}; not watching", self.summary)
+        };
+        Self {
+            roots: status.roots.clone(),
+            watching: status.watching,
+            summary,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn batch(events: &[(&str, Option<&str>)], dropped: u64) -> ChangeBatch {
+        serde_json::from_value(json!({
+            "events": events
+                .iter()
+                .map(|(name, request_id)| json!({
+                    "kind": "changed",
+                    "path": ["Workspace", name],
+                    "className": "Part",
+                    "property": "Color",
+                    "requestId": request_id,
+                }))
+                .collect::<Vec<_>>(),
+            "dropped": dropped,
+        }))
+        .expect("batch")
+    }
+
+    fn names(response: &WatchChangesResponse) -> Vec<&str> {
+        response
+            .events
+            .iter()
+            .map(|event| event.path[1].as_str())
+            .collect()
+    }
+
+    /// Six events: A, B (made by a request), C, two dropped by the plugin, then D.
+    fn sample_feed() -> ChangeFeed {
+        let mut feed = ChangeFeed::new();
+        feed.push(batch(
+            &[("A", None), ("B", Some("request")), ("C", None)],
+            2,
+        ));
+        feed.push(batch(&[("D", None)], 0));
+        feed
+    }
+
+    #[test]
+    fn numbers_events_and_notifies_subscribers() {
+        let mut feed = ChangeFeed::new();
+        let mut updates = feed.subscribe();
+        feed.push(batch(&[], 0));
+        assert!(!updates.has_changed().expect("sender alive"));
+
+        feed.push(batch(&[("A", None), ("B", None)], 3));
+        assert!(updates.has_changed().expect("sender alive"));
+        assert_eq!(*updates.borrow_and_update(), 5);
+    }
+
+    #[test]
+    fn limits_polls_and_continues_from_the_cursor() {
+        let feed = sample_feed();
+        let first = feed.poll(None, Some(2), false);
+        assert_eq!(names(&first), vec!["A", "B"]);
+        assert_eq!((first.cursor, first.more, first.missed), (2, true, 0));
+
+        let rest = feed.poll(Some(first.cursor), None, false);
+        assert_eq!(names(&rest), vec!["C", "D"]);
+        assert_eq!(rest.events[1].cursor, 6);
+        assert_eq!((rest.cursor, rest.more, rest.missed), (6, false, 2));
+        assert!(feed.poll(Some(6), None, false).events.is_empty());
+    }
+
+    #[test]
+    fn leaves_out_mcp_changes_on_request() {
+        let feed = sample_feed();
+        let response = feed.poll(None, None, true);
+        assert_eq!(names(&response), vec!["A", "C", "D"]);
+        assert_eq!(response.missed, 2);
+    }
+
+    #[test]
+    fn cursors_from_an_earlier_session_start_over() {
+        let feed = sample_feed();
+        assert_eq!(feed.poll(Some(60), None, false).events.len(), 4);
+    }
+}
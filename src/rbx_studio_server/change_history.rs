@@ -0,0 +1,201 @@
+//! Undo and redo through Studio's change history. The plugin commits each request that changes
+//! the place as one `ChangeHistoryService` recording named `StudioMCP:<request id>`, so it can walk
+//! the undo stack by request. The server keeps the tool name and summary of recent requests and
+//! adds them to the entries the plugin reports, which lets agents pick what to revert.
+
+use color_eyre::eyre::{bail, Result};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Requests remembered for `change_history` responses; older ones are reported without a summary.
+const LOGGED_REQUESTS: usize = 256;
+/// Summaries taken from responses without a `summary` field are cut to this many characters.
+const SUMMARY_CHARS: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ChangeHistoryAction {
+    /// Undo the most recent `count` changes made through MCP.
+    Undo,
+    /// Redo the most recently undone `count` changes made through MCP.
+    Redo,
+    /// List the most recent changes made through MCP, newest first.
+    ListRecent,
+    /// Undo changes made through MCP until the one made by `requestId` has been undone.
+    UndoUntil,
+}
+
+/// Request forwarded to the plugin's `ChangeHistory` tool.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ChangeHistoryRequest {
+    pub(super) action: ChangeHistoryAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Number of changes to undo or redo. Defaults to 1.")]
+    pub(super) count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "Request id of the change undo_until stops after, as reported by list_recent"
+    )]
+    pub(super) request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Maximum number of entries list_recent returns. Defaults to 20.")]
+    pub(super) limit: Option<u32>,
+}
+
+impl ChangeHistoryRequest {
+    pub(super) fn validate(&self) -> Result<()> {
+        match (self.action, &self.request_id) {
+            (ChangeHistoryAction::UndoUntil, None) => bail!("undo_until requires a requestId"),
+            (ChangeHistoryAction::UndoUntil, _) | (_, None) => {}
+            (action, Some(_)) => bail!("requestId is only used by undo_until, not {action:?}"),
+        }
+        if self.count == Some(0) {
+            bail!("count must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LoggedRequest {
+    request_id: String,
+    tool: &'static str,
+    summary: Option<String>,
+}
+
+/// The tool name and summary of the requests recently sent to Studio.
+#[derive(Debug, Default)]
+pub(super) struct RequestLog {
+    requests: Mutex<VecDeque<LoggedRequest>>,
+}
+
+impl RequestLog {
+    pub(super) fn record(&self, request_id: Uuid, tool: &'static str, response: &str) {
+        let mut requests = self.requests.lock().expect("request log poisoned");
+        if requests.len() == LOGGED_REQUESTS {
+            requests.pop_front();
+        }
+        requests.push_back(LoggedRequest {
+            request_id: request_id.to_string(),
+            tool,
+            summary: summarize(response),
+        });
+    }
+
+    /// Adds the MCP tool name and summary of each request listed in a `ChangeHistory` response.
+    pub(super) fn annotate(&self, response: String) -> String {
+        let Ok(mut parsed) = serde_json::from_str::<JsonValue>(&response) else {
+            return response;
+        };
+        let requests = self.requests.lock().expect("request log poisoned");
+        for key in ["entries", "undone", "redone"] {
+            let Some(entries) = parsed.get_mut(key).and_then(JsonValue::as_array_mut) else {
+                continue;
+            };
+            for entry in entries.iter_mut().filter_map(JsonValue::as_object_mut) {
+                let Some(logged) = entry
+                    .get("requestId")
+                    .and_then(JsonValue::as_str)
+                    .and_then(|id| requests.iter().rev().find(|logged| logged.request_id == id))
+                else {
+                    continue;
+                };
+                entry.insert("tool".into(), logged.tool.into());
+                if let Some(summary) = &logged.summary {
+                    entry.insert("summary".into(), summary.as_str().into());
+                }
+            }
+        }
+        parsed.to_string()
+    }
+}
+
+/// The `summary` of a JSON response, or else the first line of a plain one.
+fn summarize(response: &str) -> Option<String> {
+    if let Ok(parsed) = serde_json::from_str::<JsonValue>(response) {
+        return parsed
+            .get("summary")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string);
+    }
+    let line = response.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.chars().take(SUMMARY_CHARS).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn annotates_plugin_entries_with_logged_requests() {
+        let log = RequestLog::default();
+        let applied = Uuid::new_v4();
+        let ran = Uuid::new_v4();
+        log.record(
+            applied,
+            "apply_instance_operations",
+            &json!({ "summary": "Applied 2 of 2 operations", "writeOccurred": true }).to_string(),
+        );
+        log.record(ran, "run_code", "Created 4 parts\nDone");
+
+        let response = log.annotate(
+            json!({
+                "action": "undo",
+                "undone": [
+                    { "requestId": ran.to_string(), "tool": "RunCode" },
+                    { "requestId": applied.to_string(), "tool": "ApplyInstanceOperations" },
+                    { "requestId": "from-an-earlier-session", "tool": "ManageScripts" },
+                ],
+            })
+            .to_string(),
+        );
+        assert_eq!(
+            serde_json::from_str::<JsonValue>(&response).expect("json"),
+            json!({
+                "action": "undo",
+                "undone": [
+                    { "requestId": ran.to_string(), "tool": "run_code", "summary": "Created 4 parts" },
+                    { "requestId": applied.to_string(), "tool": "apply_instance_operations",
+                      "summary": "Applied 2 of 2 operations" },
+                    { "requestId": "from-an-earlier-session", "tool": "ManageScripts" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn validates_request_ids_and_counts() {
+        let request = |action, count, request_id: Option<&str>| ChangeHistoryRequest {
+            action,
+            count,
+            request_id: request_id.map(str::to_string),
+            limit: None,
+        };
+        let err = |request: ChangeHistoryRequest| {
+            request.validate().expect_err("invalid request").to_string()
+        };
+
+        assert_eq!(
+            err(request(ChangeHistoryAction::UndoUntil, None, None)),
+            "undo_until requires a requestId"
+        );
+        assert!(err(request(ChangeHistoryAction::Undo, None, Some("abc")))
+            .starts_with("requestId is only used by undo_until"));
+        assert_eq!(
+            err(request(ChangeHistoryAction::Undo, Some(0), None)),
+            "count must be at least 1"
+        );
+        assert!(request(ChangeHistoryAction::UndoUntil, None, Some("abc"))
+            .validate()
+            .is_ok());
+        assert!(request(ChangeHistoryAction::Redo, Some(2), None)
+            .validate()
+            .is_ok());
+    }
+}
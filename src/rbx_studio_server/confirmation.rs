@@ -0,0 +1,310 @@
+use super::{
+    AssetPipelineOperation, InstanceOperation, ProjectSyncAction, TerrainOperation,
+    ToolArgumentValues,
+};
+use rmcp::{elicit_safe, schemars, service::ElicitationError, Peer, RoleServer};
+use serde::{Deserialize, Serialize};
+
+/// Number of deletes in a single `apply_instance_operations` batch that triggers a prompt.
+pub const DEFAULT_DELETE_CONFIRMATION_THRESHOLD: usize = 10;
+
+/// Maximum number of paths listed in a confirmation message before the rest are elided.
+const MAX_LISTED_PATHS: usize = 8;
+
+/// Controls which destructive requests require the human to confirm them through MCP elicitation.
+#[derive(Debug, Clone)]
+pub struct ConfirmationPolicy {
+    /// Prompt when an instance batch deletes at least this many instances. `None` disables the
+    /// delete prompt entirely.
+    pub delete_threshold: Option<usize>,
+    /// Prompt before clearing terrain without explicit region corners.
+    pub confirm_full_terrain_clear: bool,
+    /// Prompt before publishing packages.
+    pub confirm_package_publish: bool,
+    /// Prompt before a filesystem sync prunes scripts or files missing from its source.
+    pub confirm_prune: bool,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self {
+            delete_threshold: Some(DEFAULT_DELETE_CONFIRMATION_THRESHOLD),
+            confirm_full_terrain_clear: true,
+            confirm_package_publish: true,
+            confirm_prune: true,
+        }
+    }
+}
+
+impl ConfirmationPolicy {
+    /// Policy that never prompts.
+    pub fn disabled() -> Self {
+        Self {
+            delete_threshold: None,
+            confirm_full_terrain_clear: false,
+            confirm_package_publish: false,
+            confirm_prune: false,
+        }
+    }
+
+    /// Returns a human readable summary of the destructive changes in `args`, or `None` when the
+    /// request does not need confirmation under this policy.
+    pub(super) fn summarize(&self, args: &ToolArgumentValues) -> Option<String> {
+        let lines = match args {
+            ToolArgumentValues::ApplyInstanceOperations(request) => {
+                self.summarize_deletes(&request.operations)
+            }
+            ToolArgumentValues::TerrainOperations(request) => {
+                self.summarize_terrain(&request.operations)
+            }
+            ToolArgumentValues::AssetPipeline(request) => {
+                self.summarize_publishes(&request.operations)
+            }
+            ToolArgumentValues::ProjectSync(request) if request.prune && self.confirm_prune => {
+                vec![match request.action {
+                    ProjectSyncAction::Push => {
+                        "- Delete scripts and folders in Studio that are not in the Rojo project"
+                    }
+                    ProjectSyncAction::Pull => {
+                        "- Delete script files and folders in the Rojo project that are not in Studio"
+                    }
+                }
+                .to_string()]
+            }
+            _ => Vec::new(),
+        };
+        if lines.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "The agent is requesting a destructive change in Roblox Studio:\n{}\n\nConfirm to proceed.",
+            lines.join("\n")
+        ))
+    }
+
+    fn summarize_deletes(&self, operations: &[InstanceOperation]) -> Vec<String> {
+        let Some(threshold) = self.delete_threshold else {
+            return Vec::new();
+        };
+        let paths: Vec<&Vec<String>> = operations
+            .iter()
+            .filter_map(|operation| match operation {
+                InstanceOperation::Delete(delete) => Some(&delete.path),
+                _ => None,
+            })
+            .collect();
+        if paths.is_empty() || paths.len() < threshold {
+            return Vec::new();
+        }
+        let mut lines = vec![format!("- Delete {} instances:", paths.len())];
+        lines.extend(
+            paths
+                .iter()
+                .take(MAX_LISTED_PATHS)
+                .map(|path| format!("  - {}", path.join("."))),
+        );
+        if paths.len() > MAX_LISTED_PATHS {
+            lines.push(format!(
+                "  - ...and {} more",
+                paths.len() - MAX_LISTED_PATHS
+            ));
+        }
+        lines
+    }
+
+    fn summarize_terrain(&self, operations: &[TerrainOperation]) -> Vec<String> {
+        if !self.confirm_full_terrain_clear {
+            return Vec::new();
+        }
+        let full_clears = operations
+            .iter()
+            .filter(|operation| {
+                matches!(
+                    operation,
+                    TerrainOperation::ClearRegion(clear)
+                        if clear.corner_min.is_none() && clear.corner_max.is_none()
+                )
+            })
+            .count();
+        if full_clears == 0 {
+            return Vec::new();
+        }
+        vec!["- Clear ALL terrain in Workspace.Terrain (no region corners supplied)".to_string()]
+    }
+
+    fn summarize_publishes(&self, operations: &[AssetPipelineOperation]) -> Vec<String> {
+        if !self.confirm_package_publish {
+            return Vec::new();
+        }
+        operations
+            .iter()
+            .filter_map(|operation| match operation {
+                AssetPipelineOperation::PublishPackage {
+                    instance_path,
+                    publish,
+                } => Some(format!(
+                    "- Publish {} as package '{}'{}{}",
+                    instance_path.join("."),
+                    publish.package_name,
+                    publish
+                        .group_id
+                        .map(|group| format!(" to group {group}"))
+                        .unwrap_or_default(),
+                    if publish.allow_overwrite {
+                        " (overwriting any existing package)"
+                    } else {
+                        ""
+                    }
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema)]
+struct DestructiveOperationConfirmation {
+    #[schemars(description = "Set to true to allow the operation to run")]
+    confirm: bool,
+}
+
+elicit_safe!(DestructiveOperationConfirmation);
+
+/// Outcome of asking the client to confirm a destructive request.
+#[derive(Debug)]
+pub(super) enum ConfirmationOutcome {
+    Confirmed,
+    /// The client does not implement elicitation, so the human could not be asked.
+    Unsupported,
+    Declined(String),
+}
+
+pub(super) async fn request_confirmation(
+    peer: &Peer<RoleServer>,
+    summary: &str,
+) -> ConfirmationOutcome {
+    match peer
+        .elicit::<DestructiveOperationConfirmation>(summary)
+        .await
+    {
+        Ok(Some(DestructiveOperationConfirmation { confirm: true })) => {
+            ConfirmationOutcome::Confirmed
+        }
+        Ok(_) => ConfirmationOutcome::Declined("the user did not confirm".to_string()),
+        Err(ElicitationError::CapabilityNotSupported) => {
+            tracing::warn!("MCP client does not support elicitation; cannot confirm the request");
+            ConfirmationOutcome::Unsupported
+        }
+        Err(ElicitationError::UserDeclined) => {
+            ConfirmationOutcome::Declined("the user declined".to_string())
+        }
+        Err(ElicitationError::UserCancelled) => {
+            ConfirmationOutcome::Declined("the user dismissed the confirmation".to_string())
+        }
+        Err(error) => ConfirmationOutcome::Declined(format!("confirmation failed: {error}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        ApplyInstanceOperationsRequest, AssetPipelineRequest, DeleteInstanceOperation,
+        PackagePublishRequest, ProjectSyncRequest, TerrainClearRegionOperation,
+        TerrainOperationsRequest,
+    };
+    use super::*;
+
+    fn delete_batch(count: usize) -> ToolArgumentValues {
+        ToolArgumentValues::ApplyInstanceOperations(ApplyInstanceOperationsRequest {
+            operations: (0..count)
+                .map(|index| {
+                    InstanceOperation::Delete(DeleteInstanceOperation {
+                        path: vec!["Workspace".to_string(), format!("Part{index}")],
+                    })
+                })
+                .collect(),
+            atomic: false,
+            dry_run: false,
+        })
+    }
+
+    #[test]
+    fn deletes_below_threshold_do_not_prompt() {
+        let policy = ConfirmationPolicy {
+            delete_threshold: Some(3),
+            ..ConfirmationPolicy::default()
+        };
+        assert!(policy.summarize(&delete_batch(2)).is_none());
+    }
+
+    #[test]
+    fn deletes_at_threshold_list_paths() {
+        let policy = ConfirmationPolicy {
+            delete_threshold: Some(3),
+            ..ConfirmationPolicy::default()
+        };
+        let summary = policy.summarize(&delete_batch(12)).expect("summary");
+        assert!(summary.contains("Delete 12 instances"));
+        assert!(summary.contains("Workspace.Part0"));
+        assert!(summary.contains("...and 4 more"));
+    }
+
+    #[test]
+    fn clear_region_without_corners_prompts() {
+        let request = |corner: Option<[i16; 3]>| {
+            ToolArgumentValues::TerrainOperations(TerrainOperationsRequest {
+                operations: vec![TerrainOperation::ClearRegion(TerrainClearRegionOperation {
+                    corner_min: corner,
+                    corner_max: corner,
+                    ..Default::default()
+                })],
+                pivot: None,
+                dry_run: false,
+            })
+        };
+        let policy = ConfirmationPolicy::default();
+        assert!(policy.summarize(&request(None)).is_some());
+        assert!(policy.summarize(&request(Some([0, 0, 0]))).is_none());
+    }
+
+    #[test]
+    fn publish_package_prompts_unless_disabled() {
+        let request = ToolArgumentValues::AssetPipeline(AssetPipelineRequest {
+            operations: vec![AssetPipelineOperation::PublishPackage {
+                instance_path: vec!["Workspace".to_string(), "Door".to_string()],
+                publish: PackagePublishRequest {
+                    package_name: "Door".to_string(),
+                    ..Default::default()
+                },
+            }],
+            ..Default::default()
+        });
+        let summary = ConfirmationPolicy::default()
+            .summarize(&request)
+            .expect("summary");
+        assert!(summary.contains("Publish Workspace.Door as package 'Door'"));
+        assert!(ConfirmationPolicy::disabled().summarize(&request).is_none());
+    }
+
+    #[test]
+    fn prune_prompts_regardless_of_delete_threshold() {
+        let request = |prune: bool| {
+            ToolArgumentValues::ProjectSync(ProjectSyncRequest {
+                action: ProjectSyncAction::Push,
+                nodes: Vec::new(),
+                roots: Vec::new(),
+                prune,
+            })
+        };
+        let policy = ConfirmationPolicy {
+            delete_threshold: None,
+            ..ConfirmationPolicy::default()
+        };
+        let summary = policy.summarize(&request(true)).expect("summary");
+        assert!(summary.contains("not in the Rojo project"));
+        assert!(policy.summarize(&request(false)).is_none());
+        assert!(ConfirmationPolicy::disabled()
+            .summarize(&request(true))
+            .is_none());
+    }
+}
@@ -0,0 +1,132 @@
+//! Dry runs of the mutating tools. With `dryRun: true` a batch is resolved and validated, and what
+//! it would do is planned without writing anything: the plugin keeps the planned state beside the
+//! DataModel, so later operations in the batch see what earlier ones would have done, and a place
+//! file runs the batch on a copy of its instance tree. The response is the tool's usual one plus
+//! the changes the batch would make, with before and after values in the RobloxValue encoding.
+
+use super::DEFAULT_DIFF_CONTEXT_LINES;
+use color_eyre::eyre::{eyre, Result};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use similar::TextDiff;
+
+/// Schema description of the `dry_run` flag on the tools that support it.
+pub(super) const DESCRIPTION: &str =
+    "Validate and plan the batch without applying it, and report the changes it would make";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ChangeKind {
+    /// The instance would be created.
+    Created,
+    /// The instance and its descendants would be destroyed.
+    Deleted,
+    Property,
+    Attribute,
+    /// A CollectionService tag would be added (`after: true`) or removed (`after: false`).
+    Tag,
+    /// A script's source would change; see `diff`.
+    Source,
+    /// A terrain region would be filled, replaced, or cleared; `after` describes the edit.
+    Terrain,
+    /// A PhysicsService collision group would be registered, or a pair of groups would change
+    /// whether they collide.
+    CollisionGroup,
+}
+
+/// One change a dry run would have made.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PlannedChange {
+    pub(super) kind: ChangeKind,
+    /// Path of the instance that would change.
+    pub(super) path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) class_name: Option<String>,
+    /// Property, attribute, tag, or collision group name, a pair of groups as `A/B`, or the terrain
+    /// operation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) before: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) after: Option<JsonValue>,
+    /// Unified diff of a script's source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) diff: Option<String>,
+}
+
+impl PlannedChange {
+    pub(super) fn new(kind: ChangeKind, path: Vec<String>) -> Self {
+        Self {
+            kind,
+            path,
+            class_name: None,
+            name: None,
+            before: None,
+            after: None,
+            diff: None,
+        }
+    }
+
+    /// A script whose source would change from `before` to `after`.
+    pub(super) fn source(path: Vec<String>, before: &str, after: &str) -> Self {
+        let name = path.join(".");
+        Self {
+            diff: Some(
+                TextDiff::from_lines(before, after)
+                    .unified_diff()
+                    .context_radius(DEFAULT_DIFF_CONTEXT_LINES)
+                    .header(&format!("a/{name}"), &format!("b/{name}"))
+                    .to_string(),
+            ),
+            ..Self::new(ChangeKind::Source, path)
+        }
+    }
+}
+
+/// Marks a tool response as a dry run: no write occurred, the summary says so, and `changes` lists
+/// what would have changed.
+pub(super) fn annotate(response: &str, changes: Vec<PlannedChange>) -> Result<String> {
+    let mut response: JsonValue = serde_json::from_str(response)?;
+    let object = response
+        .as_object_mut()
+        .ok_or_else(|| eyre!("Dry runs need a JSON object response"))?;
+    if let Some(JsonValue::String(summary)) = object.get_mut("summary") {
+        *summary = format!("Dry run: {summary}");
+    }
+    object.insert("dryRun".into(), JsonValue::Bool(true));
+    object.insert("writeOccurred".into(), JsonValue::Bool(false));
+    object.insert("changes".into(), serde_json::to_value(changes)?);
+    Ok(response.to_string())
+}
+
+/// Studio reports a script edit as a change of its `Source` property with both full texts; this
+/// replaces those with a unified diff, as place files report them.
+pub(super) fn present_source_changes(response: String) -> String {
+    let Ok(mut parsed) = serde_json::from_str::<JsonValue>(&response) else {
+        return response;
+    };
+    let Some(changes) = parsed.get_mut("changes").and_then(JsonValue::as_array_mut) else {
+        return response;
+    };
+    for change in changes.iter_mut() {
+        let Ok(planned) = serde_json::from_value::<PlannedChange>(change.clone()) else {
+            continue;
+        };
+        if let (ChangeKind::Property, Some("Source"), Some(before), Some(after)) = (
+            planned.kind,
+            planned.name.as_deref(),
+            planned.before.as_ref().and_then(JsonValue::as_str),
+            planned.after.as_ref().and_then(JsonValue::as_str),
+        ) {
+            let source = PlannedChange {
+                class_name: planned.class_name.clone(),
+                ..PlannedChange::source(planned.path.clone(), before, after)
+            };
+            *change = serde_json::to_value(source).unwrap_or_else(|_| change.clone());
+        }
+    }
+    parsed.to_string()
+}
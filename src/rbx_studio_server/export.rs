@@ -0,0 +1,229 @@
+//! Writes instances serialized by the plugin's `export_instances` operation to disk, so the
+//! plugin never needs filesystem access.
+
+use super::{
+    AssetPipelineOperation, AssetPipelineOperationKind, AssetPipelineRequest, AssetPipelineResponse,
+};
+use base64::Engine;
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use serde_json::{json, Value as JsonValue};
+use std::fs;
+use std::path::Path;
+
+/// Returns true when the request contains an `export_instances` operation.
+pub(super) fn has_exports(request: &AssetPipelineRequest) -> bool {
+    request
+        .operations
+        .iter()
+        .any(|operation| matches!(operation, AssetPipelineOperation::ExportInstances { .. }))
+}
+
+/// Replaces the base64 payload of each successful export result with the file it was written to.
+/// Results that cannot be written are marked as failed.
+pub(super) fn write_exports(request: &AssetPipelineRequest, response: &str) -> Result<String> {
+    let mut response: AssetPipelineResponse = serde_json::from_str(response)
+        .wrap_err("Unable to parse asset pipeline response from Studio")?;
+
+    for (operation, result) in request.operations.iter().zip(response.results.iter_mut()) {
+        let AssetPipelineOperation::ExportInstances {
+            file_path,
+            overwrite,
+            ..
+        } = operation
+        else {
+            continue;
+        };
+        if result.action != AssetPipelineOperationKind::ExportInstances || !result.success {
+            continue;
+        }
+
+        let mut details = match result.details.take() {
+            Some(JsonValue::Object(details)) => details,
+            _ => Default::default(),
+        };
+        let data = details.remove("data");
+        details.remove("byteLength");
+        let written = decode_data(data.as_ref())
+            .and_then(|bytes| write_export(Path::new(file_path), *overwrite, &bytes));
+
+        details.insert("filePath".to_string(), json!(file_path));
+        match written {
+            Ok(bytes_written) => {
+                details.insert("bytesWritten".to_string(), json!(bytes_written));
+                result.message = Some(format!("Exported to {file_path}"));
+            }
+            Err(err) => {
+                result.success = false;
+                result.status = "error".to_string();
+                result.message = Some(format!("Failed to write {file_path}: {err:#}"));
+            }
+        }
+        result.details = Some(JsonValue::Object(details));
+    }
+
+    let succeeded = response
+        .results
+        .iter()
+        .filter(|result| result.success)
+        .count();
+    if !response.results.is_empty() {
+        response.summary = Some(format!(
+            "Completed {succeeded} of {} asset pipeline operations",
+            response.results.len()
+        ));
+    }
+    Ok(serde_json::to_string(&response)?)
+}
+
+/// The binary model serialized by a request holding a single `export_instances` operation, for
+/// callers that keep it rather than write it to a file.
+pub(super) fn exported_model(response: &str) -> Result<Vec<u8>> {
+    let response: AssetPipelineResponse = serde_json::from_str(response)
+        .wrap_err("Unable to parse asset pipeline response from Studio")?;
+    let result = response
+        .results
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("Studio returned no export result"))?;
+    if !result.success {
+        bail!(result
+            .message
+            .unwrap_or_else(|| "Studio was unable to serialize the instance".to_string()));
+    }
+    decode_data(
+        result
+            .details
+            .as_ref()
+            .and_then(|details| details.get("data")),
+    )
+}
+
+fn decode_data(data: Option<&JsonValue>) -> Result<Vec<u8>> {
+    let data = data
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| eyre!("Studio did not return any serialized data"))?;
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .wrap_err("Studio returned invalid serialized data")
+}
+
+/// Writes binary model data to `path`, converting it to XML for `.rbxmx` files. Returns the number
+/// of bytes written.
+fn write_export(path: &Path, overwrite: bool, data: &[u8]) -> Result<usize> {
+    if !path.is_absolute() {
+        bail!("filePath must be an absolute path");
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let bytes = match extension.as_str() {
+        "rbxm" => data.to_vec(),
+        "rbxmx" => {
+            let dom = rbx_binary::from_reader(data).wrap_err("Unable to decode model data")?;
+            let mut buffer = Vec::new();
+            rbx_xml::to_writer_default(&mut buffer, &dom, dom.root().children())
+                .wrap_err("Unable to encode model as XML")?;
+            buffer
+        }
+        _ => bail!("filePath must end in .rbxm or .rbxmx"),
+    };
+    if path.exists() && !overwrite {
+        bail!("the file already exists; set overwrite to replace it");
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, &bytes)?;
+    Ok(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+    fn model_bytes() -> Vec<u8> {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root = dom.root_ref();
+        let model = dom.insert(root, InstanceBuilder::new("Model").with_name("Door"));
+        let mut buffer = Vec::new();
+        rbx_binary::to_writer(&mut buffer, &dom, &[model]).expect("encode");
+        buffer
+    }
+
+    fn export_request(file_path: &Path, overwrite: bool) -> AssetPipelineRequest {
+        serde_json::from_value(json!({
+            "operations": [
+                { "action": "search_marketplace", "query": "door" },
+                {
+                    "action": "export_instances",
+                    "instancePaths": [["Workspace", "Door"]],
+                    "filePath": file_path,
+                    "overwrite": overwrite,
+                },
+            ],
+        }))
+        .expect("request")
+    }
+
+    fn plugin_response() -> String {
+        let data = base64::engine::general_purpose::STANDARD.encode(model_bytes());
+        json!({
+            "results": [
+                { "action": "search_marketplace", "success": true, "status": "completed" },
+                {
+                    "action": "export_instances",
+                    "success": true,
+                    "status": "completed",
+                    "details": {
+                        "instancePaths": [["game", "Workspace", "Door"]],
+                        "byteLength": 1,
+                        "data": data,
+                    },
+                },
+            ],
+            "summary": "Completed 2 of 2 asset pipeline operations",
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn writes_binary_and_xml_exports() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for name in ["Door.rbxm", "nested/Door.rbxmx"] {
+            let path = dir.path().join(name);
+            let response =
+                write_exports(&export_request(&path, false), &plugin_response()).expect("response");
+            let response: JsonValue = serde_json::from_str(&response).expect("json");
+            let details = &response["results"][1]["details"];
+            assert_eq!(details["data"], JsonValue::Null);
+            assert_eq!(details["filePath"], json!(path));
+
+            let written = fs::read(&path).expect("read");
+            assert_eq!(details["bytesWritten"], json!(written.len()));
+            crate::rbx_studio_server::PlaceFile::open(&path).expect("written file decodes");
+        }
+    }
+
+    #[test]
+    fn refuses_to_overwrite_without_opt_in() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("Door.rbxm");
+        fs::write(&path, b"existing").expect("seed");
+
+        let response =
+            write_exports(&export_request(&path, false), &plugin_response()).expect("response");
+        let response: JsonValue = serde_json::from_str(&response).expect("json");
+        assert_eq!(response["results"][1]["success"], json!(false));
+        assert_eq!(
+            response["summary"],
+            json!("Completed 1 of 2 asset pipeline operations")
+        );
+        assert_eq!(fs::read(&path).expect("read"), b"existing");
+
+        write_exports(&export_request(&path, true), &plugin_response()).expect("overwrite");
+        assert_eq!(fs::read(&path).expect("read"), model_bytes());
+    }
+}
@@ -0,0 +1,387 @@
+//! Stable instance ids. Snapshot entries and instance operation results carry an id for each
+//! instance (`Instance:GetDebugId()` in Studio, the referent in a place file), and any argument
+//! that takes an instance path also accepts `{ "id": "..." }`. The server remembers the last path
+//! reported for every id in the session, and path arguments look ids up while a tool call's
+//! arguments are parsed, so agents can keep addressing an instance after it is renamed or moved.
+//! Only fields declared as paths are resolved; free-form maps that happen to hold an `id` key are
+//! left alone.
+//!
+//! A remembered path goes stale when the instance is renamed or moved outside this session, e.g.
+//! by hand in Studio. The ids a call resolved are therefore sent along with its first request,
+//! and Studio (or the place file) refuses the request when a path no longer holds the instance
+//! with that id, instead of acting on whatever replaced it.
+
+use rmcp::schemars;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    /// Ids of the session whose tool call is being handled.
+    static CALL_IDS: Arc<InstanceIds>;
+    /// Ids the call's path arguments were resolved from, until its first request is sent.
+    static RESOLVED: Mutex<Vec<ResolvedId>>;
+}
+
+/// Prefix of the error returned when a resolved id no longer matches its path.
+pub(super) const STALE_ID_ERROR: &str = "Stale instance id";
+
+/// An id a path argument was given as, and the path it was resolved to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct ResolvedId {
+    pub(super) id: String,
+    pub(super) path: Vec<String>,
+}
+
+impl ResolvedId {
+    /// The error for a request whose `path` no longer holds the instance with `id`.
+    pub(super) fn stale_error(&self) -> String {
+        format!(
+            "{STALE_ID_ERROR} '{}': {} was renamed, moved, or replaced outside this session; take a new snapshot to refresh ids",
+            self.id,
+            self.path.join(".")
+        )
+    }
+}
+
+/// Schema of an argument that takes an instance path. Such fields deserialize with [`path`] or
+/// [`optional_path`], so only the schema mentions ids.
+#[derive(schemars::JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+pub(super) enum PathOrId {
+    /// Names from the DataModel root, e.g. ["Workspace", "Map"].
+    Path(Vec<String>),
+    /// The id of an instance returned by data_model_snapshot or apply_instance_operations in
+    /// this session, e.g. {"id": "0_1234"}.
+    Id { id: String },
+}
+
+/// The last known path of every instance id reported in this session.
+#[derive(Debug, Default)]
+pub(super) struct InstanceIds {
+    paths: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl InstanceIds {
+    /// Records the ids in a tool response: the `ids` listed alongside an object's `paths`, or else
+    /// its `id` and `path`. Operation results report `path` as it was before the operation ran, so
+    /// their `paths` are preferred. Successful deletes forget the ids under the deleted path.
+    pub(super) fn record(&self, response: &str) {
+        let Ok(response) = serde_json::from_str::<JsonValue>(response) else {
+            return;
+        };
+        let mut paths = self.paths.lock().expect("instance id cache poisoned");
+        record_value(&mut paths, &response);
+    }
+
+    /// Runs `call` with these ids available to the path arguments it parses.
+    pub(super) async fn scope<F: Future>(self: Arc<Self>, call: F) -> F::Output {
+        RESOLVED
+            .scope(Mutex::default(), CALL_IDS.scope(self, call))
+            .await
+    }
+
+    fn path(&self, id: &str) -> Result<Vec<String>, String> {
+        let paths = self.paths.lock().expect("instance id cache poisoned");
+        paths.get(id).cloned().ok_or_else(|| {
+            format!(
+                "Unknown instance id '{id}'. Ids come from data_model_snapshot entries and apply_instance_operations results in this session; take a new snapshot to refresh them."
+            )
+        })
+    }
+}
+
+/// The path last reported for `id` in the session whose tool call is being parsed.
+pub(super) fn lookup(id: &str) -> Result<Vec<String>, String> {
+    let path = CALL_IDS.try_with(|ids| ids.path(id)).unwrap_or_else(|_| {
+        Err(format!(
+            "Instance id '{id}' cannot be used here; pass a path"
+        ))
+    })?;
+    let _ = RESOLVED.try_with(|resolved| {
+        let resolved = &mut *resolved.lock().expect("resolved ids poisoned");
+        let entry = ResolvedId {
+            id: id.to_string(),
+            path: path.clone(),
+        };
+        if !resolved.contains(&entry) {
+            resolved.push(entry);
+        }
+    });
+    Ok(path)
+}
+
+/// Takes the ids the current call resolved so far. Only the call's first request checks them:
+/// later requests may legitimately find the instances renamed or moved by the call itself.
+pub(super) fn take_resolved() -> Vec<ResolvedId> {
+    RESOLVED
+        .try_with(|resolved| std::mem::take(&mut *resolved.lock().expect("resolved ids poisoned")))
+        .unwrap_or_default()
+}
+
+/// Reads a path argument given as an array of names or as `{ "id": "..." }`.
+pub(super) fn path_from_json(value: JsonValue) -> Result<Vec<String>, String> {
+    match value {
+        JsonValue::Object(object) => match (object.len(), object.get("id")) {
+            (1, Some(JsonValue::String(id))) => lookup(id),
+            _ => {
+                Err("Instance paths must be arrays of names or { \"id\": ... } objects".to_string())
+            }
+        },
+        value => serde_json::from_value(value)
+            .map_err(|_| "Instance paths must be arrays of names".to_string()),
+    }
+}
+
+/// `deserialize_with` for `Vec<String>` fields that take a [`PathOrId`].
+pub(super) fn path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    path_from_json(JsonValue::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// `deserialize_with` for `Option<Vec<String>>` fields that take a [`PathOrId`].
+pub(super) fn optional_path<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::Null => Ok(None),
+        value => path_from_json(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+fn string_path(value: Option<&JsonValue>) -> Option<Vec<String>> {
+    value?
+        .as_array()?
+        .iter()
+        .map(|segment| segment.as_str().map(str::to_string))
+        .collect()
+}
+
+fn record_value(paths: &mut HashMap<String, Vec<String>>, value: &JsonValue) {
+    match value {
+        JsonValue::Array(items) => items.iter().for_each(|item| record_value(paths, item)),
+        JsonValue::Object(object) => {
+            let path = string_path(object.get("path"));
+            match (object.get("ids"), object.get("paths"), object.get("id")) {
+                (Some(JsonValue::Array(ids)), Some(JsonValue::Array(listed)), _) => {
+                    for (id, path) in ids.iter().zip(listed) {
+                        if let (Some(id), Some(path)) = (id.as_str(), string_path(Some(path))) {
+                            remember(paths, id, &path);
+                        }
+                    }
+                }
+                (None, _, Some(JsonValue::String(id))) => {
+                    if let Some(path) = &path {
+                        remember(paths, id, path);
+                    }
+                }
+                _ => {}
+            }
+            let deleted = object.get("action").and_then(JsonValue::as_str) == Some("delete")
+                && object.get("success").and_then(JsonValue::as_bool) == Some(true);
+            if let (true, Some(path)) = (deleted, &path) {
+                paths.retain(|_, known| !known.starts_with(path));
+            }
+            object.values().for_each(|value| record_value(paths, value));
+        }
+        _ => {}
+    }
+}
+
+/// Records `path` for `id`. When the instance has moved, the paths of its known descendants move
+/// with it.
+fn remember(paths: &mut HashMap<String, Vec<String>>, id: &str, path: &[String]) {
+    if id.is_empty() {
+        return;
+    }
+    if let Some(previous) = paths.get(id).filter(|previous| *previous != path).cloned() {
+        for known in paths.values_mut() {
+            if known.starts_with(&previous) {
+                known.splice(..previous.len(), path.iter().cloned());
+            }
+        }
+    }
+    paths.insert(id.to_string(), path.to_vec());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{templates::InstantiateTemplateRequest, ApplyInstanceOperationsRequest};
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde_json::json;
+
+    /// Ids 1, 2, and 3 for `Workspace.Map`, `Workspace.Map.Door`, and `Workspace.Lamp`.
+    fn sample_ids() -> Arc<InstanceIds> {
+        let ids = InstanceIds::default();
+        ids.record(
+            &json!({
+                "entries": [
+                    { "path": ["Workspace", "Map"], "id": "1", "name": "Map" },
+                    { "path": ["Workspace", "Map", "Door"], "id": "2", "name": "Door" },
+                    { "path": ["Workspace", "Lamp"], "id": "3", "name": "Lamp" },
+                ],
+            })
+            .to_string(),
+        );
+        Arc::new(ids)
+    }
+
+    fn parse<T: DeserializeOwned>(ids: &Arc<InstanceIds>, value: JsonValue) -> Result<T, String> {
+        CALL_IDS.sync_scope(Arc::clone(ids), || {
+            serde_json::from_value(value).map_err(|err| err.to_string())
+        })
+    }
+
+    #[test]
+    fn moves_follow_the_reported_paths() {
+        let ids = sample_ids();
+        ids.record(
+            &json!({
+                "results": [
+                    { "action": "reparent", "path": ["Workspace", "Map"], "id": "1",
+                      "paths": [["ServerStorage", "Map"]], "ids": ["1"], "success": true },
+                ],
+            })
+            .to_string(),
+        );
+        assert_eq!(ids.path("1").expect("map"), vec!["ServerStorage", "Map"]);
+        assert_eq!(
+            ids.path("2").expect("door"),
+            vec!["ServerStorage", "Map", "Door"]
+        );
+    }
+
+    #[test]
+    fn deletes_forget_the_ids_under_them() {
+        let ids = sample_ids();
+        ids.record(
+            &json!({
+                "results": [
+                    { "action": "delete", "path": ["Workspace", "Map"], "paths": [["Workspace", "Map"]],
+                      "ids": [""], "success": true },
+                ],
+            })
+            .to_string(),
+        );
+        assert!(ids.path("1").is_err());
+        assert!(ids.path("2").is_err());
+        assert_eq!(ids.path("3").expect("lamp"), vec!["Workspace", "Lamp"]);
+    }
+
+    #[test]
+    fn resolves_ids_in_path_arguments() {
+        let request: ApplyInstanceOperationsRequest = parse(
+            &sample_ids(),
+            json!({
+                "operations": [
+                    { "action": "update", "path": { "id": "2" }, "properties": { "Name": "Gate" } },
+                    { "action": "bulk_set_properties", "targetPaths": [{ "id": "1" }, ["Workspace"]] },
+                ],
+            }),
+        )
+        .expect("request");
+        assert_eq!(
+            serde_json::to_value(&request.operations).expect("json"),
+            json!([
+                { "action": "update", "path": ["Workspace", "Map", "Door"],
+                  "properties": { "Name": "Gate" }, "attributes": {} },
+                { "action": "bulk_set_properties", "targetPaths": [["Workspace", "Map"], ["Workspace"]],
+                  "properties": {}, "attributes": {} },
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_ids_fail_to_parse() {
+        let err = parse::<ApplyInstanceOperationsRequest>(
+            &sample_ids(),
+            json!({ "operations": [{ "action": "delete", "path": { "id": "9" } }] }),
+        )
+        .expect_err("unknown id");
+        assert!(err.contains("Unknown instance id '9'"), "{err}");
+    }
+
+    #[test]
+    fn leaves_id_keys_in_free_form_maps_alone() {
+        let ids = sample_ids();
+        let request: InstantiateTemplateRequest = parse(
+            &ids,
+            json!({
+                "name": "Door",
+                "parentPath": { "id": "1" },
+                "parameters": { "id": "7" },
+                "attributes": { "id": "7" },
+            }),
+        )
+        .expect("template request");
+        assert_eq!(request.parent_path, vec!["Workspace", "Map"]);
+        assert_eq!(request.parameters["id"], "7");
+        assert_eq!(
+            serde_json::to_value(&request.attributes).expect("json"),
+            json!({ "id": "7" })
+        );
+
+        let request: ApplyInstanceOperationsRequest = parse(
+            &ids,
+            json!({
+                "operations": [
+                    { "action": "update", "path": ["Workspace"], "attributes": { "id": "3" } },
+                ],
+            }),
+        )
+        .expect("update");
+        assert_eq!(
+            serde_json::to_value(&request.operations[0]).expect("json")["attributes"],
+            json!({ "id": "3" })
+        );
+    }
+
+    #[test]
+    fn script_results_follow_renames() {
+        let ids = sample_ids();
+        ids.record(
+            &json!({
+                "results": [
+                    { "action": "rename", "path": ["Workspace", "Map", "Gate"], "id": "2",
+                      "success": true },
+                ],
+            })
+            .to_string(),
+        );
+        assert_eq!(
+            ids.path("2").expect("door"),
+            vec!["Workspace", "Map", "Gate"]
+        );
+    }
+
+    #[test]
+    fn records_the_ids_a_call_resolves_for_its_first_request() {
+        let ids = sample_ids();
+        let (first, second) = RESOLVED.sync_scope(Mutex::default(), || {
+            parse::<ApplyInstanceOperationsRequest>(
+                &ids,
+                json!({
+                    "operations": [
+                        { "action": "delete", "path": { "id": "2" } },
+                        { "action": "delete", "path": { "id": "2" } },
+                    ],
+                }),
+            )
+            .expect("request");
+            (take_resolved(), take_resolved())
+        });
+        assert_eq!(
+            first,
+            vec![ResolvedId {
+                id: "2".to_string(),
+                path: vec!["Workspace".into(), "Map".into(), "Door".into()],
+            }]
+        );
+        assert!(second.is_empty());
+    }
+}
@@ -0,0 +1,475 @@
+//! Offline backend that serves tools directly from a `.rbxl`/`.rbxlx`/`.rbxm`/`.rbxmx` file
+//! instead of forwarding them to the Studio plugin.
+
+use super::{dry_run, instance_ids::ResolvedId, ToolArgumentValues};
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use rbx_dom_weak::{types::Ref, InstanceBuilder, WeakDom};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod assemblies;
+mod collections;
+mod diff;
+#[cfg(test)]
+mod fixtures;
+mod instances;
+mod model_json;
+mod scripts;
+mod selection;
+mod snapshot;
+mod templates;
+mod values;
+
+pub use diff::{PlaceDiff, DEFAULT_DIFF_CONTEXT_LINES};
+pub(super) use model_json::model_json_to_rbxm;
+pub(super) use templates::{inspect_template, prepare_template, TemplateContents};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileFormat {
+    Binary,
+    Xml,
+}
+
+/// Whether the file holds a full place (services at the top level) or a loose model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Place,
+    Model,
+}
+
+/// An in-memory copy of a place or model file that is written back after every mutating tool call.
+pub struct PlaceFile {
+    path: PathBuf,
+    format: FileFormat,
+    kind: FileKind,
+    dom: WeakDom,
+}
+
+fn classify(path: &Path) -> Result<(FileFormat, FileKind)> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rbxl" => Ok((FileFormat::Binary, FileKind::Place)),
+        "rbxlx" => Ok((FileFormat::Xml, FileKind::Place)),
+        "rbxm" => Ok((FileFormat::Binary, FileKind::Model)),
+        "rbxmx" => Ok((FileFormat::Xml, FileKind::Model)),
+        _ => bail!(
+            "Unsupported file extension for {}; expected .rbxl, .rbxlx, .rbxm or .rbxmx",
+            path.display()
+        ),
+    }
+}
+
+/// Removes the `game`/`DataModel` prefixes and empty segments accepted by the plugin.
+pub(super) fn normalise_path(path: &[String]) -> Vec<String> {
+    path.iter()
+        .filter(|segment| !segment.is_empty() && *segment != "game" && *segment != "DataModel")
+        .cloned()
+        .collect()
+}
+
+impl PlaceFile {
+    /// Loads `path`, picking the binary or XML codec from its extension.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let (format, kind) = classify(&path)?;
+        let bytes =
+            fs::read(&path).wrap_err_with(|| format!("Unable to read {}", path.display()))?;
+        let dom = match format {
+            FileFormat::Binary => rbx_binary::from_reader(bytes.as_slice())
+                .wrap_err_with(|| format!("Unable to decode {}", path.display()))?,
+            FileFormat::Xml => rbx_xml::from_reader_default(bytes.as_slice())
+                .wrap_err_with(|| format!("Unable to decode {}", path.display()))?,
+        };
+        Ok(Self {
+            path,
+            format,
+            kind,
+            dom,
+        })
+    }
+
+    /// Creates an empty place containing only a `DataModel` root.
+    #[cfg(test)]
+    pub(super) fn empty(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let (format, kind) = classify(&path)?;
+        Ok(Self {
+            path,
+            format,
+            kind,
+            dom: WeakDom::new(InstanceBuilder::new("DataModel")),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Serialises the DOM back to disk through a temporary file so a failed write never leaves a
+    /// truncated place behind.
+    pub fn save(&self) -> Result<()> {
+        let roots = self.dom.root().children().to_vec();
+        let mut buffer = Vec::new();
+        match self.format {
+            FileFormat::Binary => rbx_binary::to_writer(&mut buffer, &self.dom, &roots)
+                .wrap_err_with(|| format!("Unable to encode {}", self.path.display()))?,
+            FileFormat::Xml => rbx_xml::to_writer_default(&mut buffer, &self.dom, &roots)
+                .wrap_err_with(|| format!("Unable to encode {}", self.path.display()))?,
+        }
+        let mut temp_name = self.path.as_os_str().to_owned();
+        temp_name.push(".tmp");
+        let temp_path = PathBuf::from(temp_name);
+        fs::write(&temp_path, &buffer)
+            .wrap_err_with(|| format!("Unable to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, &self.path)
+            .wrap_err_with(|| format!("Unable to replace {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Runs a tool request against the file and returns the JSON the plugin would have produced.
+    /// The request runs against a copy of the instance tree, which replaces the file's tree only
+    /// once it has been saved, so a failed save or a dry run leaves the file as it was.
+    pub(super) fn run(&mut self, args: &ToolArgumentValues) -> Result<String> {
+        let mut scratch = PlaceFile {
+            path: self.path.clone(),
+            format: self.format,
+            kind: self.kind,
+            dom: self.copy_dom(),
+        };
+        let (response, write_occurred) = scratch.run_tool(args)?;
+        if args.is_dry_run() {
+            let changes = self
+                .diff(&scratch, DEFAULT_DIFF_CONTEXT_LINES)
+                .planned_changes();
+            return dry_run::annotate(&response, changes);
+        }
+        if write_occurred {
+            scratch.save()?;
+            self.dom = scratch.dom;
+        }
+        Ok(response)
+    }
+
+    /// Runs a tool request, returning its JSON response and whether it wrote to the file.
+    fn run_tool(&mut self, args: &ToolArgumentValues) -> Result<(String, bool)> {
+        Ok(match args {
+            ToolArgumentValues::DataModelSnapshot(request) => (
+                serde_json::to_string(&self.data_model_snapshot(request)?)?,
+                false,
+            ),
+            ToolArgumentValues::ApplyInstanceOperations(request) => {
+                let response = self.apply_instance_operations(request)?;
+                (serde_json::to_string(&response)?, response.write_occurred)
+            }
+            ToolArgumentValues::ManageScripts(request) => {
+                let (response, write_occurred) = self.manage_scripts(request)?;
+                (serde_json::to_string(&response)?, write_occurred)
+            }
+            ToolArgumentValues::CollectionAndAttributes(request) => {
+                let response = self.collection_and_attributes(request)?;
+                (serde_json::to_string(&response)?, response.write_occurred)
+            }
+            ToolArgumentValues::InstantiateTemplate(payload) => {
+                let response = self.instantiate_template(payload)?;
+                (serde_json::to_string(&response)?, response.write_occurred)
+            }
+            other => bail!(
+                "{} requires a live Roblox Studio session and is unavailable while serving {}",
+                other.tool_name(),
+                self.path.display()
+            ),
+        })
+    }
+
+    fn root(&self) -> Ref {
+        self.dom.root_ref()
+    }
+
+    fn name_of(&self, referent: Ref) -> &str {
+        self.dom
+            .get_by_ref(referent)
+            .map(|instance| instance.name.as_str())
+            .unwrap_or_default()
+    }
+
+    fn class_of(&self, referent: Ref) -> &str {
+        self.dom
+            .get_by_ref(referent)
+            .map(|instance| instance.class.as_str())
+            .unwrap_or_default()
+    }
+
+    fn parent_of(&self, referent: Ref) -> Option<Ref> {
+        self.dom
+            .get_by_ref(referent)
+            .map(|instance| instance.parent())
+            .filter(|parent| parent.is_some())
+    }
+
+    fn children_of(&self, referent: Ref) -> &[Ref] {
+        self.dom
+            .get_by_ref(referent)
+            .map(|instance| instance.children())
+            .unwrap_or_default()
+    }
+
+    /// Equivalent of `Instance:FindFirstChild(name)`.
+    fn find_child(&self, parent: Ref, name: &str) -> Option<Ref> {
+        self.children_of(parent)
+            .iter()
+            .copied()
+            .find(|child| self.name_of(*child) == name)
+    }
+
+    /// Resolves a path the same way the plugin does, returning its error message on failure.
+    fn resolve(&self, path: &[String]) -> Result<Ref, String> {
+        let mut current = self.root();
+        for (index, segment) in normalise_path(path).iter().enumerate() {
+            current = self.find_child(current, segment).ok_or_else(|| {
+                let parent_name = if index == 0 {
+                    "game".to_string()
+                } else {
+                    self.full_name(current)
+                };
+                format!("Unable to find '{segment}' under {parent_name}")
+            })?;
+        }
+        Ok(current)
+    }
+
+    /// Path segments from the root to `referent`, excluding the root itself.
+    fn path_of(&self, referent: Ref) -> Vec<String> {
+        let mut segments = Vec::new();
+        let mut current = Some(referent);
+        while let Some(instance) = current.filter(|current| *current != self.root()) {
+            segments.push(self.name_of(instance).to_string());
+            current = self.parent_of(instance);
+        }
+        segments.reverse();
+        segments
+    }
+
+    /// Copy of the instance tree that keeps every referent, so restoring it leaves ids unchanged.
+    fn copy_dom(&self) -> WeakDom {
+        fn copy(dom: &WeakDom, referent: Ref) -> InstanceBuilder {
+            let instance = dom
+                .get_by_ref(referent)
+                .expect("child referents are in the dom");
+            InstanceBuilder::new(instance.class.as_str())
+                .with_referent(referent)
+                .with_name(instance.name.as_str())
+                .with_properties(instance.properties.clone())
+                .with_children(instance.children().iter().map(|child| copy(dom, *child)))
+        }
+        WeakDom::new(copy(&self.dom, self.root()))
+    }
+
+    /// Stable id reported for the instance at `path`, standing in for `Instance:GetDebugId()`.
+    fn id_at(&self, path: &[String]) -> Option<String> {
+        self.resolve(path).ok().map(|referent| referent.to_string())
+    }
+
+    /// Refuses a request whose id-given paths no longer hold the instances with those ids.
+    pub(super) fn check_resolved_ids(&self, resolved: &[ResolvedId]) -> Result<()> {
+        for resolved in resolved {
+            if self.id_at(&resolved.path).as_deref() != Some(resolved.id.as_str()) {
+                bail!(resolved.stale_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Equivalent of `Instance:GetFullName()`; the root is reported as `game`.
+    fn full_name(&self, referent: Ref) -> String {
+        if referent == self.root() {
+            return "game".to_string();
+        }
+        self.path_of(referent).join(".")
+    }
+
+    /// True when `referent` is `ancestor` or one of its descendants.
+    fn is_descendant_of(&self, referent: Ref, ancestor: Ref) -> bool {
+        let mut current = Some(referent);
+        while let Some(instance) = current {
+            if instance == ancestor {
+                return true;
+            }
+            current = self.parent_of(instance);
+        }
+        false
+    }
+
+    /// Resolves a top-level service by class, mirroring `game:GetService` for services that exist.
+    fn service(&self, class_name: &str) -> Option<Ref> {
+        self.children_of(self.root())
+            .iter()
+            .copied()
+            .find(|child| self.class_of(*child) == class_name)
+    }
+
+    fn is_descendant_of_service(&self, referent: Ref, class_name: &str) -> bool {
+        self.service(class_name)
+            .is_some_and(|service| self.is_descendant_of(referent, service))
+    }
+
+    /// Returns the script placement error the plugin would raise for a script of `class_name`
+    /// parented under `parent`.
+    fn validate_script_placement(&self, class_name: &str, parent: Ref) -> Result<(), String> {
+        if values::class_is_a(class_name, "LocalScript") {
+            if self.is_descendant_of_service(parent, "ServerScriptService") {
+                return Err("LocalScripts cannot run under ServerScriptService".to_string());
+            }
+            if self.is_descendant_of_service(parent, "ServerStorage") {
+                return Err("LocalScripts cannot run under ServerStorage".to_string());
+            }
+        } else if values::class_is_a(class_name, "Script") {
+            if self.is_descendant_of_service(parent, "StarterGui") {
+                return Err("Server Scripts cannot run inside StarterGui".to_string());
+            }
+            if self.is_descendant_of_service(parent, "StarterPack") {
+                return Err("Server Scripts cannot run inside StarterPack".to_string());
+            }
+            if self.is_descendant_of_service(parent, "StarterPlayer") {
+                return Err("Server Scripts cannot run inside StarterPlayer containers".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, parent: Ref, class_name: &str, name: &str) -> Ref {
+        self.dom
+            .insert(parent, InstanceBuilder::new(class_name).with_name(name))
+    }
+
+    fn get_mut(&mut self, referent: Ref) -> Result<&mut rbx_dom_weak::Instance> {
+        self.dom
+            .get_by_ref_mut(referent)
+            .ok_or_else(|| eyre!("Instance {referent:?} no longer exists"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::instance_ids::STALE_ID_ERROR;
+    use super::fixtures::sample_place;
+    use super::*;
+
+    #[test]
+    fn resolves_paths_like_the_plugin() {
+        let place = sample_place("test.rbxl");
+        let path = |segments: &[&str]| segments.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let floor = place
+            .resolve(&path(&["game", "Workspace", "Level", "Floor"]))
+            .expect("floor");
+        assert_eq!(place.full_name(floor), "Workspace.Level.Floor");
+        assert_eq!(place.path_of(floor), path(&["Workspace", "Level", "Floor"]));
+        assert_eq!(place.resolve(&[]), Ok(place.root()));
+        assert_eq!(
+            place.resolve(&path(&["Workspace", "Missing"])),
+            Err("Unable to find 'Missing' under Workspace".to_string())
+        );
+    }
+
+    #[test]
+    fn saves_and_reloads_both_formats() {
+        let directory = tempfile::tempdir().expect("tempdir");
+        for file_name in ["level.rbxl", "level.rbxlx"] {
+            let path = directory.path().join(file_name);
+            sample_place(&path).save().expect("save");
+            let reloaded = PlaceFile::open(&path).expect("open");
+            let floor = reloaded
+                .resolve(&["Workspace".into(), "Level".into(), "Floor".into()])
+                .expect("floor");
+            assert_eq!(reloaded.class_of(floor), "Part");
+        }
+    }
+
+    #[test]
+    fn dry_runs_report_changes_without_writing() {
+        let directory = tempfile::tempdir().expect("tempdir");
+        let path = directory.path().join("level.rbxl");
+        let mut place = sample_place(&path);
+        let request = serde_json::from_value(serde_json::json!({
+            "dryRun": true,
+            "operations": [
+                { "action": "update", "path": ["Workspace", "Level", "Floor"], "properties": { "Anchored": true } },
+                { "action": "create", "path": ["Workspace", "Level", "Props"], "className": "Folder" },
+            ],
+        }))
+        .expect("request");
+        let response = place
+            .run(&ToolArgumentValues::ApplyInstanceOperations(request))
+            .expect("response");
+        let response: serde_json::Value = serde_json::from_str(&response).expect("json");
+
+        assert_eq!(response["dryRun"], true);
+        assert_eq!(response["writeOccurred"], false);
+        assert_eq!(
+            response["changes"],
+            serde_json::json!([
+                { "kind": "created", "path": ["Workspace", "Level", "Props"], "className": "Folder" },
+                {
+                    "kind": "property",
+                    "path": ["Workspace", "Level", "Floor"],
+                    "className": "Part",
+                    "name": "Anchored",
+                    "before": false,
+                    "after": true,
+                },
+            ])
+        );
+        assert!(!path.exists());
+        assert!(place
+            .resolve(&["Workspace".into(), "Level".into(), "Props".into()])
+            .is_err());
+    }
+
+    #[test]
+    fn failed_saves_keep_the_previous_tree() {
+        let directory = tempfile::tempdir().expect("tempdir");
+        let mut place = sample_place(directory.path().join("missing").join("level.rbxl"));
+        let request = serde_json::from_value(serde_json::json!({
+            "operations": [
+                { "action": "create", "path": ["Workspace", "Level", "Props"], "className": "Folder" },
+            ],
+        }))
+        .expect("request");
+
+        assert!(place
+            .run(&ToolArgumentValues::ApplyInstanceOperations(request))
+            .is_err());
+        assert!(place
+            .resolve(&["Workspace".into(), "Level".into(), "Props".into()])
+            .is_err());
+    }
+
+    #[test]
+    fn refuses_ids_whose_paths_hold_other_instances() {
+        let mut place = sample_place("test.rbxl");
+        let path: Vec<String> = vec!["Workspace".into(), "Level".into(), "Floor".into()];
+        let resolved = vec![ResolvedId {
+            id: place.id_at(&path).expect("floor id"),
+            path: path.clone(),
+        }];
+        assert!(place.check_resolved_ids(&resolved).is_ok());
+
+        let floor = place.resolve(&path).expect("floor");
+        place.dom.destroy(floor);
+        let level = place.resolve(&path[..2]).expect("level");
+        place.insert(level, "Part", "Floor");
+        let err = place
+            .check_resolved_ids(&resolved)
+            .expect_err("replaced floor");
+        assert!(err.to_string().starts_with(STALE_ID_ERROR), "{err}");
+    }
+
+    #[test]
+    fn rejects_unknown_extensions() {
+        assert!(PlaceFile::empty("level.txt").is_err());
+    }
+}
@@ -0,0 +1,687 @@
+//! Offline port of the group, ungroup, pivot, primary part, and weld actions of
+//! `ApplyInstanceOperations.luau`. Pivots follow `PVInstance:GetPivot`: a part's `CFrame` times its
+//! `PivotOffset`, and a Model's primary part pivot or else its stored `WorldPivot`, falling back to
+//! the centre of its parts' bounding box.
+
+use super::instances::{plural, OperationOutcome};
+use super::{normalise_path, values, PlaceFile};
+use crate::rbx_studio_server::{
+    GroupClass, GroupInstanceOperation, MoveByInstanceOperation, PivotToInstanceOperation,
+    SetPrimaryPartOperation, UngroupInstanceOperation, WeldInstanceOperation,
+};
+use rbx_dom_weak::types::{CFrame, Matrix3, Ref, Variant, Vector3};
+use std::collections::HashSet;
+
+/// `Model.WorldPivot` is stored under this name in place files.
+const WORLD_PIVOT_PROPERTY: &str = "WorldPivotData";
+
+fn identity() -> CFrame {
+    CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity())
+}
+
+fn add(a: Vector3, b: Vector3) -> Vector3 {
+    Vector3::new(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+fn scale(vector: Vector3, factor: f32) -> Vector3 {
+    Vector3::new(vector.x * factor, vector.y * factor, vector.z * factor)
+}
+
+fn dot(a: Vector3, b: Vector3) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+fn rotate(matrix: &Matrix3, vector: Vector3) -> Vector3 {
+    Vector3::new(
+        dot(matrix.x, vector),
+        dot(matrix.y, vector),
+        dot(matrix.z, vector),
+    )
+}
+
+fn multiply(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+    let row = |row: Vector3| add(add(scale(b.x, row.x), scale(b.y, row.y)), scale(b.z, row.z));
+    Matrix3::new(row(a.x), row(a.y), row(a.z))
+}
+
+/// `a * b` in CFrame terms.
+fn compose(a: &CFrame, b: &CFrame) -> CFrame {
+    CFrame::new(
+        add(a.position, rotate(&a.orientation, b.position)),
+        multiply(&a.orientation, &b.orientation),
+    )
+}
+
+fn inverse(cframe: &CFrame) -> CFrame {
+    let orientation = cframe.orientation.transpose();
+    CFrame::new(
+        scale(rotate(&orientation, cframe.position), -1.0),
+        orientation,
+    )
+}
+
+pub(super) fn vector([x, y, z]: [f64; 3]) -> Vector3 {
+    Vector3::new(x as f32, y as f32, z as f32)
+}
+
+pub(super) fn cframe_from_components(components: &[f64; 12]) -> CFrame {
+    let [x, y, z, r00, r01, r02, r10, r11, r12, r20, r21, r22] = *components;
+    CFrame::new(
+        vector([x, y, z]),
+        Matrix3::new(
+            vector([r00, r01, r02]),
+            vector([r10, r11, r12]),
+            vector([r20, r21, r22]),
+        ),
+    )
+}
+
+impl PlaceFile {
+    fn is_part(&self, referent: Ref) -> bool {
+        values::class_is_a(self.class_of(referent), "BasePart")
+    }
+
+    /// Models that can be moved; `Workspace` is a Model too but cannot be.
+    fn is_model(&self, referent: Ref) -> bool {
+        values::class_is_a(self.class_of(referent), "Model")
+            && self.class_of(referent) != "Workspace"
+    }
+
+    fn cframe_property(&self, referent: Ref, property: &str) -> CFrame {
+        match self.read_stored(referent, property) {
+            Some(Variant::CFrame(cframe)) => cframe,
+            _ => identity(),
+        }
+    }
+
+    fn set_stored(&mut self, referent: Ref, property: &str, value: Variant) {
+        if let Ok(instance) = self.get_mut(referent) {
+            instance.properties.insert(property.to_string(), value);
+        }
+    }
+
+    fn primary_part(&self, model: Ref) -> Option<Ref> {
+        match self.read_stored(model, "PrimaryPart") {
+            Some(Variant::Ref(part))
+                if part.is_some() && part != model && self.is_descendant_of(part, model) =>
+            {
+                Some(part)
+            }
+            _ => None,
+        }
+    }
+
+    fn descendant_parts(&self, referent: Ref) -> Vec<Ref> {
+        let mut parts = Vec::new();
+        let mut pending = self.children_of(referent).to_vec();
+        while let Some(current) = pending.pop() {
+            if self.is_part(current) {
+                parts.push(current);
+            }
+            pending.extend_from_slice(self.children_of(current));
+        }
+        parts
+    }
+
+    /// Centre of the world-aligned box around `parts`, as `Model:GetBoundingBox` reports it for a
+    /// Model without a primary part.
+    fn bounding_box_centre(&self, parts: &[Ref]) -> Option<Vector3> {
+        let mut bounds: Option<(Vector3, Vector3)> = None;
+        for &part in parts {
+            let cframe = self.cframe_property(part, "CFrame");
+            let size = match self.read_stored(part, "Size") {
+                Some(Variant::Vector3(size)) => size,
+                _ => Vector3::new(0.0, 0.0, 0.0),
+            };
+            for corner in 0..8 {
+                let sign = |bit: u32| if corner & (1 << bit) == 0 { -0.5 } else { 0.5 };
+                let local = Vector3::new(size.x * sign(0), size.y * sign(1), size.z * sign(2));
+                let point = add(cframe.position, rotate(&cframe.orientation, local));
+                bounds = Some(match bounds {
+                    None => (point, point),
+                    Some((min, max)) => (
+                        Vector3::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z)),
+                        Vector3::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z)),
+                    ),
+                });
+            }
+        }
+        bounds.map(|(min, max)| scale(add(min, max), 0.5))
+    }
+
+    /// Equivalent of `PVInstance:GetPivot()`, or `None` for instances without one.
+    pub(super) fn pivot_of(&self, referent: Ref) -> Option<CFrame> {
+        if self.is_part(referent) {
+            return Some(compose(
+                &self.cframe_property(referent, "CFrame"),
+                &self.cframe_property(referent, "PivotOffset"),
+            ));
+        }
+        if !self.is_model(referent) {
+            return None;
+        }
+        if let Some(part) = self.primary_part(referent) {
+            return self.pivot_of(part);
+        }
+        let stored = self
+            .dom
+            .get_by_ref(referent)
+            .and_then(|instance| instance.properties.get(WORLD_PIVOT_PROPERTY));
+        if let Some(Variant::OptionalCFrame(Some(pivot))) = stored {
+            return Some(*pivot);
+        }
+        let centre = self.bounding_box_centre(&self.descendant_parts(referent));
+        Some(CFrame::new(
+            centre.unwrap_or(Vector3::new(0.0, 0.0, 0.0)),
+            Matrix3::identity(),
+        ))
+    }
+
+    /// Equivalent of `PVInstance:PivotTo(pivot)`: a Model moves every part inside it.
+    pub(super) fn pivot_to(&mut self, referent: Ref, pivot: CFrame) {
+        if self.is_part(referent) {
+            let offset = inverse(&self.cframe_property(referent, "PivotOffset"));
+            self.set_stored(
+                referent,
+                "CFrame",
+                Variant::CFrame(compose(&pivot, &offset)),
+            );
+            return;
+        }
+        let Some(current) = self.pivot_of(referent) else {
+            return;
+        };
+        let delta = compose(&pivot, &inverse(&current));
+        for part in self.descendant_parts(referent) {
+            let moved = compose(&delta, &self.cframe_property(part, "CFrame"));
+            self.set_stored(part, "CFrame", Variant::CFrame(moved));
+        }
+        if self.primary_part(referent).is_none() {
+            self.set_stored(
+                referent,
+                WORLD_PIVOT_PROPERTY,
+                Variant::OptionalCFrame(Some(pivot)),
+            );
+        }
+    }
+
+    pub(super) fn apply_group(&mut self, operation: &GroupInstanceOperation) -> OperationOutcome {
+        let mut parent_path = normalise_path(&operation.path);
+        let Some(name) = parent_path.pop() else {
+            return OperationOutcome::failed("Group operations require the path of the new group");
+        };
+        let parent = match self.resolve(&parent_path) {
+            Ok(parent) => parent,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if self.find_child(parent, &name).is_some() {
+            return OperationOutcome::failed(format!(
+                "An instance named '{name}' already exists under {}",
+                self.full_name(parent)
+            ));
+        }
+        if operation.target_paths.is_empty() {
+            return OperationOutcome::failed("Group operations require targetPaths");
+        }
+        let target_paths = match self.expand_targets(&operation.target_paths) {
+            Ok(paths) => paths,
+            Err(message) => return OperationOutcome::failed(message),
+        };
+
+        let mut members = Vec::new();
+        let mut names = HashSet::new();
+        for path in &target_paths {
+            let member = match self.resolve(path) {
+                Ok(member) => member,
+                Err(error) => return OperationOutcome::failed(error),
+            };
+            if member == self.root() || self.parent_of(member) == Some(self.root()) {
+                return OperationOutcome::failed(format!(
+                    "Grouping top-level instances such as {} is not permitted",
+                    self.full_name(member)
+                ));
+            }
+            if self.is_descendant_of(parent, member) {
+                return OperationOutcome::failed(format!(
+                    "Cannot group {} into a group inside it",
+                    self.full_name(member)
+                ));
+            }
+            if members.contains(&member) {
+                continue;
+            }
+            if !names.insert(self.name_of(member).to_string()) {
+                return OperationOutcome::failed(format!(
+                    "More than one instance to group is named '{}'",
+                    self.name_of(member)
+                ));
+            }
+            let class_name = self.class_of(member).to_string();
+            if let Err(error) = self.validate_script_placement(&class_name, parent) {
+                return OperationOutcome::failed(error);
+            }
+            members.push(member);
+        }
+
+        let primary_part = match &operation.primary_part_path {
+            None => None,
+            Some(_) if operation.class_name == GroupClass::Folder => {
+                return OperationOutcome::failed("Only Model groups have a PrimaryPart");
+            }
+            Some(path) => match self.resolve(path) {
+                Ok(part)
+                    if self.is_part(part)
+                        && members
+                            .iter()
+                            .any(|member| self.is_descendant_of(part, *member)) =>
+                {
+                    Some(part)
+                }
+                Ok(_) => {
+                    return OperationOutcome::failed(
+                        "primaryPartPath must name a part that is being grouped",
+                    )
+                }
+                Err(error) => return OperationOutcome::failed(error),
+            },
+        };
+
+        let class_name = match operation.class_name {
+            GroupClass::Model => "Model",
+            GroupClass::Folder => "Folder",
+        };
+        let group = self.insert(parent, class_name, &name);
+        for member in &members {
+            self.dom.transfer_within(*member, group);
+        }
+        if let Some(part) = primary_part {
+            self.set_stored(group, "PrimaryPart", Variant::Ref(part));
+        } else if operation.class_name == GroupClass::Model {
+            if let Some(centre) = self.bounding_box_centre(&self.descendant_parts(group)) {
+                let pivot = CFrame::new(centre, Matrix3::identity());
+                self.set_stored(
+                    group,
+                    WORLD_PIVOT_PROPERTY,
+                    Variant::OptionalCFrame(Some(pivot)),
+                );
+            }
+        }
+
+        let mut paths = vec![self.path_of(group)];
+        paths.extend(members.iter().map(|member| self.path_of(*member)));
+        OperationOutcome::succeeded(format!(
+            "Grouped {} into {class_name} '{}'",
+            plural(members.len(), "instance", "instances"),
+            self.full_name(group)
+        ))
+        .with_paths(paths)
+    }
+
+    pub(super) fn apply_ungroup(
+        &mut self,
+        operation: &UngroupInstanceOperation,
+    ) -> OperationOutcome {
+        let target = match self.resolve(&operation.path) {
+            Ok(target) => target,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        let class_name = self.class_of(target).to_string();
+        if class_name != "Model" && class_name != "Folder" {
+            return OperationOutcome::failed(format!(
+                "Only Model and Folder instances can be ungrouped, not {class_name}"
+            ));
+        }
+        let Some(parent) = self
+            .parent_of(target)
+            .filter(|parent| *parent != self.root())
+        else {
+            return OperationOutcome::failed("Ungrouping top-level instances is not permitted");
+        };
+
+        let children = self.children_of(target).to_vec();
+        let mut names = HashSet::new();
+        for &child in &children {
+            let name = self.name_of(child).to_string();
+            if !names.insert(name.clone())
+                || self
+                    .find_child(parent, &name)
+                    .is_some_and(|existing| existing != target)
+            {
+                return OperationOutcome::failed(format!(
+                    "An instance named '{name}' already exists under {}",
+                    self.full_name(parent)
+                ));
+            }
+        }
+
+        let full_name = self.full_name(target);
+        for &child in &children {
+            self.dom.transfer_within(child, parent);
+        }
+        self.dom.destroy(target);
+        OperationOutcome::succeeded(format!(
+            "Ungrouped {full_name} ({} moved to {})",
+            plural(children.len(), "child", "children"),
+            self.full_name(parent)
+        ))
+        .with_paths(children.iter().map(|child| self.path_of(*child)).collect())
+    }
+
+    /// Resolves a part or Model that can be moved.
+    fn resolve_movable(&self, path: &[String]) -> Result<Ref, String> {
+        let target = self.resolve(path)?;
+        if self.is_part(target) || self.is_model(target) {
+            Ok(target)
+        } else {
+            Err(format!(
+                "{} is a {}, not a part or Model",
+                self.full_name(target),
+                self.class_of(target)
+            ))
+        }
+    }
+
+    pub(super) fn apply_pivot_to(
+        &mut self,
+        operation: &PivotToInstanceOperation,
+    ) -> OperationOutcome {
+        let target = match self.resolve_movable(&operation.path) {
+            Ok(target) => target,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        let pivot = match (&operation.cframe, operation.position) {
+            (Some(_), Some(_)) => {
+                return OperationOutcome::failed("pivot_to takes cframe or position, not both")
+            }
+            (Some(components), None) => cframe_from_components(components),
+            (None, Some(position)) => CFrame::new(
+                vector(position),
+                self.pivot_of(target).unwrap_or_else(identity).orientation,
+            ),
+            (None, None) => {
+                return OperationOutcome::failed("pivot_to operations require cframe or position")
+            }
+        };
+        self.pivot_to(target, pivot);
+        let position = pivot.position;
+        OperationOutcome::succeeded(format!(
+            "Pivoted {} to ({}, {}, {})",
+            self.full_name(target),
+            position.x,
+            position.y,
+            position.z
+        ))
+        .with_paths(vec![self.path_of(target)])
+    }
+
+    pub(super) fn apply_move_by(
+        &mut self,
+        operation: &MoveByInstanceOperation,
+    ) -> OperationOutcome {
+        if operation.target_paths.is_empty() {
+            return OperationOutcome::failed("move_by operations require targetPaths");
+        }
+        if operation.offset.is_none() && operation.rotation.is_none() {
+            return OperationOutcome::failed("move_by operations require offset or rotation");
+        }
+        let target_paths = match self.expand_targets(&operation.target_paths) {
+            Ok(paths) => paths,
+            Err(message) => return OperationOutcome::failed(message),
+        };
+
+        let mut targets = Vec::new();
+        let mut warnings = Vec::new();
+        for path in &target_paths {
+            match self.resolve_movable(path) {
+                Ok(target) if !targets.contains(&target) => targets.push(target),
+                Ok(_) => {}
+                Err(error) => warnings.push(error),
+            }
+        }
+        // Parts inside a Model that is also moved already move with it.
+        let roots: Vec<Ref> = targets
+            .iter()
+            .copied()
+            .filter(|target| {
+                !targets
+                    .iter()
+                    .any(|other| other != target && self.is_descendant_of(*target, *other))
+            })
+            .collect();
+        if roots.is_empty() {
+            return OperationOutcome::failed(
+                warnings
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| "No targets were moved".to_string()),
+            )
+            .with_paths(target_paths);
+        }
+
+        let offset = operation.offset.map(vector);
+        let rotation = operation
+            .rotation
+            .map(|rotation| values::matrix_from_orientation(vector(rotation)));
+        for &target in &roots {
+            let Some(pivot) = self.pivot_of(target) else {
+                continue;
+            };
+            let moved = CFrame::new(
+                offset.map_or(pivot.position, |offset| add(pivot.position, offset)),
+                rotation.as_ref().map_or(pivot.orientation, |rotation| {
+                    multiply(rotation, &pivot.orientation)
+                }),
+            );
+            self.pivot_to(target, moved);
+        }
+
+        let mut message = format!("Moved {}", plural(roots.len(), "instance", "instances"));
+        if !warnings.is_empty() {
+            message.push_str(&format!(" (warnings: {})", warnings.join("; ")));
+        }
+        OperationOutcome::succeeded(message).with_paths(target_paths)
+    }
+
+    pub(super) fn apply_set_primary_part(
+        &mut self,
+        operation: &SetPrimaryPartOperation,
+    ) -> OperationOutcome {
+        let model = match self.resolve(&operation.path) {
+            Ok(model) if self.is_model(model) => model,
+            Ok(other) => {
+                return OperationOutcome::failed(format!(
+                    "{} is a {}, not a Model",
+                    self.full_name(other),
+                    self.class_of(other)
+                ))
+            }
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        let message = match &operation.primary_part_path {
+            None => {
+                self.set_stored(model, "PrimaryPart", Variant::Ref(Ref::none()));
+                format!("Cleared the PrimaryPart of {}", self.full_name(model))
+            }
+            Some(path) => {
+                let part = match self.resolve(path) {
+                    Ok(part) => part,
+                    Err(error) => return OperationOutcome::failed(error),
+                };
+                if !self.is_part(part) || part == model || !self.is_descendant_of(part, model) {
+                    return OperationOutcome::failed(format!(
+                        "{} is not a part inside {}",
+                        self.full_name(part),
+                        self.full_name(model)
+                    ));
+                }
+                self.set_stored(model, "PrimaryPart", Variant::Ref(part));
+                format!(
+                    "Set the PrimaryPart of {} to {}",
+                    self.full_name(model),
+                    self.name_of(part)
+                )
+            }
+        };
+        OperationOutcome::succeeded(message).with_paths(vec![self.path_of(model)])
+    }
+
+    pub(super) fn apply_weld(&mut self, operation: &WeldInstanceOperation) -> OperationOutcome {
+        let part0 = match self.resolve(&operation.path) {
+            Ok(part) if self.is_part(part) => part,
+            Ok(other) => {
+                return OperationOutcome::failed(format!(
+                    "{} is a {}, not a part",
+                    self.full_name(other),
+                    self.class_of(other)
+                ))
+            }
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if operation.target_paths.is_empty() {
+            return OperationOutcome::failed("Weld operations require targetPaths");
+        }
+        let target_paths = match self.expand_targets(&operation.target_paths) {
+            Ok(paths) => paths,
+            Err(message) => return OperationOutcome::failed(message),
+        };
+        let name = operation
+            .name
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("WeldConstraint");
+
+        let mut weld_paths = Vec::new();
+        let mut warnings = Vec::new();
+        for path in &target_paths {
+            let part1 = match self.resolve(path) {
+                Ok(part) if self.is_part(part) && part != part0 => part,
+                Ok(other) => {
+                    warnings.push(format!(
+                        "{} cannot be welded to {}",
+                        self.full_name(other),
+                        self.full_name(part0)
+                    ));
+                    continue;
+                }
+                Err(error) => {
+                    warnings.push(error);
+                    continue;
+                }
+            };
+            let unique_name = self.reserve_unique_name(part1, name, &mut HashSet::new());
+            let weld = self.insert(part1, "WeldConstraint", &unique_name);
+            // The offset of Part1 from Part0 that the weld holds.
+            let offset = compose(
+                &inverse(&self.cframe_property(part0, "CFrame")),
+                &self.cframe_property(part1, "CFrame"),
+            );
+            self.set_stored(weld, "Part0", Variant::Ref(part0));
+            self.set_stored(weld, "Part1", Variant::Ref(part1));
+            self.set_stored(weld, "CFrame0", Variant::CFrame(offset));
+            weld_paths.push(self.path_of(weld));
+        }
+
+        if weld_paths.is_empty() {
+            return OperationOutcome::failed(
+                warnings
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| "No parts were welded".to_string()),
+            );
+        }
+        let mut message = format!(
+            "Welded {} to {}",
+            plural(weld_paths.len(), "part", "parts"),
+            self.full_name(part0)
+        );
+        if !warnings.is_empty() {
+            message.push_str(&format!(" (warnings: {})", warnings.join("; ")));
+        }
+        OperationOutcome::succeeded(message).with_paths(weld_paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rbx_studio_server::place_file::PlaceFile;
+    use crate::rbx_studio_server::ApplyInstanceOperationsRequest;
+    use serde_json::json;
+
+    #[test]
+    fn groups_pivots_and_welds_parts() {
+        let mut place = PlaceFile::empty("test.rbxl").expect("place");
+        let root = place.root();
+        let workspace = place.insert(root, "Workspace", "Workspace");
+        for (name, x) in [("Left", -2.0), ("Right", 2.0)] {
+            let part = place.insert(workspace, "Part", name);
+            place
+                .write_property(
+                    part,
+                    "Position",
+                    &json!({ "type": "Vector3", "x": x, "y": 1, "z": 0 }),
+                )
+                .expect("position");
+        }
+
+        let request: ApplyInstanceOperationsRequest = serde_json::from_value(json!({
+            "operations": [
+                { "action": "group", "path": ["Workspace", "Cart"],
+                  "targetPaths": [["Workspace", "Left"], ["Workspace", "Right"]] },
+                { "action": "move_by", "targetPaths": [["Workspace", "Cart"]], "offset": [0, 10, 0] },
+                { "action": "weld", "path": ["Workspace", "Cart", "Left"],
+                  "targetPaths": [["Workspace", "Cart", "Right"]] },
+                { "action": "set_primary_part", "path": ["Workspace", "Cart"],
+                  "primaryPartPath": ["Workspace", "Cart", "Left"] },
+                { "action": "pivot_to", "path": ["Workspace", "Cart"], "position": [0, 0, 0] },
+                { "action": "ungroup", "path": ["Workspace", "Cart"] },
+            ],
+        }))
+        .expect("request");
+        let response = place.apply_instance_operations(&request).expect("response");
+        for result in &response.results {
+            assert!(result.success, "{result:?}");
+        }
+        assert_eq!(
+            response.results[0].paths,
+            vec![
+                vec!["Workspace".to_string(), "Cart".to_string()],
+                vec![
+                    "Workspace".to_string(),
+                    "Cart".to_string(),
+                    "Left".to_string()
+                ],
+                vec![
+                    "Workspace".to_string(),
+                    "Cart".to_string(),
+                    "Right".to_string()
+                ],
+            ]
+        );
+        assert_eq!(
+            response.results[2].paths,
+            vec![vec![
+                "Workspace".to_string(),
+                "Cart".to_string(),
+                "Right".to_string(),
+                "WeldConstraint".to_string(),
+            ]]
+        );
+
+        // The group moved up by 10, then its primary part was pivoted to the origin.
+        let position = |place: &PlaceFile, name: &str| {
+            let part = place
+                .resolve(&["Workspace".into(), name.into()])
+                .expect(name);
+            place.read_property(part, "Position").expect("position")
+        };
+        assert_eq!(
+            position(&place, "Left"),
+            json!({ "type": "Vector3", "x": 0.0, "y": 0.0, "z": 0.0 })
+        );
+        assert_eq!(
+            position(&place, "Right"),
+            json!({ "type": "Vector3", "x": 4.0, "y": 0.0, "z": 0.0 })
+        );
+        assert!(place.resolve(&["Workspace".into(), "Cart".into()]).is_err());
+    }
+}
@@ -0,0 +1,492 @@
+//! Offline port of `CollectionAndAttributes.luau`. Tags live in the `Tags` property and attributes
+//! in the `Attributes` property of each instance.
+
+use super::PlaceFile;
+use crate::rbx_studio_server::{
+    CollectionAndAttributesOperation, CollectionAndAttributesOperationResult,
+    CollectionAndAttributesRequest, CollectionAndAttributesResponse, InstancePropertyMap,
+};
+use color_eyre::eyre::{bail, Result};
+use rbx_dom_weak::types::{Ref, Tags, Variant};
+use serde_json::{json, Map, Value as JsonValue};
+
+struct OperationOutcome {
+    result: CollectionAndAttributesOperationResult,
+    write_occurred: bool,
+    affected_instances: usize,
+}
+
+fn failure(index: usize, operation: &str, message: String) -> OperationOutcome {
+    OperationOutcome {
+        result: CollectionAndAttributesOperationResult {
+            index,
+            operation: operation.to_string(),
+            success: false,
+            message: Some(message),
+            details: None,
+        },
+        write_occurred: false,
+        affected_instances: 0,
+    }
+}
+
+fn outcome(
+    index: usize,
+    operation: &str,
+    success: bool,
+    message: String,
+    details: JsonValue,
+    affected_instances: usize,
+) -> OperationOutcome {
+    OperationOutcome {
+        result: CollectionAndAttributesOperationResult {
+            index,
+            operation: operation.to_string(),
+            success,
+            message: Some(message),
+            details: Some(details),
+        },
+        write_occurred: affected_instances > 0,
+        affected_instances,
+    }
+}
+
+/// Adds a non-empty list under `key`, matching the plugin which omits empty arrays.
+fn insert_list(entry: &mut Map<String, JsonValue>, key: &str, list: Vec<String>) {
+    if !list.is_empty() {
+        entry.insert(key.to_string(), json!(list));
+    }
+}
+
+fn validate_tag(tag: &str) -> Result<(), String> {
+    if tag.is_empty() {
+        Err(format!("Invalid tag '{tag}'"))
+    } else {
+        Ok(())
+    }
+}
+
+impl PlaceFile {
+    pub(super) fn collection_and_attributes(
+        &mut self,
+        request: &CollectionAndAttributesRequest,
+    ) -> Result<CollectionAndAttributesResponse> {
+        if request.operations.is_empty() {
+            bail!("CollectionAndAttributes requires at least one operation");
+        }
+
+        let mut results = Vec::with_capacity(request.operations.len());
+        let mut summaries = Vec::new();
+        let mut write_occurred = false;
+        let mut total_affected = 0;
+        for (offset, operation) in request.operations.iter().enumerate() {
+            let index = offset + 1;
+            let outcome = match operation {
+                CollectionAndAttributesOperation::ListTags {
+                    paths,
+                    include_attributes,
+                } => match self.expand_targets(paths) {
+                    Ok(paths) => self.list_tags(index, &paths, *include_attributes),
+                    Err(message) => failure(index, "list_tags", message),
+                },
+                CollectionAndAttributesOperation::AddTags { paths, tags } => {
+                    match self.expand_targets(paths) {
+                        Ok(paths) => self.add_tags(index, &paths, tags),
+                        Err(message) => failure(index, "add_tags", message),
+                    }
+                }
+                CollectionAndAttributesOperation::RemoveTags { paths, tags } => {
+                    match self.expand_targets(paths) {
+                        Ok(paths) => self.remove_tags(index, &paths, tags),
+                        Err(message) => failure(index, "remove_tags", message),
+                    }
+                }
+                CollectionAndAttributesOperation::SyncAttributes {
+                    paths,
+                    attributes,
+                    clear_missing,
+                } => match self.expand_targets(paths) {
+                    Ok(paths) => self.sync_attributes(index, &paths, attributes, *clear_missing),
+                    Err(message) => failure(index, "sync_attributes", message),
+                },
+                CollectionAndAttributesOperation::QueryByTag {
+                    tag,
+                    include_attributes,
+                    include_paths,
+                } => self.query_by_tag(index, tag, *include_attributes, *include_paths),
+            };
+            if let Some(message) = outcome.result.message.clone().filter(|m| !m.is_empty()) {
+                summaries.push(message);
+            }
+            write_occurred |= outcome.write_occurred;
+            total_affected += outcome.affected_instances;
+            results.push(outcome.result);
+        }
+
+        Ok(CollectionAndAttributesResponse {
+            results,
+            summary: (!summaries.is_empty()).then(|| summaries.join(" | ")),
+            write_occurred,
+            affected_instances: (total_affected > 0).then_some(total_affected),
+        })
+    }
+
+    /// Equivalent of `CollectionService:GetTags(instance)`.
+    pub(super) fn tags_of(&self, referent: Ref) -> Vec<String> {
+        match self
+            .dom
+            .get_by_ref(referent)
+            .and_then(|instance| instance.properties.get("Tags"))
+        {
+            Some(Variant::Tags(tags)) => tags.iter().map(str::to_string).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn set_tags(&mut self, referent: Ref, tags: Vec<String>) {
+        if let Ok(instance) = self.get_mut(referent) {
+            instance
+                .properties
+                .insert("Tags".to_string(), Variant::Tags(Tags::from(tags)));
+        }
+    }
+
+    /// Resolves each path, producing the `{ path, success: false, error }` entry on failure.
+    fn resolve_entry(&self, path: &[String]) -> (Option<Ref>, Map<String, JsonValue>) {
+        let mut entry = Map::new();
+        entry.insert("path".to_string(), json!(super::normalise_path(path)));
+        match self.resolve(path) {
+            Ok(referent) => (Some(referent), entry),
+            Err(error) => {
+                entry.insert("success".to_string(), json!(false));
+                entry.insert("error".to_string(), json!(error));
+                (None, entry)
+            }
+        }
+    }
+
+    fn list_tags(
+        &self,
+        index: usize,
+        paths: &[Vec<String>],
+        include_attributes: bool,
+    ) -> OperationOutcome {
+        if paths.is_empty() {
+            return failure(
+                index,
+                "list_tags",
+                "list_tags requires at least one instance path".to_string(),
+            );
+        }
+        let mut instances = Vec::new();
+        let mut successes = 0;
+        for path in paths {
+            let (referent, mut entry) = self.resolve_entry(path);
+            if let Some(referent) = referent {
+                entry.insert("tags".to_string(), json!(self.tags_of(referent)));
+                if include_attributes {
+                    entry.insert(
+                        "attributes".to_string(),
+                        JsonValue::Object(self.encode_attributes(&self.attributes_of(referent))),
+                    );
+                }
+                entry.insert("success".to_string(), json!(true));
+                successes += 1;
+            }
+            instances.push(JsonValue::Object(entry));
+        }
+        let total = instances.len();
+        outcome(
+            index,
+            "list_tags",
+            successes == total,
+            format!("Collected tags for {successes}/{total} instances"),
+            json!({ "instances": instances }),
+            0,
+        )
+    }
+
+    fn add_tags(
+        &mut self,
+        index: usize,
+        paths: &[Vec<String>],
+        tags: &[String],
+    ) -> OperationOutcome {
+        if paths.is_empty() {
+            return failure(
+                index,
+                "add_tags",
+                "add_tags requires at least one instance path".to_string(),
+            );
+        }
+        if tags.is_empty() {
+            return failure(
+                index,
+                "add_tags",
+                "add_tags requires at least one tag".to_string(),
+            );
+        }
+
+        let mut instances = Vec::new();
+        let mut modified = 0;
+        let mut assignments = 0;
+        let mut successes = 0;
+        for path in paths {
+            let (referent, mut entry) = self.resolve_entry(path);
+            if let Some(referent) = referent {
+                let mut current = self.tags_of(referent);
+                let (mut added, mut skipped, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+                for tag in tags {
+                    if let Err(error) = validate_tag(tag) {
+                        errors.push(error);
+                    } else if current.contains(tag) {
+                        skipped.push(tag.clone());
+                    } else {
+                        current.push(tag.clone());
+                        added.push(tag.clone());
+                    }
+                }
+                if !added.is_empty() {
+                    assignments += added.len();
+                    modified += 1;
+                    self.set_tags(referent, current);
+                }
+                insert_list(&mut entry, "added", added);
+                insert_list(&mut entry, "skipped", skipped);
+                let success = errors.is_empty();
+                insert_list(&mut entry, "errors", errors);
+                entry.insert("success".to_string(), json!(success));
+                successes += usize::from(success);
+            }
+            instances.push(JsonValue::Object(entry));
+        }
+        let total = instances.len();
+        outcome(
+            index,
+            "add_tags",
+            successes == total,
+            format!("Added tags to {modified}/{total} instances ({assignments} new assignments)"),
+            json!({ "instances": instances }),
+            modified,
+        )
+    }
+
+    fn remove_tags(
+        &mut self,
+        index: usize,
+        paths: &[Vec<String>],
+        tags: &[String],
+    ) -> OperationOutcome {
+        if paths.is_empty() {
+            return failure(
+                index,
+                "remove_tags",
+                "remove_tags requires at least one instance path".to_string(),
+            );
+        }
+        if tags.is_empty() {
+            return failure(
+                index,
+                "remove_tags",
+                "remove_tags requires at least one tag".to_string(),
+            );
+        }
+
+        let mut instances = Vec::new();
+        let mut modified = 0;
+        let mut removals = 0;
+        let mut successes = 0;
+        for path in paths {
+            let (referent, mut entry) = self.resolve_entry(path);
+            if let Some(referent) = referent {
+                let mut current = self.tags_of(referent);
+                let (mut removed, mut skipped, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+                for tag in tags {
+                    if let Err(error) = validate_tag(tag) {
+                        errors.push(error);
+                    } else if current.contains(tag) {
+                        current.retain(|existing| existing != tag);
+                        removed.push(tag.clone());
+                    } else {
+                        skipped.push(tag.clone());
+                    }
+                }
+                if !removed.is_empty() {
+                    removals += removed.len();
+                    modified += 1;
+                    self.set_tags(referent, current);
+                }
+                insert_list(&mut entry, "removed", removed);
+                insert_list(&mut entry, "skipped", skipped);
+                let success = errors.is_empty();
+                insert_list(&mut entry, "errors", errors);
+                entry.insert("success".to_string(), json!(success));
+                successes += usize::from(success);
+            }
+            instances.push(JsonValue::Object(entry));
+        }
+        let total = instances.len();
+        outcome(
+            index,
+            "remove_tags",
+            successes == total,
+            format!("Removed tags from {modified}/{total} instances ({removals} removals)"),
+            json!({ "instances": instances }),
+            modified,
+        )
+    }
+
+    fn sync_attributes(
+        &mut self,
+        index: usize,
+        paths: &[Vec<String>],
+        attributes: &InstancePropertyMap,
+        clear_missing: bool,
+    ) -> OperationOutcome {
+        if paths.is_empty() {
+            return failure(
+                index,
+                "sync_attributes",
+                "sync_attributes requires at least one instance path".to_string(),
+            );
+        }
+
+        let mut instances = Vec::new();
+        let mut modified = 0;
+        let mut successes = 0;
+        for path in paths {
+            let (referent, mut entry) = self.resolve_entry(path);
+            if let Some(referent) = referent {
+                let existing = self.attributes_of(referent);
+                let (mut updated, mut removed, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+                for (name, value) in attributes {
+                    match self.set_attribute(referent, name, &value.to_json()) {
+                        Ok(()) => updated.push(name.clone()),
+                        Err(error) => errors.push(format!("Failed to set '{name}': {error}")),
+                    }
+                }
+                if clear_missing {
+                    for (name, _) in existing.iter() {
+                        if !attributes.contains_key(name) {
+                            match self.set_attribute(referent, name, &JsonValue::Null) {
+                                Ok(()) => removed.push(name.clone()),
+                                Err(error) => {
+                                    errors.push(format!("Failed to clear '{name}': {error}"))
+                                }
+                            }
+                        }
+                    }
+                }
+                if !updated.is_empty() || !removed.is_empty() {
+                    modified += 1;
+                }
+                insert_list(&mut entry, "updated", updated);
+                insert_list(&mut entry, "removed", removed);
+                let success = errors.is_empty();
+                insert_list(&mut entry, "errors", errors);
+                entry.insert("success".to_string(), json!(success));
+                successes += usize::from(success);
+                entry.insert(
+                    "attributes".to_string(),
+                    JsonValue::Object(self.encode_attributes(&self.attributes_of(referent))),
+                );
+            }
+            instances.push(JsonValue::Object(entry));
+        }
+        let total = instances.len();
+        outcome(
+            index,
+            "sync_attributes",
+            successes == total,
+            format!("Synchronized attributes for {successes}/{total} instances"),
+            json!({ "instances": instances }),
+            modified,
+        )
+    }
+
+    fn query_by_tag(
+        &self,
+        index: usize,
+        tag: &str,
+        include_attributes: bool,
+        include_paths: bool,
+    ) -> OperationOutcome {
+        if tag.is_empty() {
+            return failure(
+                index,
+                "query_by_tag",
+                "query_by_tag requires a non-empty tag string".to_string(),
+            );
+        }
+
+        let tagged: Vec<Ref> = self
+            .dom
+            .descendants()
+            .filter(|instance| {
+                matches!(instance.properties.get("Tags"), Some(Variant::Tags(tags)) if tags.iter().any(|existing| existing == tag))
+            })
+            .map(|instance| instance.referent())
+            .collect();
+        let instances: Vec<JsonValue> = tagged
+            .iter()
+            .map(|referent| {
+                let mut entry = Map::new();
+                entry.insert("name".to_string(), json!(self.name_of(*referent)));
+                entry.insert("className".to_string(), json!(self.class_of(*referent)));
+                if include_paths {
+                    entry.insert("path".to_string(), json!(self.path_of(*referent)));
+                }
+                entry.insert("tags".to_string(), json!(self.tags_of(*referent)));
+                if include_attributes {
+                    entry.insert(
+                        "attributes".to_string(),
+                        JsonValue::Object(self.encode_attributes(&self.attributes_of(*referent))),
+                    );
+                }
+                JsonValue::Object(entry)
+            })
+            .collect();
+
+        outcome(
+            index,
+            "query_by_tag",
+            true,
+            format!("Found {} instances tagged '{tag}'", tagged.len()),
+            json!({ "tag": tag, "count": tagged.len(), "instances": instances }),
+            0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_and_attributes_round_trip() {
+        let mut place = PlaceFile::empty("test.rbxl").expect("place");
+        let root = place.root();
+        let workspace = place.insert(root, "Workspace", "Workspace");
+        place.insert(workspace, "Part", "Enemy");
+
+        let request: CollectionAndAttributesRequest = serde_json::from_value(json!({
+            "operations": [
+                { "operation": "add_tags", "paths": [["Workspace", "Enemy"]], "tags": ["Hostile", "Hostile"] },
+                { "operation": "sync_attributes", "paths": [["Workspace", "Enemy"]], "attributes": { "Health": 100 } },
+                { "operation": "query_by_tag", "tag": "Hostile", "include_attributes": true, "include_paths": true },
+            ],
+        }))
+        .expect("request");
+        let response = place.collection_and_attributes(&request).expect("response");
+
+        assert!(response.write_occurred);
+        assert_eq!(
+            response.results[0].message.as_deref(),
+            Some("Added tags to 1/1 instances (1 new assignments)")
+        );
+        let query = response.results[2].details.as_ref().expect("details");
+        assert_eq!(query["count"], json!(1));
+        assert_eq!(query["instances"][0]["path"], json!(["Workspace", "Enemy"]));
+        assert_eq!(query["instances"][0]["attributes"]["Health"], json!(100.0));
+    }
+}
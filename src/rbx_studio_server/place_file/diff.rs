@@ -0,0 +1,628 @@
+//! Structural diff between two place files, or between a place file and a live snapshot.
+
+use super::{normalise_path, values, PlaceFile};
+use crate::rbx_studio_server::dry_run::{ChangeKind, PlannedChange};
+use crate::rbx_studio_server::{
+    DataModelSnapshotInstance, DataModelSnapshotPropertyPick, DataModelSnapshotRequest,
+    InstanceTarget,
+};
+use rbx_dom_weak::types::Ref;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use similar::TextDiff;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// Properties that change on every save or are compared separately.
+const IGNORED_PROPERTIES: &[&str] = &[
+    "Attributes",
+    "HistoryId",
+    "Source",
+    "SourceAssetId",
+    "UniqueId",
+];
+
+/// Lines of context around each change in script source diffs.
+pub const DEFAULT_DIFF_CONTEXT_LINES: usize = 3;
+
+/// Page size used when pulling a live snapshot from the plugin.
+const LIVE_SNAPSHOT_PAGE_SIZE: u32 = 500;
+
+/// Numbers read back from Studio go through a float conversion, so tiny differences are ignored.
+const FLOAT_TOLERANCE: f64 = 1e-4;
+
+/// Property names to compare, keyed by class.
+type ClassProperties = BTreeMap<String, BTreeSet<String>>;
+
+#[derive(Debug, Default)]
+struct TreeNode {
+    class_name: String,
+    properties: BTreeMap<String, JsonValue>,
+    attributes: BTreeMap<String, JsonValue>,
+    source: Option<String>,
+}
+
+/// Instances keyed by path. Siblings sharing a name are disambiguated as `Name [2]`, `Name [3]`…
+/// Sorting by path keeps every descendant directly after its ancestor.
+type Tree = BTreeMap<Vec<String>, TreeNode>;
+
+/// Assigns `Name [n]` keys to siblings that share a name, in traversal order.
+#[derive(Default)]
+struct SiblingKeys {
+    seen: HashMap<(Vec<String>, String), usize>,
+}
+
+impl SiblingKeys {
+    fn child_key(&mut self, parent: &[String], name: &str) -> Vec<String> {
+        let count = self
+            .seen
+            .entry((parent.to_vec(), name.to_string()))
+            .or_default();
+        *count += 1;
+        let mut key = parent.to_vec();
+        key.push(if *count == 1 {
+            name.to_string()
+        } else {
+            format!("{name} [{count}]")
+        });
+        key
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceSummary {
+    path: Vec<String>,
+    class_name: String,
+    descendant_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueChange {
+    name: String,
+    before: JsonValue,
+    after: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifiedInstance {
+    path: Vec<String>,
+    class_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<ValueChange>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attributes: Vec<ValueChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_diff: Option<String>,
+}
+
+/// Result of comparing two DataModel trees. Added and removed subtrees are reported once at their
+/// topmost instance; an instance whose class changed is reported as removed and re-added.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceDiff {
+    before: String,
+    after: String,
+    added: Vec<InstanceSummary>,
+    removed: Vec<InstanceSummary>,
+    modified: Vec<ModifiedInstance>,
+    summary: String,
+}
+
+impl PlaceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// The differences as the changes a dry run reports, removals first.
+    pub(in crate::rbx_studio_server) fn planned_changes(self) -> Vec<PlannedChange> {
+        let mut changes = Vec::new();
+        for (kind, instances) in [
+            (ChangeKind::Deleted, self.removed),
+            (ChangeKind::Created, self.added),
+        ] {
+            changes.extend(instances.into_iter().map(|instance| PlannedChange {
+                class_name: Some(instance.class_name),
+                ..PlannedChange::new(kind, instance.path)
+            }));
+        }
+        for instance in self.modified {
+            let values = [
+                (ChangeKind::Property, instance.properties),
+                (ChangeKind::Attribute, instance.attributes),
+            ];
+            for (kind, values) in values {
+                changes.extend(values.into_iter().map(|value| PlannedChange {
+                    class_name: Some(instance.class_name.clone()),
+                    name: Some(value.name),
+                    before: Some(value.before),
+                    after: Some(value.after),
+                    ..PlannedChange::new(kind, instance.path.clone())
+                }));
+            }
+            if let Some(diff) = instance.source_diff {
+                changes.push(PlannedChange {
+                    class_name: Some(instance.class_name),
+                    diff: Some(diff),
+                    ..PlannedChange::new(ChangeKind::Source, instance.path)
+                });
+            }
+        }
+        changes
+    }
+}
+
+impl fmt::Display for PlaceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- {}", self.before)?;
+        writeln!(f, "+++ {}", self.after)?;
+        for (marker, instances) in [("-", &self.removed), ("+", &self.added)] {
+            for instance in instances {
+                write!(
+                    f,
+                    "{marker} {} ({}",
+                    instance.path.join("."),
+                    instance.class_name
+                )?;
+                match instance.descendant_count {
+                    0 => writeln!(f, ")")?,
+                    1 => writeln!(f, ", 1 descendant)")?,
+                    count => writeln!(f, ", {count} descendants)")?,
+                }
+            }
+        }
+        for instance in &self.modified {
+            writeln!(f, "~ {} ({})", instance.path.join("."), instance.class_name)?;
+            for change in &instance.properties {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    change.name, change.before, change.after
+                )?;
+            }
+            for change in &instance.attributes {
+                writeln!(
+                    f,
+                    "    @{}: {} -> {}",
+                    change.name, change.before, change.after
+                )?;
+            }
+            if let Some(source_diff) = &instance.source_diff {
+                for line in source_diff.lines() {
+                    writeln!(f, "    {line}")?;
+                }
+            }
+        }
+        write!(f, "{}", self.summary)
+    }
+}
+
+/// Compares JSON values, allowing for float rounding in numbers.
+fn values_match(before: &JsonValue, after: &JsonValue) -> bool {
+    match (before, after) {
+        (JsonValue::Number(before), JsonValue::Number(after)) => {
+            match (before.as_f64(), after.as_f64()) {
+                (Some(before), Some(after)) => (before - after).abs() <= FLOAT_TOLERANCE,
+                _ => before == after,
+            }
+        }
+        (JsonValue::Array(before), JsonValue::Array(after)) => {
+            before.len() == after.len()
+                && before
+                    .iter()
+                    .zip(after)
+                    .all(|(before, after)| values_match(before, after))
+        }
+        (JsonValue::Object(before), JsonValue::Object(after)) => {
+            before.len() == after.len()
+                && before.iter().all(|(key, before)| {
+                    after
+                        .get(key)
+                        .is_some_and(|after| values_match(before, after))
+                })
+        }
+        _ => before == after,
+    }
+}
+
+/// Changes for keys present on both sides, plus keys only one side has when `include_missing` is
+/// set. Live snapshots skip unreadable properties, so a missing property there is not a change.
+fn value_changes(
+    before: &BTreeMap<String, JsonValue>,
+    after: &BTreeMap<String, JsonValue>,
+    include_missing: bool,
+) -> Vec<ValueChange> {
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after) = match (before.get(name), after.get(name)) {
+                (Some(before), Some(after)) => (before.clone(), after.clone()),
+                (Some(before), None) if include_missing => (before.clone(), JsonValue::Null),
+                (None, Some(after)) if include_missing => (JsonValue::Null, after.clone()),
+                _ => return None,
+            };
+            (!values_match(&before, &after)).then(|| ValueChange {
+                name: name.clone(),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Collapses path-sorted keys into their topmost entries with descendant counts.
+fn subtree_roots<'a>(
+    keys: impl Iterator<Item = &'a Vec<String>>,
+    tree: &Tree,
+) -> Vec<InstanceSummary> {
+    let mut roots: Vec<InstanceSummary> = Vec::new();
+    for key in keys {
+        if let Some(root) = roots.last_mut().filter(|root| key.starts_with(&root.path)) {
+            root.descendant_count += 1;
+            continue;
+        }
+        roots.push(InstanceSummary {
+            path: key.clone(),
+            class_name: tree[key].class_name.clone(),
+            descendant_count: 0,
+        });
+    }
+    roots
+}
+
+fn compare_trees(
+    before_label: String,
+    before: &Tree,
+    after_label: String,
+    after: &Tree,
+    include_missing: bool,
+    context_lines: usize,
+) -> PlaceDiff {
+    let same_class = |key: &Vec<String>| {
+        before
+            .get(key)
+            .zip(after.get(key))
+            .is_some_and(|(before, after)| before.class_name == after.class_name)
+    };
+    let removed = subtree_roots(before.keys().filter(|key| !same_class(key)), before);
+    let added = subtree_roots(after.keys().filter(|key| !same_class(key)), after);
+
+    let mut modified = Vec::new();
+    for (key, before_node) in before {
+        let Some(after_node) = after.get(key).filter(|_| same_class(key)) else {
+            continue;
+        };
+        let source_diff = match (&before_node.source, &after_node.source) {
+            (Some(before_source), Some(after_source)) if before_source != after_source => {
+                let name = key.join(".");
+                Some(
+                    TextDiff::from_lines(before_source.as_str(), after_source.as_str())
+                        .unified_diff()
+                        .context_radius(context_lines)
+                        .header(&format!("a/{name}"), &format!("b/{name}"))
+                        .to_string(),
+                )
+            }
+            _ => None,
+        };
+        let instance = ModifiedInstance {
+            path: key.clone(),
+            class_name: before_node.class_name.clone(),
+            properties: value_changes(
+                &before_node.properties,
+                &after_node.properties,
+                include_missing,
+            ),
+            attributes: value_changes(&before_node.attributes, &after_node.attributes, true),
+            source_diff,
+        };
+        if !instance.properties.is_empty()
+            || !instance.attributes.is_empty()
+            || instance.source_diff.is_some()
+        {
+            modified.push(instance);
+        }
+    }
+
+    let summary = format!(
+        "{} added, {} removed, {} modified",
+        added.len(),
+        removed.len(),
+        modified.len()
+    );
+    PlaceDiff {
+        before: before_label,
+        after: after_label,
+        added,
+        removed,
+        modified,
+        summary,
+    }
+}
+
+impl PlaceFile {
+    /// Compares this file against `after`, reporting what changed going from one to the other.
+    pub fn diff(&self, after: &PlaceFile, context_lines: usize) -> PlaceDiff {
+        let mut class_properties = self.stored_properties();
+        for (class_name, properties) in after.stored_properties() {
+            class_properties
+                .entry(class_name)
+                .or_default()
+                .extend(properties);
+        }
+        compare_trees(
+            self.path.display().to_string(),
+            &self.tree(&class_properties),
+            after.path.display().to_string(),
+            &after.tree(&class_properties),
+            true,
+            context_lines,
+        )
+    }
+
+    /// Builds the snapshot request that reads the live counterpart of this file under
+    /// `live_root`, picking the properties the file stores for each class.
+    pub(in crate::rbx_studio_server) fn live_snapshot_request(
+        &self,
+        live_root: &[String],
+    ) -> DataModelSnapshotRequest {
+        let property_picks = self
+            .stored_properties()
+            .into_iter()
+            .map(|(class_name, mut properties)| {
+                if values::class_is_a(&class_name, "LuaSourceContainer") {
+                    properties.insert("Source".to_string());
+                }
+                DataModelSnapshotPropertyPick {
+                    classes: vec![class_name],
+                    properties: properties.into_iter().collect(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        DataModelSnapshotRequest {
+            root_paths: vec![InstanceTarget::Path(normalise_path(live_root))],
+            include_attributes: Some(true),
+            include_properties: Some(true),
+            include_full_name: Some(false),
+            sort_children_by_name: Some(true),
+            property_picks,
+            page_size: Some(LIVE_SNAPSHOT_PAGE_SIZE),
+            ..Default::default()
+        }
+    }
+
+    /// Compares this file against live snapshot entries gathered with [`live_snapshot_request`].
+    /// Only instances under the file's top-level names are compared, so services and models the
+    /// file does not contain are ignored.
+    ///
+    /// [`live_snapshot_request`]: PlaceFile::live_snapshot_request
+    pub(in crate::rbx_studio_server) fn diff_live(
+        &self,
+        entries: &[DataModelSnapshotInstance],
+        live_root: &[String],
+        context_lines: usize,
+    ) -> PlaceDiff {
+        let class_properties = self.stored_properties();
+        let top_level: BTreeSet<&str> = self
+            .children_of(self.root())
+            .iter()
+            .map(|child| self.name_of(*child))
+            .collect();
+        let live_root = normalise_path(live_root);
+
+        let mut live = Tree::new();
+        let mut keys = SiblingKeys::default();
+        // Keys of the current entry's ancestors, indexed by snapshot depth.
+        let mut ancestors: Vec<Option<Vec<String>>> = Vec::new();
+        for entry in entries {
+            let depth = entry.depth as usize;
+            ancestors.truncate(depth);
+            let key = match depth {
+                0 => None,
+                1 if top_level.contains(entry.name.as_str()) => {
+                    Some(keys.child_key(&[], &entry.name))
+                }
+                1 => None,
+                _ => ancestors
+                    .last()
+                    .cloned()
+                    .flatten()
+                    .map(|parent| keys.child_key(&parent, &entry.name)),
+            };
+            ancestors.push(key.clone());
+            let Some(key) = key else {
+                continue;
+            };
+
+            let mut properties: BTreeMap<String, JsonValue> = entry
+                .properties
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_json()))
+                .collect();
+            let source = match properties.remove("Source") {
+                Some(JsonValue::String(source)) => Some(source),
+                _ => None,
+            };
+            live.insert(
+                key,
+                TreeNode {
+                    class_name: entry.class_name.clone(),
+                    properties,
+                    attributes: entry
+                        .attributes
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.to_json()))
+                        .collect(),
+                    source,
+                },
+            );
+        }
+
+        let live_label = if live_root.is_empty() {
+            "live session".to_string()
+        } else {
+            format!("live session ({})", live_root.join("."))
+        };
+        compare_trees(
+            self.path.display().to_string(),
+            &self.tree(&class_properties),
+            live_label,
+            &live,
+            false,
+            context_lines,
+        )
+    }
+
+    /// Property names stored for each class in the file, excluding the ones that are ignored or
+    /// compared separately.
+    fn stored_properties(&self) -> ClassProperties {
+        let mut class_properties = ClassProperties::new();
+        for instance in self.dom.descendants() {
+            if instance.referent() == self.root() {
+                continue;
+            }
+            class_properties
+                .entry(instance.class.to_string())
+                .or_default()
+                .extend(
+                    instance
+                        .properties
+                        .keys()
+                        .filter(|name| !IGNORED_PROPERTIES.contains(&name.as_str()))
+                        .map(|name| name.to_string()),
+                );
+        }
+        class_properties
+    }
+
+    fn tree(&self, class_properties: &ClassProperties) -> Tree {
+        let mut tree = Tree::new();
+        let mut keys = SiblingKeys::default();
+        let mut stack: Vec<(Ref, Vec<String>)> = vec![(self.root(), Vec::new())];
+        while let Some((referent, key)) = stack.pop() {
+            let mut children = self.children_of(referent).to_vec();
+            children.sort_by_cached_key(|child| self.name_of(*child).to_lowercase());
+            for child in children.into_iter().rev() {
+                let child_key = keys.child_key(&key, self.name_of(child));
+                stack.push((child, child_key));
+            }
+            if referent != self.root() {
+                tree.insert(key, self.tree_node(referent, class_properties));
+            }
+        }
+        tree
+    }
+
+    fn tree_node(&self, referent: Ref, class_properties: &ClassProperties) -> TreeNode {
+        let class_name = self.class_of(referent);
+        let properties = class_properties
+            .get(class_name)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| {
+                let value = self.read_property(referent, name).ok().or_else(|| {
+                    self.dom
+                        .get_by_ref(referent)
+                        .and_then(|instance| instance.properties.get(name.as_str()))
+                        .map(|value| self.encode_value(value, None))
+                })?;
+                Some((name.clone(), value))
+            })
+            .collect();
+        TreeNode {
+            class_name: class_name.to_string(),
+            properties,
+            attributes: self
+                .encode_attributes(&self.attributes_of(referent))
+                .into_iter()
+                .collect(),
+            source: values::class_is_a(class_name, "LuaSourceContainer")
+                .then(|| self.source_of(referent)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::sample_place;
+    use super::*;
+    use crate::rbx_studio_server::RobloxValue;
+    use serde_json::json;
+
+    #[test]
+    fn reports_added_removed_and_modified_instances() {
+        let before = sample_place("before.rbxl");
+        let mut after = sample_place("after.rbxl");
+        let floor = after.resolve(&["Workspace".into(), "Level".into(), "Floor".into()]);
+        let floor = floor.expect("floor");
+        after
+            .write_property(floor, "Anchored", &json!(true))
+            .expect("anchored");
+        after
+            .set_attribute(floor, "Health", &json!(50))
+            .expect("attribute");
+        let level = after.parent_of(floor).expect("level");
+        let wall = after.find_child(level, "Wall").expect("wall");
+        after.dom.destroy(wall);
+        let enemy = after.insert(level, "Model", "Enemy");
+        after.insert(enemy, "Part", "Head");
+        let main = after
+            .resolve(&["ServerScriptService".into(), "Main".into()])
+            .expect("main");
+        after.set_source_of(main, "print('a')\nprint('c')\n");
+
+        let diff = before.diff(&after, 3);
+        assert_eq!(diff.summary, "1 added, 1 removed, 2 modified");
+        assert_eq!(diff.added[0].path, ["Workspace", "Level", "Enemy"]);
+        assert_eq!(diff.added[0].descendant_count, 1);
+        assert_eq!(diff.removed[0].path, ["Workspace", "Level", "Wall"]);
+
+        let floor = &diff.modified[1];
+        assert_eq!(floor.path, ["Workspace", "Level", "Floor"]);
+        assert_eq!(floor.properties[0].name, "Anchored");
+        assert_eq!(floor.properties[0].before, json!(false));
+        assert_eq!(floor.attributes[0].after, json!(50.0));
+
+        let source_diff = diff.modified[0].source_diff.as_deref().expect("diff");
+        assert!(source_diff.contains("-print('b')\n+print('c')"));
+        assert!(before.diff(&before, 3).is_empty());
+    }
+
+    #[test]
+    fn compares_live_snapshots_under_the_file_roots() {
+        let place = sample_place("before.rbxl");
+        let entry = |name: &str, class_name: &str, depth: u32| DataModelSnapshotInstance {
+            name: name.to_string(),
+            class_name: class_name.to_string(),
+            depth,
+            ..Default::default()
+        };
+        let mut main = entry("Main", "Script", 2);
+        main.properties.insert(
+            "Source".to_string(),
+            RobloxValue::String("print('a')\nprint('b')\n".to_string()),
+        );
+        let entries = vec![
+            entry("game", "DataModel", 0),
+            entry("CoreGui", "CoreGui", 1),
+            entry("RobloxGui", "ScreenGui", 2),
+            entry("Lighting", "Lighting", 1),
+            entry("ServerScriptService", "ServerScriptService", 1),
+            main,
+            entry("Workspace", "Workspace", 1),
+            entry("Crate", "Part", 2),
+            entry("Level", "Model", 2),
+            entry("Floor", "Part", 3),
+            entry("Floor", "Part", 3),
+        ];
+
+        let diff = place.diff_live(&entries, &[], 3);
+        assert_eq!(diff.summary, "1 added, 1 removed, 0 modified");
+        assert_eq!(diff.added[0].path, ["Workspace", "Level", "Floor [2]"]);
+        assert_eq!(diff.removed[0].path, ["Workspace", "Level", "Wall"]);
+    }
+}
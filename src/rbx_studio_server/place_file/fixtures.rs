@@ -0,0 +1,22 @@
+//! The place the place file tests share.
+
+use super::PlaceFile;
+use std::path::PathBuf;
+
+/// A place at `path` holding `Workspace.Level.Floor`, `Workspace.Level.Wall`, `Workspace.Crate`,
+/// the script `ServerScriptService.Main`, and `Lighting`. Level is inserted before Crate so that
+/// sorted listings differ from insertion order.
+pub(super) fn sample_place(path: impl Into<PathBuf>) -> PlaceFile {
+    let mut place = PlaceFile::empty(path).expect("place");
+    let root = place.root();
+    let workspace = place.insert(root, "Workspace", "Workspace");
+    let level = place.insert(workspace, "Model", "Level");
+    place.insert(level, "Part", "Floor");
+    place.insert(level, "Part", "Wall");
+    place.insert(workspace, "Part", "Crate");
+    let service = place.insert(root, "ServerScriptService", "ServerScriptService");
+    let main = place.insert(service, "Script", "Main");
+    place.set_source_of(main, "print('a')\nprint('b')\n");
+    place.insert(root, "Lighting", "Lighting");
+    place
+}
@@ -0,0 +1,975 @@
+//! Offline port of `ApplyInstanceOperations.luau`.
+
+use super::{normalise_path, values, FileKind, PlaceFile};
+use crate::rbx_studio_server::{
+    ApplyInstanceOperationsRequest, ApplyInstanceOperationsResponse, BulkSetPropertiesOperation,
+    CloneInstanceOperation, CreateInstanceOperation, DeleteInstanceOperation, InstanceOperation,
+    InstanceOperationAction, InstanceOperationResult, InstancePropertyMap, InstanceTarget,
+    MoveByInstanceOperation, ReparentInstanceOperation, UpdateInstanceOperation,
+};
+use color_eyre::eyre::{bail, Result};
+use rbx_dom_weak::types::Ref;
+use std::collections::HashSet;
+
+const CREATE_CLASS_ALLOWLIST: &[&str] = &[
+    "Folder",
+    "Model",
+    "Part",
+    "MeshPart",
+    "WedgePart",
+    "CornerWedgePart",
+    "UnionOperation",
+    "PointLight",
+    "SpotLight",
+    "SurfaceLight",
+    "BillboardGui",
+    "ScreenGui",
+    "Attachment",
+    "Sound",
+    "ParticleEmitter",
+    "Trail",
+    "Decal",
+    "Texture",
+    "Humanoid",
+    "UIGradient",
+];
+
+const MAX_CLONE_COUNT: u32 = 25;
+
+const ROOT_DELETE_ALLOWLIST: &[&str] = &["Folder", "Model"];
+
+const PROPERTY_ALLOWLIST: &[(&str, &[&str])] = &[
+    ("Instance", &["Name"]),
+    (
+        "BasePart",
+        &[
+            "Anchored",
+            "CFrame",
+            "Color",
+            "Material",
+            "Orientation",
+            "Position",
+            "Reflectance",
+            "Size",
+            "Transparency",
+        ],
+    ),
+    ("Model", &["PrimaryPart", "WorldPivot"]),
+    ("Light", &["Brightness", "Color", "Enabled", "Range"]),
+    ("SurfaceGui", &["Adornee", "Enabled", "LightInfluence"]),
+    (
+        "BillboardGui",
+        &[
+            "Adornee",
+            "AlwaysOnTop",
+            "Enabled",
+            "LightInfluence",
+            "Size",
+        ],
+    ),
+    (
+        "GuiObject",
+        &[
+            "Size",
+            "Position",
+            "AnchorPoint",
+            "BackgroundColor3",
+            "BackgroundTransparency",
+            "BorderSizePixel",
+            "Visible",
+        ],
+    ),
+    ("Attachment", &["CFrame", "Position", "Orientation"]),
+    (
+        "Sound",
+        &[
+            "SoundId",
+            "Volume",
+            "PlaybackSpeed",
+            "Looped",
+            "Playing",
+            "TimePosition",
+            "RollOffMaxDistance",
+            "RollOffMinDistance",
+        ],
+    ),
+    (
+        "ParticleEmitter",
+        &[
+            "Color",
+            "Texture",
+            "Transparency",
+            "Size",
+            "Lifetime",
+            "Speed",
+            "EmissionRate",
+            "Rate",
+            "Enabled",
+            "LightInfluence",
+            "LightEmission",
+        ],
+    ),
+    (
+        "Trail",
+        &[
+            "Color",
+            "Transparency",
+            "Lifetime",
+            "MinLength",
+            "MaxLength",
+            "Enabled",
+            "WidthScale",
+            "Attachment0",
+            "Attachment1",
+        ],
+    ),
+    ("Decal", &["Texture", "Color3", "Transparency", "Face"]),
+    (
+        "Texture",
+        &[
+            "Texture",
+            "Color3",
+            "Transparency",
+            "StudsPerTileU",
+            "StudsPerTileV",
+            "Face",
+        ],
+    ),
+    (
+        "Humanoid",
+        &[
+            "WalkSpeed",
+            "JumpPower",
+            "AutoRotate",
+            "HipHeight",
+            "Health",
+            "MaxHealth",
+        ],
+    ),
+    (
+        "UIGradient",
+        &["Color", "Transparency", "Rotation", "Enabled"],
+    ),
+    (
+        "TextLabel",
+        &[
+            "Text",
+            "TextColor3",
+            "TextSize",
+            "Font",
+            "RichText",
+            "TextTransparency",
+        ],
+    ),
+    (
+        "TextButton",
+        &[
+            "Text",
+            "TextColor3",
+            "TextSize",
+            "Font",
+            "RichText",
+            "TextTransparency",
+        ],
+    ),
+];
+
+fn is_property_allowed(class_name: &str, property: &str) -> bool {
+    PROPERTY_ALLOWLIST
+        .iter()
+        .any(|(allowed_class, properties)| {
+            properties.contains(&property) && values::class_is_a(class_name, allowed_class)
+        })
+}
+
+pub(super) fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{count} {}", if count == 1 { singular } else { plural })
+}
+
+/// Describes the counts applied by an operation, e.g. `applied 2 properties`.
+fn describe_counts(verb: &str, properties: usize, attributes: usize) -> Vec<String> {
+    let mut notes = Vec::new();
+    if properties > 0 {
+        notes.push(format!(
+            "{verb} {}",
+            plural(properties, "property", "properties")
+        ));
+    }
+    if attributes > 0 {
+        notes.push(format!(
+            "synced {}",
+            plural(attributes, "attribute", "attributes")
+        ));
+    }
+    notes
+}
+
+pub(super) struct OperationOutcome {
+    success: bool,
+    message: String,
+    paths: Option<Vec<Vec<String>>>,
+}
+
+impl OperationOutcome {
+    pub(super) fn failed(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            paths: None,
+        }
+    }
+
+    pub(super) fn succeeded(message: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            paths: None,
+        }
+    }
+
+    pub(super) fn with_paths(self, paths: Vec<Vec<String>>) -> Self {
+        Self {
+            paths: Some(paths),
+            ..self
+        }
+    }
+}
+
+impl InstanceOperation {
+    fn action(&self) -> InstanceOperationAction {
+        match self {
+            InstanceOperation::Create(_) => InstanceOperationAction::Create,
+            InstanceOperation::Update(_) => InstanceOperationAction::Update,
+            InstanceOperation::Delete(_) => InstanceOperationAction::Delete,
+            InstanceOperation::Reparent(_) => InstanceOperationAction::Reparent,
+            InstanceOperation::Clone(_) => InstanceOperationAction::Clone,
+            InstanceOperation::BulkSetProperties(_) => InstanceOperationAction::BulkSetProperties,
+            InstanceOperation::Group(_) => InstanceOperationAction::Group,
+            InstanceOperation::Ungroup(_) => InstanceOperationAction::Ungroup,
+            InstanceOperation::PivotTo(_) => InstanceOperationAction::PivotTo,
+            InstanceOperation::MoveBy(_) => InstanceOperationAction::MoveBy,
+            InstanceOperation::SetPrimaryPart(_) => InstanceOperationAction::SetPrimaryPart,
+            InstanceOperation::Weld(_) => InstanceOperationAction::Weld,
+        }
+    }
+
+    fn primary_path(&self) -> Vec<String> {
+        match self {
+            InstanceOperation::Create(operation) => operation.path.clone(),
+            InstanceOperation::Update(operation) => operation.path.clone(),
+            InstanceOperation::Delete(operation) => operation.path.clone(),
+            InstanceOperation::Reparent(operation) => operation.path.clone(),
+            InstanceOperation::Clone(operation) => operation.path.clone(),
+            InstanceOperation::Group(operation) => operation.path.clone(),
+            InstanceOperation::Ungroup(operation) => operation.path.clone(),
+            InstanceOperation::PivotTo(operation) => operation.path.clone(),
+            InstanceOperation::SetPrimaryPart(operation) => operation.path.clone(),
+            InstanceOperation::Weld(operation) => operation.path.clone(),
+            InstanceOperation::BulkSetProperties(BulkSetPropertiesOperation {
+                target_paths,
+                ..
+            })
+            | InstanceOperation::MoveBy(MoveByInstanceOperation { target_paths, .. }) => {
+                match target_paths.first() {
+                    Some(InstanceTarget::Path(path)) => path.clone(),
+                    _ => Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+/// Marks the results of a rolled back batch: the operations that succeeded were undone, and those
+/// after the failure never ran. The ids of affected paths are dropped since the instances they named
+/// no longer exist there.
+fn roll_back_results(results: &mut Vec<InstanceOperationResult>, operations: &[InstanceOperation]) {
+    for result in results.iter_mut() {
+        result.ids.clear();
+        if result.success {
+            result.success = false;
+            result.message = Some(format!(
+                "Rolled back: {}",
+                result.message.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+    for (index, operation) in operations.iter().enumerate().skip(results.len()) {
+        results.push(InstanceOperationResult {
+            index: index + 1,
+            action: operation.action(),
+            path: operation.primary_path(),
+            id: None,
+            paths: Vec::new(),
+            ids: Vec::new(),
+            success: false,
+            message: Some("Skipped because the batch was rolled back".to_string()),
+        });
+    }
+}
+
+impl PlaceFile {
+    pub(super) fn apply_instance_operations(
+        &mut self,
+        request: &ApplyInstanceOperationsRequest,
+    ) -> Result<ApplyInstanceOperationsResponse> {
+        if request.operations.is_empty() {
+            bail!("apply_instance_operations requires at least one operation");
+        }
+
+        // Atomic batches restore this copy when an operation fails.
+        let saved = request.atomic.then(|| self.copy_dom());
+        let mut results = Vec::with_capacity(request.operations.len());
+        let mut successes = 0;
+        let mut failure = None;
+        for (index, operation) in request.operations.iter().enumerate() {
+            let primary_path = operation.primary_path();
+            let id = (!primary_path.is_empty())
+                .then(|| self.id_at(&primary_path))
+                .flatten();
+            let outcome = match operation {
+                InstanceOperation::Create(operation) => self.apply_create(operation),
+                InstanceOperation::Update(operation) => self.apply_update(operation),
+                InstanceOperation::Delete(operation) => self.apply_delete(operation),
+                InstanceOperation::Reparent(operation) => self.apply_reparent(operation),
+                InstanceOperation::Clone(operation) => self.apply_clone(operation),
+                InstanceOperation::BulkSetProperties(operation) => {
+                    self.apply_bulk_set_properties(operation)
+                }
+                InstanceOperation::Group(operation) => self.apply_group(operation),
+                InstanceOperation::Ungroup(operation) => self.apply_ungroup(operation),
+                InstanceOperation::PivotTo(operation) => self.apply_pivot_to(operation),
+                InstanceOperation::MoveBy(operation) => self.apply_move_by(operation),
+                InstanceOperation::SetPrimaryPart(operation) => {
+                    self.apply_set_primary_part(operation)
+                }
+                InstanceOperation::Weld(operation) => self.apply_weld(operation),
+            };
+            if outcome.success {
+                successes += 1;
+            }
+            let paths = outcome.paths.unwrap_or_else(|| match operation {
+                InstanceOperation::BulkSetProperties(operation) => operation
+                    .target_paths
+                    .iter()
+                    .filter_map(|target| target.path().map(<[String]>::to_vec))
+                    .collect(),
+                _ if !primary_path.is_empty() => vec![primary_path.clone()],
+                _ => Vec::new(),
+            });
+            results.push(InstanceOperationResult {
+                index: index + 1,
+                action: operation.action(),
+                path: primary_path,
+                id,
+                ids: paths
+                    .iter()
+                    .map(|path| self.id_at(path).unwrap_or_default())
+                    .collect(),
+                paths,
+                success: outcome.success,
+                message: Some(outcome.message.clone()),
+            });
+            if request.atomic && !outcome.success {
+                failure = Some(format!(
+                    "operation {} failed: {}",
+                    index + 1,
+                    outcome.message
+                ));
+                break;
+            }
+        }
+
+        let total = request.operations.len();
+        if let (Some(saved), Some(reason)) = (saved, failure) {
+            self.dom = saved;
+            roll_back_results(&mut results, &request.operations);
+            return Ok(ApplyInstanceOperationsResponse {
+                results,
+                summary: Some(format!(
+                    "Rolled back {successes} of {total} operations because {reason}"
+                )),
+                write_occurred: false,
+                rolled_back: true,
+                rollback_reason: Some(reason),
+            });
+        }
+        let mut summary = format!("Applied {successes} of {total} operations");
+        if successes < total {
+            summary.push_str(&format!(" ({} failed)", total - successes));
+        }
+        Ok(ApplyInstanceOperationsResponse {
+            results,
+            summary: Some(summary),
+            write_occurred: successes > 0,
+            rolled_back: false,
+            rollback_reason: None,
+        })
+    }
+
+    fn set_property(
+        &mut self,
+        referent: Ref,
+        property: &str,
+        value: &serde_json::Value,
+    ) -> Result<(), String> {
+        let class_name = self.class_of(referent).to_string();
+        if !is_property_allowed(&class_name, property) {
+            return Err(format!(
+                "Property '{property}' cannot be edited on {class_name}"
+            ));
+        }
+        self.write_property(referent, property, value)
+            .map_err(|error| {
+                format!(
+                    "Failed to set {}.{property}: {error}",
+                    self.full_name(referent)
+                )
+            })
+    }
+
+    fn apply_properties(
+        &mut self,
+        referent: Ref,
+        properties: &InstancePropertyMap,
+        skip_name: bool,
+    ) -> (usize, Vec<String>) {
+        let mut applied = 0;
+        let mut errors = Vec::new();
+        for (property, value) in properties {
+            if property == "Parent" || (skip_name && property == "Name") {
+                continue;
+            }
+            match self.set_property(referent, property, &value.to_json()) {
+                Ok(()) => applied += 1,
+                Err(message) => errors.push(message),
+            }
+        }
+        (applied, errors)
+    }
+
+    fn apply_attributes(
+        &mut self,
+        referent: Ref,
+        attributes: &InstancePropertyMap,
+    ) -> (usize, Vec<String>) {
+        let mut applied = 0;
+        let mut errors = Vec::new();
+        for (name, value) in attributes {
+            match self.set_attribute(referent, name, &value.to_json()) {
+                Ok(()) => applied += 1,
+                Err(error) => errors.push(format!(
+                    "Failed to set attribute '{name}' on {}: {error}",
+                    self.full_name(referent)
+                )),
+            }
+        }
+        (applied, errors)
+    }
+
+    fn apply_create(&mut self, operation: &CreateInstanceOperation) -> OperationOutcome {
+        let class_name = operation.class_name.as_str();
+        if !CREATE_CLASS_ALLOWLIST.contains(&class_name) {
+            return OperationOutcome::failed(format!(
+                "Creation of '{class_name}' instances is not permitted"
+            ));
+        }
+        if operation.path.is_empty() {
+            return OperationOutcome::failed("Create operations require a destination path");
+        }
+        let mut parent_path = normalise_path(&operation.path);
+        let Some(last_segment) = parent_path.pop() else {
+            return OperationOutcome::failed("Create operations cannot target the DataModel root");
+        };
+        let desired_name = operation.name.clone().unwrap_or(last_segment);
+        if desired_name.is_empty() {
+            return OperationOutcome::failed(
+                "Create operations require a final path segment or explicit name",
+            );
+        }
+
+        let parent = match self.resolve(&parent_path) {
+            Ok(parent) => parent,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if self.find_child(parent, &desired_name).is_some() {
+            return OperationOutcome::failed(format!(
+                "An instance named '{desired_name}' already exists under {}",
+                self.full_name(parent)
+            ));
+        }
+        if let Err(error) = self.validate_script_placement(class_name, parent) {
+            return OperationOutcome::failed(error);
+        }
+
+        let instance = self.insert(parent, class_name, &desired_name);
+        let (property_count, property_errors) =
+            self.apply_properties(instance, &operation.properties, true);
+        if !property_errors.is_empty() {
+            self.dom.destroy(instance);
+            return OperationOutcome::failed(property_errors.join("; "));
+        }
+        let (attribute_count, attribute_errors) =
+            self.apply_attributes(instance, &operation.attributes);
+        if !attribute_errors.is_empty() {
+            self.dom.destroy(instance);
+            return OperationOutcome::failed(attribute_errors.join("; "));
+        }
+
+        let mut message = format!("Created {class_name} '{}'", self.full_name(instance));
+        let details = describe_counts("applied", property_count, attribute_count);
+        if !details.is_empty() {
+            message.push_str(&format!(" ({})", details.join(", ")));
+        }
+        OperationOutcome::succeeded(message)
+    }
+
+    fn apply_update(&mut self, operation: &UpdateInstanceOperation) -> OperationOutcome {
+        let target = match self.resolve(&operation.path) {
+            Ok(target) => target,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if operation.properties.is_empty() && operation.attributes.is_empty() {
+            return OperationOutcome::failed(
+                "Update operations require properties or attributes to apply",
+            );
+        }
+
+        let (property_count, property_errors) =
+            self.apply_properties(target, &operation.properties, false);
+        let (attribute_count, attribute_errors) =
+            self.apply_attributes(target, &operation.attributes);
+        if property_count + attribute_count == 0 {
+            let first_error = property_errors.into_iter().chain(attribute_errors).next();
+            return OperationOutcome::failed(
+                first_error.unwrap_or_else(|| "No updates were applied".to_string()),
+            );
+        }
+
+        let mut message = format!(
+            "{} on {}",
+            describe_counts("updated", property_count, attribute_count).join(" and "),
+            self.full_name(target)
+        );
+        let errors: Vec<String> = property_errors
+            .into_iter()
+            .chain(attribute_errors)
+            .collect();
+        if !errors.is_empty() {
+            message.push_str(&format!(" ({})", errors.join("; ")));
+        }
+        OperationOutcome::succeeded(message)
+    }
+
+    fn apply_delete(&mut self, operation: &DeleteInstanceOperation) -> OperationOutcome {
+        let target = match self.resolve(&operation.path) {
+            Ok(target) => target,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if target == self.root() {
+            return OperationOutcome::failed("Destroying the DataModel root is not permitted");
+        }
+        if self.kind == FileKind::Place && self.parent_of(target) == Some(self.root()) {
+            let class_name = self.class_of(target);
+            if class_name.ends_with("Service") {
+                return OperationOutcome::failed(format!(
+                    "Destroying {class_name} services is not permitted"
+                ));
+            }
+            if !ROOT_DELETE_ALLOWLIST.contains(&class_name) {
+                return OperationOutcome::failed(format!(
+                    "Destroying top-level {class_name} instances is not permitted"
+                ));
+            }
+        }
+
+        let full_name = self.full_name(target);
+        self.dom.destroy(target);
+        OperationOutcome::succeeded(format!("Destroyed {full_name}"))
+    }
+
+    fn apply_reparent(&mut self, operation: &ReparentInstanceOperation) -> OperationOutcome {
+        let target = match self.resolve(&operation.path) {
+            Ok(target) => target,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if target == self.root() {
+            return OperationOutcome::failed("Reparenting the DataModel root is not permitted");
+        }
+        if operation.new_parent_path.is_empty() {
+            return OperationOutcome::failed("Reparent operations require newParentPath");
+        }
+        let new_parent = match self.resolve(&operation.new_parent_path) {
+            Ok(parent) => parent,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if new_parent == target {
+            return OperationOutcome::failed("An instance cannot be parented to itself");
+        }
+        if self.is_descendant_of(new_parent, target) {
+            return OperationOutcome::failed(
+                "Cannot reparent an instance into one of its descendants",
+            );
+        }
+
+        let desired_name = operation
+            .name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| self.name_of(target).to_string());
+        if self
+            .find_child(new_parent, &desired_name)
+            .is_some_and(|existing| existing != target)
+        {
+            return OperationOutcome::failed(format!(
+                "An instance named '{desired_name}' already exists under {}",
+                self.full_name(new_parent)
+            ));
+        }
+        let class_name = self.class_of(target).to_string();
+        if let Err(error) = self.validate_script_placement(&class_name, new_parent) {
+            return OperationOutcome::failed(error);
+        }
+
+        let previous_full_name = self.full_name(target);
+        self.dom.transfer_within(target, new_parent);
+        if let Ok(instance) = self.get_mut(target) {
+            instance.name = desired_name;
+        }
+
+        let (property_count, property_errors) =
+            self.apply_properties(target, &operation.properties, true);
+        let (attribute_count, attribute_errors) =
+            self.apply_attributes(target, &operation.attributes);
+        let mut notes = describe_counts("applied", property_count, attribute_count);
+        let warnings: Vec<String> = property_errors
+            .into_iter()
+            .chain(attribute_errors)
+            .collect();
+        if !warnings.is_empty() {
+            notes.push(format!("warnings: {}", warnings.join("; ")));
+        }
+
+        let mut message = format!(
+            "Reparented {previous_full_name} to {}",
+            self.full_name(new_parent)
+        );
+        if !notes.is_empty() {
+            message.push_str(&format!(" ({})", notes.join("; ")));
+        }
+        OperationOutcome::succeeded(message)
+            .with_paths(vec![operation.path.clone(), self.path_of(target)])
+    }
+
+    /// Mirrors `reserveUniqueName`, appending ` (n)` until the name is free under `parent`.
+    pub(super) fn reserve_unique_name(
+        &self,
+        parent: Ref,
+        desired_name: &str,
+        reserved: &mut HashSet<String>,
+    ) -> String {
+        let base_name = if desired_name.is_empty() {
+            "Clone"
+        } else {
+            desired_name
+        };
+        let mut unique_name = base_name.to_string();
+        let mut suffix = 1;
+        while self.find_child(parent, &unique_name).is_some() || reserved.contains(&unique_name) {
+            unique_name = format!("{base_name} ({suffix})");
+            suffix += 1;
+            if suffix > 100 {
+                unique_name = format!("{base_name} {}", uuid::Uuid::new_v4());
+                break;
+            }
+        }
+        reserved.insert(unique_name.clone());
+        unique_name
+    }
+
+    fn apply_clone(&mut self, operation: &CloneInstanceOperation) -> OperationOutcome {
+        let target = match self.resolve(&operation.path) {
+            Ok(target) => target,
+            Err(error) => return OperationOutcome::failed(error),
+        };
+        if target == self.root() {
+            return OperationOutcome::failed("Cloning the DataModel root is not permitted");
+        }
+        let clone_count = operation.clone_count.unwrap_or(1);
+        if clone_count < 1 {
+            return OperationOutcome::failed("cloneCount must be at least 1");
+        }
+        if clone_count > MAX_CLONE_COUNT {
+            return OperationOutcome::failed(format!(
+                "cloneCount exceeds maximum of {MAX_CLONE_COUNT}"
+            ));
+        }
+
+        let parent = match operation
+            .new_parent_path
+            .as_ref()
+            .filter(|path| !path.is_empty())
+        {
+            Some(path) => match self.resolve(path) {
+                Ok(parent) => parent,
+                Err(error) => return OperationOutcome::failed(error),
+            },
+            None => match self.parent_of(target) {
+                Some(parent) => parent,
+                None => {
+                    return OperationOutcome::failed(
+                        "Clone operations require a destination parent",
+                    )
+                }
+            },
+        };
+        let class_name = self.class_of(target).to_string();
+        if let Err(error) = self.validate_script_placement(&class_name, parent) {
+            return OperationOutcome::failed(error);
+        }
+
+        let base_name = operation
+            .name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| self.name_of(target).to_string());
+        let mut reserved = HashSet::new();
+        let mut clone_paths = Vec::new();
+        let mut total_properties = 0;
+        let mut total_attributes = 0;
+        let mut warnings = Vec::new();
+
+        for index in 1..=clone_count {
+            let desired_name = if clone_count > 1 {
+                format!("{base_name} ({index})")
+            } else {
+                base_name.clone()
+            };
+            let unique_name = self.reserve_unique_name(parent, &desired_name, &mut reserved);
+            let clone = self.dom.clone_within(target);
+            self.dom.transfer_within(clone, parent);
+            if let Ok(instance) = self.get_mut(clone) {
+                instance.name = unique_name;
+            }
+
+            let (property_count, property_errors) =
+                self.apply_properties(clone, &operation.properties, true);
+            let (attribute_count, attribute_errors) =
+                self.apply_attributes(clone, &operation.attributes);
+            total_properties += property_count;
+            total_attributes += attribute_count;
+            warnings.extend(property_errors);
+            warnings.extend(attribute_errors);
+            clone_paths.push(self.path_of(clone));
+        }
+
+        let mut message = format!(
+            "Cloned {} into {} ({})",
+            self.full_name(target),
+            self.full_name(parent),
+            plural(clone_count as usize, "clone", "clones")
+        );
+        let mut notes = describe_counts("applied", total_properties, total_attributes);
+        if !warnings.is_empty() {
+            notes.push(format!("warnings: {}", warnings.join("; ")));
+        }
+        if !notes.is_empty() {
+            message.push_str(&format!(" ({})", notes.join("; ")));
+        }
+        OperationOutcome::succeeded(message).with_paths(clone_paths)
+    }
+
+    fn apply_bulk_set_properties(
+        &mut self,
+        operation: &BulkSetPropertiesOperation,
+    ) -> OperationOutcome {
+        if operation.target_paths.is_empty() {
+            return OperationOutcome::failed("bulk_set_properties operations require targetPaths");
+        }
+        if operation.properties.is_empty() && operation.attributes.is_empty() {
+            return OperationOutcome::failed(
+                "bulk_set_properties requires properties or attributes to apply",
+            );
+        }
+
+        let target_paths = match self.expand_targets(&operation.target_paths) {
+            Ok(paths) => paths,
+            Err(message) => return OperationOutcome::failed(message),
+        };
+
+        let mut successes = 0;
+        let mut total_properties = 0;
+        let mut total_attributes = 0;
+        let mut warnings = Vec::new();
+        for path in &target_paths {
+            let target = match self.resolve(path) {
+                Ok(target) if target != self.root() => target,
+                Ok(_) => {
+                    warnings.push("Unable to resolve target path".to_string());
+                    continue;
+                }
+                Err(error) => {
+                    warnings.push(error);
+                    continue;
+                }
+            };
+            let (property_count, property_errors) =
+                self.apply_properties(target, &operation.properties, false);
+            let (attribute_count, attribute_errors) =
+                self.apply_attributes(target, &operation.attributes);
+            if property_count + attribute_count > 0 {
+                successes += 1;
+            }
+            total_properties += property_count;
+            total_attributes += attribute_count;
+            warnings.extend(property_errors);
+            warnings.extend(attribute_errors);
+        }
+
+        let target_count = target_paths.len();
+        let affected_paths = target_paths;
+        if successes == 0 {
+            let message = warnings
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| "No targets were updated".to_string());
+            return OperationOutcome::failed(message).with_paths(affected_paths);
+        }
+
+        let mut message = format!("Updated {successes} of {target_count} instances");
+        let mut notes = describe_counts("applied", total_properties, total_attributes);
+        if !warnings.is_empty() {
+            notes.push(format!("warnings: {}", warnings.join("; ")));
+        }
+        if !notes.is_empty() {
+            message.push_str(&format!(" ({})", notes.join("; ")));
+        }
+        OperationOutcome::succeeded(message).with_paths(affected_paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::sample_place;
+    use super::*;
+    use serde_json::json;
+
+    fn request(operations: serde_json::Value) -> ApplyInstanceOperationsRequest {
+        serde_json::from_value(json!({ "operations": operations })).expect("request")
+    }
+
+    #[test]
+    fn creates_and_updates_instances() {
+        let mut place = sample_place("test.rbxl");
+        let response = place
+            .apply_instance_operations(&request(json!([
+                {
+                    "action": "create",
+                    "path": ["Workspace", "Spawn"],
+                    "className": "Part",
+                    "properties": {
+                        "Anchored": true,
+                        "Position": { "type": "Vector3", "x": 0, "y": 5, "z": 0 },
+                        "Material": "Neon",
+                    },
+                    "attributes": { "Team": "Red" },
+                },
+                {
+                    "action": "update",
+                    "path": ["Workspace", "Crate"],
+                    "properties": { "Transparency": 0.5, "Source": "print(1)" },
+                },
+            ])))
+            .expect("response");
+        assert!(response.write_occurred);
+        assert!(response.results[0].success, "{:?}", response.results[0]);
+        assert!(response.results[1].success);
+        assert!(response.results[1]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("Property 'Source' cannot be edited on Part"));
+
+        let spawn = place
+            .resolve(&["Workspace".into(), "Spawn".into()])
+            .expect("spawn");
+        assert_eq!(
+            place.read_property(spawn, "Position").unwrap(),
+            json!({ "type": "Vector3", "x": 0.0, "y": 5.0, "z": 0.0 })
+        );
+        assert_eq!(
+            place.read_property(spawn, "Material").unwrap(),
+            json!({ "type": "EnumItem", "value": "Enum.Material.Neon" })
+        );
+    }
+
+    #[test]
+    fn enforces_plugin_safety_rules() {
+        let mut place = sample_place("test.rbxl");
+        let response = place
+            .apply_instance_operations(&request(json!([
+                { "action": "delete", "path": ["Workspace"] },
+                { "action": "delete", "path": ["ServerScriptService"] },
+                { "action": "create", "path": ["Workspace", "Boom"], "className": "Explosion" },
+                { "action": "clone", "path": ["Workspace", "Crate"], "cloneCount": 26 },
+            ])))
+            .expect("response");
+        assert!(response.results.iter().all(|result| !result.success));
+        assert!(!response.write_occurred);
+        assert_eq!(
+            response.summary.as_deref(),
+            Some("Applied 0 of 4 operations (4 failed)")
+        );
+    }
+
+    #[test]
+    fn clones_with_unique_names_and_reparents() {
+        let mut place = sample_place("test.rbxl");
+        let response = place
+            .apply_instance_operations(&request(json!([
+                { "action": "clone", "path": ["Workspace", "Crate"], "cloneCount": 2 },
+                {
+                    "action": "reparent",
+                    "path": ["Workspace", "Crate (1)"],
+                    "newParentPath": ["Workspace", "Crate"],
+                },
+            ])))
+            .expect("response");
+        assert_eq!(
+            response.results[0].paths,
+            vec![
+                vec!["Workspace".to_string(), "Crate (1)".to_string()],
+                vec!["Workspace".to_string(), "Crate (2)".to_string()],
+            ]
+        );
+        assert!(response.results[1].success);
+        assert!(place
+            .resolve(&["Workspace".into(), "Crate".into(), "Crate (1)".into()])
+            .is_ok());
+    }
+
+    #[test]
+    fn atomic_batches_roll_back_on_failure() {
+        let mut place = sample_place("test.rbxl");
+        let crate_id = place.id_at(&["Workspace".into(), "Crate".into()]);
+        let mut atomic = request(json!([
+            { "action": "update", "path": ["Workspace", "Crate"], "properties": { "Name": "Box" } },
+            { "action": "delete", "path": ["Workspace", "Missing"] },
+            { "action": "create", "path": ["Workspace", "Spawn"], "className": "Part" },
+        ]));
+        atomic.atomic = true;
+        let response = place.apply_instance_operations(&atomic).expect("response");
+        assert!(response.rolled_back);
+        assert!(!response.write_occurred);
+        assert_eq!(
+            response.rollback_reason.as_deref(),
+            Some("operation 2 failed: Unable to find 'Missing' under Workspace")
+        );
+        assert!(response.results.iter().all(|result| !result.success));
+        assert_eq!(
+            response.results[2].message.as_deref(),
+            Some("Skipped because the batch was rolled back")
+        );
+        // The rename was undone and the part kept its id.
+        assert_eq!(place.id_at(&["Workspace".into(), "Crate".into()]), crate_id);
+    }
+}
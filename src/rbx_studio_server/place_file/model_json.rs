@@ -0,0 +1,109 @@
+//! Builds binary models from Rojo `.model.json` files so the plugin can insert them with
+//! `SerializationService:DeserializeInstancesAsync`.
+
+use super::values;
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use rbx_dom_weak::{types::Ref, InstanceBuilder, WeakDom};
+use serde_json::Value as JsonValue;
+
+/// Reads `key` in either Rojo's PascalCase or camelCase spelling.
+fn field<'a>(node: &'a JsonValue, key: &str) -> Option<&'a JsonValue> {
+    node.get(key).or_else(|| {
+        let mut camel = key.to_string();
+        camel[..1].make_ascii_lowercase();
+        node.get(&camel)
+    })
+}
+
+fn insert_node(dom: &mut WeakDom, parent: Ref, name: &str, node: &JsonValue) -> Result<()> {
+    let class_name = field(node, "ClassName")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| eyre!("{name} is missing a ClassName"))?;
+    if !rbx_reflection_database::get()
+        .classes
+        .contains_key(class_name)
+    {
+        bail!("{name} has unknown class {class_name}");
+    }
+    let mut builder = InstanceBuilder::new(class_name).with_name(name);
+    if let Some(properties) = field(node, "Properties").and_then(JsonValue::as_object) {
+        for (property, value) in properties {
+            let (property, value) = values::decode_rojo_property(class_name, property, value)
+                .map_err(|error| eyre!("{name}: {error}"))?;
+            builder = builder.with_property(property, value);
+        }
+    }
+    let referent = dom.insert(parent, builder);
+    for child in field(node, "Children")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let child_name = field(child, "Name")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| eyre!("A child of {name} is missing a Name"))?;
+        insert_node(dom, referent, child_name, child)?;
+    }
+    Ok(())
+}
+
+/// Converts the contents of a `.model.json` file into an `.rbxm` buffer whose single root is
+/// named `name`.
+pub(in crate::rbx_studio_server) fn model_json_to_rbxm(
+    name: &str,
+    contents: &str,
+) -> Result<Vec<u8>> {
+    let node: JsonValue = serde_json::from_str(contents).wrap_err("Invalid model JSON")?;
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root = dom.root_ref();
+    insert_node(&mut dom, root, name, &node)?;
+    let mut buffer = Vec::new();
+    rbx_binary::to_writer(&mut buffer, &dom, dom.root().children())?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::types::{Variant, Vector3};
+
+    #[test]
+    fn converts_implicit_rojo_values() {
+        let contents = r#"{
+            "ClassName": "Model",
+            "Children": [
+                {
+                    "Name": "Handle",
+                    "ClassName": "Part",
+                    "Properties": {
+                        "Anchored": true,
+                        "Size": [1, 2, 3],
+                        "Material": "Neon",
+                        "Color": { "Color3": [1, 0, 0] }
+                    }
+                }
+            ]
+        }"#;
+        let bytes = model_json_to_rbxm("Sword", contents).expect("model");
+        let dom = rbx_binary::from_reader(bytes.as_slice()).expect("decode");
+        let model = dom.get_by_ref(dom.root().children()[0]).expect("model");
+        assert_eq!(model.name, "Sword");
+        let handle = dom.get_by_ref(model.children()[0]).expect("handle");
+        assert_eq!(
+            handle.properties.get("Size"),
+            Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+        );
+        assert_eq!(
+            handle.properties.get("Anchored"),
+            Some(&Variant::Bool(true))
+        );
+        assert!(handle.properties.contains_key("Material"));
+
+        let error = model_json_to_rbxm(
+            "Bad",
+            r#"{ "ClassName": "Part", "Properties": { "Size": "big" } }"#,
+        )
+        .expect_err("invalid value");
+        assert!(error.to_string().contains("Part.Size"));
+    }
+}
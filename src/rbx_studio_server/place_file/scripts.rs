@@ -0,0 +1,1026 @@
+//! Offline port of `ManageScripts.luau`.
+
+use super::{normalise_path, values, PlaceFile};
+use crate::rbx_studio_server::{
+    InstancePropertyMap, ManageScriptsRequest, ManageScriptsResponse, ScriptDiagnostic,
+    ScriptMetadataSelection, ScriptOperation, ScriptOperationKind, ScriptOperationResult,
+    ScriptType,
+};
+use color_eyre::eyre::Result;
+use rbx_dom_weak::types::{Ref, Variant};
+use serde_json::{json, Map, Value as JsonValue};
+
+impl ScriptType {
+    fn class_name(&self) -> &'static str {
+        match self {
+            ScriptType::Script => "Script",
+            ScriptType::LocalScript => "LocalScript",
+            ScriptType::ModuleScript => "ModuleScript",
+        }
+    }
+}
+
+fn make_result(
+    action: ScriptOperationKind,
+    path: Vec<String>,
+    success: bool,
+    message: Option<String>,
+) -> ScriptOperationResult {
+    ScriptOperationResult {
+        action,
+        path,
+        id: None,
+        success,
+        message,
+        source: None,
+        metadata: None,
+        details: None,
+        diagnostics: Vec::<ScriptDiagnostic>::new(),
+    }
+}
+
+fn failure(
+    action: ScriptOperationKind,
+    path: Vec<String>,
+    message: String,
+) -> ScriptOperationResult {
+    make_result(action, path, false, Some(message))
+}
+
+/// Marks the results of a rolled back batch: the operations that succeeded were undone, and those
+/// after the failure never ran.
+fn roll_back_results(results: &mut Vec<ScriptOperationResult>, operations: &[ScriptOperation]) {
+    for result in results.iter_mut().filter(|result| result.success) {
+        result.success = false;
+        result.id = None;
+        result.message = Some(format!(
+            "Rolled back: {}",
+            result.message.as_deref().unwrap_or_default()
+        ));
+    }
+    for operation in &operations[results.len()..] {
+        results.push(failure(
+            operation.kind(),
+            normalise_path(operation.path()),
+            "Skipped because the batch was rolled back".to_string(),
+        ));
+    }
+}
+
+impl PlaceFile {
+    pub(super) fn manage_scripts(
+        &mut self,
+        request: &ManageScriptsRequest,
+    ) -> Result<(ManageScriptsResponse, bool)> {
+        // Atomic batches restore this copy when an operation fails.
+        let saved = request.atomic.then(|| self.copy_dom());
+        let mut results = Vec::with_capacity(request.operations.len());
+        let mut write_occurred = false;
+        let mut failure_reason = None;
+        for (index, operation) in request.operations.iter().enumerate() {
+            let (metadata_override, is_write) = match operation {
+                ScriptOperation::Create { metadata, .. }
+                | ScriptOperation::SetSource { metadata, .. }
+                | ScriptOperation::FormatScript { metadata, .. }
+                | ScriptOperation::ApplyPatch { metadata, .. }
+                | ScriptOperation::ReplaceRange { metadata, .. }
+                | ScriptOperation::Rename { metadata, .. }
+                | ScriptOperation::Delete { metadata, .. }
+                | ScriptOperation::Move { metadata, .. }
+                | ScriptOperation::Duplicate { metadata, .. }
+                | ScriptOperation::ConvertType { metadata, .. }
+                | ScriptOperation::SetEnabled { metadata, .. } => (metadata, true),
+                ScriptOperation::GetSource { metadata, .. }
+                | ScriptOperation::Outline { metadata, .. } => (metadata, false),
+            };
+            let metadata = metadata_override
+                .as_ref()
+                .or(request.default_metadata.as_ref());
+            let mut result = match operation {
+                ScriptOperation::Create {
+                    path,
+                    script_type,
+                    source,
+                    run_context,
+                    attributes,
+                    ..
+                } => self.create_script(
+                    path,
+                    script_type,
+                    source.as_deref(),
+                    run_context.as_deref(),
+                    attributes,
+                    metadata,
+                ),
+                ScriptOperation::GetSource { path, .. } => self.get_source(path, metadata),
+                ScriptOperation::SetSource {
+                    path,
+                    source,
+                    expected_source,
+                    ..
+                } => self.set_source(path, source, expected_source.as_deref(), metadata),
+                ScriptOperation::Rename { path, new_name, .. } => {
+                    self.rename_script(path, new_name, metadata)
+                }
+                ScriptOperation::Delete { path, .. } => self.delete_script(path, metadata),
+                ScriptOperation::Move {
+                    path,
+                    new_parent,
+                    new_name,
+                    ..
+                } => self.move_script(path, new_parent, new_name.as_deref(), metadata),
+                ScriptOperation::Duplicate {
+                    path,
+                    new_parent,
+                    new_name,
+                    ..
+                } => self.duplicate_script(
+                    path,
+                    new_parent.as_deref(),
+                    new_name.as_deref(),
+                    metadata,
+                ),
+                ScriptOperation::ConvertType {
+                    path,
+                    script_type,
+                    run_context,
+                    ..
+                } => self.convert_script(path, script_type, run_context.as_deref(), metadata),
+                ScriptOperation::SetEnabled { path, enabled, .. } => {
+                    self.set_script_enabled(path, *enabled, metadata)
+                }
+                // The server rewrites these into get_source or set_source before dispatching.
+                ScriptOperation::Outline { path, .. }
+                | ScriptOperation::FormatScript { path, .. }
+                | ScriptOperation::ApplyPatch { path, .. }
+                | ScriptOperation::ReplaceRange { path, .. } => failure(
+                    operation.kind(),
+                    path.clone(),
+                    "This operation must be resolved by the server".to_string(),
+                ),
+            };
+            if result.success {
+                result.id = self.id_at(&result.path);
+            }
+            write_occurred |= is_write && result.success;
+            if request.atomic && !result.success {
+                failure_reason = Some(format!(
+                    "operation {} failed: {}",
+                    index + 1,
+                    result.message.as_deref().unwrap_or("unknown error")
+                ));
+            }
+            results.push(result);
+            if failure_reason.is_some() {
+                break;
+            }
+        }
+
+        let succeeded = results.iter().filter(|result| result.success).count();
+        let total = request.operations.len();
+        if let (Some(saved), Some(reason)) = (saved, failure_reason) {
+            self.dom = saved;
+            roll_back_results(&mut results, &request.operations);
+            return Ok((
+                ManageScriptsResponse {
+                    results,
+                    summary: Some(format!(
+                        "Rolled back {succeeded} of {total} script operations because {reason}"
+                    )),
+                    dry_run: false,
+                    changes: Vec::new(),
+                    rolled_back: true,
+                    rollback_reason: Some(reason),
+                },
+                false,
+            ));
+        }
+        let summary = format!(
+            "Processed {} script operations ({succeeded} succeeded, {} failed)",
+            results.len(),
+            results.len() - succeeded
+        );
+        Ok((
+            ManageScriptsResponse {
+                results,
+                summary: Some(summary),
+                dry_run: false,
+                changes: Vec::new(),
+                rolled_back: false,
+                rollback_reason: None,
+            },
+            write_occurred,
+        ))
+    }
+
+    pub(super) fn source_of(&self, referent: Ref) -> String {
+        match self
+            .dom
+            .get_by_ref(referent)
+            .and_then(|instance| instance.properties.get("Source"))
+        {
+            Some(Variant::String(source)) => source.clone(),
+            _ => String::new(),
+        }
+    }
+
+    pub(super) fn set_source_of(&mut self, referent: Ref, source: &str) {
+        if let Ok(instance) = self.get_mut(referent) {
+            instance
+                .properties
+                .insert("Source".to_string(), Variant::String(source.to_string()));
+        }
+    }
+
+    /// Resolves `path` to a `LuaSourceContainer`, returning the plugin's error otherwise.
+    fn resolve_script(&self, path: &[String]) -> Result<Ref, String> {
+        let referent = self.resolve(path)?;
+        let class_name = self.class_of(referent);
+        if !values::class_is_a(class_name, "LuaSourceContainer") {
+            return Err(format!(
+                "{} is a {class_name}, expected a script",
+                self.full_name(referent)
+            ));
+        }
+        Ok(referent)
+    }
+
+    fn gather_metadata(
+        &self,
+        referent: Ref,
+        selection: Option<&ScriptMetadataSelection>,
+    ) -> Option<JsonValue> {
+        let selection = selection?;
+        let mut metadata = Map::new();
+        if selection.include_class_name {
+            metadata.insert("className".to_string(), json!(self.class_of(referent)));
+        }
+        if selection.include_full_name {
+            metadata.insert("fullName".to_string(), json!(self.full_name(referent)));
+        }
+        if selection.include_parent_path {
+            let parent_path = self
+                .parent_of(referent)
+                .map(|parent| self.path_of(parent))
+                .unwrap_or_default();
+            metadata.insert("parentPath".to_string(), json!(parent_path));
+        }
+        if selection.include_run_context {
+            if let Ok(JsonValue::Object(run_context)) = self.read_property(referent, "RunContext") {
+                if let Some(JsonValue::String(value)) = run_context.get("value") {
+                    let item = value.rsplit('.').next().unwrap_or(value);
+                    metadata.insert("runContext".to_string(), json!(item));
+                }
+            }
+        }
+        if selection.include_attributes {
+            metadata.insert(
+                "attributes".to_string(),
+                JsonValue::Object(self.encode_attributes(&self.attributes_of(referent))),
+            );
+        }
+        (!metadata.is_empty()).then_some(JsonValue::Object(metadata))
+    }
+
+    fn create_script(
+        &mut self,
+        path: &[String],
+        script_type: &ScriptType,
+        source: Option<&str>,
+        run_context: Option<&str>,
+        attributes: &InstancePropertyMap,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::Create;
+        let mut parent_path = normalise_path(path);
+        let normalised = parent_path.clone();
+        let Some(desired_name) = parent_path.pop() else {
+            return failure(
+                action,
+                normalised,
+                "Create operations require a destination path".to_string(),
+            );
+        };
+        let parent = match self.resolve(&parent_path) {
+            Ok(parent) => parent,
+            Err(error) => return failure(action, normalised, error),
+        };
+        let class_name = script_type.class_name();
+        if let Err(error) = self.validate_script_placement(class_name, parent) {
+            return failure(action, normalised, error);
+        }
+        if self.find_child(parent, &desired_name).is_some() {
+            return failure(
+                action,
+                normalised,
+                format!(
+                    "An instance named '{desired_name}' already exists under {}",
+                    self.full_name(parent)
+                ),
+            );
+        }
+
+        let script = self.insert(parent, class_name, &desired_name);
+        if let Some(source) = source {
+            self.set_source_of(script, source);
+        }
+        if let Some(run_context) = run_context {
+            if let Err(error) = self.write_property(script, "RunContext", &json!(run_context)) {
+                self.dom.destroy(script);
+                return failure(
+                    action,
+                    normalised,
+                    format!("Failed to set RunContext: {error}"),
+                );
+            }
+        }
+        for (name, value) in attributes {
+            if let Err(error) = self.set_attribute(script, name, &value.to_json()) {
+                self.dom.destroy(script);
+                return failure(
+                    action,
+                    normalised,
+                    format!("Failed to set attribute '{name}': {error}"),
+                );
+            }
+        }
+
+        let mut result = make_result(
+            action,
+            self.path_of(script),
+            true,
+            Some(format!("Created {}", self.full_name(script))),
+        );
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({
+            "created": true,
+            "className": class_name,
+            "parentFullName": self.full_name(parent),
+        }));
+        result
+    }
+
+    fn get_source(
+        &self,
+        path: &[String],
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::GetSource;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let source = self.source_of(script);
+        let mut result = make_result(action, self.path_of(script), true, None);
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({ "characters": source.len() }));
+        result.source = Some(source);
+        result
+    }
+
+    fn set_source(
+        &mut self,
+        path: &[String],
+        source: &str,
+        expected_source: Option<&str>,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::SetSource;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let previous_source = self.source_of(script);
+        if expected_source.is_some_and(|expected| expected != previous_source) {
+            return failure(
+                action,
+                self.path_of(script),
+                "Script changed since it was read; fetch it again and retry".to_string(),
+            );
+        }
+        self.set_source_of(script, source);
+
+        let mut result = make_result(
+            action,
+            self.path_of(script),
+            true,
+            Some(format!("Updated {}", self.full_name(script))),
+        );
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({
+            "characters": source.len(),
+            "previousCharacters": previous_source.len(),
+            "changed": previous_source != source,
+        }));
+        result.source = Some(source.to_string());
+        result
+    }
+
+    fn rename_script(
+        &mut self,
+        path: &[String],
+        new_name: &str,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::Rename;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let previous_path = self.path_of(script);
+        if new_name.is_empty() {
+            return failure(
+                action,
+                previous_path,
+                "New name must be a non-empty string".to_string(),
+            );
+        }
+        if let Some(parent) = self.parent_of(script) {
+            if self
+                .find_child(parent, new_name)
+                .is_some_and(|sibling| sibling != script)
+            {
+                return failure(
+                    action,
+                    previous_path,
+                    format!(
+                        "An instance named '{new_name}' already exists under {}",
+                        self.full_name(parent)
+                    ),
+                );
+            }
+        }
+
+        let previous_name = self.name_of(script).to_string();
+        if let Ok(instance) = self.get_mut(script) {
+            instance.name = new_name.to_string();
+        }
+
+        let mut result = make_result(
+            action,
+            self.path_of(script),
+            true,
+            Some(format!("Renamed script to '{new_name}'")),
+        );
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({
+            "previousName": previous_name,
+            "currentName": new_name,
+            "previousPath": previous_path,
+        }));
+        result
+    }
+
+    fn delete_script(
+        &mut self,
+        path: &[String],
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::Delete;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let full_name = self.full_name(script);
+        let mut result = make_result(
+            action,
+            self.path_of(script),
+            true,
+            Some(format!("Deleted {full_name}")),
+        );
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({
+            "className": self.class_of(script),
+            "descendants": self.dom.descendants_of(script).count() - 1,
+        }));
+        self.dom.destroy(script);
+        result
+    }
+
+    /// Checks that a script of `class_name` may be given `name` under `parent`, ignoring `script`
+    /// itself when it is already there.
+    fn check_destination(
+        &self,
+        script: Ref,
+        class_name: &str,
+        parent: Ref,
+        name: &str,
+    ) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("New name must be a non-empty string".to_string());
+        }
+        if self
+            .find_child(parent, name)
+            .is_some_and(|existing| existing != script)
+        {
+            return Err(format!(
+                "An instance named '{name}' already exists under {}",
+                self.full_name(parent)
+            ));
+        }
+        self.validate_script_placement(class_name, parent)
+    }
+
+    fn move_script(
+        &mut self,
+        path: &[String],
+        new_parent: &[String],
+        new_name: Option<&str>,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::Move;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let previous_path = self.path_of(script);
+        if normalise_path(new_parent).is_empty() {
+            return failure(
+                action,
+                previous_path,
+                "Move operations require newParent".to_string(),
+            );
+        }
+        let parent = match self.resolve(new_parent) {
+            Ok(parent) => parent,
+            Err(error) => return failure(action, previous_path, error),
+        };
+        if parent == script || self.is_descendant_of(parent, script) {
+            return failure(
+                action,
+                previous_path,
+                "Cannot move a script into itself or one of its descendants".to_string(),
+            );
+        }
+        let name = new_name.unwrap_or(self.name_of(script)).to_string();
+        let class_name = self.class_of(script).to_string();
+        if let Err(error) = self.check_destination(script, &class_name, parent, &name) {
+            return failure(action, previous_path, error);
+        }
+
+        let previous_full_name = self.full_name(script);
+        self.dom.transfer_within(script, parent);
+        if let Ok(instance) = self.get_mut(script) {
+            instance.name = name;
+        }
+
+        let mut result = make_result(
+            action,
+            self.path_of(script),
+            true,
+            Some(format!(
+                "Moved {previous_full_name} to {}",
+                self.full_name(script)
+            )),
+        );
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({
+            "previousPath": previous_path,
+            "parentFullName": self.full_name(parent),
+        }));
+        result
+    }
+
+    fn duplicate_script(
+        &mut self,
+        path: &[String],
+        new_parent: Option<&[String]>,
+        new_name: Option<&str>,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::Duplicate;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let source_path = self.path_of(script);
+        let parent = match new_parent.filter(|path| !normalise_path(path).is_empty()) {
+            Some(new_parent) => match self.resolve(new_parent) {
+                Ok(parent) => parent,
+                Err(error) => return failure(action, source_path, error),
+            },
+            None => match self.parent_of(script) {
+                Some(parent) => parent,
+                None => {
+                    return failure(
+                        action,
+                        source_path,
+                        "Script has no parent to duplicate into".to_string(),
+                    )
+                }
+            },
+        };
+        let name = match new_name {
+            Some(name) => name.to_string(),
+            None if self.parent_of(script) == Some(parent) => {
+                format!("{}Copy", self.name_of(script))
+            }
+            None => self.name_of(script).to_string(),
+        };
+        let class_name = self.class_of(script).to_string();
+        if let Err(error) = self.check_destination(script, &class_name, parent, &name) {
+            return failure(action, source_path, error);
+        }
+
+        let copy = self.dom.clone_within(script);
+        self.dom.transfer_within(copy, parent);
+        if let Ok(instance) = self.get_mut(copy) {
+            instance.name = name;
+        }
+
+        let mut result = make_result(
+            action,
+            self.path_of(copy),
+            true,
+            Some(format!(
+                "Duplicated {} as {}",
+                self.full_name(script),
+                self.full_name(copy)
+            )),
+        );
+        result.metadata = self.gather_metadata(copy, metadata);
+        result.details = Some(json!({
+            "sourcePath": source_path,
+            "className": class_name,
+            "parentFullName": self.full_name(parent),
+        }));
+        result
+    }
+
+    fn convert_script(
+        &mut self,
+        path: &[String],
+        script_type: &ScriptType,
+        run_context: Option<&str>,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::ConvertType;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let script_path = self.path_of(script);
+        let previous_class = self.class_of(script).to_string();
+        let class_name = script_type.class_name();
+        if previous_class == class_name {
+            return failure(
+                action,
+                script_path,
+                format!("{} is already a {class_name}", self.full_name(script)),
+            );
+        }
+        let Some(parent) = self.parent_of(script) else {
+            return failure(
+                action,
+                script_path,
+                "Script has no parent to convert in".to_string(),
+            );
+        };
+        if let Err(error) = self.validate_script_placement(class_name, parent) {
+            return failure(action, script_path, error);
+        }
+
+        let name = self.name_of(script).to_string();
+        let converted = self.insert(parent, class_name, &name);
+        let both_toggleable = values::class_is_a(&previous_class, "BaseScript")
+            && values::class_is_a(class_name, "BaseScript");
+        let mut kept = vec!["Source", "Attributes", "Tags"];
+        if both_toggleable {
+            kept.push("Disabled");
+        }
+        let copied: Vec<(String, Variant)> = match self.dom.get_by_ref(script) {
+            Some(instance) => kept
+                .into_iter()
+                .filter_map(|property| {
+                    let value = instance.properties.get(property)?;
+                    Some((property.to_string(), value.clone()))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Ok(instance) = self.get_mut(converted) {
+            instance.properties.extend(copied);
+        }
+        if let Some(run_context) = run_context {
+            if let Err(error) = self.write_property(converted, "RunContext", &json!(run_context)) {
+                self.dom.destroy(converted);
+                return failure(
+                    action,
+                    script_path,
+                    format!("Failed to set RunContext: {error}"),
+                );
+            }
+        }
+        let children = self.children_of(script).to_vec();
+        for child in &children {
+            self.dom.transfer_within(*child, converted);
+        }
+        let attributes = self.attributes_of(converted).iter().count();
+        let tags = self.tags_of(converted).len();
+        self.dom.destroy(script);
+
+        let mut result = make_result(
+            action,
+            self.path_of(converted),
+            true,
+            Some(format!(
+                "Converted {} from {previous_class} to {class_name}",
+                self.full_name(converted)
+            )),
+        );
+        result.metadata = self.gather_metadata(converted, metadata);
+        result.details = Some(json!({
+            "previousClassName": previous_class,
+            "className": class_name,
+            "attributes": attributes,
+            "tags": tags,
+            "children": children.len(),
+        }));
+        result
+    }
+
+    fn set_script_enabled(
+        &mut self,
+        path: &[String],
+        enabled: bool,
+        metadata: Option<&ScriptMetadataSelection>,
+    ) -> ScriptOperationResult {
+        let action = ScriptOperationKind::SetEnabled;
+        let script = match self.resolve_script(path) {
+            Ok(script) => script,
+            Err(error) => return failure(action, normalise_path(path), error),
+        };
+        let script_path = self.path_of(script);
+        let class_name = self.class_of(script).to_string();
+        if !values::class_is_a(&class_name, "BaseScript") {
+            return failure(
+                action,
+                script_path,
+                format!(
+                    "{} is a {class_name} and cannot be enabled or disabled",
+                    self.full_name(script)
+                ),
+            );
+        }
+        // Files store the inverse `Disabled` property; `Enabled` does not serialize.
+        let previous_enabled = !matches!(
+            self.dom
+                .get_by_ref(script)
+                .and_then(|instance| instance.properties.get("Disabled")),
+            Some(Variant::Bool(true))
+        );
+        if let Ok(instance) = self.get_mut(script) {
+            instance
+                .properties
+                .insert("Disabled".to_string(), Variant::Bool(!enabled));
+        }
+
+        let verb = if enabled { "Enabled" } else { "Disabled" };
+        let mut result = make_result(
+            action,
+            script_path,
+            true,
+            Some(format!("{verb} {}", self.full_name(script))),
+        );
+        result.metadata = self.gather_metadata(script, metadata);
+        result.details = Some(json!({
+            "previousEnabled": previous_enabled,
+            "enabled": enabled,
+            "changed": previous_enabled != enabled,
+        }));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_edits_and_renames_scripts() {
+        let mut place = PlaceFile::empty("test.rbxl").expect("place");
+        let root = place.root();
+        place.insert(root, "ServerScriptService", "ServerScriptService");
+        place.insert(root, "StarterGui", "StarterGui");
+
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "operations": [
+                {
+                    "action": "create",
+                    "path": ["ServerScriptService", "Main"],
+                    "scriptType": "Script",
+                    "source": "print('hi')",
+                    "runContext": "Server",
+                },
+                { "action": "create", "path": ["StarterGui", "Bad"], "scriptType": "Script" },
+                { "action": "set_source", "path": ["ServerScriptService", "Main"], "source": "return 1" },
+                { "action": "rename", "path": ["ServerScriptService", "Main"], "newName": "Boot" },
+                {
+                    "action": "get_source",
+                    "path": ["ServerScriptService", "Boot"],
+                    "metadata": { "includeRunContext": true },
+                },
+            ],
+        }))
+        .expect("request");
+
+        let (response, write_occurred) = place.manage_scripts(&request).expect("response");
+        assert!(write_occurred);
+        let successes: Vec<bool> = response.results.iter().map(|r| r.success).collect();
+        assert_eq!(successes, [true, false, true, true, true]);
+        assert_eq!(
+            response.results[1].message.as_deref(),
+            Some("Server Scripts cannot run inside StarterGui")
+        );
+        assert_eq!(response.results[4].source.as_deref(), Some("return 1"));
+        assert_eq!(
+            response.results[4].metadata,
+            Some(json!({ "runContext": "Server" }))
+        );
+    }
+
+    #[test]
+    fn atomic_batches_roll_back_on_failure() {
+        let mut place = PlaceFile::empty("test.rbxl").expect("place");
+        let root = place.root();
+        place.insert(root, "ServerScriptService", "ServerScriptService");
+
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "atomic": true,
+            "operations": [
+                { "action": "create", "path": ["ServerScriptService", "Main"], "scriptType": "Script" },
+                { "action": "delete", "path": ["ServerScriptService", "Missing"] },
+                { "action": "create", "path": ["ServerScriptService", "Other"], "scriptType": "Script" },
+            ],
+        }))
+        .expect("request");
+
+        let (response, write_occurred) = place.manage_scripts(&request).expect("response");
+        assert!(!write_occurred);
+        assert!(response.rolled_back);
+        assert!(response.results.iter().all(|result| !result.success));
+        assert_eq!(
+            response.results[2].message.as_deref(),
+            Some("Skipped because the batch was rolled back")
+        );
+        assert!(place
+            .resolve(&["ServerScriptService".into(), "Main".into()])
+            .is_err());
+    }
+
+    /// A place whose `ServerScriptService.Main` script returns 1 and holds a `Config` folder and a
+    /// `Speed` attribute, next to an empty ReplicatedStorage and StarterGui.
+    fn sample_scripts() -> PlaceFile {
+        let mut place = PlaceFile::empty("test.rbxl").expect("place");
+        let root = place.root();
+        let service = place.insert(root, "ServerScriptService", "ServerScriptService");
+        place.insert(root, "ReplicatedStorage", "ReplicatedStorage");
+        place.insert(root, "StarterGui", "StarterGui");
+        let main = place.insert(service, "Script", "Main");
+        place.set_source_of(main, "return 1");
+        place.insert(main, "Folder", "Config");
+        place
+            .set_attribute(main, "Speed", &json!(4))
+            .expect("attribute");
+        place
+    }
+
+    fn run(place: &mut PlaceFile, operations: JsonValue) -> ManageScriptsResponse {
+        let request: ManageScriptsRequest =
+            serde_json::from_value(json!({ "operations": operations })).expect("request");
+        place.manage_scripts(&request).expect("response").0
+    }
+
+    #[test]
+    fn duplicates_scripts_beside_the_original() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([{ "action": "duplicate", "path": ["ServerScriptService", "Main"] }]),
+        );
+        assert!(response.results[0].success);
+        assert_eq!(
+            response.results[0].path,
+            ["ServerScriptService", "MainCopy"]
+        );
+        assert!(place
+            .resolve(&["ServerScriptService".to_string(), "MainCopy".to_string()])
+            .is_ok());
+    }
+
+    #[test]
+    fn refuses_moves_that_break_placement_rules() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([
+                { "action": "move", "path": ["ServerScriptService", "Main"], "newParent": ["StarterGui"] },
+            ]),
+        );
+        assert!(!response.results[0].success);
+        assert_eq!(
+            response.results[0].message.as_deref(),
+            Some("Server Scripts cannot run inside StarterGui")
+        );
+    }
+
+    #[test]
+    fn converts_script_types_keeping_contents() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([
+                {
+                    "action": "convert_type",
+                    "path": ["ServerScriptService", "Main"],
+                    "scriptType": "ModuleScript",
+                },
+            ]),
+        );
+        assert_eq!(
+            response.results[0].details,
+            Some(json!({
+                "previousClassName": "Script",
+                "className": "ModuleScript",
+                "attributes": 1,
+                "tags": 0,
+                "children": 1,
+            }))
+        );
+        let main = place
+            .resolve(&["ServerScriptService".to_string(), "Main".to_string()])
+            .expect("converted script");
+        assert_eq!(place.class_of(main), "ModuleScript");
+        assert_eq!(place.source_of(main), "return 1");
+    }
+
+    #[test]
+    fn moves_and_renames_scripts() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([
+                {
+                    "action": "convert_type",
+                    "path": ["ServerScriptService", "Main"],
+                    "scriptType": "ModuleScript",
+                },
+                {
+                    "action": "move",
+                    "path": ["ServerScriptService", "Main"],
+                    "newParent": ["ReplicatedStorage"],
+                    "newName": "Shared",
+                },
+            ]),
+        );
+        assert!(response.results[1].success);
+        assert!(place
+            .resolve(&["ReplicatedStorage".to_string(), "Shared".to_string()])
+            .is_ok());
+    }
+
+    #[test]
+    fn refuses_to_enable_module_scripts() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([
+                { "action": "set_enabled", "path": ["ServerScriptService", "Main"], "enabled": false },
+                {
+                    "action": "convert_type",
+                    "path": ["ServerScriptService", "Main"],
+                    "scriptType": "ModuleScript",
+                },
+                { "action": "set_enabled", "path": ["ServerScriptService", "Main"], "enabled": true },
+            ]),
+        );
+        let successes: Vec<bool> = response.results.iter().map(|r| r.success).collect();
+        assert_eq!(successes, [true, true, false]);
+    }
+
+    #[test]
+    fn deletes_scripts() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([{ "action": "delete", "path": ["ServerScriptService", "Main"] }]),
+        );
+        assert!(response.results[0].success);
+        assert!(place
+            .resolve(&["ServerScriptService".to_string(), "Main".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn refuses_targets_that_are_not_scripts() {
+        let mut place = sample_scripts();
+        let response = run(
+            &mut place,
+            json!([{ "action": "get_source", "path": ["ServerScriptService", "Main", "Config"] }]),
+        );
+        assert_eq!(
+            response.results[0].message.as_deref(),
+            Some("ServerScriptService.Main.Config is a Folder, expected a script")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Offline port of `Selector.luau`: resolves parsed selectors against the file's instance tree.
+
+use super::{values, PlaceFile};
+use crate::rbx_studio_server::selector::{
+    glob_matches, Combinator, Filter, InstanceTarget, Selector,
+};
+use rbx_dom_weak::types::Ref;
+use std::collections::HashSet;
+
+impl PlaceFile {
+    /// Instances matched by any alternative of `selector`, without duplicates.
+    pub(super) fn select(&self, selector: &Selector) -> Vec<Ref> {
+        let mut seen = HashSet::new();
+        let mut matched = Vec::new();
+        for steps in &selector.alternatives {
+            let mut current = vec![self.root()];
+            for step in steps {
+                let mut next_seen = HashSet::new();
+                let mut next = Vec::new();
+                for &scope in &current {
+                    let candidates: Vec<Ref> = match step.combinator {
+                        Combinator::Child => self.children_of(scope).to_vec(),
+                        Combinator::Descendant => self
+                            .dom
+                            .descendants_of(scope)
+                            .skip(1)
+                            .map(|i| i.referent())
+                            .collect(),
+                    };
+                    for candidate in candidates {
+                        if step
+                            .filters
+                            .iter()
+                            .all(|filter| self.matches(candidate, filter))
+                            && next_seen.insert(candidate)
+                        {
+                            next.push(candidate);
+                        }
+                    }
+                }
+                current = next;
+            }
+            matched.extend(
+                current
+                    .into_iter()
+                    .filter(|referent| seen.insert(*referent)),
+            );
+        }
+        matched
+    }
+
+    fn matches(&self, referent: Ref, filter: &Filter) -> bool {
+        match filter {
+            Filter::Class { class_name } => {
+                class_name == "*" || values::class_is_a(self.class_of(referent), class_name)
+            }
+            Filter::ExactClass { class_name } => self.class_of(referent) == class_name,
+            Filter::Name { pattern } => glob_matches(pattern, self.name_of(referent)),
+            Filter::Tag { tag } => self.tags_of(referent).contains(tag),
+            Filter::Attribute { name, op, value } => {
+                let attributes = self.encode_attributes(&self.attributes_of(referent));
+                match (attributes.get(name), op, value) {
+                    (None, _, _) => false,
+                    (Some(actual), Some(op), Some(expected)) => op.matches(actual, expected),
+                    (Some(_), _, _) => true,
+                }
+            }
+            Filter::Property { name, op, value } => self
+                .read_property(referent, name)
+                .is_ok_and(|actual| op.matches(&actual, value)),
+        }
+    }
+
+    /// Expands targets into instance paths: paths are kept as given, and each selector becomes the
+    /// paths of the instances it matches. A selector that matches nothing is an error, like a path
+    /// that does not resolve.
+    pub(super) fn expand_targets(
+        &self,
+        targets: &[InstanceTarget],
+    ) -> Result<Vec<Vec<String>>, String> {
+        let mut paths = Vec::with_capacity(targets.len());
+        for target in targets {
+            match target {
+                InstanceTarget::Path(path) => paths.push(path.clone()),
+                InstanceTarget::Selector(selector) => {
+                    let matched = self.select(selector);
+                    if matched.is_empty() {
+                        return Err(format!(
+                            "Selector '{}' matched no instances",
+                            selector.source
+                        ));
+                    }
+                    paths.extend(matched.into_iter().map(|referent| self.path_of(referent)));
+                }
+            }
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rbx_studio_server::CollectionAndAttributesRequest;
+    use serde_json::json;
+
+    #[test]
+    fn selects_by_class_tag_attribute_and_property() {
+        let mut place = PlaceFile::empty("test.rbxl").expect("place");
+        let root = place.root();
+        let workspace = place.insert(root, "Workspace", "Workspace");
+        let enemies = place.insert(workspace, "Model", "Goblin");
+        place.insert(workspace, "Part", "Loose");
+        place.insert(enemies, "Part", "Body");
+        place.insert(enemies, "MeshPart", "Sword");
+
+        let request: CollectionAndAttributesRequest = serde_json::from_value(json!({
+            "operations": [
+                { "operation": "add_tags", "paths": ["Workspace > Model#Gob*"], "tags": ["Enemy"] },
+                { "operation": "sync_attributes", "paths": ["Model[Tag=Enemy] > Part"], "attributes": { "Health": 80 } },
+                { "operation": "add_tags", "paths": ["Part#Missing"], "tags": ["Enemy"] },
+            ],
+        }))
+        .expect("request");
+        let response = place.collection_and_attributes(&request).expect("response");
+        assert!(response.results[1].success);
+        assert_eq!(
+            response.results[2].message.as_deref(),
+            Some("Selector 'Part#Missing' matched no instances")
+        );
+
+        let select = |source: &str| {
+            let selector = Selector::parse(source).expect("selector");
+            place
+                .select(&selector)
+                .into_iter()
+                .map(|referent| place.full_name(referent))
+                .collect::<Vec<_>>()
+        };
+        // MeshPart is a BasePart, not a Part, so only the body received the attribute.
+        assert_eq!(select("*[@Health>50]"), ["Workspace.Goblin.Body"]);
+        assert_eq!(
+            select("[ClassName=Part].Anchored=false"),
+            ["Workspace.Loose", "Workspace.Goblin.Body"]
+        );
+        assert_eq!(
+            select("Workspace > Part, MeshPart"),
+            ["Workspace.Loose", "Workspace.Goblin.Sword"]
+        );
+    }
+}
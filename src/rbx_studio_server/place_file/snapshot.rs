@@ -0,0 +1,318 @@
+//! Offline port of `DataModelSnapshot.luau`.
+
+use super::{normalise_path, PlaceFile};
+use crate::rbx_studio_server::{
+    DataModelSnapshotInstance, DataModelSnapshotPropertyError, DataModelSnapshotPropertyPick,
+    DataModelSnapshotRequest, DataModelSnapshotResponse, RobloxValue,
+};
+use color_eyre::eyre::{eyre, Result};
+use rbx_dom_weak::types::Ref;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+struct TraversalItem {
+    referent: Ref,
+    path: Vec<String>,
+    depth: u32,
+}
+
+/// A property pick with empty entries removed, as `sanitisePropertyPicks` does.
+struct PropertyPick<'a> {
+    classes: Vec<&'a str>,
+    properties: Vec<&'a str>,
+    sample_count: Option<usize>,
+    randomize: bool,
+}
+
+fn sanitise_property_picks(raw: &[DataModelSnapshotPropertyPick]) -> Vec<PropertyPick<'_>> {
+    raw.iter()
+        .filter_map(|pick| {
+            let properties: Vec<&str> = pick
+                .properties
+                .iter()
+                .map(String::as_str)
+                .filter(|property| !property.is_empty())
+                .collect();
+            if properties.is_empty() {
+                return None;
+            }
+            let sample_count = pick
+                .sample_count
+                .filter(|count| *count > 0)
+                .map(|count| count as usize);
+            Some(PropertyPick {
+                classes: pick
+                    .classes
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|class| !class.is_empty())
+                    .collect(),
+                properties,
+                sample_count,
+                randomize: pick.randomize == Some(true) && sample_count.is_some(),
+            })
+        })
+        .collect()
+}
+
+fn gather_properties<'a>(
+    class_name: &str,
+    picks: &[PropertyPick<'a>],
+    rng: &mut fastrand::Rng,
+) -> Vec<&'a str> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for pick in picks {
+        if !pick.classes.is_empty() && !pick.classes.contains(&class_name) {
+            continue;
+        }
+        let mut working = pick.properties.clone();
+        if let Some(sample_count) = pick.sample_count.filter(|count| working.len() > *count) {
+            if pick.randomize {
+                rng.shuffle(&mut working);
+            }
+            working.truncate(sample_count);
+        }
+        for property in working {
+            if seen.insert(property) {
+                result.push(property);
+            }
+        }
+    }
+    result
+}
+
+fn build_set(list: &[String]) -> Option<HashSet<&str>> {
+    let set: HashSet<&str> = list
+        .iter()
+        .map(String::as_str)
+        .filter(|value| !value.is_empty())
+        .collect();
+    (!set.is_empty()).then_some(set)
+}
+
+impl PlaceFile {
+    pub(super) fn data_model_snapshot(
+        &self,
+        request: &DataModelSnapshotRequest,
+    ) -> Result<DataModelSnapshotResponse> {
+        let allow_list = build_set(&request.class_allow_list);
+        let block_list = build_set(&request.class_block_list);
+        let picks = sanitise_property_picks(&request.property_picks);
+        let include_properties = request.include_properties.unwrap_or(!picks.is_empty());
+        let include_attributes = request.include_attributes != Some(false);
+        let include_full_name = request.include_full_name != Some(false);
+        let sort_children = request.sort_children_by_name != Some(false);
+        let page_size = request
+            .page_size
+            .filter(|size| *size > 0)
+            .map(|size| size as usize);
+        let start_index = match &request.page_cursor {
+            Some(cursor) => cursor
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(f64::floor)
+                .filter(|cursor| *cursor >= 1.0)
+                .ok_or_else(|| eyre!("pageCursor must be a positive integer or numeric string"))?
+                as u64,
+            None => 1,
+        };
+        let mut rng = match request.random_seed {
+            Some(seed) => fastrand::Rng::with_seed(seed.max(1)),
+            None => fastrand::Rng::new(),
+        };
+
+        let mut roots = Vec::new();
+        if request.root_paths.is_empty() {
+            roots.push(TraversalItem {
+                referent: self.root(),
+                path: Vec::new(),
+                depth: 0,
+            });
+        } else {
+            for path in self
+                .expand_targets(&request.root_paths)
+                .map_err(|error| eyre!(error))?
+            {
+                let referent = self.resolve(&path).map_err(|error| eyre!(error))?;
+                roots.push(TraversalItem {
+                    referent,
+                    path: normalise_path(&path),
+                    depth: 0,
+                });
+            }
+        }
+        let root_count = roots.len();
+
+        let mut stack: Vec<TraversalItem> = roots.into_iter().rev().collect();
+        let mut entries = Vec::new();
+        let mut total_visited = 0u64;
+        let mut total_matched = 0u64;
+        let mut truncated = false;
+
+        while let Some(current) = stack.pop() {
+            total_visited += 1;
+            let class_name = self.class_of(current.referent);
+            if block_list
+                .as_ref()
+                .is_some_and(|block_list| block_list.contains(class_name))
+            {
+                continue;
+            }
+
+            let mut children = self.children_of(current.referent).to_vec();
+            let include_entry = allow_list
+                .as_ref()
+                .is_none_or(|allow_list| allow_list.contains(class_name));
+            if include_entry {
+                total_matched += 1;
+                if total_matched >= start_index {
+                    entries.push(self.snapshot_entry(
+                        &current,
+                        children.len(),
+                        include_attributes,
+                        include_full_name,
+                        include_properties.then_some(picks.as_slice()),
+                        &mut rng,
+                    ));
+                    if page_size.is_some_and(|page_size| entries.len() >= page_size) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+
+            let can_traverse = request
+                .max_depth
+                .is_none_or(|max_depth| current.depth < max_depth);
+            if can_traverse && !children.is_empty() {
+                if sort_children {
+                    children.sort_by_cached_key(|child| self.name_of(*child).to_lowercase());
+                }
+                for child in children.into_iter().rev() {
+                    let mut path = current.path.clone();
+                    path.push(self.name_of(child).to_string());
+                    stack.push(TraversalItem {
+                        referent: child,
+                        path,
+                        depth: current.depth + 1,
+                    });
+                }
+            }
+        }
+
+        Ok(DataModelSnapshotResponse {
+            entries,
+            next_cursor: truncated.then(|| (total_matched + 1).to_string()),
+            total_matched,
+            total_visited,
+            truncated,
+            metadata: HashMap::from([
+                (
+                    "generatedAt".to_string(),
+                    json!(humantime::format_rfc3339_millis(SystemTime::now()).to_string()),
+                ),
+                ("rootCount".to_string(), json!(root_count)),
+                ("startIndex".to_string(), json!(start_index)),
+                ("source".to_string(), json!(self.path.display().to_string())),
+            ]),
+        })
+    }
+
+    fn snapshot_entry(
+        &self,
+        item: &TraversalItem,
+        child_count: usize,
+        include_attributes: bool,
+        include_full_name: bool,
+        picks: Option<&[PropertyPick]>,
+        rng: &mut fastrand::Rng,
+    ) -> DataModelSnapshotInstance {
+        let class_name = self.class_of(item.referent);
+        let mut entry = DataModelSnapshotInstance {
+            path: item.path.clone(),
+            id: Some(item.referent.to_string()),
+            name: self.name_of(item.referent).to_string(),
+            class_name: class_name.to_string(),
+            full_name: include_full_name.then(|| self.full_name(item.referent)),
+            depth: item.depth,
+            child_count: Some(child_count as u32),
+            ..Default::default()
+        };
+
+        if include_attributes {
+            entry.attributes = self
+                .encode_attributes(&self.attributes_of(item.referent))
+                .into_iter()
+                .map(|(name, value)| (name, RobloxValue::from_json(value)))
+                .collect();
+        }
+
+        if let Some(picks) = picks {
+            for property in gather_properties(class_name, picks, rng) {
+                match self.read_property(item.referent, property) {
+                    Ok(value) => {
+                        entry
+                            .properties
+                            .insert(property.to_string(), RobloxValue::from_json(value));
+                    }
+                    Err(message) => entry.property_errors.push(DataModelSnapshotPropertyError {
+                        property: property.to_string(),
+                        message,
+                    }),
+                }
+            }
+        }
+
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::sample_place;
+    use super::*;
+
+    #[test]
+    fn traverses_depth_first_with_sorted_children_and_paging() {
+        let place = sample_place("test.rbxl");
+        let request = DataModelSnapshotRequest {
+            root_paths: vec![vec!["Workspace".to_string()].into()],
+            page_size: Some(3),
+            ..Default::default()
+        };
+        let response = place.data_model_snapshot(&request).expect("snapshot");
+        let names: Vec<&str> = response.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Workspace", "Crate", "Level"]);
+        assert!(response.truncated);
+        assert_eq!(response.next_cursor.as_deref(), Some("4"));
+
+        let request = DataModelSnapshotRequest {
+            page_cursor: response.next_cursor,
+            ..request
+        };
+        let response = place.data_model_snapshot(&request).expect("second page");
+        assert_eq!(response.entries.len(), 2);
+        assert_eq!(response.entries[0].path, ["Workspace", "Level", "Floor"]);
+    }
+
+    #[test]
+    fn reads_picked_properties_with_defaults() {
+        let place = sample_place("test.rbxl");
+        let request = DataModelSnapshotRequest {
+            class_allow_list: vec!["Part".to_string()],
+            property_picks: vec![DataModelSnapshotPropertyPick {
+                properties: vec!["Anchored".to_string(), "Bogus".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let response = place.data_model_snapshot(&request).expect("snapshot");
+        assert_eq!(response.total_matched, 3);
+        let entry = &response.entries[0];
+        assert_eq!(entry.properties["Anchored"], RobloxValue::Bool(false));
+        assert_eq!(entry.property_errors[0].property, "Bogus");
+    }
+}
@@ -0,0 +1,392 @@
+//! Template models for `instantiate_template`: reading their parameters, filling them in, and the
+//! offline port of the plugin's `InstantiateTemplate` tool.
+
+use super::assemblies::{cframe_from_components, vector};
+use super::{FileFormat, FileKind, PlaceFile};
+use crate::rbx_studio_server::templates::{
+    InstantiateTemplatePayload, InstantiateTemplateRequest, InstantiateTemplateResponse,
+};
+use base64::Engine;
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use rbx_dom_weak::types::{Attributes, CFrame, Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::PathBuf;
+
+/// What `manage_templates` reports about a template model.
+pub(in crate::rbx_studio_server) struct TemplateContents {
+    pub(in crate::rbx_studio_server) class_name: String,
+    pub(in crate::rbx_studio_server) root_name: String,
+    pub(in crate::rbx_studio_server) instance_count: usize,
+    pub(in crate::rbx_studio_server) parameters: Vec<String>,
+}
+
+fn placeholder_pattern() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid placeholder pattern")
+}
+
+/// Decodes a binary model, which templates require to have a single root.
+fn decode_template(model: &[u8]) -> Result<(PlaceFile, Ref)> {
+    let dom: WeakDom =
+        rbx_binary::from_reader(model).wrap_err("Unable to decode template model")?;
+    let place = PlaceFile {
+        path: PathBuf::from("template.rbxm"),
+        format: FileFormat::Binary,
+        kind: FileKind::Model,
+        dom,
+    };
+    match place.children_of(place.root()) {
+        [root] => {
+            let root = *root;
+            Ok((place, root))
+        }
+        [] => bail!("The template model contains no instances"),
+        roots => bail!(
+            "Templates have a single root instance, but the model has {}; group them into a Model first",
+            roots.len()
+        ),
+    }
+}
+
+impl PlaceFile {
+    /// `root` and its descendants, root first.
+    fn subtree(&self, root: Ref) -> Vec<Ref> {
+        let mut instances = vec![root];
+        let mut index = 0;
+        while index < instances.len() {
+            instances.extend_from_slice(self.children_of(instances[index]));
+            index += 1;
+        }
+        instances
+    }
+
+    /// The texts that can hold placeholders: the instance's name and its string attributes.
+    fn template_texts(&self, referent: Ref) -> Vec<String> {
+        let mut texts = vec![self.name_of(referent).to_string()];
+        texts.extend(
+            self.attributes_of(referent)
+                .iter()
+                .filter_map(|(_, value)| attribute_text(value)),
+        );
+        texts
+    }
+
+    /// Serialises the instance at `path` and everything under it as a template model.
+    pub(in crate::rbx_studio_server) fn export_template(&self, path: &[String]) -> Result<Vec<u8>> {
+        let referent = self.resolve(path).map_err(|error| eyre!(error))?;
+        if referent == self.root() {
+            bail!("The DataModel itself cannot be saved as a template");
+        }
+        let mut buffer = Vec::new();
+        rbx_binary::to_writer(&mut buffer, &self.dom, &[referent])
+            .wrap_err("Unable to encode the template model")?;
+        Ok(buffer)
+    }
+
+    /// Equivalent of the plugin's `InstantiateTemplate` tool.
+    pub(super) fn instantiate_template(
+        &mut self,
+        payload: &InstantiateTemplatePayload,
+    ) -> Result<InstantiateTemplateResponse> {
+        let model = base64::engine::general_purpose::STANDARD
+            .decode(&payload.model)
+            .wrap_err("Invalid template model data")?;
+        let (template, template_root) = decode_template(&model)?;
+        let parent = self
+            .resolve(&payload.parent_path)
+            .map_err(|error| eyre!(error))?;
+        self.validate_script_placement(template.class_of(template_root), parent)
+            .map_err(|error| eyre!(error))?;
+
+        let name =
+            self.reserve_unique_name(parent, template.name_of(template_root), &mut HashSet::new());
+        let instance = template
+            .dom
+            .clone_into_external(template_root, &mut self.dom);
+        self.get_mut(instance)?.name = name;
+        self.dom.transfer_within(instance, parent);
+
+        let instance_count = self.subtree(instance).len();
+        Ok(InstantiateTemplateResponse {
+            template: payload.template.clone(),
+            path: self.path_of(instance),
+            id: Some(instance.to_string()),
+            summary: format!(
+                "Instantiated template '{}' as {} ({instance_count} instance{})",
+                payload.template,
+                self.full_name(instance),
+                if instance_count == 1 { "" } else { "s" }
+            ),
+            write_occurred: true,
+        })
+    }
+}
+
+/// Describes a template model: its root and the parameters its placeholders name.
+pub(in crate::rbx_studio_server) fn inspect_template(model: &[u8]) -> Result<TemplateContents> {
+    let (template, root) = decode_template(model)?;
+    let pattern = placeholder_pattern();
+    let instances = template.subtree(root);
+    let parameters: BTreeSet<String> = instances
+        .iter()
+        .flat_map(|referent| template.template_texts(*referent))
+        .flat_map(|text| {
+            pattern
+                .captures_iter(&text)
+                .map(|captures| captures[1].to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    Ok(TemplateContents {
+        class_name: template.class_of(root).to_string(),
+        root_name: template.name_of(root).to_string(),
+        instance_count: instances.len(),
+        parameters: parameters.into_iter().collect(),
+    })
+}
+
+/// String attributes read back from a binary model are `BinaryString`s.
+fn attribute_text(value: &Variant) -> Option<String> {
+    match value {
+        Variant::String(text) => Some(text.clone()),
+        Variant::BinaryString(bytes) => {
+            String::from_utf8(AsRef::<[u8]>::as_ref(bytes).to_vec()).ok()
+        }
+        _ => None,
+    }
+}
+
+fn substitute(
+    pattern: &Regex,
+    text: &str,
+    parameters: &BTreeMap<String, String>,
+    missing: &mut BTreeSet<String>,
+) -> String {
+    pattern
+        .replace_all(text, |captures: &regex::Captures| {
+            match parameters.get(&captures[1]) {
+                Some(value) => value.clone(),
+                None => {
+                    missing.insert(captures[1].to_string());
+                    captures[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+/// Fills in a template model's parameters, then names, sets attributes on, and pivots its root as
+/// `request` asks. Returns the binary model to insert.
+pub(in crate::rbx_studio_server) fn prepare_template(
+    model: &[u8],
+    request: &InstantiateTemplateRequest,
+) -> Result<Vec<u8>> {
+    let (mut template, root) = decode_template(model)?;
+    let pattern = placeholder_pattern();
+    let known = inspect_template(model)?.parameters;
+    let unknown: Vec<&str> = request
+        .parameters
+        .keys()
+        .filter(|name| !known.contains(name))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        bail!(
+            "Template '{}' has no parameter named {}; it takes: {}",
+            request.name,
+            unknown.join(", "),
+            if known.is_empty() {
+                "no parameters".to_string()
+            } else {
+                known.join(", ")
+            }
+        );
+    }
+
+    let mut missing = BTreeSet::new();
+    for referent in template.subtree(root) {
+        let name = substitute(
+            &pattern,
+            template.name_of(referent),
+            &request.parameters,
+            &mut missing,
+        );
+        let attributes: Attributes = template
+            .attributes_of(referent)
+            .into_iter()
+            .map(|(key, value)| match attribute_text(&value) {
+                Some(text) => {
+                    let text = substitute(&pattern, &text, &request.parameters, &mut missing);
+                    (key, Variant::String(text))
+                }
+                None => (key, value),
+            })
+            .collect();
+        let instance = template.get_mut(referent)?;
+        instance.name = name;
+        if !attributes.is_empty() {
+            instance
+                .properties
+                .insert("Attributes".to_string(), Variant::Attributes(attributes));
+        }
+    }
+    if !missing.is_empty() {
+        bail!(
+            "Template '{}' needs values for these parameters: {}",
+            request.name,
+            missing.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    if let Some(name) = request
+        .instance_name
+        .as_deref()
+        .filter(|name| !name.is_empty())
+    {
+        template.get_mut(root)?.name = name.to_string();
+    }
+    for (name, value) in &request.attributes {
+        template
+            .set_attribute(root, name, &value.to_json())
+            .map_err(|error| eyre!("Failed to set attribute '{name}': {error}"))?;
+    }
+    if request.cframe.is_some() || request.position.is_some() {
+        let Some(pivot) = template.pivot_of(root) else {
+            bail!(
+                "Template '{}' is a {}, which has no pivot to place",
+                request.name,
+                template.class_of(root)
+            );
+        };
+        let target = match (&request.cframe, request.position) {
+            (Some(components), _) => cframe_from_components(components),
+            (None, Some(position)) => CFrame::new(vector(position), pivot.orientation),
+            (None, None) => pivot,
+        };
+        template.pivot_to(root, target);
+    }
+
+    let mut buffer = Vec::new();
+    rbx_binary::to_writer(&mut buffer, &template.dom, &[root])
+        .wrap_err("Unable to encode the template model")?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A place holding a parameterised `Crate_{{id}}` model, and that model exported as a template.
+    fn sample_template() -> (PlaceFile, Vec<u8>) {
+        let mut source = PlaceFile::empty("source.rbxl").expect("place");
+        let root = source.root();
+        let workspace = source.insert(root, "Workspace", "Workspace");
+        let model = source.insert(workspace, "Model", "Crate_{{id}}");
+        let lid = source.insert(model, "Part", "Lid");
+        source
+            .set_attribute(model, "Team", &json!("{{team}}"))
+            .expect("attribute");
+        source
+            .write_property(
+                lid,
+                "Position",
+                &json!({ "type": "Vector3", "x": 0, "y": 1, "z": 0 }),
+            )
+            .expect("position");
+        source
+            .set_attribute(lid, "Fragile", &json!(true))
+            .expect("attribute");
+        let exported = source
+            .export_template(&["Workspace".into(), "Crate_{{id}}".into()])
+            .expect("export");
+        (source, exported)
+    }
+
+    fn request(parameters: serde_json::Value) -> InstantiateTemplateRequest {
+        serde_json::from_value(json!({
+            "name": "crate",
+            "parentPath": ["Workspace"],
+            "parameters": parameters,
+            "attributes": { "Loot": 3 },
+            "position": [10, 0, 0],
+        }))
+        .expect("request")
+    }
+
+    fn payload(
+        exported: &[u8],
+        request: &InstantiateTemplateRequest,
+    ) -> InstantiateTemplatePayload {
+        let prepared = prepare_template(exported, request).expect("prepare");
+        InstantiateTemplatePayload {
+            template: "crate".into(),
+            parent_path: vec!["Workspace".into()],
+            model: base64::engine::general_purpose::STANDARD.encode(prepared),
+        }
+    }
+
+    #[test]
+    fn lists_the_parameters_of_a_template() {
+        let (_, exported) = sample_template();
+        assert_eq!(
+            inspect_template(&exported).expect("inspect").parameters,
+            vec!["id".to_string(), "team".to_string()]
+        );
+    }
+
+    #[test]
+    fn requires_every_parameter() {
+        let (_, exported) = sample_template();
+        assert!(prepare_template(&exported, &request(json!({ "id": "7" })))
+            .expect_err("missing parameter")
+            .to_string()
+            .contains("team"));
+    }
+
+    #[test]
+    fn fills_in_parameters_and_attributes() {
+        let (mut source, exported) = sample_template();
+        let request = request(json!({ "id": "7", "team": "Red" }));
+        let copy = source
+            .instantiate_template(&payload(&exported, &request))
+            .expect("instantiate");
+        assert_eq!(
+            copy.path,
+            vec!["Workspace".to_string(), "Crate_7".to_string()]
+        );
+
+        let copy = source.resolve(&copy.path).expect("copy");
+        let attributes = source.encode_attributes(&source.attributes_of(copy));
+        assert_eq!(attributes.get("Team"), Some(&json!("Red")));
+        assert_eq!(attributes.get("Loot"), Some(&json!(3.0)));
+    }
+
+    #[test]
+    fn numbers_copies_whose_name_is_taken() {
+        let (mut source, exported) = sample_template();
+        let payload = payload(&exported, &request(json!({ "id": "7", "team": "Red" })));
+        source.instantiate_template(&payload).expect("instantiate");
+        let second = source.instantiate_template(&payload).expect("instantiate");
+        assert_eq!(
+            second.path,
+            vec!["Workspace".to_string(), "Crate_7 (1)".to_string()]
+        );
+    }
+
+    #[test]
+    fn moves_the_copy_to_the_requested_position() {
+        let (mut source, exported) = sample_template();
+        let request = request(json!({ "id": "7", "team": "Red" }));
+        let copy = source
+            .instantiate_template(&payload(&exported, &request))
+            .expect("instantiate");
+        let copy = source.resolve(&copy.path).expect("copy");
+        let lid = source.find_child(copy, "Lid").expect("lid");
+        assert_eq!(
+            source.read_property(lid, "Position").expect("position"),
+            json!({ "type": "Vector3", "x": 10.0, "y": 0.0, "z": 0.0 })
+        );
+    }
+}
@@ -0,0 +1,869 @@
+//! Conversion between rbx-dom variants and the JSON shapes used by the Studio plugin.
+//!
+//! Encoding mirrors `encodeValue` in `DataModelSnapshot.luau` and decoding accepts the shapes
+//! understood by `decodePropertyValue` in `ApplyInstanceOperations.luau`, plus the encoded forms so
+//! snapshot output can be written back unchanged.
+
+use super::PlaceFile;
+use crate::rbx_studio_server::roblox_value::find_property;
+use rbx_dom_weak::types::{
+    Attributes, BrickColor, CFrame, Color3, ColorSequence, ColorSequenceKeypoint, Content,
+    CustomPhysicalProperties, Enum, Font, FontStyle, FontWeight, Matrix3, NumberRange,
+    NumberSequence, NumberSequenceKeypoint, PhysicalProperties, Rect, Ref, UDim, UDim2, Variant,
+    VariantType, Vector2, Vector2int16, Vector3, Vector3int16,
+};
+use rbx_reflection::DataType;
+use serde_json::{json, Map, Value as JsonValue};
+
+/// Equivalent of `Instance:IsA(ancestor)` using the bundled reflection database.
+pub(super) fn class_is_a(class_name: &str, ancestor: &str) -> bool {
+    let database = rbx_reflection_database::get();
+    let mut current = database.classes.get(class_name);
+    while let Some(class) = current {
+        if class.name == ancestor {
+            return true;
+        }
+        current = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| database.classes.get(superclass));
+    }
+    false
+}
+
+fn default_property(class_name: &str, property: &str) -> Option<&'static Variant> {
+    let database = rbx_reflection_database::get();
+    database.find_default_property(database.classes.get(class_name)?, property)
+}
+
+fn enum_item_name(enum_name: &str, value: u32) -> Option<&'static str> {
+    rbx_reflection_database::get()
+        .enums
+        .get(enum_name)?
+        .items
+        .iter()
+        .find(|(_, item_value)| **item_value == value)
+        .map(|(name, _)| name.as_ref())
+}
+
+/// Properties that Studio exposes but that are derived from `CFrame` rather than stored.
+fn has_derived_transform(class_name: &str) -> bool {
+    class_is_a(class_name, "BasePart") || class_is_a(class_name, "Attachment")
+}
+
+fn encode_number(value: f64) -> JsonValue {
+    if value.is_nan() {
+        json!({ "type": "number", "value": "nan" })
+    } else if value.is_infinite() {
+        let text = if value > 0.0 { "inf" } else { "-inf" };
+        json!({ "type": "number", "value": text })
+    } else {
+        json!(value)
+    }
+}
+
+fn cframe_components(cframe: &CFrame) -> [f32; 12] {
+    let CFrame {
+        position,
+        orientation,
+    } = cframe;
+    [
+        position.x,
+        position.y,
+        position.z,
+        orientation.x.x,
+        orientation.x.y,
+        orientation.x.z,
+        orientation.y.x,
+        orientation.y.y,
+        orientation.y.z,
+        orientation.z.x,
+        orientation.z.y,
+        orientation.z.z,
+    ]
+}
+
+/// Roblox orientation (degrees, applied Y then X then Z) for a rotation matrix.
+fn orientation_of(matrix: &Matrix3) -> Vector3 {
+    let x = (-matrix.y.z).clamp(-1.0, 1.0).asin();
+    let y = matrix.x.z.atan2(matrix.z.z);
+    let z = matrix.y.x.atan2(matrix.y.y);
+    Vector3::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+/// Inverse of [`orientation_of`], equivalent to `CFrame.fromOrientation`.
+pub(super) fn matrix_from_orientation(orientation: Vector3) -> Matrix3 {
+    let (sx, cx) = orientation.x.to_radians().sin_cos();
+    let (sy, cy) = orientation.y.to_radians().sin_cos();
+    let (sz, cz) = orientation.z.to_radians().sin_cos();
+    Matrix3::new(
+        Vector3::new(cy * cz + sy * sx * sz, -cy * sz + sy * sx * cz, sy * cx),
+        Vector3::new(cx * sz, cx * cz, -sx),
+        Vector3::new(-sy * cz + cy * sx * sz, sy * sz + cy * sx * cz, cy * cx),
+    )
+}
+
+/// Reads the first numeric field present under any of `keys`.
+fn number(value: &JsonValue, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|key| value.get(*key)?.as_f64())
+}
+
+fn type_marker(value: &JsonValue) -> Option<&str> {
+    value
+        .get("__type")
+        .or_else(|| value.get("type"))
+        .and_then(JsonValue::as_str)
+}
+
+fn decode_vector3(value: &JsonValue) -> Option<Vector3> {
+    Some(Vector3::new(
+        number(value, &["x", "X"])? as f32,
+        number(value, &["y", "Y"])? as f32,
+        number(value, &["z", "Z"])? as f32,
+    ))
+}
+
+fn decode_vector2(value: &JsonValue) -> Option<Vector2> {
+    Some(Vector2::new(
+        number(value, &["x", "X"])? as f32,
+        number(value, &["y", "Y"])? as f32,
+    ))
+}
+
+fn decode_color3(value: &JsonValue) -> Option<Color3> {
+    Some(Color3::new(
+        number(value, &["r", "R"])? as f32,
+        number(value, &["g", "G"])? as f32,
+        number(value, &["b", "B"])? as f32,
+    ))
+}
+
+fn decode_cframe(value: &JsonValue) -> Option<CFrame> {
+    let components = value
+        .get("components")
+        .or_else(|| value.get("value"))?
+        .as_array()?;
+    let components: Vec<f32> = components
+        .iter()
+        .map(|component| component.as_f64().map(|component| component as f32))
+        .collect::<Option<_>>()?;
+    let [x, y, z, r00, r01, r02, r10, r11, r12, r20, r21, r22] = components[..] else {
+        return None;
+    };
+    Some(CFrame::new(
+        Vector3::new(x, y, z),
+        Matrix3::new(
+            Vector3::new(r00, r01, r02),
+            Vector3::new(r10, r11, r12),
+            Vector3::new(r20, r21, r22),
+        ),
+    ))
+}
+
+fn decode_udim(value: &JsonValue) -> Option<UDim> {
+    Some(UDim::new(
+        number(value, &["scale", "Scale"]).unwrap_or(0.0) as f32,
+        number(value, &["offset", "Offset"]).unwrap_or(0.0) as i32,
+    ))
+}
+
+fn decode_udim2(value: &JsonValue) -> Option<UDim2> {
+    if let (Some(x), Some(y)) = (value.get("x"), value.get("y")) {
+        return Some(UDim2::new(decode_udim(x)?, decode_udim(y)?));
+    }
+    Some(UDim2::new(
+        UDim::new(
+            number(value, &["xScale", "XScale"]).unwrap_or(0.0) as f32,
+            number(value, &["xOffset", "XOffset"]).unwrap_or(0.0) as i32,
+        ),
+        UDim::new(
+            number(value, &["yScale", "YScale"]).unwrap_or(0.0) as f32,
+            number(value, &["yOffset", "YOffset"]).unwrap_or(0.0) as i32,
+        ),
+    ))
+}
+
+fn decode_brick_color(value: &JsonValue) -> Option<BrickColor> {
+    match value {
+        JsonValue::Number(number) => BrickColor::from_number(u16::try_from(number.as_u64()?).ok()?),
+        JsonValue::String(name) => BrickColor::from_name(name),
+        JsonValue::Object(_) => value
+            .get("number")
+            .and_then(decode_brick_color)
+            .or_else(|| value.get("name").and_then(decode_brick_color)),
+        _ => None,
+    }
+}
+
+fn decode_number_range(value: &JsonValue) -> Option<NumberRange> {
+    if let Some(single) = value.as_f64() {
+        return Some(NumberRange::new(single as f32, single as f32));
+    }
+    Some(NumberRange::new(
+        number(value, &["min", "Min"])? as f32,
+        number(value, &["max", "Max"])? as f32,
+    ))
+}
+
+fn decode_physical_properties(value: &JsonValue) -> Option<PhysicalProperties> {
+    if type_marker(value) == Some("nil") {
+        return Some(PhysicalProperties::Default);
+    }
+    Some(PhysicalProperties::Custom(CustomPhysicalProperties {
+        density: number(value, &["density", "Density"])? as f32,
+        friction: number(value, &["friction", "Friction"])? as f32,
+        elasticity: number(value, &["elasticity", "Elasticity"])? as f32,
+        friction_weight: number(value, &["frictionWeight", "FrictionWeight"]).unwrap_or(1.0) as f32,
+        elasticity_weight: number(value, &["elasticityWeight", "ElasticityWeight"]).unwrap_or(1.0)
+            as f32,
+    }))
+}
+
+fn decode_font(value: &JsonValue) -> Option<Font> {
+    let family = value.get("family")?.as_str()?;
+    let weight = match value.get("weight") {
+        Some(weight) => FontWeight::from_u16(decode_enum("FontWeight", weight)?.to_u32() as u16)?,
+        None => FontWeight::default(),
+    };
+    let style = match value.get("style") {
+        Some(style) => FontStyle::from_u8(decode_enum("FontStyle", style)?.to_u32() as u8)?,
+        None => FontStyle::default(),
+    };
+    Some(Font::new(family, weight, style))
+}
+
+fn decode_number_sequence(value: &JsonValue) -> Option<NumberSequence> {
+    if let Some(constant) = value.as_f64() {
+        let constant = constant as f32;
+        return Some(NumberSequence {
+            keypoints: vec![
+                NumberSequenceKeypoint::new(0.0, constant, 0.0),
+                NumberSequenceKeypoint::new(1.0, constant, 0.0),
+            ],
+        });
+    }
+    let keypoints = value
+        .get("keypoints")?
+        .as_array()?
+        .iter()
+        .map(|keypoint| {
+            Some(NumberSequenceKeypoint::new(
+                number(keypoint, &["time"])? as f32,
+                number(keypoint, &["value"])? as f32,
+                number(keypoint, &["envelope"]).unwrap_or(0.0) as f32,
+            ))
+        })
+        .collect::<Option<_>>()?;
+    Some(NumberSequence { keypoints })
+}
+
+fn decode_color_sequence(value: &JsonValue) -> Option<ColorSequence> {
+    if value.get("keypoints").is_none() {
+        let color = decode_color3(value)?;
+        return Some(ColorSequence {
+            keypoints: vec![
+                ColorSequenceKeypoint::new(0.0, color),
+                ColorSequenceKeypoint::new(1.0, color),
+            ],
+        });
+    }
+    let keypoints = value
+        .get("keypoints")?
+        .as_array()?
+        .iter()
+        .map(|keypoint| {
+            Some(ColorSequenceKeypoint::new(
+                number(keypoint, &["time"])? as f32,
+                decode_color3(keypoint.get("value")?)?,
+            ))
+        })
+        .collect::<Option<_>>()?;
+    Some(ColorSequence { keypoints })
+}
+
+fn decode_rect(value: &JsonValue) -> Option<Rect> {
+    Some(Rect::new(
+        decode_vector2(value.get("min")?)?,
+        decode_vector2(value.get("max")?)?,
+    ))
+}
+
+fn decode_enum(enum_name: &str, value: &JsonValue) -> Option<Enum> {
+    let descriptor = rbx_reflection_database::get().enums.get(enum_name)?;
+    match value {
+        JsonValue::Number(number) => {
+            let number = u32::try_from(number.as_u64()?).ok()?;
+            descriptor
+                .items
+                .values()
+                .any(|item| *item == number)
+                .then(|| Enum::from_u32(number))
+        }
+        JsonValue::String(text) => {
+            let item = text.rsplit('.').next().unwrap_or(text);
+            descriptor.items.get(item).copied().map(Enum::from_u32)
+        }
+        JsonValue::Object(_) => decode_enum(enum_name, value.get("value")?),
+        _ => None,
+    }
+}
+
+/// Decodes a plain JSON value into `ty`, returning `None` when the shape does not match.
+fn decode_typed(ty: VariantType, value: &JsonValue) -> Option<Variant> {
+    Some(match ty {
+        VariantType::Bool => Variant::Bool(value.as_bool()?),
+        VariantType::Int32 => Variant::Int32(i32::try_from(value.as_f64()?.floor() as i64).ok()?),
+        VariantType::Int64 => Variant::Int64(value.as_f64()?.floor() as i64),
+        VariantType::Float32 => Variant::Float32(value.as_f64()? as f32),
+        VariantType::Float64 => Variant::Float64(value.as_f64()?),
+        VariantType::String => Variant::String(value.as_str()?.to_string()),
+        VariantType::Content => Variant::Content(Content::from(value.as_str()?)),
+        VariantType::Vector3 => Variant::Vector3(decode_vector3(value)?),
+        VariantType::Vector2 => Variant::Vector2(decode_vector2(value)?),
+        VariantType::Vector3int16 => {
+            let vector = decode_vector3(value)?;
+            Variant::Vector3int16(Vector3int16::new(
+                vector.x as i16,
+                vector.y as i16,
+                vector.z as i16,
+            ))
+        }
+        VariantType::Vector2int16 => {
+            let vector = decode_vector2(value)?;
+            Variant::Vector2int16(Vector2int16::new(vector.x as i16, vector.y as i16))
+        }
+        VariantType::Color3 => Variant::Color3(decode_color3(value)?),
+        VariantType::Color3uint8 => Variant::Color3uint8(decode_color3(value)?.into()),
+        VariantType::CFrame => Variant::CFrame(decode_cframe(value)?),
+        VariantType::OptionalCFrame => Variant::OptionalCFrame(match value {
+            JsonValue::Null => None,
+            _ => Some(decode_cframe(value)?),
+        }),
+        VariantType::UDim => Variant::UDim(decode_udim(value)?),
+        VariantType::UDim2 => Variant::UDim2(decode_udim2(value)?),
+        VariantType::BrickColor => Variant::BrickColor(decode_brick_color(value)?),
+        VariantType::NumberRange => Variant::NumberRange(decode_number_range(value)?),
+        VariantType::NumberSequence => Variant::NumberSequence(decode_number_sequence(value)?),
+        VariantType::ColorSequence => Variant::ColorSequence(decode_color_sequence(value)?),
+        VariantType::Rect => Variant::Rect(decode_rect(value)?),
+        VariantType::PhysicalProperties => {
+            Variant::PhysicalProperties(decode_physical_properties(value)?)
+        }
+        VariantType::Font => Variant::Font(decode_font(value)?),
+        _ => return None,
+    })
+}
+
+/// Unwraps Rojo's explicit property form, e.g. `{ "Vector3": [1, 2, 3] }`.
+fn unwrap_explicit(value: &JsonValue) -> &JsonValue {
+    match value.as_object() {
+        Some(map) if map.len() == 1 => {
+            let (key, inner) = map.iter().next().expect("map has one entry");
+            if key.starts_with(|first: char| first.is_ascii_uppercase()) {
+                inner
+            } else {
+                value
+            }
+        }
+        _ => value,
+    }
+}
+
+/// Converts Rojo's implicit property forms, such as `[1, 2, 3]` for a Vector3, into the shapes
+/// `decode_typed` understands.
+fn rojo_shape(ty: VariantType, value: &JsonValue) -> JsonValue {
+    if ty == VariantType::CFrame {
+        let position = value.get("position").and_then(JsonValue::as_array);
+        let orientation = value.get("orientation").and_then(JsonValue::as_array);
+        if let (Some(position), Some(orientation)) = (position, orientation) {
+            let mut components = position.clone();
+            for row in orientation {
+                components.extend(row.as_array().cloned().unwrap_or_default());
+            }
+            return json!({ "components": components });
+        }
+        return value.clone();
+    }
+    let Some(items) = value.as_array() else {
+        return value.clone();
+    };
+    let numbers: Vec<f64> = items.iter().filter_map(JsonValue::as_f64).collect();
+    match (ty, numbers.as_slice()) {
+        (VariantType::Vector3 | VariantType::Vector3int16, [x, y, z]) => {
+            json!({ "x": x, "y": y, "z": z })
+        }
+        (VariantType::Vector2 | VariantType::Vector2int16, [x, y]) => json!({ "x": x, "y": y }),
+        (VariantType::Color3 | VariantType::Color3uint8, [r, g, b]) => {
+            json!({ "r": r, "g": g, "b": b })
+        }
+        (VariantType::UDim, [scale, offset]) => json!({ "scale": scale, "offset": offset }),
+        (VariantType::NumberRange, [min, max]) => json!({ "min": min, "max": max }),
+        (VariantType::UDim2, []) if items.len() == 2 => json!({
+            "x": rojo_shape(VariantType::UDim, &items[0]),
+            "y": rojo_shape(VariantType::UDim, &items[1]),
+        }),
+        (VariantType::Rect, []) if items.len() == 2 => json!({
+            "min": rojo_shape(VariantType::Vector2, &items[0]),
+            "max": rojo_shape(VariantType::Vector2, &items[1]),
+        }),
+        _ => value.clone(),
+    }
+}
+
+/// Decodes a property from a Rojo `.model.json` file, returning its canonical name.
+pub(super) fn decode_rojo_property(
+    class_name: &str,
+    property: &str,
+    value: &JsonValue,
+) -> Result<(String, Variant), String> {
+    let descriptor = find_property(class_name, property)
+        .ok_or_else(|| format!("{property} is not a valid member of {class_name}"))?;
+    let value = unwrap_explicit(value);
+    let variant = match &descriptor.data_type {
+        DataType::Enum(enum_name) => decode_enum(enum_name, value).map(Variant::Enum),
+        DataType::Value(ty) => decode_typed(*ty, &rojo_shape(*ty, value)),
+        _ => None,
+    }
+    .ok_or_else(|| format!("invalid value for {class_name}.{property}: {value}"))?;
+    Ok((descriptor.name.to_string(), variant))
+}
+
+/// Attribute types that can be created from a `type` marker, matching `Instance:SetAttribute`.
+fn attribute_type(marker: &str) -> Option<VariantType> {
+    Some(match marker {
+        "Vector3" => VariantType::Vector3,
+        "Vector2" => VariantType::Vector2,
+        "Color3" => VariantType::Color3,
+        "CFrame" => VariantType::CFrame,
+        "UDim" => VariantType::UDim,
+        "UDim2" => VariantType::UDim2,
+        "BrickColor" => VariantType::BrickColor,
+        "NumberRange" => VariantType::NumberRange,
+        "NumberSequence" => VariantType::NumberSequence,
+        "ColorSequence" => VariantType::ColorSequence,
+        "Rect" => VariantType::Rect,
+        "Font" => VariantType::Font,
+        _ => return None,
+    })
+}
+
+/// Converts an attribute value to a variant. `Ok(None)` means the attribute should be removed.
+pub(super) fn decode_attribute(value: &JsonValue) -> Result<Option<Variant>, String> {
+    match value {
+        JsonValue::Null => Ok(None),
+        JsonValue::Bool(flag) => Ok(Some(Variant::Bool(*flag))),
+        JsonValue::Number(number) => Ok(Some(Variant::Float64(number.as_f64().unwrap_or(0.0)))),
+        JsonValue::String(text) => Ok(Some(Variant::String(text.clone()))),
+        JsonValue::Object(_) => {
+            let marker = type_marker(value).unwrap_or_default();
+            if marker == "nil" {
+                return Ok(None);
+            }
+            if marker == "number" {
+                let text = value
+                    .get("value")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or("nan");
+                let parsed = match text {
+                    "inf" => f64::INFINITY,
+                    "-inf" => f64::NEG_INFINITY,
+                    _ => f64::NAN,
+                };
+                return Ok(Some(Variant::Float64(parsed)));
+            }
+            attribute_type(marker)
+                .and_then(|ty| decode_typed(ty, value))
+                .map(Some)
+                .ok_or_else(|| format!("Unsupported attribute value {value}"))
+        }
+        JsonValue::Array(_) => Err("Attributes cannot store arrays".to_string()),
+    }
+}
+
+/// Validates attribute names the same way `Instance:SetAttribute` does.
+pub(super) fn validate_attribute_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Attribute names must be non-empty strings".to_string());
+    }
+    if name.len() > 100 {
+        return Err(format!(
+            "Attribute name '{name}' is longer than 100 characters"
+        ));
+    }
+    if name.starts_with("RBX") {
+        return Err(format!(
+            "Attribute name '{name}' uses the reserved RBX prefix"
+        ));
+    }
+    if !name
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric() || character == '_')
+    {
+        return Err(format!(
+            "Attribute name '{name}' may only contain letters, digits and underscores"
+        ));
+    }
+    Ok(())
+}
+
+impl PlaceFile {
+    /// Encodes a variant as the plugin's `encodeValue` would.
+    pub(super) fn encode_value(&self, value: &Variant, enum_name: Option<&str>) -> JsonValue {
+        match value {
+            Variant::Bool(flag) => json!(flag),
+            Variant::Int32(number) => json!(number),
+            Variant::Int64(number) => json!(number),
+            Variant::Float32(number) => encode_number(f64::from(*number)),
+            Variant::Float64(number) => encode_number(*number),
+            Variant::String(text) => json!(text),
+            // String attributes read from binary files.
+            Variant::BinaryString(bytes)
+                if std::str::from_utf8(AsRef::<[u8]>::as_ref(bytes)).is_ok() =>
+            {
+                json!(String::from_utf8_lossy(AsRef::<[u8]>::as_ref(bytes)))
+            }
+            Variant::Content(content) => json!(AsRef::<str>::as_ref(content)),
+            Variant::Vector3(vector) => {
+                json!({ "type": "Vector3", "x": vector.x, "y": vector.y, "z": vector.z })
+            }
+            Variant::Vector2(vector) => json!({ "type": "Vector2", "x": vector.x, "y": vector.y }),
+            Variant::Vector2int16(vector) => {
+                json!({ "type": "Vector2int16", "x": vector.x, "y": vector.y })
+            }
+            Variant::Vector3int16(vector) => {
+                json!({ "type": "Vector3int16", "x": vector.x, "y": vector.y, "z": vector.z })
+            }
+            Variant::CFrame(cframe) | Variant::OptionalCFrame(Some(cframe)) => {
+                json!({ "type": "CFrame", "components": cframe_components(cframe) })
+            }
+            Variant::Color3(color) => {
+                json!({ "type": "Color3", "r": color.r, "g": color.g, "b": color.b })
+            }
+            Variant::Color3uint8(color) => {
+                let color = Color3::from(*color);
+                json!({ "type": "Color3", "r": color.r, "g": color.g, "b": color.b })
+            }
+            Variant::BrickColor(brick) => {
+                json!({ "type": "BrickColor", "name": brick.to_string(), "number": *brick as u16 })
+            }
+            Variant::UDim(udim) => {
+                json!({ "type": "UDim", "scale": udim.scale, "offset": udim.offset })
+            }
+            Variant::UDim2(udim2) => json!({
+                "type": "UDim2",
+                "x": { "scale": udim2.x.scale, "offset": udim2.x.offset },
+                "y": { "scale": udim2.y.scale, "offset": udim2.y.offset },
+            }),
+            Variant::Rect(rect) => json!({
+                "type": "Rect",
+                "min": { "x": rect.min.x, "y": rect.min.y },
+                "max": { "x": rect.max.x, "y": rect.max.y },
+            }),
+            Variant::NumberRange(range) => {
+                json!({ "type": "NumberRange", "min": range.min, "max": range.max })
+            }
+            Variant::PhysicalProperties(PhysicalProperties::Custom(properties)) => json!({
+                "type": "PhysicalProperties",
+                "density": properties.density,
+                "friction": properties.friction,
+                "elasticity": properties.elasticity,
+                "frictionWeight": properties.friction_weight,
+                "elasticityWeight": properties.elasticity_weight,
+            }),
+            Variant::Enum(item) => {
+                let value = enum_name
+                    .and_then(|enum_name| {
+                        enum_item_name(enum_name, item.to_u32())
+                            .map(|item_name| format!("Enum.{enum_name}.{item_name}"))
+                    })
+                    .unwrap_or_else(|| item.to_u32().to_string());
+                json!({ "type": "EnumItem", "value": value })
+            }
+            Variant::Ref(referent)
+                if referent.is_some() && self.dom.get_by_ref(*referent).is_some() =>
+            {
+                json!({
+                    "type": "Instance",
+                    "path": self.path_of(*referent),
+                    "value": self.full_name(*referent),
+                })
+            }
+            Variant::ColorSequence(sequence) => {
+                let keypoints: Vec<JsonValue> = sequence
+                    .keypoints
+                    .iter()
+                    .map(|keypoint| {
+                        json!({
+                            "time": keypoint.time,
+                            "value": { "r": keypoint.color.r, "g": keypoint.color.g, "b": keypoint.color.b },
+                        })
+                    })
+                    .collect();
+                json!({ "type": "ColorSequence", "keypoints": keypoints })
+            }
+            Variant::NumberSequence(sequence) => {
+                let keypoints: Vec<JsonValue> = sequence
+                    .keypoints
+                    .iter()
+                    .map(|keypoint| {
+                        json!({ "time": keypoint.time, "value": keypoint.value, "envelope": keypoint.envelope })
+                    })
+                    .collect();
+                json!({ "type": "NumberSequence", "keypoints": keypoints })
+            }
+            Variant::Font(font) => json!({
+                "type": "Font",
+                "family": font.family,
+                "weight": format!("{:?}", font.weight),
+                "style": format!("{:?}", font.style),
+            }),
+            Variant::Tags(tags) => json!(tags.iter().collect::<Vec<_>>()),
+            Variant::Attributes(attributes) => {
+                JsonValue::Object(self.encode_attributes(attributes))
+            }
+            Variant::Ref(_)
+            | Variant::OptionalCFrame(None)
+            | Variant::PhysicalProperties(PhysicalProperties::Default) => json!({ "type": "nil" }),
+            other => json!({ "type": format!("{:?}", other.ty()), "value": JsonValue::Null }),
+        }
+    }
+
+    pub(super) fn encode_attributes(&self, attributes: &Attributes) -> Map<String, JsonValue> {
+        attributes
+            .iter()
+            .map(|(name, value)| (name.clone(), self.encode_value(value, None)))
+            .collect()
+    }
+
+    /// Equivalent of `Instance:GetAttributes()`.
+    pub(super) fn attributes_of(&self, referent: Ref) -> Attributes {
+        match self
+            .dom
+            .get_by_ref(referent)
+            .and_then(|instance| instance.properties.get("Attributes"))
+        {
+            Some(Variant::Attributes(attributes)) => attributes.clone(),
+            _ => Attributes::new(),
+        }
+    }
+
+    /// Equivalent of `Instance:SetAttribute(name, value)`.
+    pub(super) fn set_attribute(
+        &mut self,
+        referent: Ref,
+        name: &str,
+        value: &JsonValue,
+    ) -> Result<(), String> {
+        validate_attribute_name(name)?;
+        let decoded = decode_attribute(value)?;
+        let mut attributes = self.attributes_of(referent);
+        match decoded {
+            Some(variant) => {
+                attributes.insert(name.to_string(), variant);
+            }
+            None => {
+                attributes.remove(name);
+            }
+        }
+        let instance = self
+            .dom
+            .get_by_ref_mut(referent)
+            .ok_or_else(|| "Instance no longer exists".to_string())?;
+        instance
+            .properties
+            .insert("Attributes".to_string(), Variant::Attributes(attributes));
+        Ok(())
+    }
+
+    /// Reads a property as Studio would report it, falling back to the reflection default for
+    /// properties that are not stored in the file.
+    pub(super) fn read_property(&self, referent: Ref, property: &str) -> Result<JsonValue, String> {
+        let instance = self
+            .dom
+            .get_by_ref(referent)
+            .ok_or_else(|| "Instance no longer exists".to_string())?;
+        match property {
+            "Name" => return Ok(json!(instance.name)),
+            "ClassName" => return Ok(json!(instance.class)),
+            "Parent" => return Ok(self.encode_value(&Variant::Ref(instance.parent()), None)),
+            "Position" | "Orientation" if has_derived_transform(&instance.class) => {
+                let cframe = match self.read_stored(referent, "CFrame") {
+                    Some(Variant::CFrame(cframe)) => cframe,
+                    _ => CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity()),
+                };
+                let vector = if property == "Position" {
+                    cframe.position
+                } else {
+                    orientation_of(&cframe.orientation)
+                };
+                return Ok(self.encode_value(&Variant::Vector3(vector), None));
+            }
+            _ => {}
+        }
+        let descriptor = find_property(&instance.class, property).ok_or_else(|| {
+            format!(
+                "{property} is not a valid member of {} \"{}\"",
+                instance.class,
+                self.full_name(referent)
+            )
+        })?;
+        let enum_name = match &descriptor.data_type {
+            DataType::Enum(enum_name) => Some(enum_name.as_ref()),
+            _ => None,
+        };
+        Ok(self
+            .read_stored(referent, &descriptor.name)
+            .map(|value| self.encode_value(&value, enum_name))
+            .unwrap_or_else(|| json!({ "type": "nil" })))
+    }
+
+    pub(super) fn read_stored(&self, referent: Ref, property: &str) -> Option<Variant> {
+        let instance = self.dom.get_by_ref(referent)?;
+        instance
+            .properties
+            .get(property)
+            .or_else(|| default_property(&instance.class, property))
+            .cloned()
+    }
+
+    /// Writes a JSON property value, converting it to the type the reflection database expects.
+    pub(super) fn write_property(
+        &mut self,
+        referent: Ref,
+        property: &str,
+        value: &JsonValue,
+    ) -> Result<(), String> {
+        let class_name = self.class_of(referent).to_string();
+        let full_name = self.full_name(referent);
+        let invalid = || format!("invalid value for {class_name}.{property}: {value}");
+
+        if property == "Name" {
+            let name = value.as_str().ok_or_else(invalid)?.to_string();
+            self.get_mut(referent)
+                .map_err(|error| error.to_string())?
+                .name = name;
+            return Ok(());
+        }
+
+        if matches!(property, "Position" | "Orientation") && has_derived_transform(&class_name) {
+            let vector = decode_vector3(value).ok_or_else(invalid)?;
+            let mut cframe = match self.read_stored(referent, "CFrame") {
+                Some(Variant::CFrame(cframe)) => cframe,
+                _ => CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity()),
+            };
+            if property == "Position" {
+                cframe.position = vector;
+            } else {
+                cframe.orientation = matrix_from_orientation(vector);
+            }
+            self.get_mut(referent)
+                .map_err(|error| error.to_string())?
+                .properties
+                .insert("CFrame".to_string(), Variant::CFrame(cframe));
+            return Ok(());
+        }
+
+        let descriptor = find_property(&class_name, property).ok_or_else(|| {
+            format!("{property} is not a valid member of {class_name} \"{full_name}\"")
+        })?;
+        let variant = match &descriptor.data_type {
+            DataType::Enum(enum_name) => decode_enum(enum_name, value).map(Variant::Enum),
+            DataType::Value(VariantType::Ref) => self.decode_ref(value),
+            DataType::Value(ty) => decode_typed(*ty, value),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        self.get_mut(referent)
+            .map_err(|error| error.to_string())?
+            .properties
+            .insert(descriptor.name.to_string(), variant);
+        Ok(())
+    }
+
+    /// Accepts `null`, an instance path array, or an encoded `{ "type": "Instance" }` value.
+    fn decode_ref(&self, value: &JsonValue) -> Option<Variant> {
+        let path: Vec<String> = match value {
+            JsonValue::Null => return Some(Variant::Ref(Ref::none())),
+            JsonValue::Array(segments) => segments
+                .iter()
+                .map(|segment| segment.as_str().map(str::to_string))
+                .collect::<Option<_>>()?,
+            JsonValue::Object(_) if type_marker(value) == Some("nil") => {
+                return Some(Variant::Ref(Ref::none()))
+            }
+            JsonValue::Object(_) if value.get("path").is_some() => value
+                .get("path")?
+                .as_array()?
+                .iter()
+                .map(|segment| segment.as_str().map(str::to_string))
+                .collect::<Option<_>>()?,
+            JsonValue::Object(_) => value
+                .get("value")?
+                .as_str()?
+                .split('.')
+                .map(str::to_string)
+                .collect(),
+            _ => return None,
+        };
+        self.resolve(&path).ok().map(Variant::Ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orientation_round_trips_through_matrix() {
+        let orientation = Vector3::new(30.0, -45.0, 60.0);
+        let decoded = orientation_of(&matrix_from_orientation(orientation));
+        assert!((decoded.x - orientation.x).abs() < 1e-3);
+        assert!((decoded.y - orientation.y).abs() < 1e-3);
+        assert!((decoded.z - orientation.z).abs() < 1e-3);
+    }
+
+    #[test]
+    fn decodes_plugin_property_shapes() {
+        let udim2 = decode_typed(
+            VariantType::UDim2,
+            &json!({ "type": "UDim2", "xScale": 0.5, "yOffset": 10 }),
+        );
+        assert_eq!(
+            udim2,
+            Some(Variant::UDim2(UDim2::new(
+                UDim::new(0.5, 0),
+                UDim::new(0.0, 10)
+            )))
+        );
+        assert_eq!(
+            decode_enum("Material", &json!("Enum.Material.Neon")),
+            decode_enum("Material", &json!("Neon"))
+        );
+        assert!(decode_enum("Material", &json!("NotAMaterial")).is_none());
+        assert!(decode_typed(VariantType::Vector3, &json!({ "x": 1 })).is_none());
+        assert_eq!(
+            decode_typed(
+                VariantType::Font,
+                &json!({ "type": "Font", "family": "rbxasset://fonts/families/Arial.json", "weight": "Enum.FontWeight.Bold" }),
+            ),
+            Some(Variant::Font(Font::new(
+                "rbxasset://fonts/families/Arial.json",
+                FontWeight::Bold,
+                FontStyle::Normal
+            )))
+        );
+    }
+
+    #[test]
+    fn attributes_follow_set_attribute_rules() {
+        assert_eq!(decode_attribute(&JsonValue::Null), Ok(None));
+        assert_eq!(
+            decode_attribute(&json!({ "type": "Vector3", "x": 1, "y": 2, "z": 3 })),
+            Ok(Some(Variant::Vector3(Vector3::new(1.0, 2.0, 3.0))))
+        );
+        assert!(decode_attribute(&json!([1, 2])).is_err());
+        assert!(validate_attribute_name("Health_2").is_ok());
+        assert!(validate_attribute_name("RBXHidden").is_err());
+        assert!(validate_attribute_name("has space").is_err());
+    }
+}
@@ -0,0 +1,913 @@
+//! Rojo project sync. Reads `default.project.json`, turns the mapped directories into trees the
+//! plugin reconciles against the live DataModel, and writes pulled Studio edits back to disk using
+//! Rojo's file naming conventions.
+
+use super::place_file::model_json_to_rbxm;
+use super::script_typecheck::sanitise;
+use base64::Engine;
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROJECT_FILE_NAME: &str = "default.project.json";
+
+/// Init files that turn a directory into a script, in the order Rojo checks them.
+const INIT_FILES: &[(&str, ScriptKind)] = &[
+    ("init.server.luau", ScriptKind::Server),
+    ("init.server.lua", ScriptKind::Server),
+    ("init.client.luau", ScriptKind::Client),
+    ("init.client.lua", ScriptKind::Client),
+    ("init.luau", ScriptKind::Module),
+    ("init.lua", ScriptKind::Module),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScriptKind {
+    Server,
+    Client,
+    Module,
+}
+
+impl ScriptKind {
+    fn from_class(class_name: &str) -> Option<Self> {
+        match class_name {
+            "Script" => Some(ScriptKind::Server),
+            "LocalScript" => Some(ScriptKind::Client),
+            "ModuleScript" => Some(ScriptKind::Module),
+            _ => None,
+        }
+    }
+
+    fn class_name(self) -> &'static str {
+        match self {
+            ScriptKind::Server => "Script",
+            ScriptKind::Client => "LocalScript",
+            ScriptKind::Module => "ModuleScript",
+        }
+    }
+
+    /// Extension used for files created by a pull.
+    fn extension(self) -> &'static str {
+        match self {
+            ScriptKind::Server => ".server.luau",
+            ScriptKind::Client => ".client.luau",
+            ScriptKind::Module => ".luau",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Script(ScriptKind),
+    Model,
+}
+
+/// Splits a file name into the instance name and file kind, or `None` for files Rojo would not
+/// turn into scripts or models.
+fn classify_file(file_name: &str) -> Option<(&str, FileKind)> {
+    if let Some(name) = file_name.strip_suffix(".model.json") {
+        return Some((name, FileKind::Model));
+    }
+    let stem = file_name
+        .strip_suffix(".luau")
+        .or_else(|| file_name.strip_suffix(".lua"))?;
+    Some(if let Some(name) = stem.strip_suffix(".server") {
+        (name, FileKind::Script(ScriptKind::Server))
+    } else if let Some(name) = stem.strip_suffix(".client") {
+        (name, FileKind::Script(ScriptKind::Client))
+    } else {
+        (stem, FileKind::Script(ScriptKind::Module))
+    })
+}
+
+/// How an instance is laid out on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Layout {
+    File,
+    Directory,
+    InitDirectory { init_file: PathBuf },
+}
+
+/// An instance read from the filesystem.
+#[derive(Debug, Clone)]
+struct FsNode {
+    name: String,
+    class_name: String,
+    source: Option<String>,
+    model: Option<Vec<u8>>,
+    path: PathBuf,
+    layout: Layout,
+    children: Vec<FsNode>,
+}
+
+impl FsNode {
+    fn child(&self, name: &str) -> Option<&FsNode> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+}
+
+/// Reads `path` as the instance `name`. Returns `None` when the path does not exist or is a file
+/// Rojo would ignore.
+fn read_fs(path: &Path, name: &str) -> Result<Option<FsNode>> {
+    if path.is_dir() {
+        let init = INIT_FILES
+            .iter()
+            .map(|(file, kind)| (path.join(file), *kind))
+            .find(|(file, _)| file.is_file());
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)
+            .wrap_err_with(|| format!("Unable to read {}", path.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        entries.sort();
+
+        let mut children = Vec::new();
+        for entry in entries {
+            let entry_name = file_name(&entry);
+            if entry_name.starts_with('.') || INIT_FILES.iter().any(|(file, _)| *file == entry_name)
+            {
+                continue;
+            }
+            let child_name = if entry.is_dir() {
+                entry_name
+            } else {
+                match classify_file(entry_name) {
+                    Some((child_name, _)) => child_name,
+                    None => continue,
+                }
+            };
+            if let Some(child) = read_fs(&entry, child_name)? {
+                children.push(child);
+            }
+        }
+
+        let (class_name, source, layout) = match init {
+            Some((init_file, kind)) => (
+                kind.class_name().to_string(),
+                Some(
+                    fs::read_to_string(&init_file)
+                        .wrap_err_with(|| format!("Unable to read {}", init_file.display()))?,
+                ),
+                Layout::InitDirectory { init_file },
+            ),
+            None => ("Folder".to_string(), None, Layout::Directory),
+        };
+        return Ok(Some(FsNode {
+            name: name.to_string(),
+            class_name,
+            source,
+            model: None,
+            path: path.to_path_buf(),
+            layout,
+            children,
+        }));
+    }
+
+    let Some((_, kind)) = classify_file(file_name(path)) else {
+        return Ok(None);
+    };
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let contents =
+        fs::read_to_string(path).wrap_err_with(|| format!("Unable to read {}", path.display()))?;
+    let (class_name, source, model) = match kind {
+        FileKind::Script(kind) => (kind.class_name().to_string(), Some(contents), None),
+        FileKind::Model => {
+            let model = model_json_to_rbxm(name, &contents)
+                .wrap_err_with(|| format!("Unable to convert {}", path.display()))?;
+            ("Model".to_string(), None, Some(model))
+        }
+    };
+    Ok(Some(FsNode {
+        name: name.to_string(),
+        class_name,
+        source,
+        model,
+        path: path.to_path_buf(),
+        layout: Layout::File,
+        children: Vec::new(),
+    }))
+}
+
+/// A node of the tree exchanged with the plugin's `ProjectSync` tool.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SyncNode {
+    name: String,
+    class_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Base64 encoded `.rbxm` that replaces the instance wholesale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Remove scripts and folders under this instance that are not listed in `children`.
+    #[serde(default)]
+    prune: bool,
+    #[serde(default)]
+    children: Vec<SyncNode>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ProjectSyncAction {
+    Push,
+    Pull,
+}
+
+/// Request forwarded to the plugin's `ProjectSync` tool.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ProjectSyncRequest {
+    pub(super) action: ProjectSyncAction,
+    #[serde(default)]
+    pub(super) nodes: Vec<SyncNode>,
+    #[serde(default)]
+    pub(super) roots: Vec<Vec<String>>,
+    #[serde(default)]
+    pub(super) prune: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PulledRoot {
+    path: Vec<String>,
+    #[serde(default)]
+    node: Option<SyncNode>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PullResponse {
+    roots: Vec<PulledRoot>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum SyncAction {
+    Created,
+    Updated,
+    Unchanged,
+    Deleted,
+    Skipped,
+    Error,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SyncResult {
+    path: Vec<String>,
+    action: SyncAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SyncResponse {
+    results: Vec<SyncResult>,
+    summary: String,
+    write_occurred: bool,
+}
+
+/// A node of the project file's `tree`.
+#[derive(Debug, Clone, Default)]
+struct ProjectNode {
+    class_name: Option<String>,
+    path: Option<PathBuf>,
+    children: Vec<(String, ProjectNode)>,
+}
+
+impl ProjectNode {
+    fn parse(value: &JsonValue, root_dir: &Path) -> Result<Self> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| eyre!("Project tree nodes must be objects"))?;
+        let mut node = ProjectNode::default();
+        for (key, value) in object {
+            match key.as_str() {
+                "$className" => node.class_name = value.as_str().map(str::to_string),
+                "$path" => {
+                    let path = value
+                        .as_str()
+                        .or_else(|| value.get("optional").and_then(JsonValue::as_str))
+                        .ok_or_else(|| eyre!("$path must be a string"))?;
+                    node.path = Some(root_dir.join(path));
+                }
+                key if key.starts_with('$') => {}
+                _ => node
+                    .children
+                    .push((key.clone(), ProjectNode::parse(value, root_dir)?)),
+            }
+        }
+        Ok(node)
+    }
+}
+
+/// A Rojo project whose tree describes a place.
+#[derive(Debug, Clone)]
+pub(super) struct RojoProject {
+    file: PathBuf,
+    tree: ProjectNode,
+}
+
+impl RojoProject {
+    /// Loads a project file, or `default.project.json` inside a directory.
+    pub(super) fn load(path: &Path) -> Result<Self> {
+        let file = if path.is_dir() {
+            path.join(PROJECT_FILE_NAME)
+        } else {
+            path.to_path_buf()
+        };
+        let contents = fs::read_to_string(&file)
+            .wrap_err_with(|| format!("Unable to read {}", file.display()))?;
+        let json: JsonValue = serde_json::from_str(&contents)
+            .wrap_err_with(|| format!("Unable to parse {}", file.display()))?;
+        let root_dir = file.parent().unwrap_or(Path::new("."));
+        let tree = ProjectNode::parse(
+            json.get("tree")
+                .ok_or_else(|| eyre!("{} has no tree", file.display()))?,
+            root_dir,
+        )?;
+        if tree.class_name.as_deref() != Some("DataModel") {
+            bail!(
+                "{} does not describe a place; only projects whose tree is a DataModel can be synced",
+                file.display()
+            );
+        }
+        Ok(Self { file, tree })
+    }
+
+    /// Builds the trees pushed to Studio, one per service in the project.
+    pub(super) fn push_nodes(&self, prune: bool) -> Result<Vec<SyncNode>> {
+        self.tree
+            .children
+            .iter()
+            .map(|(name, node)| {
+                let mut sync_node = project_sync_node(name, node, prune)?;
+                sync_node.class_name = node.class_name.clone().unwrap_or_else(|| name.clone());
+                Ok(sync_node)
+            })
+            .collect()
+    }
+
+    /// Instance paths and directories of every `$path` node in the project.
+    fn mapped_paths(&self) -> Vec<(Vec<String>, PathBuf)> {
+        fn collect(
+            node: &ProjectNode,
+            instance_path: &mut Vec<String>,
+            out: &mut Vec<(Vec<String>, PathBuf)>,
+        ) {
+            if let Some(path) = &node.path {
+                out.push((instance_path.clone(), path.clone()));
+            }
+            for (name, child) in &node.children {
+                instance_path.push(name.clone());
+                collect(child, instance_path, out);
+                instance_path.pop();
+            }
+        }
+        let mut out = Vec::new();
+        collect(&self.tree, &mut Vec::new(), &mut out);
+        out
+    }
+
+    /// Instance paths the plugin should return for a pull.
+    pub(super) fn pull_roots(&self) -> Vec<Vec<String>> {
+        self.mapped_paths()
+            .into_iter()
+            .map(|(instance_path, _)| instance_path)
+            .collect()
+    }
+
+    /// Instance paths declared in the project tree. Pulls skip them below other mapped roots so
+    /// nested `$path` nodes are written only once.
+    fn declared_paths(&self) -> HashSet<Vec<String>> {
+        fn collect(node: &ProjectNode, path: &mut Vec<String>, out: &mut HashSet<Vec<String>>) {
+            for (name, child) in &node.children {
+                path.push(name.clone());
+                out.insert(path.clone());
+                collect(child, path, out);
+                path.pop();
+            }
+        }
+        let mut out = HashSet::new();
+        collect(&self.tree, &mut Vec::new(), &mut out);
+        out
+    }
+
+    /// Writes the plugin's pull response to disk and returns the sync response JSON.
+    pub(super) fn write_pull(&self, response: &str, prune: bool) -> Result<String> {
+        let response: PullResponse = serde_json::from_str(response)
+            .wrap_err("Unable to parse project sync response from Studio")?;
+        let mut writer = PullWriter {
+            prune,
+            declared: self.declared_paths(),
+            results: Vec::new(),
+        };
+        let mapped = self.mapped_paths();
+        for root in response.roots {
+            let Some((_, fs_path)) = mapped.iter().find(|(path, _)| *path == root.path) else {
+                continue;
+            };
+            match (root.node, root.error) {
+                (Some(node), _) => writer.write_root(&root.path, &node, fs_path),
+                (None, error) => writer.results.push(SyncResult {
+                    path: root.path,
+                    action: SyncAction::Error,
+                    file_path: Some(fs_path.display().to_string()),
+                    message: Some(
+                        error.unwrap_or_else(|| "Studio returned no instance".to_string()),
+                    ),
+                }),
+            }
+        }
+        Ok(serde_json::to_string(&writer.finish(&self.file))?)
+    }
+}
+
+fn project_sync_node(name: &str, node: &ProjectNode, prune: bool) -> Result<SyncNode> {
+    let mut sync_node = match &node.path {
+        Some(path) => {
+            let fs_node = read_fs(path, name)?
+                .ok_or_else(|| eyre!("Project path {} does not exist", path.display()))?;
+            let mut sync_node = fs_sync_node(&fs_node, prune);
+            if let (Some(class_name), Layout::Directory) = (&node.class_name, &fs_node.layout) {
+                sync_node.class_name = class_name.clone();
+            }
+            sync_node
+        }
+        None => SyncNode {
+            name: name.to_string(),
+            class_name: node
+                .class_name
+                .clone()
+                .unwrap_or_else(|| "Folder".to_string()),
+            ..Default::default()
+        },
+    };
+    for (child_name, child) in &node.children {
+        let child_node = project_sync_node(child_name, child, prune)?;
+        sync_node
+            .children
+            .retain(|existing| existing.name != *child_name);
+        sync_node.children.push(child_node);
+    }
+    Ok(sync_node)
+}
+
+fn fs_sync_node(node: &FsNode, prune: bool) -> SyncNode {
+    SyncNode {
+        name: node.name.clone(),
+        class_name: node.class_name.clone(),
+        source: node.source.clone(),
+        model: node
+            .model
+            .as_ref()
+            .map(|model| base64::engine::general_purpose::STANDARD.encode(model)),
+        prune: prune && node.layout != Layout::File,
+        children: node
+            .children
+            .iter()
+            .map(|child| fs_sync_node(child, prune))
+            .collect(),
+    }
+}
+
+/// Instances that a pull writes to disk.
+fn is_pullable(node: &SyncNode) -> bool {
+    node.class_name == "Folder" || ScriptKind::from_class(&node.class_name).is_some()
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+struct PullWriter {
+    prune: bool,
+    declared: HashSet<Vec<String>>,
+    results: Vec<SyncResult>,
+}
+
+impl PullWriter {
+    fn record(
+        &mut self,
+        path: &[String],
+        action: SyncAction,
+        file: &Path,
+        message: Option<String>,
+    ) {
+        self.results.push(SyncResult {
+            path: path.to_vec(),
+            action,
+            file_path: Some(file.display().to_string()),
+            message,
+        });
+    }
+
+    fn record_io(&mut self, path: &[String], file: &Path, result: std::io::Result<SyncAction>) {
+        match result {
+            Ok(action) => self.record(path, action, file, None),
+            Err(err) => self.record(path, SyncAction::Error, file, Some(err.to_string())),
+        }
+    }
+
+    fn write_source(&mut self, path: &[String], file: &Path, source: &str) {
+        let result = match fs::read_to_string(file) {
+            Ok(existing) if existing == source => Ok(SyncAction::Unchanged),
+            Ok(_) => fs::write(file, source).map(|_| SyncAction::Updated),
+            Err(_) => file
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(file, source))
+                .map(|_| SyncAction::Created),
+        };
+        self.record_io(path, file, result);
+    }
+
+    fn ensure_dir(&mut self, path: &[String], dir: &Path) -> bool {
+        if dir.is_dir() {
+            return true;
+        }
+        let result = fs::create_dir_all(dir).map(|_| SyncAction::Created);
+        let created = result.is_ok();
+        self.record_io(path, dir, result);
+        created
+    }
+
+    /// Removes `stale` when the instance it held is now stored at a different path.
+    fn remove_stale(&mut self, path: &[String], stale: &Path) {
+        if let Err(err) = remove_path(stale) {
+            self.record(path, SyncAction::Error, stale, Some(err.to_string()));
+        }
+    }
+
+    fn write_root(&mut self, path: &[String], node: &SyncNode, fs_path: &Path) {
+        let existing = match read_fs(fs_path, &node.name) {
+            Ok(existing) => existing,
+            Err(err) => {
+                self.record(path, SyncAction::Error, fs_path, Some(format!("{err:#}")));
+                return;
+            }
+        };
+        let is_file_path = existing
+            .as_ref()
+            .map_or(classify_file(file_name(fs_path)).is_some(), |existing| {
+                existing.layout == Layout::File
+            });
+        match ScriptKind::from_class(&node.class_name) {
+            Some(_) if is_file_path => {
+                self.write_source(path, fs_path, node.source.as_deref().unwrap_or_default());
+            }
+            Some(kind) => self.write_init_directory(path, node, kind, fs_path, existing.as_ref()),
+            None => {
+                if self.ensure_dir(path, fs_path) {
+                    self.write_children(path, node, fs_path, existing.as_ref());
+                }
+            }
+        }
+    }
+
+    /// Writes `node`, stored at `dest` (a directory or a file name without extension).
+    fn write_node(
+        &mut self,
+        path: &[String],
+        node: &SyncNode,
+        dest: &Path,
+        existing: Option<&FsNode>,
+    ) {
+        let has_children = node.children.iter().any(is_pullable);
+        match ScriptKind::from_class(&node.class_name) {
+            Some(kind)
+                if !has_children
+                    && !existing.is_some_and(|existing| {
+                        matches!(existing.layout, Layout::InitDirectory { .. })
+                    }) =>
+            {
+                let file = match existing {
+                    Some(existing)
+                        if existing.layout == Layout::File
+                            && existing.class_name == node.class_name =>
+                    {
+                        existing.path.clone()
+                    }
+                    _ => {
+                        let mut file = dest.as_os_str().to_owned();
+                        file.push(kind.extension());
+                        PathBuf::from(file)
+                    }
+                };
+                if let Some(existing) = existing.filter(|existing| existing.path != file) {
+                    self.remove_stale(path, &existing.path);
+                }
+                self.write_source(path, &file, node.source.as_deref().unwrap_or_default());
+            }
+            Some(kind) => {
+                let dir = match existing {
+                    Some(existing) if existing.layout != Layout::File => existing.path.clone(),
+                    Some(existing) => {
+                        self.remove_stale(path, &existing.path);
+                        dest.to_path_buf()
+                    }
+                    None => dest.to_path_buf(),
+                };
+                self.write_init_directory(path, node, kind, &dir, existing);
+            }
+            None if node.class_name == "Folder" => {
+                let dir = match existing {
+                    Some(existing) if existing.layout == Layout::File => {
+                        self.remove_stale(path, &existing.path);
+                        dest.to_path_buf()
+                    }
+                    Some(existing) => {
+                        if let Layout::InitDirectory { init_file } = &existing.layout {
+                            self.remove_stale(path, init_file);
+                        }
+                        existing.path.clone()
+                    }
+                    None => dest.to_path_buf(),
+                };
+                if self.ensure_dir(path, &dir) {
+                    self.write_children(path, node, &dir, existing);
+                }
+            }
+            None => self.results.push(SyncResult {
+                path: path.to_vec(),
+                action: SyncAction::Skipped,
+                file_path: existing.map(|existing| existing.path.display().to_string()),
+                message: Some(format!(
+                    "{} instances are not written to disk; only scripts and folders are pulled",
+                    node.class_name
+                )),
+            }),
+        }
+    }
+
+    fn write_init_directory(
+        &mut self,
+        path: &[String],
+        node: &SyncNode,
+        kind: ScriptKind,
+        dir: &Path,
+        existing: Option<&FsNode>,
+    ) {
+        if !self.ensure_dir(path, dir) {
+            return;
+        }
+        let existing_dir = existing.filter(|existing| existing.layout != Layout::File);
+        let init_file = match existing_dir.map(|existing| &existing.layout) {
+            Some(Layout::InitDirectory { init_file })
+                if existing_dir.is_some_and(|existing| existing.class_name == node.class_name) =>
+            {
+                init_file.clone()
+            }
+            Some(Layout::InitDirectory { init_file }) => {
+                self.remove_stale(path, init_file);
+                dir.join(format!("init{}", kind.extension()))
+            }
+            _ => dir.join(format!("init{}", kind.extension())),
+        };
+        self.write_source(path, &init_file, node.source.as_deref().unwrap_or_default());
+        self.write_children(path, node, dir, existing_dir);
+    }
+
+    fn write_children(
+        &mut self,
+        path: &[String],
+        node: &SyncNode,
+        dir: &Path,
+        existing: Option<&FsNode>,
+    ) {
+        let mut child_path = path.to_vec();
+        for child in &node.children {
+            child_path.push(child.name.clone());
+            if !self.declared.contains(&child_path) {
+                if sanitise(&child.name) == child.name {
+                    let child_existing = existing.and_then(|existing| existing.child(&child.name));
+                    self.write_node(&child_path, child, &dir.join(&child.name), child_existing);
+                } else {
+                    self.results.push(SyncResult {
+                        path: child_path.clone(),
+                        action: SyncAction::Skipped,
+                        file_path: None,
+                        message: Some(format!(
+                            "'{}' cannot be used as a file name; rename the instance to pull it",
+                            child.name
+                        )),
+                    });
+                }
+            }
+            child_path.pop();
+        }
+
+        if !self.prune {
+            return;
+        }
+        let Some(existing) = existing else {
+            return;
+        };
+        for stale in &existing.children {
+            if node.children.iter().any(|child| child.name == stale.name) || stale.model.is_some() {
+                continue;
+            }
+            child_path.push(stale.name.clone());
+            if !self.declared.contains(&child_path) {
+                let result = remove_path(&stale.path).map(|_| SyncAction::Deleted);
+                self.record_io(&child_path, &stale.path, result);
+            }
+            child_path.pop();
+        }
+    }
+
+    fn finish(self, project_file: &Path) -> SyncResponse {
+        let count = |action| {
+            self.results
+                .iter()
+                .filter(|result| result.action == action)
+                .count()
+        };
+        let written = count(SyncAction::Created) + count(SyncAction::Updated);
+        let summary = format!(
+            "Pulled {} into {}: {} created, {} updated, {} unchanged, {} deleted, {} skipped, {} failed",
+            if self.results.len() == 1 {
+                "1 entry".to_string()
+            } else {
+                format!("{} entries", self.results.len())
+            },
+            project_file
+                .parent()
+                .unwrap_or(Path::new("."))
+                .display(),
+            count(SyncAction::Created),
+            count(SyncAction::Updated),
+            count(SyncAction::Unchanged),
+            count(SyncAction::Deleted),
+            count(SyncAction::Skipped),
+            count(SyncAction::Error),
+        );
+        SyncResponse {
+            write_occurred: written + count(SyncAction::Deleted) > 0,
+            results: self.results,
+            summary,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(path, contents).expect("write");
+    }
+
+    fn sample_project(dir: &Path) -> RojoProject {
+        write(
+            &dir.join(PROJECT_FILE_NAME),
+            r#"{
+                "name": "game",
+                "tree": {
+                    "$className": "DataModel",
+                    "ServerScriptService": { "$path": "src/server" },
+                    "ReplicatedStorage": {
+                        "Shared": { "$path": "src/shared" }
+                    }
+                }
+            }"#,
+        );
+        write(&dir.join("src/server/Main.server.luau"), "print('main')\n");
+        write(&dir.join("src/server/Combat/init.luau"), "return {}\n");
+        write(&dir.join("src/server/Combat/Damage.lua"), "return 1\n");
+        write(&dir.join("src/server/notes.txt"), "ignored");
+        write(&dir.join("src/shared/Util.luau"), "return 'util'\n");
+        RojoProject::load(dir).expect("project")
+    }
+
+    #[test]
+    fn builds_push_trees_from_the_filesystem() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = sample_project(dir.path());
+        let nodes = serde_json::to_value(project.push_nodes(false).expect("nodes")).expect("json");
+
+        assert_eq!(nodes[0]["name"], json!("ReplicatedStorage"));
+        assert_eq!(nodes[0]["className"], json!("ReplicatedStorage"));
+        assert_eq!(nodes[0]["children"][0]["className"], json!("Folder"));
+        assert_eq!(
+            nodes[0]["children"][0]["children"][0]["name"],
+            json!("Util")
+        );
+
+        let server = &nodes[1];
+        assert_eq!(server["className"], json!("ServerScriptService"));
+        let children = server["children"].as_array().expect("children");
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0]["name"], json!("Combat"));
+        assert_eq!(children[0]["className"], json!("ModuleScript"));
+        assert_eq!(children[0]["children"][0]["source"], json!("return 1\n"));
+        assert_eq!(children[1]["className"], json!("Script"));
+        assert_eq!(
+            project.pull_roots(),
+            [
+                vec!["ReplicatedStorage".to_string(), "Shared".to_string()],
+                vec!["ServerScriptService".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_pulled_edits_with_rojo_names() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = sample_project(dir.path());
+        let response = json!({
+            "roots": [
+                {
+                    "path": ["ServerScriptService"],
+                    "node": {
+                        "name": "ServerScriptService",
+                        "className": "ServerScriptService",
+                        "children": [
+                            { "name": "Main", "className": "Script", "source": "print('edited')\n" },
+                            { "name": "Combat", "className": "ModuleScript", "source": "return {}\n" },
+                            { "name": "Client", "className": "LocalScript", "source": "-- new\n" },
+                            { "name": "Baseplate", "className": "Part" },
+                        ],
+                    },
+                },
+                { "path": ["ReplicatedStorage", "Shared"], "error": "Unable to find 'Shared'" },
+            ],
+        });
+
+        let result = project
+            .write_pull(&response.to_string(), true)
+            .expect("pull");
+        let result: JsonValue = serde_json::from_str(&result).expect("json");
+        let server = dir.path().join("src/server");
+        assert_eq!(
+            fs::read_to_string(server.join("Main.server.luau")).expect("main"),
+            "print('edited')\n"
+        );
+        assert!(server.join("Client.client.luau").is_file());
+        assert!(server.join("Combat/init.luau").is_file());
+        assert!(!server.join("Combat/Damage.lua").exists(), "pruned");
+        assert!(server.join("notes.txt").is_file());
+        assert_eq!(result["writeOccurred"], json!(true));
+        assert!(result["summary"]
+            .as_str()
+            .expect("summary")
+            .contains("1 created, 1 updated, 1 unchanged, 1 deleted, 1 skipped, 1 failed"));
+    }
+
+    #[test]
+    fn skips_instances_whose_names_are_not_file_names() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = sample_project(dir.path());
+        let response = json!({
+            "roots": [{
+                "path": ["ServerScriptService"],
+                "node": {
+                    "name": "ServerScriptService",
+                    "className": "ServerScriptService",
+                    "children": [
+                        { "name": "../../Escape", "className": "Script", "source": "-- out\n" },
+                        { "name": "..", "className": "Folder" },
+                        { "name": "/tmp/Rooted", "className": "ModuleScript", "source": "return 1\n" },
+                    ],
+                },
+            }],
+        });
+
+        let result = project
+            .write_pull(&response.to_string(), false)
+            .expect("pull");
+        let result: JsonValue = serde_json::from_str(&result).expect("json");
+        assert!(!dir.path().join("Escape.server.luau").exists());
+        assert!(!dir.path().join("src/Escape.server.luau").exists());
+        assert!(!Path::new("/tmp/Rooted.luau").exists());
+        let skipped: Vec<&JsonValue> = result["results"]
+            .as_array()
+            .expect("results")
+            .iter()
+            .filter(|result| result["action"] == "skipped")
+            .collect();
+        assert_eq!(skipped.len(), 3);
+        assert_eq!(result["writeOccurred"], json!(false));
+    }
+}
@@ -0,0 +1,899 @@
+//! `RobloxValue`: the JSON encoding of Roblox datatypes shared by the property and attribute bags
+//! of `apply_instance_operations`, `data_model_snapshot` entries, and the offline place file.
+//! Primitives are plain JSON; every other datatype is an object tagged with its `type`, in the
+//! shape the plugin's snapshot encoder emits, so a value read from a snapshot can be written back
+//! unchanged. Property values are also checked against the bundled reflection database, so a
+//! Vector3 sent for `Color` is rejected before anything is written.
+
+use super::{CollectionAndAttributesOperation, InstanceOperation, ScriptOperation};
+use color_eyre::eyre::{eyre, Result};
+use rbx_dom_weak::types::{BrickColor, VariantType};
+use rbx_reflection::{DataType, PropertyDescriptor, PropertyKind};
+use rmcp::schemars;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+
+/// A property or attribute value.
+#[derive(Debug, Clone, PartialEq, Serialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub(super) enum RobloxValue {
+    /// Removes an attribute, or clears an instance reference.
+    Null,
+    Bool(bool),
+    /// Any number; integer properties are truncated by Studio.
+    Number(f64),
+    /// A string, Content id, or enum item name such as "Neon".
+    String(String),
+    Typed(TypedValue),
+    /// A value that did not match any datatype, kept so it can be reported with its error.
+    #[schemars(skip)]
+    Invalid(InvalidValue),
+}
+
+/// Roblox datatypes, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub(super) enum TypedValue {
+    /// Explicit nil; removes an attribute or clears a reference.
+    #[serde(rename = "nil")]
+    Nil,
+    /// A number JSON cannot represent: "nan", "inf", or "-inf".
+    #[serde(rename = "number")]
+    SpecialNumber {
+        value: String,
+    },
+    Vector3 {
+        #[serde(alias = "X")]
+        x: f64,
+        #[serde(alias = "Y")]
+        y: f64,
+        #[serde(alias = "Z")]
+        z: f64,
+    },
+    Vector2 {
+        #[serde(alias = "X")]
+        x: f64,
+        #[serde(alias = "Y")]
+        y: f64,
+    },
+    Vector3int16 {
+        x: i16,
+        y: i16,
+        z: i16,
+    },
+    Vector2int16 {
+        x: i16,
+        y: i16,
+    },
+    /// `CFrame:GetComponents()`: position followed by the rotation matrix rows.
+    CFrame {
+        #[serde(alias = "value")]
+        #[schemars(length(equal = 12))]
+        components: Vec<f64>,
+    },
+    /// Components from 0 to 1.
+    Color3 {
+        #[serde(alias = "R")]
+        r: f64,
+        #[serde(alias = "G")]
+        g: f64,
+        #[serde(alias = "B")]
+        b: f64,
+    },
+    /// Components from 0 to 255.
+    Color3uint8 {
+        r: u8,
+        g: u8,
+        b: u8,
+    },
+    /// A BrickColor by `name` (e.g. "Bright red") or palette `number`.
+    BrickColor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        number: Option<u16>,
+    },
+    UDim {
+        #[serde(default)]
+        scale: f64,
+        #[serde(default)]
+        offset: i32,
+    },
+    UDim2 {
+        x: UDimValue,
+        y: UDimValue,
+    },
+    Rect {
+        min: Vector2Value,
+        max: Vector2Value,
+    },
+    NumberRange {
+        min: f64,
+        max: f64,
+    },
+    /// At least two keypoints, the first at time 0 and the last at time 1.
+    NumberSequence {
+        keypoints: Vec<NumberSequenceKeypoint>,
+    },
+    /// At least two keypoints, the first at time 0 and the last at time 1.
+    ColorSequence {
+        keypoints: Vec<ColorSequenceKeypoint>,
+    },
+    PhysicalProperties {
+        density: f64,
+        friction: f64,
+        elasticity: f64,
+        #[serde(default = "one")]
+        friction_weight: f64,
+        #[serde(default = "one")]
+        elasticity_weight: f64,
+    },
+    /// An enum item such as "Enum.Material.Neon".
+    EnumItem {
+        value: String,
+    },
+    /// A reference to another instance, by `path` or by full name in `value`.
+    Instance {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+    /// An ISO 8601 timestamp.
+    DateTime {
+        value: String,
+    },
+    /// A font family asset id with optional `Enum.FontWeight` and `Enum.FontStyle` item names.
+    Font {
+        family: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weight: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        style: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub(super) struct UDimValue {
+    #[serde(default, alias = "Scale")]
+    scale: f64,
+    #[serde(default, alias = "Offset")]
+    offset: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub(super) struct Vector2Value {
+    #[serde(alias = "X")]
+    x: f64,
+    #[serde(alias = "Y")]
+    y: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub(super) struct NumberSequenceKeypoint {
+    time: f64,
+    value: f64,
+    #[serde(default)]
+    envelope: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub(super) struct ColorSequenceKeypoint {
+    time: f64,
+    value: Color3Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub(super) struct Color3Value {
+    #[serde(alias = "R")]
+    r: f64,
+    #[serde(alias = "G")]
+    g: f64,
+    #[serde(alias = "B")]
+    b: f64,
+}
+
+/// The JSON that failed to decode and why. Serializes as the original JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct InvalidValue {
+    value: JsonValue,
+    error: String,
+}
+
+impl Serialize for InvalidValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+impl<'de> Deserialize<'de> for RobloxValue {
+    /// Never fails, so one malformed value is reported by name instead of rejecting the request
+    /// with a generic parse error.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        JsonValue::deserialize(deserializer).map(RobloxValue::from_json)
+    }
+}
+
+impl RobloxValue {
+    pub(super) fn from_json(value: JsonValue) -> Self {
+        let invalid =
+            |value: JsonValue, error: String| RobloxValue::Invalid(InvalidValue { value, error });
+        match value {
+            JsonValue::Null => RobloxValue::Null,
+            JsonValue::Bool(value) => RobloxValue::Bool(value),
+            JsonValue::Number(ref number) => match number.as_f64() {
+                Some(number) => RobloxValue::Number(number),
+                None => invalid(value, "Number is out of range".to_string()),
+            },
+            JsonValue::String(value) => RobloxValue::String(value),
+            JsonValue::Array(_) => invalid(
+                value,
+                "Arrays are not Roblox values; encode datatypes as objects with a \"type\", e.g. {\"type\": \"Vector3\", \"x\": 0, \"y\": 1, \"z\": 0}".to_string(),
+            ),
+            JsonValue::Object(ref object) => {
+                let Some(type_name) = object
+                    .get("type")
+                    .or_else(|| object.get("__type"))
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string)
+                else {
+                    return invalid(
+                        value,
+                        "Objects must name their Roblox datatype in \"type\", e.g. {\"type\": \"Color3\", \"r\": 1, \"g\": 0, \"b\": 0}".to_string(),
+                    );
+                };
+                match serde_json::from_value(JsonValue::Object(legacy_shape(&type_name, object))) {
+                    Ok(typed) => RobloxValue::Typed(typed),
+                    Err(err) => invalid(value, format!("Invalid {type_name} value: {err}")),
+                }
+            }
+        }
+    }
+
+    pub(super) fn to_json(&self) -> JsonValue {
+        serde_json::to_value(self).unwrap_or(JsonValue::Null)
+    }
+
+    /// Checks what the schema cannot express, such as enum item names and keypoint order.
+    pub(super) fn check(&self) -> Result<(), String> {
+        match self {
+            RobloxValue::Invalid(invalid) => Err(invalid.error.clone()),
+            RobloxValue::Typed(typed) => typed.check(),
+            _ => Ok(()),
+        }
+    }
+
+    /// As [`RobloxValue::check`], and rejects datatypes attributes cannot hold.
+    pub(super) fn check_attribute(&self) -> Result<(), String> {
+        self.check()?;
+        match self {
+            RobloxValue::Typed(
+                typed @ (TypedValue::Instance { .. }
+                | TypedValue::PhysicalProperties { .. }
+                | TypedValue::Vector3int16 { .. }
+                | TypedValue::Vector2int16 { .. }
+                | TypedValue::Color3uint8 { .. }
+                | TypedValue::DateTime { .. }),
+            ) => Err(format!(
+                "{} values cannot be stored in attributes",
+                typed.type_name()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// As [`RobloxValue::check`], and checks the value against the type of `property` in the
+    /// reflection database: on `class_name` when it is known, otherwise on any class that has a
+    /// property of that name.
+    pub(super) fn check_property(
+        &self,
+        class_name: Option<&str>,
+        property: &str,
+    ) -> Result<(), String> {
+        self.check()?;
+        let database = rbx_reflection_database::get();
+        let descriptors: Vec<&PropertyDescriptor> =
+            match class_name.filter(|class_name| database.classes.contains_key(*class_name)) {
+                Some(class_name) => vec![find_property(class_name, property)
+                    .ok_or_else(|| format!("{class_name} has no property named '{property}'"))?],
+                None => database
+                    .classes
+                    .keys()
+                    .filter_map(|class_name| find_property(class_name, property))
+                    .collect(),
+            };
+        if descriptors.is_empty() {
+            return Err(format!("No Roblox class has a property named '{property}'"));
+        }
+        if descriptors
+            .iter()
+            .any(|descriptor| self.fits(&descriptor.data_type))
+        {
+            return Ok(());
+        }
+        let mut expected: Vec<String> = descriptors
+            .iter()
+            .map(|descriptor| data_type_name(&descriptor.data_type))
+            .collect();
+        expected.sort_unstable();
+        expected.dedup();
+        Err(format!(
+            "{property} expects {}, not {}",
+            expected.join(" or "),
+            self.type_name()
+        ))
+    }
+
+    /// Whether Studio can assign this value to a property of type `data_type`.
+    fn fits(&self, data_type: &DataType) -> bool {
+        let value_type = match data_type {
+            DataType::Enum(enum_name) => {
+                return match self {
+                    RobloxValue::Number(_) => true,
+                    RobloxValue::String(item) => {
+                        check_enum_item(enum_name, enum_item_name(item)).is_ok()
+                    }
+                    RobloxValue::Typed(TypedValue::EnumItem { value }) => {
+                        value
+                            .strip_prefix("Enum.")
+                            .unwrap_or(value)
+                            .split('.')
+                            .next()
+                            == Some(enum_name.as_ref())
+                    }
+                    _ => false,
+                }
+            }
+            DataType::Value(value_type) => *value_type,
+            // Data types newer than this server; Studio reports any mismatch itself.
+            _ => return true,
+        };
+        match self {
+            RobloxValue::Null | RobloxValue::Typed(TypedValue::Nil) => {
+                matches!(value_type, VariantType::Ref | VariantType::OptionalCFrame)
+            }
+            RobloxValue::Bool(_) => value_type == VariantType::Bool,
+            RobloxValue::Number(_) => matches!(
+                value_type,
+                VariantType::Float32
+                    | VariantType::Float64
+                    | VariantType::Int32
+                    | VariantType::Int64
+            ),
+            RobloxValue::String(_) => matches!(
+                value_type,
+                VariantType::String | VariantType::BinaryString | VariantType::Content
+            ),
+            RobloxValue::Typed(typed) => typed.variant_types().contains(&value_type),
+            RobloxValue::Invalid(_) => false,
+        }
+    }
+
+    fn type_name(&self) -> String {
+        match self {
+            RobloxValue::Null => "nil".to_string(),
+            RobloxValue::Bool(_) => "a boolean".to_string(),
+            RobloxValue::Number(_) => "a number".to_string(),
+            RobloxValue::String(_) => "a string".to_string(),
+            RobloxValue::Typed(typed) => typed.type_name(),
+            RobloxValue::Invalid(_) => "an invalid value".to_string(),
+        }
+    }
+}
+
+impl TypedValue {
+    fn type_name(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(JsonValue::Object(object)) => object
+                .get("type")
+                .and_then(JsonValue::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// The property types this datatype can be assigned to.
+    fn variant_types(&self) -> &'static [VariantType] {
+        match self {
+            TypedValue::Nil => &[VariantType::Ref, VariantType::OptionalCFrame],
+            TypedValue::SpecialNumber { .. } => &[VariantType::Float32, VariantType::Float64],
+            TypedValue::Vector3 { .. } => &[VariantType::Vector3],
+            TypedValue::Vector2 { .. } => &[VariantType::Vector2],
+            TypedValue::Vector3int16 { .. } => &[VariantType::Vector3int16],
+            TypedValue::Vector2int16 { .. } => &[VariantType::Vector2int16],
+            TypedValue::CFrame { .. } => &[VariantType::CFrame, VariantType::OptionalCFrame],
+            TypedValue::Color3 { .. } | TypedValue::Color3uint8 { .. } => {
+                &[VariantType::Color3, VariantType::Color3uint8]
+            }
+            TypedValue::BrickColor { .. } => &[VariantType::BrickColor],
+            TypedValue::UDim { .. } => &[VariantType::UDim],
+            TypedValue::UDim2 { .. } => &[VariantType::UDim2],
+            TypedValue::Rect { .. } => &[VariantType::Rect],
+            TypedValue::NumberRange { .. } => &[VariantType::NumberRange],
+            TypedValue::NumberSequence { .. } => &[VariantType::NumberSequence],
+            TypedValue::ColorSequence { .. } => &[VariantType::ColorSequence],
+            TypedValue::PhysicalProperties { .. } => &[VariantType::PhysicalProperties],
+            TypedValue::Instance { .. } => &[VariantType::Ref],
+            TypedValue::Font { .. } => &[VariantType::Font],
+            // Enum items are matched against the enum name instead; no property stores a DateTime.
+            TypedValue::EnumItem { .. } | TypedValue::DateTime { .. } => &[],
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        match self {
+            TypedValue::SpecialNumber { value } => match value.as_str() {
+                "nan" | "inf" | "-inf" => Ok(()),
+                _ => Err(format!(
+                    "Special numbers must be \"nan\", \"inf\", or \"-inf\", not \"{value}\""
+                )),
+            },
+            TypedValue::CFrame { components } if components.len() != 12 => Err(format!(
+                "CFrame values need 12 components (position then rotation rows), got {}",
+                components.len()
+            )),
+            TypedValue::BrickColor { name, number } => match (name, number) {
+                (None, None) => Err("BrickColor values need a name or a number".to_string()),
+                (_, Some(number)) if BrickColor::from_number(*number).is_none() => {
+                    Err(format!("{number} is not a BrickColor number"))
+                }
+                (Some(name), None) if BrickColor::from_name(name).is_none() => {
+                    Err(format!("'{name}' is not a BrickColor name"))
+                }
+                _ => Ok(()),
+            },
+            TypedValue::NumberRange { min, max } if min > max => {
+                Err(format!("NumberRange min {min} is greater than max {max}"))
+            }
+            TypedValue::NumberSequence { keypoints } => {
+                if keypoints.iter().any(|keypoint| keypoint.envelope < 0.0) {
+                    return Err("NumberSequence envelopes cannot be negative".to_string());
+                }
+                check_keypoint_times(keypoints.iter().map(|keypoint| keypoint.time))
+            }
+            TypedValue::ColorSequence { keypoints } => {
+                check_keypoint_times(keypoints.iter().map(|keypoint| keypoint.time))
+            }
+            TypedValue::EnumItem { value } => {
+                let mut segments = value.strip_prefix("Enum.").unwrap_or(value).split('.');
+                match (segments.next(), segments.next(), segments.next()) {
+                    (Some(enum_name), Some(item), None) => check_enum_item(enum_name, item),
+                    _ => Err(format!(
+                        "Enum items are written as \"Enum.<Enum>.<Item>\", not \"{value}\""
+                    )),
+                }
+            }
+            TypedValue::Instance { path, value } if path.is_none() && value.is_none() => {
+                Err("Instance references need a path or a full name in value".to_string())
+            }
+            TypedValue::DateTime { value } => humantime::parse_rfc3339_weak(value)
+                .map(|_| ())
+                .map_err(|err| format!("'{value}' is not an ISO 8601 timestamp: {err}")),
+            TypedValue::Font { weight, style, .. } => {
+                if let Some(weight) = weight {
+                    check_enum_item("FontWeight", enum_item_name(weight))?;
+                }
+                if let Some(style) = style {
+                    check_enum_item("FontStyle", enum_item_name(style))?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Accepts the shapes older callers sent: `__type` tags and flat UDim2 fields.
+fn legacy_shape(type_name: &str, object: &Map<String, JsonValue>) -> Map<String, JsonValue> {
+    let mut object = object.clone();
+    if let Some(tag) = object.remove("__type") {
+        object.entry("type").or_insert(tag);
+    }
+    if type_name == "UDim2" && !object.contains_key("x") {
+        let field = |keys: [&str; 2]| {
+            keys.iter()
+                .find_map(|key| object.get(*key).cloned())
+                .unwrap_or(JsonValue::from(0))
+        };
+        let x = serde_json::json!({
+            "scale": field(["xScale", "XScale"]),
+            "offset": field(["xOffset", "XOffset"]),
+        });
+        let y = serde_json::json!({
+            "scale": field(["yScale", "YScale"]),
+            "offset": field(["yOffset", "YOffset"]),
+        });
+        object.insert("x".to_string(), x);
+        object.insert("y".to_string(), y);
+        for key in ["xScale", "XScale", "xOffset", "XOffset"] {
+            object.remove(key);
+        }
+        for key in ["yScale", "YScale", "yOffset", "YOffset"] {
+            object.remove(key);
+        }
+    }
+    object
+}
+
+fn check_keypoint_times(times: impl Iterator<Item = f64>) -> Result<(), String> {
+    let times: Vec<f64> = times.collect();
+    if times.len() < 2 {
+        return Err("Sequences need at least two keypoints".to_string());
+    }
+    if times[0] != 0.0 || times[times.len() - 1] != 1.0 {
+        return Err("Sequences must start at time 0 and end at time 1".to_string());
+    }
+    if times.windows(2).any(|pair| pair[0] > pair[1]) {
+        return Err("Sequence keypoints must be in time order".to_string());
+    }
+    Ok(())
+}
+
+/// `Bold` for any of `Bold`, `FontWeight.Bold`, or `Enum.FontWeight.Bold`.
+fn enum_item_name(value: &str) -> &str {
+    value.rsplit('.').next().unwrap_or(value)
+}
+
+fn check_enum_item(enum_name: &str, item: &str) -> Result<(), String> {
+    let Some(descriptor) = rbx_reflection_database::get().enums.get(enum_name) else {
+        return Err(format!("Enum.{enum_name} is not a Roblox enum"));
+    };
+    if descriptor.items.contains_key(item) {
+        return Ok(());
+    }
+    let mut items: Vec<&str> = descriptor.items.keys().map(|item| item.as_ref()).collect();
+    items.sort_unstable();
+    Err(format!(
+        "{item} is not an item of Enum.{enum_name} (expected one of {})",
+        items.join(", ")
+    ))
+}
+
+/// Finds the canonical descriptor for `property` on `class_name`, following aliases such as
+/// `BasePart.size` to `BasePart.Size`.
+pub(super) fn find_property(
+    class_name: &str,
+    property: &str,
+) -> Option<&'static PropertyDescriptor<'static>> {
+    let database = rbx_reflection_database::get();
+    let mut current = database.classes.get(class_name);
+    while let Some(class) = current {
+        if let Some(descriptor) = class.properties.get(property) {
+            return match &descriptor.kind {
+                PropertyKind::Alias { alias_for } => find_property(class_name, alias_for),
+                _ => Some(descriptor),
+            };
+        }
+        current = class
+            .superclass
+            .as_ref()
+            .and_then(|superclass| database.classes.get(superclass));
+    }
+    None
+}
+
+fn data_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Value(value_type) => format!("{value_type:?}"),
+        DataType::Enum(enum_name) => format!("Enum.{enum_name}"),
+        _ => "an unknown type".to_string(),
+    }
+}
+
+/// Fails with every message in `errors`, so a batch with one bad value is rejected before
+/// anything is written.
+pub(super) fn ensure_valid(errors: Vec<String>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(eyre!(
+        "Invalid property or attribute values; no changes were made:\n{}",
+        errors.join("\n")
+    ))
+}
+
+/// Checks every property and attribute in an `apply_instance_operations` batch, returning one
+/// message per invalid value, e.g. `operations[0].properties.Color: Color expects Color3, not
+/// Vector3`. Properties are checked on the created class, or on any class for other operations.
+pub(super) fn operation_value_errors(operations: &[InstanceOperation]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        let class_name = match operation {
+            InstanceOperation::Create(operation) => Some(operation.class_name.as_str()),
+            _ => None,
+        };
+        let (properties, attributes) = match operation {
+            InstanceOperation::Create(operation) => (&operation.properties, &operation.attributes),
+            InstanceOperation::Update(operation) => (&operation.properties, &operation.attributes),
+            InstanceOperation::Reparent(operation) => {
+                (&operation.properties, &operation.attributes)
+            }
+            InstanceOperation::Clone(operation) => (&operation.properties, &operation.attributes),
+            InstanceOperation::BulkSetProperties(operation) => {
+                (&operation.properties, &operation.attributes)
+            }
+            InstanceOperation::Delete(_)
+            | InstanceOperation::Group(_)
+            | InstanceOperation::Ungroup(_)
+            | InstanceOperation::PivotTo(_)
+            | InstanceOperation::MoveBy(_)
+            | InstanceOperation::SetPrimaryPart(_)
+            | InstanceOperation::Weld(_) => continue,
+        };
+        collect_errors(
+            &mut errors,
+            index,
+            "properties",
+            properties,
+            |name, value| value.check_property(class_name, name),
+        );
+        collect_attribute_errors(&mut errors, index, attributes);
+    }
+    errors
+}
+
+/// As [`operation_value_errors`], for the attributes of `manage_scripts` creates.
+pub(super) fn script_attribute_errors(operations: &[ScriptOperation]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        if let ScriptOperation::Create { attributes, .. } = operation {
+            collect_attribute_errors(&mut errors, index, attributes);
+        }
+    }
+    errors
+}
+
+/// As [`operation_value_errors`], for `collection_and_attributes` syncs.
+pub(super) fn collection_attribute_errors(
+    operations: &[CollectionAndAttributesOperation],
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        if let CollectionAndAttributesOperation::SyncAttributes { attributes, .. } = operation {
+            collect_attribute_errors(&mut errors, index, attributes);
+        }
+    }
+    errors
+}
+
+fn collect_attribute_errors(
+    errors: &mut Vec<String>,
+    index: usize,
+    attributes: &HashMap<String, RobloxValue>,
+) {
+    collect_errors(errors, index, "attributes", attributes, |_, value| {
+        value.check_attribute()
+    });
+}
+
+fn collect_errors(
+    errors: &mut Vec<String>,
+    index: usize,
+    field: &str,
+    values: &HashMap<String, RobloxValue>,
+    check: impl Fn(&str, &RobloxValue) -> Result<(), String>,
+) {
+    let mut names: Vec<&String> = values.keys().collect();
+    names.sort();
+    for name in names {
+        if let Err(error) = check(name, &values[name]) {
+            errors.push(format!("operations[{index}].{field}.{name}: {error}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(value: JsonValue) -> RobloxValue {
+        serde_json::from_value(value).expect("RobloxValue never fails to deserialize")
+    }
+
+    #[test]
+    fn normalises_typed_values() {
+        assert_eq!(
+            parse(json!({ "type": "Vector3", "X": 1, "y": 2, "z": 3 })).to_json(),
+            json!({ "type": "Vector3", "x": 1.0, "y": 2.0, "z": 3.0 })
+        );
+    }
+
+    #[test]
+    fn accepts_legacy_udim2_shape() {
+        assert_eq!(
+            parse(json!({ "__type": "UDim2", "xScale": 0.5, "yOffset": 10 })).to_json(),
+            json!({
+                "type": "UDim2",
+                "x": { "scale": 0.5, "offset": 0 },
+                "y": { "scale": 0.0, "offset": 10 },
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_null() {
+        assert_eq!(parse(json!(null)), RobloxValue::Null);
+    }
+
+    #[test]
+    fn accepts_enum_items() {
+        assert_eq!(
+            parse(json!({ "type": "EnumItem", "value": "Enum.Material.Neon" })).check(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn accepts_brick_color_names() {
+        assert_eq!(
+            parse(json!({ "type": "BrickColor", "name": "Bright red" })).check(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn keeps_invalid_values_and_reports_missing_fields() {
+        let missing = parse(json!({ "type": "Color3", "r": 1, "g": 0 }));
+        assert_eq!(
+            missing.to_json(),
+            json!({ "type": "Color3", "r": 1, "g": 0 })
+        );
+        assert_eq!(
+            missing.check(),
+            Err("Invalid Color3 value: missing field `b`".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_arrays() {
+        assert!(parse(json!([1, 2, 3]))
+            .check()
+            .unwrap_err()
+            .starts_with("Arrays are not Roblox values"));
+    }
+
+    #[test]
+    fn rejects_unknown_enum_items() {
+        assert!(
+            parse(json!({ "type": "EnumItem", "value": "Enum.Material.Lava" }))
+                .check()
+                .unwrap_err()
+                .starts_with("Lava is not an item of Enum.Material")
+        );
+    }
+
+    #[test]
+    fn rejects_sequences_that_do_not_span_zero_to_one() {
+        assert_eq!(
+            parse(json!({
+                "type": "NumberSequence",
+                "keypoints": [{ "time": 0, "value": 1 }, { "time": 0.5, "value": 0 }],
+            }))
+            .check(),
+            Err("Sequences must start at time 0 and end at time 1".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_instances_in_attributes() {
+        assert_eq!(
+            parse(json!({ "type": "Instance", "path": ["Workspace"] })).check_attribute(),
+            Err("Instance values cannot be stored in attributes".to_string())
+        );
+    }
+
+    #[test]
+    fn accepts_property_values_of_the_reflected_type() {
+        let color = parse(json!({ "type": "Color3", "r": 1, "g": 0, "b": 0 }));
+        assert_eq!(color.check_property(Some("Part"), "Color"), Ok(()));
+        assert_eq!(
+            parse(json!("Neon")).check_property(Some("Part"), "Material"),
+            Ok(())
+        );
+        assert_eq!(
+            parse(json!(0.5)).check_property(None, "Transparency"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn follows_property_aliases() {
+        let size = parse(json!({ "type": "Vector3", "x": 4, "y": 1, "z": 2 }));
+        assert_eq!(size.check_property(Some("Part"), "size"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_property_values_of_another_type() {
+        let vector = parse(json!({ "type": "Vector3", "x": 1, "y": 0, "z": 0 }));
+        assert_eq!(
+            vector.check_property(Some("Part"), "Color"),
+            Err("Color expects Color3, not Vector3".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_items_of_another_enum() {
+        let item = parse(json!({ "type": "EnumItem", "value": "Enum.NormalId.Top" }));
+        assert_eq!(
+            item.check_property(Some("Part"), "Material"),
+            Err("Material expects Enum.Material, not EnumItem".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_properties() {
+        assert_eq!(
+            parse(json!(1)).check_property(Some("Part"), "Colour"),
+            Err("Part has no property named 'Colour'".to_string())
+        );
+        assert_eq!(
+            parse(json!(1)).check_property(None, "Colour"),
+            Err("No Roblox class has a property named 'Colour'".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_invalid_values_by_operation_and_name() {
+        let operations: Vec<InstanceOperation> = serde_json::from_value(json!([
+            {
+                "action": "create",
+                "path": ["Workspace"],
+                "className": "Part",
+                "properties": { "Color": { "type": "Vector3", "x": 1, "y": 0, "z": 0 } },
+                "attributes": { "Owner": { "type": "Instance", "path": ["Workspace"] } },
+            },
+        ]))
+        .unwrap();
+        assert_eq!(
+            operation_value_errors(&operations),
+            vec![
+                "operations[0].properties.Color: Color expects Color3, not Vector3".to_string(),
+                "operations[0].attributes.Owner: Instance values cannot be stored in attributes"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_script_and_sync_attributes() {
+        let scripts: Vec<ScriptOperation> = serde_json::from_value(json!([
+            {
+                "action": "create",
+                "path": ["ServerScriptService", "Main"],
+                "scriptType": "Script",
+                "attributes": { "Speed": { "type": "Vector3int16", "x": 1, "y": 2, "z": 3 } },
+            },
+        ]))
+        .unwrap();
+        assert_eq!(
+            script_attribute_errors(&scripts),
+            vec![
+                "operations[0].attributes.Speed: Vector3int16 values cannot be stored in attributes"
+                    .to_string()
+            ]
+        );
+
+        let collections: Vec<CollectionAndAttributesOperation> = serde_json::from_value(json!([
+            {
+                "operation": "sync_attributes",
+                "paths": [["Workspace", "Enemy"]],
+                "attributes": { "Tint": { "type": "Color3", "r": 1, "g": 0 } },
+            },
+        ]))
+        .unwrap();
+        assert_eq!(
+            collection_attribute_errors(&collections),
+            vec![
+                "operations[0].attributes.Tint: Invalid Color3 value: missing field `b`"
+                    .to_string()
+            ]
+        );
+    }
+}
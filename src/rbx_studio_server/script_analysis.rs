@@ -0,0 +1,973 @@
+//! Server-side processing of `manage_scripts` batches. Sources are formatted with StyLua when
+//! requested, parsed with full-moon before they reach Studio, and optionally linted with selene.
+//! Operations whose source does not parse are refused unless they set `force`.
+
+use super::script_patch::{self, EditError, TextRange};
+use super::{
+    script_format, script_outline, ManageScriptsRequest, ManageScriptsResponse, ScriptDiagnostic,
+    ScriptOperation, ScriptOperationKind, ScriptOperationResult,
+};
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use full_moon::LuaVersion;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// Returns the syntax errors in `source`, or an empty list when it parses as Luau.
+pub(super) fn syntax_diagnostics(source: &str) -> Vec<ScriptDiagnostic> {
+    full_moon::parse_fallible(source, LuaVersion::luau())
+        .errors()
+        .iter()
+        .map(|error| {
+            let (start, _) = error.range();
+            ScriptDiagnostic {
+                kind: Some("syntax".to_string()),
+                severity: Some("error".to_string()),
+                code: None,
+                message: error.error_message().into_owned(),
+                line: Some(start.line() as u32),
+                column: Some(start.character() as u32),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct SeleneSpan {
+    start_line: u32,
+    start_column: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeleneLabel {
+    span: SeleneSpan,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeleneDiagnostic {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    severity: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    primary_label: Option<SeleneLabel>,
+}
+
+/// Parses selene's `json2` output. Selene reports 0-indexed positions.
+fn parse_selene_output(stdout: &str) -> Vec<ScriptDiagnostic> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<SeleneDiagnostic>(line).ok())
+        .filter(|diagnostic| diagnostic.kind == "Diagnostic")
+        .map(|diagnostic| {
+            let span = diagnostic.primary_label.map(|label| label.span);
+            ScriptDiagnostic {
+                kind: Some("lint".to_string()),
+                severity: diagnostic
+                    .severity
+                    .map(|severity| severity.to_ascii_lowercase()),
+                code: diagnostic.code,
+                message: diagnostic.message,
+                line: span.as_ref().map(|span| span.start_line + 1),
+                column: span.as_ref().map(|span| span.start_column + 1),
+            }
+        })
+        .collect()
+}
+
+/// Runs selene over `source`, reading `selene.toml` from `config_dir` when given.
+async fn selene_diagnostics(
+    selene: &Path,
+    config_dir: Option<&Path>,
+    source: &str,
+) -> Result<Vec<ScriptDiagnostic>> {
+    let mut command = tokio::process::Command::new(selene);
+    command
+        .args(["--display-style", "json2", "--no-summary", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = config_dir {
+        command.current_dir(dir);
+    }
+    let mut child = command
+        .spawn()
+        .wrap_err_with(|| format!("Unable to run {}", selene.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(source.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let diagnostics = parse_selene_output(&stdout);
+    if diagnostics.is_empty() && !output.status.success() {
+        return Err(eyre!(
+            "selene exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(diagnostics)
+}
+
+/// How one operation of a `manage_scripts` batch is handled.
+#[derive(Debug)]
+enum Prepared {
+    /// Sent to Studio, with the diagnostics attached to its result and the action reported back.
+    Forward {
+        operation: ScriptOperation,
+        action: ScriptOperationKind,
+        diagnostics: Vec<ScriptDiagnostic>,
+        /// How the source Studio returns is presented to the caller.
+        view: SourceView,
+    },
+    /// Answered by the server without reaching Studio.
+    Done(ScriptOperationResult),
+}
+
+/// What a forwarded operation returns in place of the source Studio sends back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceView {
+    Full,
+    /// Dropped, for edits whose caller already knows the result.
+    Omitted,
+    /// The 1-indexed lines `start..=end`.
+    Lines {
+        start: usize,
+        end: Option<usize>,
+    },
+    /// The symbols the source declares, in `details.symbols`.
+    Outline,
+}
+
+fn local_result(
+    operation: &ScriptOperation,
+    success: bool,
+    message: String,
+    diagnostics: Vec<ScriptDiagnostic>,
+) -> ScriptOperationResult {
+    ScriptOperationResult {
+        action: operation.kind(),
+        path: operation.path().to_vec(),
+        id: None,
+        success,
+        message: Some(message),
+        source: None,
+        metadata: None,
+        details: None,
+        diagnostics,
+    }
+}
+
+fn edit_failure(operation: &ScriptOperation, error: EditError) -> ScriptOperationResult {
+    let mut result = local_result(operation, false, error.to_string(), Vec::new());
+    if let EditError::Conflicts(conflicts) = &error {
+        result.details = Some(json!({ "conflicts": conflicts }));
+    }
+    result
+}
+
+/// Returns true for operations the server turns into a `set_source` of the edited current source.
+fn edits_current_source(operation: &ScriptOperation) -> bool {
+    matches!(
+        operation,
+        ScriptOperation::FormatScript { .. }
+            | ScriptOperation::ApplyPatch { .. }
+            | ScriptOperation::ReplaceRange { .. }
+    )
+}
+
+/// Request that fetches the current source of every script edited by `format_script`,
+/// `apply_patch`, or `replace_range`, or `None` when the batch has no such operations.
+pub(super) fn source_fetch_request(request: &ManageScriptsRequest) -> Option<ManageScriptsRequest> {
+    let mut operations: Vec<ScriptOperation> = Vec::new();
+    for operation in request
+        .operations
+        .iter()
+        .filter(|op| edits_current_source(op))
+    {
+        if !operations
+            .iter()
+            .any(|fetch| fetch.path() == operation.path())
+        {
+            operations.push(ScriptOperation::GetSource {
+                path: operation.path().to_vec(),
+                start_line: None,
+                end_line: None,
+                metadata: None,
+            });
+        }
+    }
+    (!operations.is_empty()).then_some(ManageScriptsRequest {
+        operations,
+        default_metadata: None,
+        atomic: false,
+        dry_run: false,
+    })
+}
+
+/// A `manage_scripts` batch after the server has formatted, parsed, and linted its sources.
+#[derive(Debug)]
+pub(super) struct ScriptBatch {
+    operations: Vec<Prepared>,
+    rewritten: bool,
+    atomic: bool,
+}
+
+impl ScriptBatch {
+    /// Prepares every operation in `request`. `fetched` is Studio's response to
+    /// [`source_fetch_request`], and `config_dir` is where `selene.toml` and `stylua.toml` are read.
+    pub(super) async fn prepare(
+        request: &ManageScriptsRequest,
+        fetched: Option<&str>,
+        selene: Option<&Path>,
+        config_dir: Option<&Path>,
+    ) -> Result<Self> {
+        // Current source of each edited script, updated as the batch writes to it.
+        let mut sources: HashMap<Vec<String>, ScriptOperationResult> = HashMap::new();
+        if let (Some(fetch), Some(fetched)) = (source_fetch_request(request), fetched) {
+            let results = parse_response(fetched)?.results;
+            if results.len() != fetch.operations.len() {
+                bail!(
+                    "Studio returned {} sources for {} scripts",
+                    results.len(),
+                    fetch.operations.len()
+                );
+            }
+            for (operation, result) in fetch.operations.iter().zip(results) {
+                sources.insert(operation.path().to_vec(), result);
+            }
+        }
+        let mut cached_style = None;
+        let mut style = || {
+            cached_style
+                .get_or_insert_with(|| {
+                    script_format::load_style(config_dir).map_err(|err| format!("{err:#}"))
+                })
+                .clone()
+        };
+
+        let mut batch = ScriptBatch {
+            operations: Vec::with_capacity(request.operations.len()),
+            rewritten: false,
+            atomic: request.atomic,
+        };
+        for operation in &request.operations {
+            let prepared = match operation {
+                ScriptOperation::Create {
+                    source: Some(source),
+                    force,
+                    format,
+                    ..
+                }
+                | ScriptOperation::SetSource {
+                    source,
+                    force,
+                    format,
+                    ..
+                } => {
+                    let mut operation = operation.clone();
+                    if *format {
+                        match style() {
+                            Ok(style) => {
+                                // Unparsable sources are left alone; the syntax check reports them.
+                                if let Ok(formatted) = script_format::format_source(source, style) {
+                                    operation.set_source(formatted);
+                                    batch.rewritten = true;
+                                }
+                            }
+                            Err(err) => {
+                                batch.operations.push(Prepared::Done(local_result(
+                                    &operation,
+                                    false,
+                                    err,
+                                    Vec::new(),
+                                )));
+                                continue;
+                            }
+                        }
+                    }
+                    let action = operation.kind();
+                    let prepared =
+                        check_source(operation, *force, selene, config_dir, action).await;
+                    record_write(&mut sources, &prepared);
+                    prepared
+                }
+                operation if edits_current_source(operation) => {
+                    batch.rewritten = true;
+                    let current = match sources.get(operation.path()) {
+                        Some(current) if current.success => {
+                            current.source.clone().unwrap_or_default()
+                        }
+                        Some(current) => {
+                            batch.operations.push(Prepared::Done(ScriptOperationResult {
+                                action: operation.kind(),
+                                ..current.clone()
+                            }));
+                            continue;
+                        }
+                        None => bail!(
+                            "Studio did not return the source of {}",
+                            operation.path().join(".")
+                        ),
+                    };
+                    let edited = match operation {
+                        ScriptOperation::FormatScript { .. } => match style() {
+                            Err(err) => Err(local_result(operation, false, err, Vec::new())),
+                            Ok(style) => {
+                                script_format::format_source(&current, style).map_err(|_| {
+                                    local_result(
+                                    operation,
+                                    false,
+                                    "The current source does not parse, so it cannot be formatted"
+                                        .to_string(),
+                                    syntax_diagnostics(&current),
+                                )
+                                })
+                            }
+                        },
+                        ScriptOperation::ApplyPatch { patch, .. } => {
+                            script_patch::apply_patch(&current, patch)
+                                .map_err(|err| edit_failure(operation, err))
+                        }
+                        ScriptOperation::ReplaceRange {
+                            start_line,
+                            start_column,
+                            end_line,
+                            end_column,
+                            text,
+                            expected_text,
+                            ..
+                        } => script_patch::replace_range(
+                            &current,
+                            TextRange {
+                                start_line: *start_line as usize,
+                                start_column: start_column.unwrap_or(1) as usize,
+                                end_line: *end_line as usize,
+                                end_column: end_column.map(|column| column as usize),
+                            },
+                            text,
+                            expected_text.as_deref(),
+                        )
+                        .map_err(|err| edit_failure(operation, err)),
+                        _ => unreachable!("edits_current_source covers these operations"),
+                    };
+                    match edited {
+                        Err(result) => Prepared::Done(result),
+                        Ok(edited) if edited == current => {
+                            let message = match operation {
+                                ScriptOperation::FormatScript { .. } => {
+                                    "Source is already formatted"
+                                }
+                                _ => "The edit does not change the source",
+                            };
+                            let mut result =
+                                local_result(operation, true, message.to_string(), Vec::new());
+                            result.details = Some(json!({ "changed": false }));
+                            Prepared::Done(result)
+                        }
+                        Ok(edited) => {
+                            let (force, metadata) = match operation {
+                                ScriptOperation::ApplyPatch {
+                                    force, metadata, ..
+                                }
+                                | ScriptOperation::ReplaceRange {
+                                    force, metadata, ..
+                                } => (*force, metadata.clone()),
+                                ScriptOperation::FormatScript { metadata, .. } => {
+                                    (false, metadata.clone())
+                                }
+                                _ => (false, None),
+                            };
+                            let write = ScriptOperation::SetSource {
+                                path: operation.path().to_vec(),
+                                source: edited,
+                                expected_source: Some(current),
+                                force,
+                                format: false,
+                                metadata,
+                            };
+                            let mut prepared =
+                                check_source(write, force, selene, config_dir, operation.kind())
+                                    .await;
+                            if let Prepared::Forward { view, .. } = &mut prepared {
+                                *view = SourceView::Omitted;
+                            }
+                            record_write(&mut sources, &prepared);
+                            prepared
+                        }
+                    }
+                }
+                ScriptOperation::GetSource {
+                    start_line,
+                    end_line,
+                    ..
+                } if start_line.is_some() || end_line.is_some() => Prepared::Forward {
+                    operation: operation.clone(),
+                    action: operation.kind(),
+                    diagnostics: Vec::new(),
+                    view: SourceView::Lines {
+                        start: start_line.unwrap_or(1) as usize,
+                        end: end_line.map(|line| line as usize),
+                    },
+                },
+                ScriptOperation::Outline { path, metadata } => Prepared::Forward {
+                    operation: ScriptOperation::GetSource {
+                        path: path.clone(),
+                        start_line: None,
+                        end_line: None,
+                        metadata: metadata.clone(),
+                    },
+                    action: operation.kind(),
+                    diagnostics: Vec::new(),
+                    view: SourceView::Outline,
+                },
+                _ => Prepared::Forward {
+                    operation: operation.clone(),
+                    action: operation.kind(),
+                    diagnostics: Vec::new(),
+                    view: SourceView::Full,
+                },
+            };
+            batch.operations.push(prepared);
+        }
+        Ok(batch)
+    }
+
+    /// True when the request can be forwarded to Studio untouched.
+    pub(super) fn is_passthrough(&self) -> bool {
+        !self.rewritten
+            && self.operations.iter().all(|prepared| {
+                matches!(
+                    prepared,
+                    Prepared::Forward { diagnostics, view: SourceView::Full, .. }
+                        if diagnostics.is_empty()
+                )
+            })
+    }
+
+    /// Why an atomic batch fails before reaching Studio: the first operation the server refused.
+    fn refusal(&self) -> Option<String> {
+        if !self.atomic {
+            return None;
+        }
+        self.operations
+            .iter()
+            .enumerate()
+            .find_map(|(index, prepared)| match prepared {
+                Prepared::Done(result) if !result.success => Some(format!(
+                    "operation {} failed: {}",
+                    index + 1,
+                    result.message.as_deref().unwrap_or("unknown error")
+                )),
+                _ => None,
+            })
+    }
+
+    /// The request to send to Studio, or `None` when the server answered every operation or
+    /// refused an atomic batch.
+    pub(super) fn forwarded(&self, request: &ManageScriptsRequest) -> Option<ManageScriptsRequest> {
+        if self.refusal().is_some() {
+            return None;
+        }
+        let operations: Vec<ScriptOperation> = self
+            .operations
+            .iter()
+            .filter_map(|prepared| match prepared {
+                Prepared::Forward { operation, .. } => Some(operation.clone()),
+                Prepared::Done(_) => None,
+            })
+            .collect();
+        (!operations.is_empty()).then(|| ManageScriptsRequest {
+            operations,
+            default_metadata: request.default_metadata.clone(),
+            atomic: request.atomic,
+            dry_run: request.dry_run,
+        })
+    }
+
+    /// Combines Studio's response for the forwarded operations with the results answered by the
+    /// server, keeping the original operation order. When either side rolled an atomic batch back,
+    /// every result is reported as undone.
+    pub(super) fn merge(self, response: Option<&str>) -> Result<String> {
+        let forwarded_positions: Vec<usize> = self
+            .operations
+            .iter()
+            .enumerate()
+            .filter(|(_, prepared)| matches!(prepared, Prepared::Forward { .. }))
+            .map(|(index, _)| index + 1)
+            .collect();
+        let (mut forwarded, dry_run, changes, rollback_reason) = match response {
+            Some(response) => {
+                let response = parse_response(response)?;
+                let reason = response.rolled_back.then(|| {
+                    renumber_failure(
+                        response.rollback_reason.as_deref().unwrap_or_default(),
+                        &forwarded_positions,
+                    )
+                });
+                (
+                    response.results.into_iter(),
+                    response.dry_run,
+                    response.changes,
+                    reason,
+                )
+            }
+            None => (Vec::new().into_iter(), false, Vec::new(), self.refusal()),
+        };
+
+        let mut results = Vec::with_capacity(self.operations.len());
+        for prepared in self.operations {
+            results.push(match prepared {
+                Prepared::Done(result) => result,
+                Prepared::Forward {
+                    operation,
+                    action,
+                    diagnostics,
+                    view,
+                } => match forwarded.next() {
+                    Some(mut result) => {
+                        result.action = action;
+                        result.diagnostics.extend(diagnostics);
+                        present_source(&mut result, view);
+                        result
+                    }
+                    None if rollback_reason.is_some() => {
+                        let mut result = local_result(
+                            &operation,
+                            false,
+                            "Skipped because the batch was rolled back".to_string(),
+                            diagnostics,
+                        );
+                        result.action = action;
+                        result
+                    }
+                    None => bail!("Studio returned fewer results than operations"),
+                },
+            });
+        }
+
+        let succeeded = results.iter().filter(|result| result.success).count();
+        let summary = match &rollback_reason {
+            Some(reason) => {
+                for result in results.iter_mut().filter(|result| result.success) {
+                    result.success = false;
+                    result.id = None;
+                    result.message = Some(format!(
+                        "Rolled back: {}",
+                        result.message.as_deref().unwrap_or_default()
+                    ));
+                }
+                format!(
+                    "Rolled back {succeeded} of {} script operations because {reason}",
+                    results.len()
+                )
+            }
+            None => format!(
+                "{}Processed {} script operations ({succeeded} succeeded, {} failed)",
+                if dry_run { "Dry run: " } else { "" },
+                results.len(),
+                results.len() - succeeded
+            ),
+        };
+        Ok(serde_json::to_string(&ManageScriptsResponse {
+            results,
+            summary: Some(summary),
+            dry_run,
+            changes,
+            rolled_back: rollback_reason.is_some(),
+            rollback_reason,
+        })?)
+    }
+}
+
+/// Rewrites Studio's "operation N failed" rollback reason, which counts only the forwarded
+/// operations, to use the operation's position in the caller's batch.
+fn renumber_failure(reason: &str, forwarded_positions: &[usize]) -> String {
+    reason
+        .strip_prefix("operation ")
+        .and_then(|rest| rest.split_once(' '))
+        .and_then(|(index, rest)| {
+            let position = forwarded_positions.get(index.parse::<usize>().ok()?.checked_sub(1)?)?;
+            Some(format!("operation {position} {rest}"))
+        })
+        .unwrap_or_else(|| reason.to_string())
+}
+
+/// Replaces the full source in a forwarded result with what `view` asks for.
+fn present_source(result: &mut ScriptOperationResult, view: SourceView) {
+    if view == SourceView::Full || !result.success {
+        return;
+    }
+    let source = result.source.take().unwrap_or_default();
+    let details = match view {
+        SourceView::Full | SourceView::Omitted => return,
+        SourceView::Lines { start, end } => match script_outline::line_range(&source, start, end) {
+            Ok((lines, end, total)) => {
+                result.source = Some(lines);
+                json!({ "startLine": start, "endLine": end, "lineCount": total })
+            }
+            Err(message) => {
+                result.success = false;
+                result.message = Some(message);
+                return;
+            }
+        },
+        SourceView::Outline => {
+            result.message = Some(format!("Outlined {}", result.path.join(".")));
+            result.diagnostics.extend(syntax_diagnostics(&source));
+            json!({
+                "symbols": script_outline::outline(&source),
+                "lineCount": source.split_inclusive('\n').count(),
+            })
+        }
+    };
+    match (&mut result.details, details) {
+        (Some(JsonValue::Object(existing)), JsonValue::Object(added)) => existing.extend(added),
+        (slot, details) => *slot = Some(details),
+    }
+}
+
+fn parse_response(response: &str) -> Result<ManageScriptsResponse> {
+    serde_json::from_str(response).map_err(|_| eyre!("{response}"))
+}
+
+/// Parses (and optionally lints) the source of a `create` or `set_source` operation.
+async fn check_source(
+    operation: ScriptOperation,
+    force: bool,
+    selene: Option<&Path>,
+    config_dir: Option<&Path>,
+    action: ScriptOperationKind,
+) -> Prepared {
+    let source = match &operation {
+        ScriptOperation::Create {
+            source: Some(source),
+            ..
+        }
+        | ScriptOperation::SetSource { source, .. } => source.as_str(),
+        _ => "",
+    };
+    let mut diagnostics = syntax_diagnostics(source);
+    if !diagnostics.is_empty() && !force {
+        let errors = diagnostics.len();
+        let mut result = local_result(
+            &operation,
+            false,
+            format!(
+                "Source has {errors} syntax error{}; set force to apply it anyway",
+                if errors == 1 { "" } else { "s" }
+            ),
+            diagnostics,
+        );
+        result.action = action;
+        return Prepared::Done(result);
+    }
+    if let (Some(selene), true) = (selene, diagnostics.is_empty()) {
+        match selene_diagnostics(selene, config_dir, source).await {
+            Ok(lints) => diagnostics.extend(lints),
+            Err(err) => diagnostics.push(ScriptDiagnostic {
+                kind: Some("lint".to_string()),
+                severity: Some("warning".to_string()),
+                code: None,
+                message: format!("{err:#}"),
+                line: None,
+                column: None,
+            }),
+        }
+    }
+    Prepared::Forward {
+        operation,
+        action,
+        diagnostics,
+        view: SourceView::Full,
+    }
+}
+
+/// Remembers the source a forwarded write leaves behind, so later edits in the batch apply to it.
+fn record_write(sources: &mut HashMap<Vec<String>, ScriptOperationResult>, prepared: &Prepared) {
+    let Prepared::Forward {
+        operation: ScriptOperation::SetSource { path, source, .. },
+        ..
+    } = prepared
+    else {
+        return;
+    };
+    if let Some(current) = sources.get_mut(path) {
+        current.success = true;
+        current.source = Some(source.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_syntax_errors_with_positions() {
+        assert!(syntax_diagnostics("local x: number = 1\nreturn x").is_empty());
+
+        let diagnostics = syntax_diagnostics("local x = \nprint(x");
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].kind.as_deref(), Some("syntax"));
+        assert!(diagnostics[0].line.is_some());
+    }
+
+    #[test]
+    fn parses_selene_json_output() {
+        let stdout = concat!(
+            r#"{"type":"Diagnostic","severity":"Warning","code":"unused_variable","message":"x is defined, but never used","primary_label":{"message":"","span":{"start":6,"start_line":0,"start_column":6,"end":7,"end_line":0,"end_column":7}},"notes":[],"secondary_labels":[]}"#,
+            "\n",
+            r#"{"type":"Summary","errors":0,"warnings":1,"parse_errors":0}"#,
+        );
+        let diagnostics = parse_selene_output(stdout);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity.as_deref(), Some("warning"));
+        assert_eq!(diagnostics[0].code.as_deref(), Some("unused_variable"));
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(1), Some(7))
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_unparsable_sources_unless_forced() {
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "operations": [
+                { "action": "set_source", "path": ["ServerScriptService", "A"], "source": "end" },
+                { "action": "get_source", "path": ["ServerScriptService", "A"] },
+                {
+                    "action": "set_source",
+                    "path": ["ServerScriptService", "B"],
+                    "source": "end",
+                    "force": true,
+                },
+            ],
+        }))
+        .expect("request");
+
+        let batch = ScriptBatch::prepare(&request, None, None, None)
+            .await
+            .expect("batch");
+        assert!(!batch.is_passthrough());
+        let forwarded = batch.forwarded(&request).expect("forwarded");
+        assert_eq!(forwarded.operations.len(), 2);
+
+        let studio = json!({
+            "results": [
+                { "action": "get_source", "path": ["ServerScriptService", "A"], "success": true, "source": "" },
+                { "action": "set_source", "path": ["ServerScriptService", "B"], "success": true },
+            ],
+        });
+        let merged = batch.merge(Some(&studio.to_string())).expect("merge");
+        let merged: JsonValue = serde_json::from_str(&merged).expect("json");
+        let results = merged["results"].as_array().expect("results");
+        assert_eq!(results[0]["success"], json!(false));
+        assert_eq!(results[0]["diagnostics"][0]["kind"], json!("syntax"));
+        assert_eq!(results[1]["action"], json!("get_source"));
+        assert_eq!(results[2]["success"], json!(true));
+        assert_eq!(results[2]["diagnostics"][0]["kind"], json!("syntax"));
+        assert_eq!(
+            merged["summary"],
+            json!("Processed 3 script operations (2 succeeded, 1 failed)")
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_atomic_batches_before_studio() {
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "atomic": true,
+            "operations": [
+                { "action": "set_source", "path": ["ServerScriptService", "A"], "source": "return 1" },
+                { "action": "set_source", "path": ["ServerScriptService", "B"], "source": "end" },
+            ],
+        }))
+        .expect("request");
+
+        let batch = ScriptBatch::prepare(&request, None, None, None)
+            .await
+            .expect("batch");
+        assert!(batch.forwarded(&request).is_none());
+        let merged: JsonValue =
+            serde_json::from_str(&batch.merge(None).expect("merge")).expect("json");
+        assert_eq!(merged["rolledBack"], json!(true));
+        assert_eq!(
+            merged["results"][0]["message"],
+            json!("Skipped because the batch was rolled back")
+        );
+        assert_eq!(merged["results"][1]["success"], json!(false));
+        assert!(merged["rollbackReason"]
+            .as_str()
+            .is_some_and(|reason| reason.starts_with("operation 2 failed: ")));
+    }
+
+    #[test]
+    fn renumbers_studio_rollback_reasons() {
+        assert_eq!(
+            renumber_failure("operation 2 failed: boom", &[1, 3]),
+            "operation 3 failed: boom"
+        );
+        assert_eq!(renumber_failure("timeout", &[1]), "timeout");
+    }
+
+    #[tokio::test]
+    async fn formats_sources_and_format_script_targets() {
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "operations": [
+                {
+                    "action": "set_source",
+                    "path": ["ServerScriptService", "A"],
+                    "source": "print( 1 )",
+                    "format": true,
+                },
+                { "action": "format_script", "path": ["ServerScriptService", "B"] },
+                { "action": "format_script", "path": ["ServerScriptService", "C"] },
+            ],
+        }))
+        .expect("request");
+        let fetch = source_fetch_request(&request).expect("fetch");
+        assert_eq!(fetch.operations.len(), 2);
+
+        let fetched = json!({
+            "results": [
+                { "action": "get_source", "path": ["ServerScriptService", "B"], "success": true, "source": "local  x=1\n" },
+                { "action": "get_source", "path": ["ServerScriptService", "C"], "success": true, "source": "return 1\n" },
+            ],
+        });
+        let batch = ScriptBatch::prepare(&request, Some(&fetched.to_string()), None, None)
+            .await
+            .expect("batch");
+        let forwarded =
+            serde_json::to_value(batch.forwarded(&request).expect("forwarded")).expect("json");
+        assert_eq!(forwarded["operations"][0]["source"], json!("print(1)\n"));
+        assert_eq!(forwarded["operations"][1]["action"], json!("set_source"));
+        assert_eq!(forwarded["operations"][1]["source"], json!("local x = 1\n"));
+
+        let studio = json!({
+            "results": [
+                { "action": "set_source", "path": ["ServerScriptService", "A"], "success": true },
+                { "action": "set_source", "path": ["ServerScriptService", "B"], "success": true },
+            ],
+        });
+        let merged = batch.merge(Some(&studio.to_string())).expect("merge");
+        let merged: JsonValue = serde_json::from_str(&merged).expect("json");
+        assert_eq!(merged["results"][1]["action"], json!("format_script"));
+        assert_eq!(merged["results"][2]["details"]["changed"], json!(false));
+    }
+
+    #[tokio::test]
+    async fn chains_patches_and_reports_conflicts() {
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "operations": [
+                {
+                    "action": "apply_patch",
+                    "path": ["ServerScriptService", "A"],
+                    "patch": "@@ -1,2 +1,2 @@\n local x = 1\n-print(x)\n+print(x + 1)\n",
+                },
+                {
+                    "action": "replace_range",
+                    "path": ["ServerScriptService", "A"],
+                    "startLine": 1,
+                    "startColumn": 11,
+                    "endLine": 1,
+                    "endColumn": 12,
+                    "text": "2",
+                    "expectedText": "1",
+                },
+                {
+                    "action": "apply_patch",
+                    "path": ["ServerScriptService", "B"],
+                    "patch": "@@ -1 +1 @@\n-return 2\n+return 3\n",
+                },
+            ],
+        }))
+        .expect("request");
+        let fetch = source_fetch_request(&request).expect("fetch");
+        assert_eq!(fetch.operations.len(), 2);
+
+        let fetched = json!({
+            "results": [
+                { "action": "get_source", "path": ["ServerScriptService", "A"], "success": true, "source": "local x = 1\nprint(x)\n" },
+                { "action": "get_source", "path": ["ServerScriptService", "B"], "success": true, "source": "return 1\n" },
+            ],
+        });
+        let batch = ScriptBatch::prepare(&request, Some(&fetched.to_string()), None, None)
+            .await
+            .expect("batch");
+        let forwarded =
+            serde_json::to_value(batch.forwarded(&request).expect("forwarded")).expect("json");
+        let operations = forwarded["operations"].as_array().expect("operations");
+        assert_eq!(operations.len(), 2);
+        assert_eq!(
+            operations[0]["source"],
+            json!("local x = 1\nprint(x + 1)\n")
+        );
+        assert_eq!(
+            operations[1]["source"],
+            json!("local x = 2\nprint(x + 1)\n")
+        );
+        assert_eq!(operations[1]["expectedSource"], operations[0]["source"]);
+
+        let studio = json!({
+            "results": [
+                { "action": "set_source", "path": ["ServerScriptService", "A"], "success": true, "source": "..." },
+                { "action": "set_source", "path": ["ServerScriptService", "A"], "success": true, "source": "..." },
+            ],
+        });
+        let merged = batch.merge(Some(&studio.to_string())).expect("merge");
+        let merged: JsonValue = serde_json::from_str(&merged).expect("json");
+        assert_eq!(merged["results"][0]["action"], json!("apply_patch"));
+        assert_eq!(merged["results"][0]["source"], JsonValue::Null);
+        assert_eq!(merged["results"][1]["action"], json!("replace_range"));
+        assert_eq!(merged["results"][2]["success"], json!(false));
+        assert_eq!(
+            merged["results"][2]["details"]["conflicts"][0]["found"],
+            json!("return 1")
+        );
+    }
+
+    #[tokio::test]
+    async fn slices_lines_and_outlines_fetched_sources() {
+        let request: ManageScriptsRequest = serde_json::from_value(json!({
+            "operations": [
+                { "action": "get_source", "path": ["ReplicatedStorage", "M"], "startLine": 2, "endLine": 3 },
+                { "action": "outline", "path": ["ReplicatedStorage", "M"] },
+            ],
+        }))
+        .expect("request");
+        let batch = ScriptBatch::prepare(&request, None, None, None)
+            .await
+            .expect("batch");
+        assert!(!batch.is_passthrough());
+        let forwarded =
+            serde_json::to_value(batch.forwarded(&request).expect("forwarded")).expect("json");
+        assert_eq!(forwarded["operations"][1]["action"], json!("get_source"));
+
+        let source = "local M = {}\nfunction M.run()\nend\nreturn M\n";
+        let studio = json!({
+            "results": [
+                { "action": "get_source", "path": ["ReplicatedStorage", "M"], "success": true, "source": source, "details": { "characters": source.len() } },
+                { "action": "get_source", "path": ["ReplicatedStorage", "M"], "success": true, "source": source },
+            ],
+        });
+        let merged = batch.merge(Some(&studio.to_string())).expect("merge");
+        let merged: JsonValue = serde_json::from_str(&merged).expect("json");
+        let results = merged["results"].as_array().expect("results");
+        assert_eq!(results[0]["source"], json!("function M.run()\nend\n"));
+        assert_eq!(results[0]["details"]["lineCount"], json!(4));
+        assert_eq!(results[0]["details"]["characters"], json!(source.len()));
+        assert_eq!(results[1]["action"], json!("outline"));
+        assert_eq!(results[1]["source"], JsonValue::Null);
+        assert_eq!(
+            results[1]["details"]["symbols"][1],
+            json!({ "kind": "function", "name": "M.run", "line": 2, "endLine": 3, "exported": true })
+        );
+    }
+}
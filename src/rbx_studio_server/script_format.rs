@@ -0,0 +1,72 @@
+//! StyLua formatting for sources written through `manage_scripts`. The style is read from the
+//! project's `stylua.toml` so agent-written code matches the rest of the codebase.
+
+use color_eyre::eyre::{Result, WrapErr};
+use std::fs;
+use std::path::Path;
+use stylua_lib::{Config, OutputVerification};
+
+/// Config file names StyLua looks for, in priority order.
+const STYLE_FILES: &[&str] = &["stylua.toml", ".stylua.toml"];
+
+/// Loads the StyLua config from `dir`, falling back to StyLua's defaults when the directory has no
+/// config file.
+pub(super) fn load_style(dir: Option<&Path>) -> Result<Config> {
+    let Some(file) = dir.and_then(|dir| {
+        STYLE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|file| file.is_file())
+    }) else {
+        return Ok(Config::default());
+    };
+    let contents =
+        fs::read_to_string(&file).wrap_err_with(|| format!("Unable to read {}", file.display()))?;
+    toml::from_str(&contents).wrap_err_with(|| format!("Invalid StyLua config {}", file.display()))
+}
+
+/// Formats `source` with `style`. Fails when the source does not parse.
+pub(super) fn format_source(source: &str, style: Config) -> Result<String> {
+    Ok(stylua_lib::format_code(
+        source,
+        style,
+        None,
+        OutputVerification::None,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_SOURCE: &str = "local  x = {1,2}\nif x then print( x ) end\n";
+
+    #[test]
+    fn formats_with_the_default_style() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let formatted = format_source(SAMPLE_SOURCE, load_style(Some(dir.path())).expect("style"))
+            .expect("format");
+        assert_eq!(
+            formatted,
+            "local x = { 1, 2 }\nif x then\n\tprint(x)\nend\n"
+        );
+    }
+
+    #[test]
+    fn formats_with_the_project_stylua_toml() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        fs::write(
+            dir.path().join("stylua.toml"),
+            "indent_type = \"Spaces\"\nindent_width = 2\n",
+        )
+        .expect("config");
+        let formatted = format_source(SAMPLE_SOURCE, load_style(Some(dir.path())).expect("style"))
+            .expect("format");
+        assert!(formatted.contains("\n  print(x)\n"));
+    }
+
+    #[test]
+    fn rejects_sources_that_do_not_parse() {
+        assert!(format_source("local = ", Config::default()).is_err());
+    }
+}
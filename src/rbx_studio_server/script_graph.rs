@@ -0,0 +1,532 @@
+//! `script_dependency_graph`: statically resolves the `require(...)` calls of every script into a
+//! module graph, then reports cycles and modules nothing requires. Instance expressions are
+//! followed through `script`, `game`, `.Parent`, `GetService`, `FindFirstChild`, `WaitForChild`,
+//! `FindFirstAncestor`, and locals bound to such expressions.
+
+use super::{
+    script_search, DataModelSnapshotInstance, DataModelSnapshotRequest, GraphFormat, RobloxValue,
+    ScriptDependencyGraphRequest,
+};
+use color_eyre::eyre::Result;
+use full_moon::ast::{
+    Call, Expression, FunctionArgs, FunctionCall, Index, LocalAssignment, Prefix, Suffix, Var,
+};
+use full_moon::node::Node;
+use full_moon::tokenizer::{TokenReference, TokenType};
+use full_moon::visitors::Visitor;
+use full_moon::LuaVersion;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Snapshot request returning the source of every script under the request's roots.
+pub(super) fn snapshot_request(request: &ScriptDependencyGraphRequest) -> DataModelSnapshotRequest {
+    script_search::script_sources_request(&request.roots, script_search::SCRIPT_CLASSES)
+}
+
+/// Where an instance expression points, as far as it can be followed statically.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// An instance path relative to the DataModel.
+    Path(Vec<String>),
+    /// A numeric asset id, loaded from the marketplace at runtime.
+    Asset(String),
+}
+
+/// A `require` call found in one script.
+#[derive(Debug)]
+struct RequireCall {
+    line: usize,
+    expression: String,
+    target: Option<Target>,
+}
+
+/// Collects the `require` calls of one script, tracking locals bound to instance paths.
+struct RequireCollector<'a> {
+    script: &'a [String],
+    aliases: HashMap<String, Vec<String>>,
+    calls: Vec<RequireCall>,
+}
+
+impl RequireCollector<'_> {
+    fn resolve(&self, expression: &Expression) -> Option<Vec<String>> {
+        match expression {
+            Expression::Var(Var::Name(name)) => self.resolve_name(name),
+            Expression::Var(Var::Expression(var)) => {
+                self.resolve_chain(var.prefix(), var.suffixes())
+            }
+            Expression::FunctionCall(call) => self.resolve_chain(call.prefix(), call.suffixes()),
+            Expression::Parentheses { expression, .. }
+            | Expression::TypeAssertion { expression, .. } => self.resolve(expression),
+            _ => None,
+        }
+    }
+
+    fn resolve_name(&self, name: &TokenReference) -> Option<Vec<String>> {
+        match name.token().to_string().as_str() {
+            "script" => Some(self.script.to_vec()),
+            "game" => Some(Vec::new()),
+            name => self.aliases.get(name).cloned(),
+        }
+    }
+
+    fn resolve_chain<'s>(
+        &self,
+        prefix: &Prefix,
+        suffixes: impl Iterator<Item = &'s Suffix>,
+    ) -> Option<Vec<String>> {
+        let mut path = match prefix {
+            Prefix::Name(name) => self.resolve_name(name)?,
+            Prefix::Expression(expression) => self.resolve(expression)?,
+            _ => return None,
+        };
+        for suffix in suffixes {
+            match suffix {
+                Suffix::Index(Index::Dot { name, .. }) => match name.token().to_string().as_str() {
+                    "Parent" => {
+                        path.pop()?;
+                    }
+                    child => path.push(child.to_string()),
+                },
+                Suffix::Index(Index::Brackets { expression, .. }) => {
+                    path.push(string_literal(expression)?)
+                }
+                Suffix::Call(Call::MethodCall(call)) => {
+                    let argument = match call.args() {
+                        FunctionArgs::Parentheses { arguments, .. } => {
+                            string_literal(arguments.iter().next()?)?
+                        }
+                        FunctionArgs::String(token) => string_token(token)?,
+                        _ => return None,
+                    };
+                    match call.name().token().to_string().as_str() {
+                        "GetService" => path = vec![argument],
+                        "FindFirstChild" | "WaitForChild" => path.push(argument),
+                        "FindFirstAncestor" => {
+                            let ancestor = path[..path.len().saturating_sub(1)]
+                                .iter()
+                                .rposition(|segment| *segment == argument)?;
+                            path.truncate(ancestor + 1);
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(path)
+    }
+}
+
+impl Visitor for RequireCollector<'_> {
+    fn visit_local_assignment(&mut self, assignment: &LocalAssignment) {
+        let mut values = assignment.expressions().iter();
+        for name in assignment.names().iter() {
+            let name = name.token().to_string();
+            match values.next().and_then(|value| self.resolve(value)) {
+                Some(path) => self.aliases.insert(name, path),
+                None => self.aliases.remove(&name),
+            };
+        }
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall) {
+        let Prefix::Name(name) = call.prefix() else {
+            return;
+        };
+        if name.token().to_string() != "require" {
+            return;
+        }
+        let Some(Suffix::Call(Call::AnonymousCall(args))) = call.suffixes().next() else {
+            return;
+        };
+        let FunctionArgs::Parentheses { arguments, .. } = args.as_ref() else {
+            return;
+        };
+        let Some(argument) = arguments.iter().next() else {
+            return;
+        };
+        let target = match argument {
+            Expression::Number(number) => Some(Target::Asset(number.token().to_string())),
+            argument => self.resolve(argument).map(Target::Path),
+        };
+        self.calls.push(RequireCall {
+            line: call.start_position().map_or(0, |position| position.line()),
+            expression: argument.to_string().trim().to_string(),
+            target,
+        });
+    }
+}
+
+fn string_token(token: &TokenReference) -> Option<String> {
+    match token.token_type() {
+        TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+        _ => None,
+    }
+}
+
+fn string_literal(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::String(token) => string_token(token),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphScript {
+    path: Vec<String>,
+    class_name: String,
+    requires: Vec<Vec<String>>,
+    required_by: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphEdge {
+    from: Vec<String>,
+    to: Vec<String>,
+    line: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UnresolvedRequire {
+    from: Vec<String>,
+    line: usize,
+    expression: String,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DependencyGraph {
+    scripts: Vec<GraphScript>,
+    edges: Vec<GraphEdge>,
+    unresolved: Vec<UnresolvedRequire>,
+    /// Groups of modules that require each other, directly or indirectly.
+    cycles: Vec<Vec<Vec<String>>>,
+    /// ModuleScripts no script in the graph requires.
+    unused_modules: Vec<Vec<String>>,
+    summary: String,
+}
+
+/// Builds the dependency graph of the scripts in `entries`, which come from [`snapshot_request`],
+/// and renders it in the requested format.
+pub(super) fn dependency_graph(
+    request: &ScriptDependencyGraphRequest,
+    entries: &[DataModelSnapshotInstance],
+) -> Result<String> {
+    let scripts: Vec<(&DataModelSnapshotInstance, &str)> = entries
+        .iter()
+        .filter_map(|entry| match entry.properties.get("Source") {
+            Some(RobloxValue::String(source)) => Some((entry, source.as_str())),
+            _ => None,
+        })
+        .collect();
+    let index: HashMap<&[String], usize> = scripts
+        .iter()
+        .enumerate()
+        .map(|(position, (entry, _))| (entry.path.as_slice(), position))
+        .collect();
+
+    let mut graph = DependencyGraph {
+        scripts: scripts
+            .iter()
+            .map(|(entry, _)| GraphScript {
+                path: entry.path.clone(),
+                class_name: entry.class_name.clone(),
+                requires: Vec::new(),
+                required_by: 0,
+            })
+            .collect(),
+        edges: Vec::new(),
+        unresolved: Vec::new(),
+        cycles: Vec::new(),
+        unused_modules: Vec::new(),
+        summary: String::new(),
+    };
+    let mut adjacency: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); scripts.len()];
+    for (from, (entry, source)) in scripts.iter().enumerate() {
+        let ast = full_moon::parse_fallible(source, LuaVersion::luau());
+        let mut collector = RequireCollector {
+            script: &entry.path,
+            aliases: HashMap::new(),
+            calls: Vec::new(),
+        };
+        collector.visit_ast(ast.ast());
+        for call in collector.calls {
+            let reason = match &call.target {
+                None => "The required instance could not be determined statically".to_string(),
+                Some(Target::Asset(id)) => {
+                    format!("Requires asset {id}, which is loaded at runtime")
+                }
+                Some(Target::Path(path)) => match index.get(path.as_slice()) {
+                    Some(&to) if graph.scripts[to].class_name == "ModuleScript" => {
+                        adjacency[from].insert(to);
+                        graph.edges.push(GraphEdge {
+                            from: entry.path.clone(),
+                            to: path.clone(),
+                            line: call.line,
+                        });
+                        continue;
+                    }
+                    Some(&to) => {
+                        format!("{} is a {}", path.join("."), graph.scripts[to].class_name)
+                    }
+                    None => format!("No script at {}", path.join(".")),
+                },
+            };
+            graph.unresolved.push(UnresolvedRequire {
+                from: entry.path.clone(),
+                line: call.line,
+                expression: call.expression,
+                reason,
+            });
+        }
+    }
+
+    for (from, targets) in adjacency.iter().enumerate() {
+        graph.scripts[from].requires = targets
+            .iter()
+            .map(|to| graph.scripts[*to].path.clone())
+            .collect();
+        for to in targets {
+            graph.scripts[*to].required_by += 1;
+        }
+    }
+    let cycles = strongly_connected(&adjacency)
+        .into_iter()
+        .filter(|component| component.len() > 1 || adjacency[component[0]].contains(&component[0]))
+        .collect::<Vec<_>>();
+    graph.cycles = cycles
+        .iter()
+        .map(|component| {
+            component
+                .iter()
+                .map(|node| graph.scripts[*node].path.clone())
+                .collect()
+        })
+        .collect();
+    graph.unused_modules = graph
+        .scripts
+        .iter()
+        .filter(|script| script.class_name == "ModuleScript" && script.required_by == 0)
+        .map(|script| script.path.clone())
+        .collect();
+    graph.summary = format!(
+        "{} script(s), {} require(s), {} cycle(s), {} unused module(s), {} unresolved require(s)",
+        graph.scripts.len(),
+        graph.edges.len(),
+        graph.cycles.len(),
+        graph.unused_modules.len(),
+        graph.unresolved.len()
+    );
+
+    match request.format.unwrap_or_default() {
+        GraphFormat::Json => Ok(serde_json::to_string(&graph)?),
+        GraphFormat::Dot => Ok(to_dot(&graph, &cycles)),
+    }
+}
+
+/// Tarjan's algorithm. Components are returned with their nodes sorted.
+fn strongly_connected(adjacency: &[BTreeSet<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        adjacency: &'a [BTreeSet<usize>],
+        next: usize,
+        order: Vec<Option<usize>>,
+        low: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) {
+            self.order[node] = Some(self.next);
+            self.low[node] = self.next;
+            self.next += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+            for &target in self.adjacency[node].iter() {
+                match self.order[target] {
+                    None => {
+                        self.visit(target);
+                        self.low[node] = self.low[node].min(self.low[target]);
+                    }
+                    Some(order) if self.on_stack[target] => {
+                        self.low[node] = self.low[node].min(order);
+                    }
+                    Some(_) => {}
+                }
+            }
+            if Some(self.low[node]) == self.order[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        adjacency,
+        next: 0,
+        order: vec![None; adjacency.len()],
+        low: vec![0; adjacency.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; adjacency.len()],
+        components: Vec::new(),
+    };
+    for node in 0..adjacency.len() {
+        if tarjan.order[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.components
+}
+
+/// Graphviz rendering: modules are boxes, unused modules are dashed, and edges inside a cycle are
+/// red.
+fn to_dot(graph: &DependencyGraph, cycles: &[Vec<usize>]) -> String {
+    let mut component_of = HashMap::new();
+    for (component, members) in cycles.iter().enumerate() {
+        for member in members {
+            component_of.insert(graph.scripts[*member].path.as_slice(), component);
+        }
+    }
+    let quote = |path: &[String]| format!("{:?}", path.join("."));
+
+    let mut dot = String::from("digraph dependencies {\n    rankdir=LR;\n");
+    for script in &graph.scripts {
+        let shape = if script.class_name == "ModuleScript" {
+            "box"
+        } else {
+            "ellipse"
+        };
+        let style = if script.class_name == "ModuleScript" && script.required_by == 0 {
+            ", style=dashed"
+        } else {
+            ""
+        };
+        let _ = writeln!(dot, "    {} [shape={shape}{style}];", quote(&script.path));
+    }
+    for edge in &graph.edges {
+        let in_cycle = matches!(
+            (component_of.get(edge.from.as_slice()), component_of.get(edge.to.as_slice())),
+            (Some(from), Some(to)) if from == to
+        );
+        let color = if in_cycle { " [color=red]" } else { "" };
+        let _ = writeln!(
+            dot,
+            "    {} -> {}{color};",
+            quote(&edge.from),
+            quote(&edge.to)
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value as JsonValue};
+
+    fn script(path: &str, class_name: &str, source: &str) -> DataModelSnapshotInstance {
+        DataModelSnapshotInstance {
+            path: path.split('.').map(str::to_string).collect(),
+            class_name: class_name.to_string(),
+            properties: [(
+                "Source".to_string(),
+                RobloxValue::String(source.to_string()),
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    /// Modules A and B that require each other, an unused module, and a server script that
+    /// requires A and three things that do not resolve.
+    fn sample_entries() -> Vec<DataModelSnapshotInstance> {
+        vec![
+            script(
+                "ReplicatedStorage.Shared.A",
+                "ModuleScript",
+                "local Shared = script.Parent\nlocal B = require(Shared.B)\nreturn {}",
+            ),
+            script(
+                "ReplicatedStorage.Shared.B",
+                "ModuleScript",
+                "local RS = game:GetService(\"ReplicatedStorage\")\nreturn require(RS:WaitForChild(\"Shared\")[\"A\"])",
+            ),
+            script("ReplicatedStorage.Shared.Unused", "ModuleScript", "return {}"),
+            script(
+                "ServerScriptService.Main",
+                "Script",
+                "require(game.ReplicatedStorage.Shared.A)\nrequire(12345)\nrequire(script.Missing)\nrequire(getModule())",
+            ),
+        ]
+    }
+
+    fn graph(request: JsonValue) -> String {
+        let request: ScriptDependencyGraphRequest =
+            serde_json::from_value(request).expect("request");
+        dependency_graph(&request, &sample_entries()).expect("graph")
+    }
+
+    fn json_graph() -> JsonValue {
+        serde_json::from_str(&graph(json!({}))).expect("json")
+    }
+
+    #[test]
+    fn resolves_requires_into_edges() {
+        assert_eq!(json_graph()["edges"].as_array().map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn reports_require_cycles() {
+        assert_eq!(
+            json_graph()["cycles"],
+            json!([[
+                ["ReplicatedStorage", "Shared", "A"],
+                ["ReplicatedStorage", "Shared", "B"],
+            ]])
+        );
+    }
+
+    #[test]
+    fn reports_unused_modules() {
+        assert_eq!(
+            json_graph()["unusedModules"],
+            json!([["ReplicatedStorage", "Shared", "Unused"]])
+        );
+    }
+
+    #[test]
+    fn reports_requires_that_do_not_resolve() {
+        let graph = json_graph();
+        let unresolved = graph["unresolved"].as_array().expect("unresolved");
+        assert_eq!(unresolved.len(), 3);
+        assert_eq!(unresolved[0]["line"], json!(2));
+        assert_eq!(
+            unresolved[1]["reason"],
+            json!("No script at ServerScriptService.Main.Missing")
+        );
+    }
+
+    #[test]
+    fn renders_dot_with_cycles_and_unused_modules() {
+        let dot = graph(json!({ "format": "dot" }));
+        assert!(dot.contains(
+            "\"ReplicatedStorage.Shared.A\" -> \"ReplicatedStorage.Shared.B\" [color=red];"
+        ));
+        assert!(dot.contains("\"ReplicatedStorage.Shared.Unused\" [shape=box, style=dashed];"));
+    }
+}
@@ -0,0 +1,495 @@
+//! Local history of script sources. Every source the server reads from or writes to a script is
+//! stored once under its BLAKE3 hash, and an append-only index records which instance path held it,
+//! in which server session, and when. This outlives Studio's undo stack, so a module can be
+//! restored after a run of edits has been undone past or lost with the session. Only the newest
+//! versions of each script are kept; older ones are dropped from the index, and their sources are
+//! deleted once no other version refers to them.
+
+use super::{ManageScriptsRequest, ManageScriptsResponse, ScriptOperation};
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+const INDEX_FILE: &str = "index.jsonl";
+const OBJECTS_DIR: &str = "objects";
+
+/// Characters of the hash shown as a version id.
+const SHORT_ID_LENGTH: usize = 12;
+
+/// Shortest version prefix accepted when looking a version up.
+const MIN_PREFIX_LENGTH: usize = 4;
+
+/// Versions kept per script when the server is not given `--history-max-versions`.
+pub const DEFAULT_MAX_VERSIONS: usize = 50;
+
+/// Default location of the store: the platform's local data directory.
+pub fn default_history_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("rbx-studio-mcp").join("history"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Origin {
+    /// The source was read from the script.
+    Read,
+    /// The server wrote the source to the script.
+    Write,
+}
+
+/// One line of the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    hash: String,
+    path: Vec<String>,
+    session: String,
+    origin: Origin,
+    recorded_at: String,
+    characters: usize,
+    lines: usize,
+}
+
+#[derive(Debug)]
+pub(super) struct ScriptHistory {
+    root: PathBuf,
+    session: String,
+    /// Versions kept for each path before the oldest are pruned.
+    max_versions: usize,
+    /// Hash most recently recorded for each path in this session, so unchanged reads are skipped.
+    latest: Mutex<HashMap<Vec<String>, String>>,
+}
+
+impl ScriptHistory {
+    pub(super) fn new(root: PathBuf, session: String, max_versions: usize) -> Self {
+        Self {
+            root,
+            session,
+            max_versions: max_versions.max(1),
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Stores `source` as the current version of the script at `path`, unless it is already the
+    /// version last recorded for that path in this session.
+    pub(super) fn record(&self, path: &[String], source: &str, origin: Origin) -> Result<()> {
+        let hash = blake3::hash(source.as_bytes()).to_hex().to_string();
+        let mut latest = self
+            .latest
+            .lock()
+            .map_err(|_| eyre!("script history lock poisoned"))?;
+        if latest.get(path) == Some(&hash) {
+            return Ok(());
+        }
+
+        let object = self.object_path(&hash);
+        if !object.exists() {
+            let dir = object.parent().expect("objects are stored in a directory");
+            fs::create_dir_all(dir)
+                .wrap_err_with(|| format!("failed to create {}", dir.display()))?;
+            fs::write(&object, source)
+                .wrap_err_with(|| format!("failed to write {}", object.display()))?;
+        }
+        let entry = Entry {
+            hash: hash.clone(),
+            path: path.to_vec(),
+            session: self.session.clone(),
+            origin,
+            recorded_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            characters: source.chars().count(),
+            lines: source.lines().count(),
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let index = self.root.join(INDEX_FILE);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .wrap_err_with(|| format!("failed to append to {}", index.display()))?;
+        latest.insert(path.to_vec(), hash);
+        self.prune(path)
+    }
+
+    /// Drops the oldest versions of `path` beyond `max_versions`, and the sources no remaining
+    /// version refers to.
+    fn prune(&self, path: &[String]) -> Result<()> {
+        let entries = self.entries()?;
+        let count = entries.iter().filter(|entry| entry.path == path).count();
+        let Some(mut excess) = count
+            .checked_sub(self.max_versions)
+            .filter(|excess| *excess > 0)
+        else {
+            return Ok(());
+        };
+        let mut dropped = Vec::new();
+        let kept: Vec<Entry> = entries
+            .into_iter()
+            .filter(|entry| {
+                if excess > 0 && entry.path == path {
+                    excess -= 1;
+                    dropped.push(entry.hash.clone());
+                    return false;
+                }
+                true
+            })
+            .collect();
+
+        let mut contents = String::new();
+        for entry in &kept {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        let index = self.root.join(INDEX_FILE);
+        let temp = self.root.join(format!("{INDEX_FILE}.tmp"));
+        fs::write(&temp, contents)
+            .wrap_err_with(|| format!("failed to write {}", temp.display()))?;
+        fs::rename(&temp, &index)
+            .wrap_err_with(|| format!("failed to replace {}", index.display()))?;
+
+        for hash in dropped {
+            if !kept.iter().any(|entry| entry.hash == hash) {
+                let object = self.object_path(&hash);
+                if let Err(err) = fs::remove_file(&object) {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        return Err(err)
+                            .wrap_err_with(|| format!("failed to remove {}", object.display()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the sources in Studio's response to `request`: full `get_source` reads, and the
+    /// sources of successful `create` and `set_source` writes.
+    pub(super) fn record_batch(
+        &self,
+        request: &ManageScriptsRequest,
+        response: &ManageScriptsResponse,
+    ) -> Result<()> {
+        for (operation, result) in request.operations.iter().zip(&response.results) {
+            if !result.success {
+                continue;
+            }
+            match operation {
+                ScriptOperation::GetSource {
+                    start_line: None,
+                    end_line: None,
+                    ..
+                } => {
+                    if let Some(source) = &result.source {
+                        self.record(&result.path, source, Origin::Read)?;
+                    }
+                }
+                ScriptOperation::Create {
+                    source: Some(source),
+                    ..
+                }
+                | ScriptOperation::SetSource { source, .. } => {
+                    self.record(&result.path, source, Origin::Write)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Every entry of the index, oldest first.
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let index = self.root.join(INDEX_FILE);
+        let contents = match fs::read_to_string(&index) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("failed to read {}", index.display()))
+            }
+        };
+        Ok(contents
+            .lines()
+            // A line cut short by a crash is skipped rather than failing the whole history.
+            .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+            .collect())
+    }
+
+    /// Versions recorded for `path` in any session, newest first.
+    fn versions(&self, path: &[String]) -> Result<Vec<Entry>> {
+        let mut versions: Vec<Entry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.path == path)
+            .collect();
+        versions.reverse();
+        Ok(versions)
+    }
+
+    /// Finds the version of `path` whose id starts with `reference` and loads its source.
+    fn load(&self, path: &[String], reference: &str) -> Result<(Entry, String)> {
+        let reference = reference.trim().to_ascii_lowercase();
+        if reference.len() < MIN_PREFIX_LENGTH {
+            bail!("Version ids must be at least {MIN_PREFIX_LENGTH} characters");
+        }
+        let versions = self.versions(path)?;
+        let mut matches = versions
+            .into_iter()
+            .filter(|entry| entry.hash.starts_with(&reference));
+        let Some(entry) = matches.next() else {
+            bail!(
+                "No version {reference} is recorded for {}; use list_script_versions to see the versions",
+                path.join(".")
+            );
+        };
+        if matches.any(|other| other.hash != entry.hash) {
+            bail!("Version {reference} is ambiguous; use more characters of the id");
+        }
+        let object = self.object_path(&entry.hash);
+        let source = fs::read_to_string(&object)
+            .wrap_err_with(|| format!("failed to read {}", object.display()))?;
+        Ok((entry, source))
+    }
+
+    /// The JSON answer to `list_script_versions`.
+    pub(super) fn list(&self, path: &[String], limit: usize) -> Result<String> {
+        let versions = self.versions(path)?;
+        let listed: Vec<_> = versions
+            .iter()
+            .take(limit)
+            .map(|entry| {
+                json!({
+                    "version": short_id(&entry.hash),
+                    "origin": entry.origin,
+                    "recordedAt": entry.recorded_at,
+                    "session": entry.session,
+                    "currentSession": entry.session == self.session,
+                    "characters": entry.characters,
+                    "lines": entry.lines,
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string(&json!({
+            "path": path,
+            "total": versions.len(),
+            "versions": listed,
+        }))?)
+    }
+
+    /// The JSON answer to `diff_script_versions`. `current` is the script's source in Studio, used
+    /// when `to` is omitted or `current`.
+    pub(super) fn diff(
+        &self,
+        path: &[String],
+        from: &str,
+        to: Option<&str>,
+        current: Option<&str>,
+        context_lines: usize,
+    ) -> Result<String> {
+        let (from_label, before) = self.side(path, from, current)?;
+        let (to_label, after) = self.side(path, to.unwrap_or(CURRENT), current)?;
+        let name = path.join(".");
+        let diff = TextDiff::from_lines(before.as_str(), after.as_str())
+            .unified_diff()
+            .context_radius(context_lines)
+            .header(
+                &format!("{name}@{from_label}"),
+                &format!("{name}@{to_label}"),
+            )
+            .to_string();
+        Ok(serde_json::to_string(&json!({
+            "path": path,
+            "from": from_label,
+            "to": to_label,
+            "changed": before != after,
+            "diff": diff,
+        }))?)
+    }
+
+    fn side(
+        &self,
+        path: &[String],
+        reference: &str,
+        current: Option<&str>,
+    ) -> Result<(String, String)> {
+        if is_current(reference) {
+            let source = current.ok_or_else(|| eyre!("The current source is unavailable"))?;
+            return Ok((CURRENT.to_string(), source.to_string()));
+        }
+        let (entry, source) = self.load(path, reference)?;
+        Ok((short_id(&entry.hash).to_string(), source))
+    }
+
+    /// The source of a recorded version and its short id, for `restore_script_version`.
+    pub(super) fn source_of(&self, path: &[String], reference: &str) -> Result<(String, String)> {
+        let (entry, source) = self.load(path, reference)?;
+        Ok((short_id(&entry.hash).to_string(), source))
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2);
+        self.root.join(OBJECTS_DIR).join(prefix).join(rest)
+    }
+}
+
+/// Version reference for the script's live source.
+const CURRENT: &str = "current";
+
+pub(super) fn is_current(reference: &str) -> bool {
+    reference.trim().eq_ignore_ascii_case(CURRENT)
+}
+
+fn short_id(hash: &str) -> &str {
+    &hash[..SHORT_ID_LENGTH.min(hash.len())]
+}
+
+/// Fetches the current source of every script a batch is about to overwrite, delete, or convert,
+/// so the version it replaces is in the history even if it was never read. This costs one extra
+/// `get_source` round trip to Studio before such a batch; scripts written with `expectedSource`, or
+/// edited through a fetched source, are already covered and need none.
+pub(super) fn prior_sources_request(
+    request: &ManageScriptsRequest,
+) -> Option<ManageScriptsRequest> {
+    let mut operations: Vec<ScriptOperation> = Vec::new();
+    for operation in &request.operations {
+        let replaces = matches!(
+            operation,
+            ScriptOperation::SetSource {
+                expected_source: None,
+                ..
+            } | ScriptOperation::Delete { .. }
+                | ScriptOperation::ConvertType { .. }
+        );
+        if replaces
+            && !operations
+                .iter()
+                .any(|fetch| fetch.path() == operation.path())
+        {
+            operations.push(ScriptOperation::GetSource {
+                path: operation.path().to_vec(),
+                start_line: None,
+                end_line: None,
+                metadata: None,
+            });
+        }
+    }
+    (!operations.is_empty()).then_some(ManageScriptsRequest {
+        operations,
+        default_metadata: None,
+        atomic: false,
+        dry_run: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn main_path() -> Vec<String> {
+        vec!["ServerScriptService".to_string(), "Main".to_string()]
+    }
+
+    #[test]
+    fn records_each_new_version_once() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let history = ScriptHistory::new(dir.path().to_path_buf(), "a".to_string(), 10);
+        for (source, origin) in [
+            ("print(1)\n", Origin::Read),
+            ("print(1)\n", Origin::Read),
+            ("print(2)\n", Origin::Write),
+        ] {
+            history
+                .record(&main_path(), source, origin)
+                .expect("record");
+        }
+
+        let versions = history.versions(&main_path()).expect("versions");
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].origin, Origin::Write);
+    }
+
+    #[test]
+    fn lists_versions_from_earlier_sessions() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        ScriptHistory::new(dir.path().to_path_buf(), "a".to_string(), 10)
+            .record(&main_path(), "print(1)\n", Origin::Write)
+            .expect("record");
+
+        let later = ScriptHistory::new(dir.path().to_path_buf(), "b".to_string(), 10);
+        let listed: serde_json::Value =
+            serde_json::from_str(&later.list(&main_path(), 10).expect("list")).expect("json");
+        assert_eq!(listed["total"], 1);
+        assert_eq!(listed["versions"][0]["origin"], "write");
+        assert_eq!(listed["versions"][0]["currentSession"], false);
+    }
+
+    #[test]
+    fn diffs_a_version_against_the_current_source() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let history = ScriptHistory::new(dir.path().to_path_buf(), "a".to_string(), 10);
+        history
+            .record(&main_path(), "print(1)\n", Origin::Read)
+            .expect("record");
+        let version =
+            short_id(&history.versions(&main_path()).expect("versions")[0].hash).to_string();
+
+        let diff: serde_json::Value = serde_json::from_str(
+            &history
+                .diff(&main_path(), &version, None, Some("print(3)\n"), 3)
+                .expect("diff"),
+        )
+        .expect("json");
+        assert_eq!(diff["changed"], true);
+        assert!(diff["diff"]
+            .as_str()
+            .is_some_and(|diff| diff.contains("-print(1)\n+print(3)\n")));
+    }
+
+    #[test]
+    fn loads_versions_by_id_prefix() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let history = ScriptHistory::new(dir.path().to_path_buf(), "a".to_string(), 10);
+        history
+            .record(&main_path(), "print(1)\n", Origin::Read)
+            .expect("record");
+        let hash = history.versions(&main_path()).expect("versions")[0]
+            .hash
+            .clone();
+
+        let (_, source) = history.source_of(&main_path(), &hash[..6]).expect("source");
+        assert_eq!(source, "print(1)\n");
+        assert!(history.source_of(&main_path(), "zzzzzz").is_err());
+        assert!(history.source_of(&main_path(), &hash[..2]).is_err());
+    }
+
+    #[test]
+    fn prunes_versions_beyond_the_cap() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let history = ScriptHistory::new(dir.path().to_path_buf(), "a".to_string(), 2);
+        let other = vec!["ServerScriptService".to_string(), "Other".to_string()];
+        history
+            .record(&other, "print(1)\n", Origin::Read)
+            .expect("record");
+        for source in ["print(1)\n", "print(2)\n", "print(3)\n", "print(4)\n"] {
+            history
+                .record(&main_path(), source, Origin::Write)
+                .expect("record");
+        }
+
+        let versions = history.versions(&main_path()).expect("versions");
+        assert_eq!(versions.len(), 2);
+        assert!(history.source_of(&main_path(), &versions[1].hash).is_ok());
+        // print(1) is still referenced by Other; print(2) is not referenced anywhere.
+        assert!(history
+            .object_path(blake3::hash(b"print(1)\n").to_hex().as_str())
+            .exists());
+        assert!(!history
+            .object_path(blake3::hash(b"print(2)\n").to_hex().as_str())
+            .exists());
+        assert_eq!(history.versions(&other).expect("versions").len(), 1);
+    }
+}
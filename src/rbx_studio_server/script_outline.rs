@@ -0,0 +1,351 @@
+//! Navigation reads for `manage_scripts`: line ranges of a source and an outline of the symbols a
+//! module declares at its top level, computed from the Luau AST.
+
+use full_moon::ast::{
+    Block, Expression, Field, FunctionBody, Index, LastStmt, Parameter, Prefix, Stmt, Suffix, Var,
+};
+use full_moon::node::Node;
+use full_moon::tokenizer::TokenReference;
+use full_moon::LuaVersion;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum SymbolKind {
+    /// `function name()` or a function stored in a table field.
+    Function,
+    /// `function Table:name()`.
+    Method,
+    /// `local function name()` or `local name = function() end`.
+    LocalFunction,
+    /// Any other top-level local.
+    Local,
+    /// A non-function value stored in a table field.
+    Field,
+    /// `type Name = ...`.
+    Type,
+    /// `type function Name()`.
+    TypeFunction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Symbol {
+    pub(super) kind: SymbolKind,
+    pub(super) name: String,
+    pub(super) line: usize,
+    pub(super) end_line: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(super) parameters: Vec<String>,
+    /// Exported types, and fields of the table the module returns.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(super) exported: bool,
+}
+
+/// The lines `start..=end` (1-indexed) of `source`, with `end` clamped to the last line. Returns
+/// the text, the last line actually included, and the number of lines in the source.
+pub(super) fn line_range(
+    source: &str,
+    start: usize,
+    end: Option<usize>,
+) -> Result<(String, usize, usize), String> {
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let total = lines.len();
+    if start == 0 {
+        return Err("startLine is 1-indexed".to_string());
+    }
+    if start > total.max(1) {
+        return Err(format!(
+            "startLine {start} is past the end of the script ({total} lines)"
+        ));
+    }
+    let end = end.unwrap_or(total).min(total);
+    if end < start {
+        return Err(format!("endLine {end} is before startLine {start}"));
+    }
+    Ok((lines[start - 1..end].concat(), end, total))
+}
+
+/// Lists the functions, locals, table fields, and types declared at the top level of `source`.
+/// Sources with syntax errors are outlined as far as the parser could recover.
+pub(super) fn outline(source: &str) -> Vec<Symbol> {
+    let result = full_moon::parse_fallible(source, LuaVersion::luau());
+    let block = result.ast().nodes();
+    let module = returned_name(block);
+    let is_module = |root: &str| module.as_deref() == Some(root);
+
+    let mut symbols = Vec::new();
+    for stmt in block.stmts() {
+        match stmt {
+            Stmt::LocalFunction(function) => symbols.push(symbol(
+                SymbolKind::LocalFunction,
+                text(function.name()),
+                stmt,
+                parameters(function.body()),
+                false,
+            )),
+            Stmt::FunctionDeclaration(declaration) => {
+                let name = declaration.name();
+                let segments: Vec<String> = name.names().iter().map(text).collect();
+                let exported =
+                    (segments.len() > 1 || name.method_name().is_some()) && is_module(&segments[0]);
+                let (kind, name) = match name.method_name() {
+                    Some(method) => (
+                        SymbolKind::Method,
+                        format!("{}:{}", segments.join("."), text(method)),
+                    ),
+                    None => (SymbolKind::Function, segments.join(".")),
+                };
+                symbols.push(symbol(
+                    kind,
+                    name,
+                    stmt,
+                    parameters(declaration.body()),
+                    exported,
+                ));
+            }
+            Stmt::LocalAssignment(assignment) => {
+                let mut values = assignment.expressions().iter();
+                for name in assignment.names().iter() {
+                    let name = text(name);
+                    let value = values.next();
+                    symbols.push(match value.and_then(function_body) {
+                        Some(body) => symbol(
+                            SymbolKind::LocalFunction,
+                            name.clone(),
+                            stmt,
+                            parameters(body),
+                            false,
+                        ),
+                        None => symbol(SymbolKind::Local, name.clone(), stmt, Vec::new(), false),
+                    });
+                    if let Some(Expression::TableConstructor(table)) = value {
+                        for field in table.fields().iter() {
+                            if let Field::NameKey { key, value, .. } = field {
+                                symbols.push(value_symbol(
+                                    format!("{name}.{}", text(key)),
+                                    value,
+                                    field,
+                                    is_module(&name),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            Stmt::Assignment(assignment) => {
+                let pairs = assignment
+                    .variables()
+                    .iter()
+                    .zip(assignment.expressions().iter());
+                for (var, value) in pairs {
+                    if let Some(segments) = field_path(var) {
+                        symbols.push(value_symbol(
+                            segments.join("."),
+                            value,
+                            stmt,
+                            is_module(&segments[0]),
+                        ));
+                    }
+                }
+            }
+            Stmt::TypeDeclaration(declaration) => symbols.push(symbol(
+                SymbolKind::Type,
+                text(declaration.type_name()),
+                stmt,
+                Vec::new(),
+                false,
+            )),
+            Stmt::ExportedTypeDeclaration(declaration) => symbols.push(symbol(
+                SymbolKind::Type,
+                text(declaration.type_declaration().type_name()),
+                stmt,
+                Vec::new(),
+                true,
+            )),
+            Stmt::TypeFunction(function) => symbols.push(symbol(
+                SymbolKind::TypeFunction,
+                text(function.function_name()),
+                stmt,
+                parameters(function.function_body()),
+                false,
+            )),
+            Stmt::ExportedTypeFunction(function) => symbols.push(symbol(
+                SymbolKind::TypeFunction,
+                text(function.type_function().function_name()),
+                stmt,
+                parameters(function.type_function().function_body()),
+                true,
+            )),
+            _ => {}
+        }
+    }
+    symbols
+}
+
+fn symbol(
+    kind: SymbolKind,
+    name: String,
+    node: &impl Node,
+    parameters: Vec<String>,
+    exported: bool,
+) -> Symbol {
+    let (line, end_line) = node
+        .range()
+        .map(|(start, end)| (start.line(), end.line()))
+        .unwrap_or_default();
+    Symbol {
+        kind,
+        name,
+        line,
+        end_line,
+        parameters,
+        exported,
+    }
+}
+
+/// A table field holding `value`: a function when it is one, a field otherwise.
+fn value_symbol(name: String, value: &Expression, node: &impl Node, exported: bool) -> Symbol {
+    match function_body(value) {
+        Some(body) => symbol(SymbolKind::Function, name, node, parameters(body), exported),
+        None => symbol(SymbolKind::Field, name, node, Vec::new(), exported),
+    }
+}
+
+/// The name of the local the module returns, as in `return Module`.
+fn returned_name(block: &Block) -> Option<String> {
+    let Some(LastStmt::Return(statement)) = block.last_stmt() else {
+        return None;
+    };
+    match statement.returns().iter().next()? {
+        Expression::Var(Var::Name(name)) => Some(text(name)),
+        _ => None,
+    }
+}
+
+/// `["Table", "field", ...]` for assignments such as `Table.field.nested = value`.
+fn field_path(var: &Var) -> Option<Vec<String>> {
+    let Var::Expression(expression) = var else {
+        return None;
+    };
+    let Prefix::Name(root) = expression.prefix() else {
+        return None;
+    };
+    let mut segments = vec![text(root)];
+    for suffix in expression.suffixes() {
+        match suffix {
+            Suffix::Index(Index::Dot { name, .. }) => segments.push(text(name)),
+            _ => return None,
+        }
+    }
+    (segments.len() > 1).then_some(segments)
+}
+
+fn function_body(expression: &Expression) -> Option<&FunctionBody> {
+    match expression {
+        Expression::Function(function) => Some(function.body()),
+        _ => None,
+    }
+}
+
+fn parameters(body: &FunctionBody) -> Vec<String> {
+    body.parameters()
+        .iter()
+        .map(|parameter| match parameter {
+            Parameter::Name(name) => text(name),
+            _ => "...".to_string(),
+        })
+        .collect()
+}
+
+fn text(token: &TokenReference) -> String {
+    token.token().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_SOURCE: &str = "\
+--!strict
+export type Options = { speed: number }
+type Internal = string
+
+local Module = {
+\tversion = 2,
+\tcreate = function(options: Options) end,
+}
+
+local function helper(a, ...)
+\treturn a
+end
+
+function Module.start(self, options)
+\thelper(options)
+end
+
+function Module:stop()
+end
+
+Module.enabled = false
+local cache, count = {}, 0
+
+return Module
+";
+
+    #[test]
+    fn outlines_types_locals_functions_and_fields() {
+        let symbols = outline(SAMPLE_SOURCE);
+        let summary: Vec<(SymbolKind, &str, usize, bool)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.kind,
+                    symbol.name.as_str(),
+                    symbol.line,
+                    symbol.exported,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (SymbolKind::Type, "Options", 2, true),
+                (SymbolKind::Type, "Internal", 3, false),
+                (SymbolKind::Local, "Module", 5, false),
+                (SymbolKind::Field, "Module.version", 6, true),
+                (SymbolKind::Function, "Module.create", 7, true),
+                (SymbolKind::LocalFunction, "helper", 10, false),
+                (SymbolKind::Function, "Module.start", 14, true),
+                (SymbolKind::Method, "Module:stop", 18, true),
+                (SymbolKind::Field, "Module.enabled", 21, true),
+                (SymbolKind::Local, "cache", 22, false),
+                (SymbolKind::Local, "count", 22, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_function_ranges_and_parameters() {
+        let symbols = outline(SAMPLE_SOURCE);
+        let helper = symbols
+            .iter()
+            .find(|symbol| symbol.name == "helper")
+            .expect("helper");
+        assert_eq!(helper.end_line, 12);
+        assert_eq!(helper.parameters, vec!["a", "..."]);
+    }
+
+    #[test]
+    fn slices_line_ranges_clamped_to_the_source() {
+        assert_eq!(
+            line_range("a\nb\nc\n", 2, Some(9)),
+            Ok(("b\nc\n".to_string(), 3, 3))
+        );
+    }
+
+    #[test]
+    fn rejects_ranges_that_start_past_the_end() {
+        assert!(line_range("a\n", 3, None).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+//! Applies `apply_patch` and `replace_range` edits to script sources on the server, so agents can
+//! change large modules without resending them. Edits are validated against the current source and
+//! mismatches are reported as conflicts instead of being applied.
+
+use serde::Serialize;
+use std::fmt;
+
+/// A place where an edit's expectations do not match the current source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Conflict {
+    /// 1-indexed hunk number, for patches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) hunk: Option<usize>,
+    /// 1-indexed line in the current source where the mismatch starts.
+    pub(super) line: usize,
+    pub(super) expected: String,
+    /// Text found at that position, or `None` past the end of the source.
+    pub(super) found: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum EditError {
+    /// The edit itself is malformed.
+    Invalid(String),
+    /// The edit is well formed but does not match the current source.
+    Conflicts(Vec<Conflict>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Invalid(message) => f.write_str(message),
+            EditError::Conflicts(conflicts) => {
+                let conflict = &conflicts[0];
+                let location = match conflict.hunk {
+                    Some(hunk) => format!("Hunk {hunk} conflicts at line {}", conflict.line),
+                    None => format!("Range conflicts at line {}", conflict.line),
+                };
+                match &conflict.found {
+                    Some(found) => write!(
+                        f,
+                        "{location}: expected {:?} but found {found:?}",
+                        conflict.expected
+                    )?,
+                    None => write!(
+                        f,
+                        "{location}: expected {:?} past the end of the script",
+                        conflict.expected
+                    )?,
+                }
+                if conflicts.len() > 1 {
+                    write!(f, " ({} conflicts in total)", conflicts.len())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Splits `source` into lines that keep their line endings.
+fn lines_of(source: &str) -> Vec<&str> {
+    source.split_inclusive('\n').collect()
+}
+
+fn content(line: &str) -> &str {
+    line.trim_end_matches('\n').trim_end_matches('\r')
+}
+
+fn newline_of(source: &str) -> &'static str {
+    if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+#[derive(Debug)]
+enum HunkLine<'a> {
+    Context(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+    /// `\ No newline at end of file` after the previous line.
+    NoNewline,
+}
+
+#[derive(Debug)]
+struct Hunk<'a> {
+    old_start: usize,
+    lines: Vec<HunkLine<'a>>,
+}
+
+impl Hunk<'_> {
+    fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Remove(text) => Some(*text),
+            _ => None,
+        })
+    }
+
+    fn old_len(&self) -> usize {
+        self.old_lines().count()
+    }
+
+    /// Returns true when the hunk's context and removed lines match `lines` at `start`.
+    fn matches_at(&self, lines: &[&str], start: usize) -> bool {
+        start + self.old_len() <= lines.len()
+            && self
+                .old_lines()
+                .zip(&lines[start..])
+                .all(|(expected, actual)| expected == content(actual))
+    }
+
+    /// Describes the first mismatch when the hunk is applied at `start`.
+    fn first_mismatch(&self, hunk: usize, lines: &[&str], start: usize) -> Conflict {
+        for (index, expected) in self.old_lines().enumerate() {
+            let found = lines.get(start + index).map(|line| content(line));
+            if found != Some(expected) {
+                return Conflict {
+                    hunk: Some(hunk),
+                    line: start + index + 1,
+                    expected: expected.to_string(),
+                    found: found.map(str::to_string),
+                };
+            }
+        }
+        Conflict {
+            hunk: Some(hunk),
+            line: start + 1,
+            expected: String::new(),
+            found: None,
+        }
+    }
+}
+
+/// Parses `@@ -a,b +c,d @@` and returns `a`.
+fn parse_hunk_header(header: &str) -> Option<usize> {
+    let old = header
+        .strip_prefix("@@ -")?
+        .split_whitespace()
+        .next()?
+        .split(',')
+        .next()?;
+    old.parse().ok()
+}
+
+fn parse_patch(patch: &str) -> Result<Vec<Hunk<'_>>, EditError> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in patch.lines() {
+        if line.starts_with("@@") {
+            let old_start = parse_hunk_header(line)
+                .ok_or_else(|| EditError::Invalid(format!("Invalid hunk header {line:?}")))?;
+            hunks.push(Hunk {
+                old_start,
+                lines: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // File headers (`diff`, `index`, `---`, `+++`) before the first hunk.
+            continue;
+        };
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let parsed = match line.chars().next() {
+            Some(' ') => HunkLine::Context(&line[1..]),
+            Some('-') => HunkLine::Remove(&line[1..]),
+            Some('+') => HunkLine::Add(&line[1..]),
+            Some('\\') => HunkLine::NoNewline,
+            // Some tools strip the leading space from empty context lines.
+            None => HunkLine::Context(""),
+            Some(_) => {
+                return Err(EditError::Invalid(format!(
+                    "Unexpected line in hunk {}: {line:?}",
+                    hunks.len()
+                )))
+            }
+        };
+        hunk.lines.push(parsed);
+    }
+    if hunks.is_empty() {
+        return Err(EditError::Invalid(
+            "The patch contains no hunks; expected a unified diff with @@ headers".to_string(),
+        ));
+    }
+    Ok(hunks)
+}
+
+/// Finds where `hunk` applies, preferring its declared position and then the nearest offset.
+/// Hunks never apply before `floor`, the end of the previous hunk.
+fn locate(hunk: &Hunk, lines: &[&str], floor: usize) -> Option<usize> {
+    let declared = if hunk.old_start == 0 {
+        0
+    } else {
+        hunk.old_start - 1
+    }
+    .max(floor);
+    let last = lines.len().saturating_sub(hunk.old_len());
+    (0..=lines.len()).find_map(|distance| {
+        let after = declared + distance;
+        if after <= last && hunk.matches_at(lines, after) {
+            return Some(after);
+        }
+        let before = declared.checked_sub(distance)?;
+        (before >= floor && distance > 0 && hunk.matches_at(lines, before)).then_some(before)
+    })
+}
+
+/// Applies a unified diff to `source`. Line counts in hunk headers are not checked; context and
+/// removed lines must match the source exactly, though hunks may have moved.
+pub(super) fn apply_patch(source: &str, patch: &str) -> Result<String, EditError> {
+    let hunks = parse_patch(patch)?;
+    let lines = lines_of(source);
+    let newline = newline_of(source);
+
+    let mut output = String::with_capacity(source.len());
+    let mut conflicts = Vec::new();
+    let mut cursor = 0;
+    for (index, hunk) in hunks.iter().enumerate() {
+        let Some(start) = locate(hunk, &lines, cursor) else {
+            let declared = hunk.old_start.saturating_sub(1).max(cursor);
+            conflicts.push(hunk.first_mismatch(index + 1, &lines, declared));
+            continue;
+        };
+        output.extend(lines[cursor..start].iter().copied());
+        cursor = start;
+        let mut previous_was_add = false;
+        for line in &hunk.lines {
+            match line {
+                HunkLine::Context(_) => {
+                    output.push_str(lines[cursor]);
+                    cursor += 1;
+                }
+                HunkLine::Remove(_) => cursor += 1,
+                HunkLine::Add(text) => {
+                    output.push_str(text);
+                    output.push_str(newline);
+                }
+                HunkLine::NoNewline => {
+                    if previous_was_add {
+                        output.truncate(output.len() - newline.len());
+                    }
+                }
+            }
+            previous_was_add = matches!(line, HunkLine::Add(_));
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(EditError::Conflicts(conflicts));
+    }
+    output.extend(lines[cursor..].iter().copied());
+    Ok(output)
+}
+
+/// Byte offset of the 1-indexed `column` in `line`, where `column` may be one past the last
+/// character.
+fn column_offset(line: &str, column: usize) -> Option<usize> {
+    let text = content(line);
+    if column == 0 {
+        return None;
+    }
+    text.char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .nth(column - 1)
+}
+
+/// A range of text in a script. Lines and columns are 1-indexed; the end column is exclusive and
+/// defaults to the end of `end_line`.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TextRange {
+    pub(super) start_line: usize,
+    pub(super) start_column: usize,
+    pub(super) end_line: usize,
+    pub(super) end_column: Option<usize>,
+}
+
+/// Replaces `range` in `source` with `text`. When `expected` is given, the current text in the
+/// range must equal it.
+pub(super) fn replace_range(
+    source: &str,
+    range: TextRange,
+    text: &str,
+    expected: Option<&str>,
+) -> Result<String, EditError> {
+    let lines = lines_of(source);
+    let line_count = lines.len().max(1);
+    let line = |number: usize| -> Result<&str, EditError> {
+        if number == 0 || number > line_count {
+            return Err(EditError::Invalid(format!(
+                "Line {number} is outside the script, which has {line_count} lines"
+            )));
+        }
+        Ok(lines.get(number - 1).copied().unwrap_or(""))
+    };
+    if range.end_line < range.start_line {
+        return Err(EditError::Invalid(
+            "endLine must not be before startLine".to_string(),
+        ));
+    }
+    let line_start = |number: usize| -> usize { lines[..number - 1].iter().map(|l| l.len()).sum() };
+
+    let start_text = line(range.start_line)?;
+    let start = line_start(range.start_line)
+        + column_offset(start_text, range.start_column).ok_or_else(|| {
+            EditError::Invalid(format!(
+                "startColumn {} is outside line {}",
+                range.start_column, range.start_line
+            ))
+        })?;
+    let end_text = line(range.end_line)?;
+    let end_column = range
+        .end_column
+        .unwrap_or(content(end_text).chars().count() + 1);
+    let end = line_start(range.end_line)
+        + column_offset(end_text, end_column).ok_or_else(|| {
+            EditError::Invalid(format!(
+                "endColumn {end_column} is outside line {}",
+                range.end_line
+            ))
+        })?;
+    if end < start {
+        return Err(EditError::Invalid(
+            "The range ends before it starts".to_string(),
+        ));
+    }
+
+    let current = &source[start..end];
+    if let Some(expected) = expected {
+        if current != expected {
+            return Err(EditError::Conflicts(vec![Conflict {
+                hunk: None,
+                line: range.start_line,
+                expected: expected.to_string(),
+                found: Some(current.to_string()),
+            }]));
+        }
+    }
+    Ok(format!("{}{text}{}", &source[..start], &source[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "local a = 1\nlocal b = 2\nlocal c = 3\nreturn a + b + c\n";
+
+    #[test]
+    fn applies_moved_hunks_and_reports_conflicts() {
+        // Declared one line too early; the hunk still applies at its real position.
+        let patch = "--- a/Module.luau\n+++ b/Module.luau\n@@ -1,2 +1,2 @@\n local b = 2\n-local c = 3\n+local c = 30\n";
+        assert_eq!(
+            apply_patch(SOURCE, patch).expect("patch"),
+            "local a = 1\nlocal b = 2\nlocal c = 30\nreturn a + b + c\n"
+        );
+
+        let stale = "@@ -3,2 +3,2 @@\n local c = 3\n-return a + b\n+return a * b\n";
+        let error = apply_patch(SOURCE, stale).expect_err("conflict");
+        assert_eq!(
+            error,
+            EditError::Conflicts(vec![Conflict {
+                hunk: Some(1),
+                line: 4,
+                expected: "return a + b".to_string(),
+                found: Some("return a + b + c".to_string()),
+            }])
+        );
+        assert_eq!(
+            error.to_string(),
+            "Hunk 1 conflicts at line 4: expected \"return a + b\" but found \"return a + b + c\""
+        );
+    }
+
+    #[test]
+    fn replaces_line_and_column_ranges() {
+        let range = TextRange {
+            start_line: 2,
+            start_column: 11,
+            end_line: 2,
+            end_column: Some(12),
+        };
+        assert_eq!(
+            replace_range(SOURCE, range, "20", Some("2")).expect("replace"),
+            "local a = 1\nlocal b = 20\nlocal c = 3\nreturn a + b + c\n"
+        );
+        assert!(matches!(
+            replace_range(SOURCE, range, "20", Some("3")),
+            Err(EditError::Conflicts(_))
+        ));
+
+        let whole_lines = TextRange {
+            start_line: 1,
+            start_column: 1,
+            end_line: 3,
+            end_column: None,
+        };
+        assert_eq!(
+            replace_range(SOURCE, whole_lines, "local a, b, c = 1, 2, 3", None).expect("replace"),
+            "local a, b, c = 1, 2, 3\nreturn a + b + c\n"
+        );
+        assert!(matches!(
+            replace_range(
+                SOURCE,
+                TextRange {
+                    start_line: 9,
+                    ..whole_lines
+                },
+                "",
+                None
+            ),
+            Err(EditError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,559 @@
+//! `search_scripts` and `replace_in_scripts`: literal and regex search and replace across script
+//! sources. Sources are pulled in bulk with a data model snapshot and matched in Rust, so the full
+//! `regex` syntax is available rather than Lua patterns.
+
+use super::script_analysis::syntax_diagnostics;
+use super::{
+    DataModelSnapshotInstance, DataModelSnapshotPropertyPick, DataModelSnapshotRequest,
+    InstanceTarget, ManageScriptsRequest, ManageScriptsResponse, ReplaceInScriptsRequest,
+    RobloxValue, ScriptDiagnostic, ScriptOperation, ScriptQuery, SearchScriptsRequest,
+};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::Serialize;
+use similar::TextDiff;
+
+/// Script classes searched when the request does not name any.
+pub(super) const SCRIPT_CLASSES: &[&str] = &["Script", "LocalScript", "ModuleScript"];
+
+/// Matches returned when the request does not set `maxResults`.
+const DEFAULT_MAX_RESULTS: usize = 200;
+
+/// Scripts fetched per snapshot page.
+const SEARCH_PAGE_SIZE: u32 = 200;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchMatch {
+    path: Vec<String>,
+    class_name: String,
+    /// 1-indexed line of the start of the match.
+    line: usize,
+    /// 1-indexed character column of the start of the match.
+    column: usize,
+    /// Text of the matched line, without its line ending.
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    after: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResponse {
+    matches: Vec<SearchMatch>,
+    scripts_searched: usize,
+    scripts_matched: usize,
+    /// True when `maxResults` cut the match list short.
+    truncated: bool,
+}
+
+/// Snapshot request that returns the source of every script `request` may match.
+pub(super) fn snapshot_request(request: &ScriptQuery) -> DataModelSnapshotRequest {
+    if request.class_names.is_empty() {
+        script_sources_request(&request.roots, SCRIPT_CLASSES)
+    } else {
+        script_sources_request(&request.roots, &request.class_names)
+    }
+}
+
+/// Snapshot request that returns the `Source` of every instance of `classes` under `roots`.
+pub(super) fn script_sources_request(
+    roots: &[InstanceTarget],
+    classes: &[impl AsRef<str>],
+) -> DataModelSnapshotRequest {
+    let classes: Vec<String> = classes
+        .iter()
+        .map(|class| class.as_ref().to_string())
+        .collect();
+    DataModelSnapshotRequest {
+        root_paths: roots.to_vec(),
+        class_allow_list: classes.clone(),
+        include_properties: Some(true),
+        property_picks: vec![DataModelSnapshotPropertyPick {
+            classes,
+            properties: vec!["Source".to_string()],
+            ..Default::default()
+        }],
+        sort_children_by_name: Some(true),
+        page_size: Some(SEARCH_PAGE_SIZE),
+        ..Default::default()
+    }
+}
+
+/// As [`script_sources_request`], without the sources: lists the instances of `classes` under
+/// `roots`, e.g. to resolve selectors to script paths.
+pub(super) fn script_paths_request(
+    roots: &[InstanceTarget],
+    classes: &[impl AsRef<str>],
+) -> DataModelSnapshotRequest {
+    DataModelSnapshotRequest {
+        include_properties: Some(false),
+        property_picks: Vec::new(),
+        ..script_sources_request(roots, classes)
+    }
+}
+
+/// The compiled query and the scripts of `entries` it applies to, with their sources.
+fn matching_scripts<'a>(
+    request: &ScriptQuery,
+    entries: &'a [DataModelSnapshotInstance],
+) -> Result<(Regex, Vec<(&'a DataModelSnapshotInstance, &'a str)>)> {
+    let pattern = if request.regex {
+        request.query.clone()
+    } else {
+        regex::escape(&request.query)
+    };
+    let query = RegexBuilder::new(&pattern)
+        .case_insensitive(!request.case_sensitive.unwrap_or(true))
+        .multi_line(true)
+        .build()
+        .wrap_err("Invalid search pattern")?;
+    let path_filter = request
+        .path_filter
+        .as_deref()
+        .map(|filter| {
+            RegexBuilder::new(filter)
+                .case_insensitive(true)
+                .build()
+                .wrap_err("Invalid path filter")
+        })
+        .transpose()?;
+    let scripts = entries
+        .iter()
+        .filter_map(|entry| match entry.properties.get("Source") {
+            Some(RobloxValue::String(source)) => Some((entry, source.as_str())),
+            _ => None,
+        })
+        .filter(|(entry, _)| {
+            path_filter
+                .as_ref()
+                .is_none_or(|filter| filter.is_match(&entry.path.join(".")))
+        })
+        .collect();
+    Ok((query, scripts))
+}
+
+/// Searches the scripts in `entries`, which come from [`snapshot_request`].
+pub(super) fn search(
+    request: &SearchScriptsRequest,
+    entries: &[DataModelSnapshotInstance],
+) -> Result<String> {
+    let (query, scripts) = matching_scripts(&request.query, entries)?;
+    let context = request.context_lines.unwrap_or(0) as usize;
+    let max_results = request
+        .max_results
+        .map_or(DEFAULT_MAX_RESULTS, |limit| limit as usize);
+
+    let mut response = SearchResponse {
+        matches: Vec::new(),
+        scripts_searched: 0,
+        scripts_matched: 0,
+        truncated: false,
+    };
+    for (entry, source) in scripts {
+        response.scripts_searched += 1;
+
+        let lines: Vec<&str> = source.lines().collect();
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        let mut matched = false;
+        for found in query.find_iter(source) {
+            if found.is_empty() {
+                continue;
+            }
+            if response.matches.len() >= max_results {
+                response.truncated = true;
+                break;
+            }
+            matched = true;
+            let line = line_starts.partition_point(|start| *start <= found.start()) - 1;
+            let column = source[line_starts[line]..found.start()].chars().count() + 1;
+            let text = |index: usize| lines.get(index).copied().unwrap_or_default().to_string();
+            response.matches.push(SearchMatch {
+                path: entry.path.clone(),
+                class_name: entry.class_name.clone(),
+                line: line + 1,
+                column,
+                text: text(line),
+                before: (line.saturating_sub(context)..line).map(text).collect(),
+                after: (line + 1..(line + 1 + context).min(lines.len()))
+                    .map(text)
+                    .collect(),
+            });
+        }
+        response.scripts_matched += usize::from(matched);
+        if response.truncated {
+            break;
+        }
+    }
+    Ok(serde_json::to_string(&response)?)
+}
+
+/// One script rewritten by `replace_in_scripts`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptChange {
+    path: Vec<String>,
+    class_name: String,
+    replacements: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    success: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<ScriptDiagnostic>,
+    #[serde(skip)]
+    before: String,
+    #[serde(skip)]
+    after: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplaceResponse {
+    dry_run: bool,
+    scripts: Vec<ScriptChange>,
+    summary: String,
+}
+
+/// The rewrites `replace_in_scripts` makes, computed before anything is written.
+#[derive(Debug)]
+pub(super) struct ReplacePlan {
+    changes: Vec<ScriptChange>,
+    force: bool,
+}
+
+impl ReplacePlan {
+    /// Applies the replacement to every matching script in `entries`, which come from
+    /// [`snapshot_request`].
+    pub(super) fn new(
+        request: &ReplaceInScriptsRequest,
+        entries: &[DataModelSnapshotInstance],
+    ) -> Result<Self> {
+        let (query, scripts) = matching_scripts(&request.query, entries)?;
+        let context = request
+            .context_lines
+            .map_or(super::DEFAULT_DIFF_CONTEXT_LINES, |lines| lines as usize);
+        let mut changes = Vec::new();
+        for (entry, source) in scripts {
+            let replacements = query
+                .find_iter(source)
+                .filter(|found| !found.is_empty())
+                .count();
+            if replacements == 0 {
+                continue;
+            }
+            let after = if request.query.regex {
+                query.replace_all(source, request.replacement.as_str())
+            } else {
+                query.replace_all(source, NoExpand(&request.replacement))
+            };
+            if after == source {
+                continue;
+            }
+            let name = entry.path.join(".");
+            changes.push(ScriptChange {
+                path: entry.path.clone(),
+                class_name: entry.class_name.clone(),
+                replacements,
+                diff: Some(
+                    TextDiff::from_lines(source, after.as_ref())
+                        .unified_diff()
+                        .context_radius(context)
+                        .header(&format!("a/{name}"), &format!("b/{name}"))
+                        .to_string(),
+                ),
+                success: None,
+                message: None,
+                diagnostics: Vec::new(),
+                before: source.to_string(),
+                after: after.into_owned(),
+            });
+        }
+        Ok(ReplacePlan {
+            changes,
+            force: request.force,
+        })
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn replacements(&self) -> usize {
+        self.changes.iter().map(|change| change.replacements).sum()
+    }
+
+    /// The preview returned for dry runs and plans with nothing to write: every change as a
+    /// unified diff.
+    pub(super) fn preview(self, dry_run: bool) -> Result<String> {
+        let summary = if self.changes.is_empty() {
+            "No scripts matched the query".to_string()
+        } else {
+            format!(
+                "Would replace {} occurrence(s) in {} script(s)",
+                self.replacements(),
+                self.changes.len()
+            )
+        };
+        Ok(serde_json::to_string(&ReplaceResponse {
+            dry_run,
+            scripts: self.changes,
+            summary,
+        })?)
+    }
+
+    /// The response explaining why nothing was written, when a rewritten script no longer parses
+    /// and the request did not set `force`.
+    pub(super) fn refusal(&mut self) -> Option<Result<String>> {
+        if self.force {
+            return None;
+        }
+        let mut broken = 0;
+        for change in &mut self.changes {
+            change.diagnostics = syntax_diagnostics(&change.after);
+            if !change.diagnostics.is_empty() {
+                broken += 1;
+                change.success = Some(false);
+                change.message = Some("The rewritten source does not parse".to_string());
+            }
+        }
+        if broken == 0 {
+            return None;
+        }
+        let changes = std::mem::take(&mut self.changes);
+        let summary = format!(
+            "Nothing was written: {broken} rewritten script(s) no longer parse; set force to write them anyway"
+        );
+        Some(
+            serde_json::to_string(&ReplaceResponse {
+                dry_run: false,
+                scripts: changes,
+                summary,
+            })
+            .map_err(Into::into),
+        )
+    }
+
+    /// One atomic `manage_scripts` batch writing every change, so Studio records it as a single
+    /// undo step and a failed write leaves every script untouched. Each write only applies if the
+    /// script still has the source the plan was made from.
+    pub(super) fn write_request(&self) -> ManageScriptsRequest {
+        ManageScriptsRequest {
+            operations: self
+                .changes
+                .iter()
+                .map(|change| ScriptOperation::SetSource {
+                    path: change.path.clone(),
+                    source: change.after.clone(),
+                    expected_source: Some(change.before.clone()),
+                    force: self.force,
+                    format: false,
+                    metadata: None,
+                })
+                .collect(),
+            default_metadata: None,
+            atomic: true,
+            dry_run: false,
+        }
+    }
+
+    /// Reports the outcome of [`write_request`](Self::write_request) for each script.
+    pub(super) fn report(mut self, response: &str) -> Result<String> {
+        let response: ManageScriptsResponse =
+            serde_json::from_str(response).map_err(|_| eyre!("{response}"))?;
+        if response.results.len() != self.changes.len() {
+            return Err(eyre!(
+                "Studio returned {} results for {} scripts",
+                response.results.len(),
+                self.changes.len()
+            ));
+        }
+        let replacements = self.replacements();
+        let mut written = 0;
+        for (change, result) in self.changes.iter_mut().zip(response.results) {
+            written += usize::from(result.success);
+            change.diff = None;
+            change.success = Some(result.success);
+            change.message = result.message;
+            change.diagnostics = result.diagnostics;
+        }
+        let summary = match response.rollback_reason.filter(|_| response.rolled_back) {
+            Some(reason) => format!("Nothing was written: the batch was rolled back because {reason}"),
+            None => format!(
+                "Replaced {replacements} occurrence(s) in {} script(s) ({written} written, {} failed)",
+                self.changes.len(),
+                self.changes.len() - written
+            ),
+        };
+        Ok(serde_json::to_string(&ReplaceResponse {
+            dry_run: false,
+            scripts: self.changes,
+            summary,
+        })?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value as JsonValue};
+
+    fn script(path: &[&str], source: &str) -> DataModelSnapshotInstance {
+        DataModelSnapshotInstance {
+            path: path.iter().map(|segment| segment.to_string()).collect(),
+            class_name: "ModuleScript".to_string(),
+            properties: [(
+                "Source".to_string(),
+                RobloxValue::String(source.to_string()),
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    /// A module with `(x)` in a comment, and a server script that prints `(x)`.
+    fn sample_scripts() -> Vec<DataModelSnapshotInstance> {
+        vec![
+            script(
+                &["ReplicatedStorage", "Util"],
+                "local x = 1\nlocal y = x + 1 -- (x)\nreturn y\n",
+            ),
+            script(&["ServerScriptService", "Main"], "print(\"(x)\")\n"),
+        ]
+    }
+
+    fn search_json(params: JsonValue) -> JsonValue {
+        let request: SearchScriptsRequest = serde_json::from_value(params).expect("request");
+        serde_json::from_str(&search(&request, &sample_scripts()).expect("search")).expect("json")
+    }
+
+    #[test]
+    fn finds_literal_matches_with_context() {
+        let literal = search_json(json!({ "query": "(x)", "contextLines": 1 }));
+        assert_eq!(literal["scriptsMatched"], json!(2));
+        assert_eq!(literal["matches"][0]["line"], json!(2));
+        assert_eq!(literal["matches"][0]["column"], json!(20));
+        assert_eq!(literal["matches"][0]["before"], json!(["local x = 1"]));
+        assert_eq!(literal["matches"][0]["after"], json!(["return y"]));
+    }
+
+    #[test]
+    fn finds_case_insensitive_regex_matches_under_a_path_filter() {
+        let regex = search_json(json!({
+            "query": "^LOCAL (\\w+)",
+            "regex": true,
+            "caseSensitive": false,
+            "pathFilter": "^ReplicatedStorage\\.",
+        }));
+        assert_eq!(regex["scriptsSearched"], json!(1));
+        assert_eq!(regex["matches"].as_array().map(Vec::len), Some(2));
+        assert_eq!(regex["matches"][1]["text"], json!("local y = x + 1 -- (x)"));
+    }
+
+    #[test]
+    fn rejects_invalid_regexes() {
+        let request: SearchScriptsRequest =
+            serde_json::from_value(json!({ "query": "(", "regex": true })).expect("request");
+        assert!(search(&request, &sample_scripts()).is_err());
+    }
+
+    /// A module that requires `x` into a local named `old`, and a script that does not mention it.
+    fn sample_replace_scripts() -> Vec<DataModelSnapshotInstance> {
+        vec![
+            script(
+                &["ReplicatedStorage", "A"],
+                "local old = require(x)\nreturn old\n",
+            ),
+            script(&["ReplicatedStorage", "B"], "print(\"unrelated\")\n"),
+        ]
+    }
+
+    fn rename_old_plan() -> ReplacePlan {
+        let request: ReplaceInScriptsRequest = serde_json::from_value(json!({
+            "query": "\\bold\\b",
+            "regex": true,
+            "replacement": "${0}Value",
+        }))
+        .expect("request");
+        ReplacePlan::new(&request, &sample_replace_scripts()).expect("plan")
+    }
+
+    #[test]
+    fn plans_regex_replacements_guarded_by_the_current_source() {
+        let mut plan = rename_old_plan();
+        assert!(plan.refusal().is_none());
+        let write = serde_json::to_value(plan.write_request()).expect("json");
+        assert_eq!(write["operations"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            write["operations"][0]["source"],
+            json!("local oldValue = require(x)\nreturn oldValue\n")
+        );
+        assert_eq!(
+            write["operations"][0]["expectedSource"],
+            json!("local old = require(x)\nreturn old\n")
+        );
+    }
+
+    #[test]
+    fn previews_replacements_as_diffs() {
+        let plan = rename_old_plan();
+        let preview: JsonValue =
+            serde_json::from_str(&plan.preview(true).expect("preview")).expect("json");
+        assert_eq!(preview["scripts"][0]["replacements"], json!(2));
+        assert!(preview["scripts"][0]["diff"]
+            .as_str()
+            .is_some_and(|diff| diff.contains("+return oldValue")));
+    }
+
+    #[test]
+    fn refuses_rewrites_that_break_scripts() {
+        let broken: ReplaceInScriptsRequest = serde_json::from_value(json!({
+            "query": "return",
+            "replacement": "retrun",
+        }))
+        .expect("request");
+        let mut plan = ReplacePlan::new(&broken, &sample_replace_scripts()).expect("plan");
+        let refusal: JsonValue =
+            serde_json::from_str(&plan.refusal().expect("refused").expect("json")).expect("json");
+        assert_eq!(refusal["scripts"][0]["success"], json!(false));
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn reports_rolled_back_replacements() {
+        let entries = vec![
+            script(&["ReplicatedStorage", "A"], "return old\n"),
+            script(&["ReplicatedStorage", "B"], "return old\n"),
+        ];
+        let request: ReplaceInScriptsRequest = serde_json::from_value(json!({
+            "query": "old",
+            "replacement": "new",
+        }))
+        .expect("request");
+        let plan = ReplacePlan::new(&request, &entries).expect("plan");
+        assert!(plan.write_request().atomic);
+
+        let studio = json!({
+            "results": [
+                { "action": "set_source", "path": ["ReplicatedStorage", "A"], "success": false, "message": "Rolled back: Updated" },
+                { "action": "set_source", "path": ["ReplicatedStorage", "B"], "success": false, "message": "Source changed" },
+            ],
+            "rolledBack": true,
+            "rollbackReason": "operation 2 failed: Source changed",
+        });
+        let report: JsonValue =
+            serde_json::from_str(&plan.report(&studio.to_string()).expect("report")).expect("json");
+        assert_eq!(
+            report["summary"],
+            json!("Nothing was written: the batch was rolled back because operation 2 failed: Source changed")
+        );
+        assert_eq!(report["scripts"][0]["success"], json!(false));
+        assert_eq!(report["scripts"][1]["message"], json!("Source changed"));
+    }
+}
@@ -0,0 +1,412 @@
+//! `analyze_scripts`: type-checks scripts with a locally installed `luau-lsp` or `luau-analyze`.
+//! The scripts are written to a temporary Rojo-style tree with a generated `sourcemap.json`, so
+//! `script.Parent` and `require` resolve as they do in Studio, and the analyzer's output is mapped
+//! back to instance paths.
+
+use super::{DataModelSnapshotInstance, RobloxValue, ScriptDiagnostic};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::LazyLock;
+
+/// Name of the generated sourcemap inside the temporary tree.
+const SOURCEMAP_FILE: &str = "sourcemap.json";
+
+/// `file(line,col): Kind: message`, optionally with a column or line range, as printed by both
+/// `luau-analyze` and `luau-lsp analyze`.
+static DIAGNOSTIC_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<file>.+?)\((?P<line>\d+),(?P<column>\d+)(?:-\d+(?:,\d+)?)?\): (?P<kind>\w+): (?P<message>.*)$")
+        .expect("valid diagnostic pattern")
+});
+
+/// The analyzer configured with `--luau-analyzer`.
+#[derive(Debug, Clone)]
+pub(super) struct LuauAnalyzer {
+    pub(super) executable: PathBuf,
+    /// Type definitions for the Roblox API passed to `luau-lsp` with `--definitions`.
+    pub(super) definitions: Option<PathBuf>,
+}
+
+impl LuauAnalyzer {
+    /// `luau-lsp` takes an `analyze` subcommand and a sourcemap; `luau-analyze` takes neither.
+    fn is_luau_lsp(&self) -> bool {
+        self.executable
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().contains("luau-lsp"))
+    }
+}
+
+/// A script written to the temporary tree.
+#[derive(Debug)]
+struct ExportedScript<'a> {
+    entry: &'a DataModelSnapshotInstance,
+    file: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcemapNode {
+    name: String,
+    class_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    file_paths: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<SourcemapNode>,
+}
+
+impl SourcemapNode {
+    fn new(name: &str, class_name: &str) -> Self {
+        SourcemapNode {
+            name: name.to_string(),
+            class_name: class_name.to_string(),
+            file_paths: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds `script` at `path` below this node, creating folders (or services, at the top level)
+    /// for ancestors the snapshot did not include. Scripts sharing a path get separate nodes.
+    fn insert(&mut self, path: &[String], script: &ExportedScript) {
+        let Some((name, rest)) = path.split_first() else {
+            return;
+        };
+        let existing = self.children.iter().position(|child| {
+            child.name == *name && (!rest.is_empty() || child.file_paths.is_empty())
+        });
+        let position = existing.unwrap_or_else(|| {
+            let class_name = if self.class_name == "DataModel" {
+                name.as_str()
+            } else {
+                "Folder"
+            };
+            self.children.push(SourcemapNode::new(name, class_name));
+            self.children.len() - 1
+        });
+        let child = &mut self.children[position];
+        if rest.is_empty() {
+            child.class_name = script.entry.class_name.clone();
+            child.file_paths.push(script.file.clone());
+        } else {
+            child.insert(rest, script);
+        }
+    }
+}
+
+/// Turns an instance name into a single safe path segment.
+pub(super) fn sanitise(segment: &str) -> String {
+    let cleaned: String = segment
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match cleaned.trim() {
+        "" | "." | ".." => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Relative file path for a script, following Rojo's `.server`/`.client` naming.
+fn file_for(entry: &DataModelSnapshotInstance, taken: &mut HashSet<String>) -> String {
+    let (name, parents) = entry
+        .path
+        .split_last()
+        .map_or(("script", &[][..]), |(name, parents)| {
+            (name.as_str(), parents)
+        });
+    let directory: Vec<String> = parents.iter().map(|segment| sanitise(segment)).collect();
+    let suffix = match entry.class_name.as_str() {
+        "Script" => ".server",
+        "LocalScript" => ".client",
+        _ => "",
+    };
+    let stem = sanitise(name);
+    let mut attempt = 1;
+    loop {
+        let file_name = match attempt {
+            1 => format!("{stem}{suffix}.luau"),
+            n => format!("{stem}~{n}{suffix}.luau"),
+        };
+        let file = directory
+            .iter()
+            .cloned()
+            .chain([file_name])
+            .collect::<Vec<_>>()
+            .join("/");
+        if taken.insert(file.clone()) {
+            return file;
+        }
+        attempt += 1;
+    }
+}
+
+/// Writes the scripts in `entries` and their sourcemap under `dir`.
+fn export_tree<'a>(
+    dir: &Path,
+    entries: &'a [DataModelSnapshotInstance],
+) -> Result<Vec<ExportedScript<'a>>> {
+    let mut taken = HashSet::new();
+    let mut root = SourcemapNode::new("Game", "DataModel");
+    let mut exported = Vec::new();
+    for entry in entries {
+        let Some(RobloxValue::String(source)) = entry.properties.get("Source") else {
+            continue;
+        };
+        let script = ExportedScript {
+            entry,
+            file: file_for(entry, &mut taken),
+        };
+        let target = dir.join(&script.file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, source)
+            .wrap_err_with(|| format!("Unable to write {}", target.display()))?;
+        root.insert(&entry.path, &script);
+        exported.push(script);
+    }
+    fs::write(dir.join(SOURCEMAP_FILE), serde_json::to_string(&root)?)?;
+    Ok(exported)
+}
+
+/// Parses analyzer output into diagnostics keyed by the file they were reported against.
+/// Indented lines and `caused by:` lines continue the previous message; anything else, such as
+/// progress output, is ignored.
+fn parse_output(output: &str) -> Vec<(String, ScriptDiagnostic)> {
+    let mut diagnostics: Vec<(String, ScriptDiagnostic)> = Vec::new();
+    for line in output.lines() {
+        let Some(captures) = DIAGNOSTIC_LINE.captures(line) else {
+            if let Some((_, last)) = diagnostics.last_mut() {
+                let continues =
+                    line.starts_with(char::is_whitespace) || line.starts_with("caused by");
+                if continues && !line.trim().is_empty() {
+                    last.message.push('\n');
+                    last.message.push_str(line.trim_end());
+                }
+            }
+            continue;
+        };
+        let kind = &captures["kind"];
+        let (category, severity) = match kind {
+            "SyntaxError" => ("syntax", "error"),
+            kind if kind.starts_with("Lint") => ("lint", "warning"),
+            _ => ("type", "error"),
+        };
+        let file = captures["file"]
+            .replace('\\', "/")
+            .trim_start_matches("./")
+            .to_string();
+        diagnostics.push((
+            file,
+            ScriptDiagnostic {
+                kind: Some(category.to_string()),
+                severity: Some(severity.to_string()),
+                code: Some(kind.to_string()),
+                message: captures["message"].to_string(),
+                line: captures["line"].parse().ok(),
+                column: captures["column"].parse().ok(),
+            },
+        ));
+    }
+    diagnostics
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzedScript {
+    path: Vec<String>,
+    class_name: String,
+    diagnostics: Vec<ScriptDiagnostic>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzeResponse {
+    scripts: Vec<AnalyzedScript>,
+    scripts_analyzed: usize,
+    errors: usize,
+    warnings: usize,
+    summary: String,
+}
+
+/// Type-checks the scripts in `entries`, which come from a script sources snapshot of the
+/// request's roots, and reports diagnostics for the scripts whose paths are in `report_paths`, or for
+/// every script when it is `None`.
+pub(super) async fn analyze(
+    analyzer: &LuauAnalyzer,
+    entries: &[DataModelSnapshotInstance],
+    report_paths: Option<&HashSet<Vec<String>>>,
+) -> Result<String> {
+    let dir = tempfile::tempdir()?;
+    let exported = export_tree(dir.path(), entries)?;
+
+    let mut command = tokio::process::Command::new(&analyzer.executable);
+    if analyzer.is_luau_lsp() {
+        command
+            .arg("analyze")
+            .arg(format!("--sourcemap={SOURCEMAP_FILE}"));
+        if let Some(definitions) = &analyzer.definitions {
+            command.arg(format!("--definitions={}", definitions.display()));
+        }
+    }
+    command
+        .args(exported.iter().map(|script| &script.file))
+        .current_dir(dir.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let output = command
+        .output()
+        .await
+        .wrap_err_with(|| format!("Unable to run {}", analyzer.executable.display()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reported = parse_output(&format!("{stdout}\n{stderr}"));
+    if reported.is_empty() && !output.status.success() {
+        return Err(eyre!(
+            "{} exited with {}: {}",
+            analyzer.executable.display(),
+            output.status,
+            stderr.trim()
+        ));
+    }
+
+    let root = dir.path().to_string_lossy().replace('\\', "/");
+    let by_file: HashMap<&str, usize> = exported
+        .iter()
+        .enumerate()
+        .map(|(index, script)| (script.file.as_str(), index))
+        .collect();
+    let mut diagnostics: Vec<Vec<ScriptDiagnostic>> = vec![Vec::new(); exported.len()];
+    for (file, diagnostic) in reported {
+        let file = file
+            .strip_prefix(&root)
+            .map_or(file.as_str(), |relative| relative.trim_start_matches('/'));
+        if let Some(index) = by_file.get(file) {
+            diagnostics[*index].push(diagnostic);
+        }
+    }
+
+    let reported = |path: &Vec<String>| report_paths.is_none_or(|paths| paths.contains(path));
+    let mut response = AnalyzeResponse {
+        scripts: Vec::new(),
+        scripts_analyzed: 0,
+        errors: 0,
+        warnings: 0,
+        summary: String::new(),
+    };
+    for (script, diagnostics) in exported.iter().zip(diagnostics) {
+        if !reported(&script.entry.path) {
+            continue;
+        }
+        response.scripts_analyzed += 1;
+        if diagnostics.is_empty() {
+            continue;
+        }
+        for diagnostic in &diagnostics {
+            if diagnostic.severity.as_deref() == Some("warning") {
+                response.warnings += 1;
+            } else {
+                response.errors += 1;
+            }
+        }
+        response.scripts.push(AnalyzedScript {
+            path: script.entry.path.clone(),
+            class_name: script.entry.class_name.clone(),
+            diagnostics,
+        });
+    }
+    response.summary = format!(
+        "Analyzed {} script(s): {} error(s), {} warning(s)",
+        response.scripts_analyzed, response.errors, response.warnings
+    );
+    Ok(serde_json::to_string(&response)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value as JsonValue};
+
+    fn script(path: &str, class_name: &str, source: &str) -> DataModelSnapshotInstance {
+        DataModelSnapshotInstance {
+            path: path.split('.').map(str::to_string).collect(),
+            class_name: class_name.to_string(),
+            properties: [(
+                "Source".to_string(),
+                RobloxValue::String(source.to_string()),
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exports_a_tree_with_a_sourcemap() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let entries = vec![
+            script("ServerScriptService.Main", "Script", "print(1)"),
+            script("ReplicatedStorage.Shared.Util", "ModuleScript", "return {}"),
+            script(
+                "ReplicatedStorage.Shared.Util.Child",
+                "ModuleScript",
+                "return 1",
+            ),
+            script("ReplicatedStorage.Shared.Util", "ModuleScript", "return 2"),
+        ];
+        let exported = export_tree(dir.path(), &entries).expect("export");
+        let files: Vec<&str> = exported.iter().map(|script| script.file.as_str()).collect();
+        assert_eq!(
+            files,
+            vec![
+                "ServerScriptService/Main.server.luau",
+                "ReplicatedStorage/Shared/Util.luau",
+                "ReplicatedStorage/Shared/Util/Child.luau",
+                "ReplicatedStorage/Shared/Util~2.luau",
+            ]
+        );
+        assert!(dir
+            .path()
+            .join("ReplicatedStorage/Shared/Util/Child.luau")
+            .is_file());
+
+        let sourcemap: JsonValue = serde_json::from_str(
+            &fs::read_to_string(dir.path().join(SOURCEMAP_FILE)).expect("sourcemap"),
+        )
+        .expect("json");
+        let shared = &sourcemap["children"][1]["children"][0];
+        assert_eq!(
+            sourcemap["children"][1]["className"],
+            json!("ReplicatedStorage")
+        );
+        assert_eq!(shared["className"], json!("Folder"));
+        assert_eq!(shared["children"][0]["className"], json!("ModuleScript"));
+        assert_eq!(shared["children"][0]["children"][0]["name"], json!("Child"));
+    }
+
+    #[test]
+    fn parses_analyzer_output() {
+        let output = "\
+ReplicatedStorage/Shared/Util.luau(3,7): TypeError: Type 'string' could not be converted into 'number'
+caused by: mismatch
+./ServerScriptService/Main.server.luau(1,1-5): LintWarning: Unknown global 'foo'
+";
+        let diagnostics = parse_output(output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].0, "ReplicatedStorage/Shared/Util.luau");
+        assert_eq!(diagnostics[0].1.kind.as_deref(), Some("type"));
+        assert!(diagnostics[0].1.message.ends_with("\ncaused by: mismatch"));
+        assert_eq!(diagnostics[1].0, "ServerScriptService/Main.server.luau");
+        assert_eq!(diagnostics[1].1.severity.as_deref(), Some("warning"));
+        assert_eq!(
+            (diagnostics[1].1.line, diagnostics[1].1.column),
+            (Some(1), Some(1))
+        );
+    }
+}
@@ -0,0 +1,840 @@
+//! Instance selectors: a small CSS-like language accepted anywhere a list of instance paths is, so
+//! agents can target instances by class, name, tag, attribute, or property instead of enumerating
+//! exact paths. The server parses and validates selectors, and sends the parsed form to the plugin
+//! (or the offline place file) to resolve against the DataModel.
+//!
+//! ```text
+//! Workspace > Model[Tag=Enemy] Part.Anchored=false
+//! ```
+//!
+//! - `Part` matches instances that are a `Part` (`IsA`); `*` matches any instance.
+//! - `#Door*` matches names with `*` and `?` globs; quote names with spaces: `#"Spawn Pad"`.
+//! - `[Tag=Enemy]` matches CollectionService tags, `[Name=Door*]` names, and `[ClassName=Part]`
+//!   exact classes.
+//! - `[@Health]` matches instances with an attribute, and `[@Health>=50]` compares its value.
+//! - `.Anchored=false` or `[Anchored=false]` compares a property.
+//! - `A B` matches `B` anywhere under `A`, `A > B` only direct children, and `A, B` either.
+//!
+//! Comparisons are `=`, `!=`, `<`, `<=`, `>`, and `>=`. Values are numbers, `true`, `false`,
+//! `nil`, or strings, quoted when they contain anything but letters, digits, `_`, `-`, or `.`.
+//! Enum properties compare by item name, e.g. `.Material=Neon`.
+
+use super::instance_ids::lookup;
+use rmcp::schemars;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::fmt;
+
+/// An instance addressed by exact path or by selector.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(super) enum InstanceTarget {
+    /// Names from the DataModel root, e.g. ["Workspace", "Map"].
+    Path(Vec<String>),
+    /// A selector such as "Workspace > Model[Tag=Enemy] Part.Anchored=false", matching every
+    /// instance it describes.
+    Selector(Selector),
+}
+
+impl schemars::JsonSchema for InstanceTarget {
+    fn schema_name() -> Cow<'static, str> {
+        "InstanceTarget".into()
+    }
+
+    /// Instance ids are looked up as targets are parsed (see `instance_ids.rs`), so only the
+    /// schema mentions them.
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "anyOf": [
+                {
+                    "description": "Names from the DataModel root, e.g. [\"Workspace\", \"Map\"].",
+                    "type": "array",
+                    "items": { "type": "string" },
+                },
+                {
+                    "description": "An instance id from an earlier result, e.g. {\"id\": \"0_1234\"}.",
+                    "type": "object",
+                    "properties": { "id": { "type": "string" } },
+                    "required": ["id"],
+                },
+                {
+                    "description": "A selector such as \"Workspace > Model[Tag=Enemy] Part.Anchored=false\", matching every instance it describes.",
+                    "type": "string",
+                },
+            ],
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for InstanceTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = JsonValue::deserialize(deserializer)?;
+        InstanceTarget::from_json(value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl InstanceTarget {
+    fn from_json(value: JsonValue) -> Result<Self, String> {
+        match value {
+            JsonValue::Array(_) => serde_json::from_value(value)
+                .map(InstanceTarget::Path)
+                .map_err(|_| "Instance paths must be arrays of names".to_string()),
+            JsonValue::String(source) => Selector::parse(&source).map(InstanceTarget::Selector),
+            JsonValue::Object(object) => match (object.get("id"), object.get("selector")) {
+                (Some(JsonValue::String(id)), None) if object.len() == 1 => {
+                    lookup(id).map(InstanceTarget::Path)
+                }
+                // The parsed form sent to the plugin, read back from a recorded request.
+                (None, Some(JsonValue::String(source))) => {
+                    Selector::parse(source).map(InstanceTarget::Selector)
+                }
+                _ => Err("Instance targets must be a path array or a selector string".to_string()),
+            },
+            _ => Err("Instance targets must be a path array or a selector string".to_string()),
+        }
+    }
+
+    pub(super) fn path(&self) -> Option<&[String]> {
+        match self {
+            InstanceTarget::Path(path) => Some(path),
+            InstanceTarget::Selector(_) => None,
+        }
+    }
+}
+
+impl From<Vec<String>> for InstanceTarget {
+    fn from(path: Vec<String>) -> Self {
+        InstanceTarget::Path(path)
+    }
+}
+
+impl fmt::Display for InstanceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceTarget::Path(path) => f.write_str(&path.join(".")),
+            InstanceTarget::Selector(selector) => f.write_str(&selector.source),
+        }
+    }
+}
+
+/// A parsed selector. Serializes as its source plus the parsed alternatives the plugin evaluates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(super) struct Selector {
+    #[serde(rename = "selector")]
+    pub(super) source: String,
+    /// Comma-separated alternatives, each a chain of steps from the DataModel root.
+    pub(super) alternatives: Vec<Vec<Step>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(super) struct Step {
+    /// How this step relates to the instances matched by the previous one. The first step is
+    /// always a descendant of the DataModel.
+    pub(super) combinator: Combinator,
+    /// All must match.
+    pub(super) filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub(super) enum Filter {
+    /// `Instance:IsA(className)`, or any instance for `*`.
+    Class {
+        class_name: String,
+    },
+    /// Exact `ClassName`.
+    ExactClass {
+        class_name: String,
+    },
+    /// Name glob with `*` and `?`.
+    Name {
+        pattern: String,
+    },
+    Tag {
+        tag: String,
+    },
+    /// Attribute presence, or a comparison when `op` is set.
+    Attribute {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        op: Option<Comparison>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<JsonValue>,
+    },
+    Property {
+        name: String,
+        op: Comparison,
+        value: JsonValue,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(super) enum Comparison {
+    #[serde(rename = "=")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+}
+
+impl Comparison {
+    /// Applies the comparison to two JSON values. Enum items and instance references compare by
+    /// their last name segment, and ordering only applies to numbers.
+    pub(super) fn matches(self, actual: &JsonValue, expected: &JsonValue) -> bool {
+        let ordering = match (actual.as_f64(), expected.as_f64()) {
+            (Some(actual), Some(expected)) => actual.partial_cmp(&expected),
+            _ => None,
+        };
+        match self {
+            Comparison::Eq => {
+                ordering.map_or_else(|| loosely_equal(actual, expected), |o| o.is_eq())
+            }
+            Comparison::Ne => {
+                !ordering.map_or_else(|| loosely_equal(actual, expected), |o| o.is_eq())
+            }
+            Comparison::Lt => ordering.is_some_and(|o| o.is_lt()),
+            Comparison::Le => ordering.is_some_and(|o| o.is_le()),
+            Comparison::Gt => ordering.is_some_and(|o| o.is_gt()),
+            Comparison::Ge => ordering.is_some_and(|o| o.is_ge()),
+        }
+    }
+}
+
+fn loosely_equal(actual: &JsonValue, expected: &JsonValue) -> bool {
+    if actual == expected {
+        return true;
+    }
+    let (Some(object), Some(expected)) = (actual.as_object(), expected.as_str()) else {
+        return actual.is_object()
+            && expected.is_null()
+            && actual.get("type").and_then(JsonValue::as_str) == Some("nil");
+    };
+    match object.get("value").and_then(JsonValue::as_str) {
+        Some(value) => value == expected || value.rsplit('.').next() == Some(expected),
+        None => false,
+    }
+}
+
+/// Matches `name` against a glob where `*` is any run of characters and `?` any one character.
+pub(super) fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl Selector {
+    pub(super) fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            source,
+            chars: source.chars().collect(),
+            position: 0,
+        };
+        let alternatives = parser.selector()?;
+        Ok(Selector {
+            source: source.trim().to_string(),
+            alternatives,
+        })
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl fmt::Display) -> String {
+        format!(
+            "{message} at column {} of selector '{}'",
+            self.position + 1,
+            self.source
+        )
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+        self.position > start
+    }
+
+    fn selector(&mut self) -> Result<Vec<Vec<Step>>, String> {
+        let mut alternatives = vec![self.chain()?];
+        while self.eat(',') {
+            alternatives.push(self.chain()?);
+        }
+        if let Some(unexpected) = self.peek() {
+            return Err(self.error(format!("Unexpected '{unexpected}'")));
+        }
+        Ok(alternatives)
+    }
+
+    fn chain(&mut self) -> Result<Vec<Step>, String> {
+        self.skip_whitespace();
+        let mut steps = vec![Step {
+            combinator: Combinator::Descendant,
+            filters: self.compound()?,
+        }];
+        loop {
+            let spaced = self.skip_whitespace();
+            let combinator = if self.eat('>') {
+                self.skip_whitespace();
+                Combinator::Child
+            } else if spaced && !matches!(self.peek(), None | Some(',')) {
+                Combinator::Descendant
+            } else {
+                return Ok(steps);
+            };
+            steps.push(Step {
+                combinator,
+                filters: self.compound()?,
+            });
+        }
+    }
+
+    fn compound(&mut self) -> Result<Vec<Filter>, String> {
+        let mut filters = Vec::new();
+        if self.eat('*') {
+            filters.push(Filter::Class {
+                class_name: "*".to_string(),
+            });
+        } else if self.peek().is_some_and(is_identifier_char) {
+            let class_name = self.identifier()?;
+            check_class(&class_name).map_err(|message| self.error(message))?;
+            filters.push(Filter::Class { class_name });
+        }
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.position += 1;
+                    let pattern = self.name_pattern()?;
+                    filters.push(Filter::Name { pattern });
+                }
+                Some('[') => {
+                    self.position += 1;
+                    filters.push(self.bracket()?);
+                }
+                Some('.') => {
+                    self.position += 1;
+                    let name = self.identifier()?;
+                    let op = self.comparison()?.ok_or_else(|| {
+                        self.error(format!("Expected a comparison after .{name}"))
+                    })?;
+                    let value = self.value(false)?;
+                    filters.push(Filter::Property { name, op, value });
+                }
+                _ => break,
+            }
+        }
+        if filters.is_empty() {
+            let found = self
+                .peek()
+                .map_or("the end".to_string(), |c| format!("'{c}'"));
+            return Err(self.error(format!(
+                "Expected a class name, '*', '#', '[', or '.' but found {found}"
+            )));
+        }
+        Ok(filters)
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        let start = self.position;
+        while self.peek().is_some_and(is_identifier_char) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error("Expected a name"));
+        }
+        Ok(self.chars[start..self.position].iter().collect())
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = self.peek().expect("called at a quote");
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated string")),
+                Some('\\') => {
+                    self.position += 1;
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("Unterminated string"))?;
+                    text.push(escaped);
+                    self.position += 1;
+                }
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    fn name_pattern(&mut self) -> Result<String, String> {
+        if matches!(self.peek(), Some('"' | '\'')) {
+            return self.quoted();
+        }
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| is_identifier_char(c) || matches!(c, '*' | '?' | '-'))
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error("Expected a name after '#'"));
+        }
+        Ok(self.chars[start..self.position].iter().collect())
+    }
+
+    fn comparison(&mut self) -> Result<Option<Comparison>, String> {
+        let op = match (self.peek(), self.chars.get(self.position + 1).copied()) {
+            (Some('!'), Some('=')) => Comparison::Ne,
+            (Some('<'), Some('=')) => Comparison::Le,
+            (Some('>'), Some('=')) => Comparison::Ge,
+            (Some('='), _) => Comparison::Eq,
+            (Some('<'), _) => Comparison::Lt,
+            (Some('>'), _) => Comparison::Gt,
+            (Some('!'), _) => return Err(self.error("Expected '!='")),
+            _ => return Ok(None),
+        };
+        self.position += match op {
+            Comparison::Ne | Comparison::Le | Comparison::Ge => 2,
+            _ => 1,
+        };
+        Ok(Some(op))
+    }
+
+    /// A quoted string, or a bare word read as a number, boolean, `nil`, or string. Inside
+    /// brackets bare words may also contain `*`, `?`, and `.`.
+    fn value(&mut self, bracketed: bool) -> Result<JsonValue, String> {
+        if matches!(self.peek(), Some('"' | '\'')) {
+            return self.quoted().map(JsonValue::String);
+        }
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            let next_is_digit = self
+                .chars
+                .get(self.position + 1)
+                .is_some_and(char::is_ascii_digit);
+            let allowed = is_identifier_char(c)
+                || matches!(c, '-' | '+')
+                || (c == '.' && (bracketed || next_is_digit))
+                || (bracketed && matches!(c, '*' | '?' | ':' | '/'));
+            if !allowed {
+                break;
+            }
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error("Expected a value"));
+        }
+        let word: String = self.chars[start..self.position].iter().collect();
+        Ok(match word.as_str() {
+            "true" => JsonValue::Bool(true),
+            "false" => JsonValue::Bool(false),
+            "nil" => JsonValue::Null,
+            _ => match word.parse::<f64>() {
+                Ok(number) if number.is_finite() => serde_json::json!(number),
+                _ => JsonValue::String(word),
+            },
+        })
+    }
+
+    fn bracket(&mut self) -> Result<Filter, String> {
+        self.skip_whitespace();
+        let attribute = self.eat('@');
+        let name = self.identifier()?;
+        self.skip_whitespace();
+        let op = self.comparison()?;
+        self.skip_whitespace();
+        let value = match op {
+            Some(_) => Some(self.value(true)?),
+            None => None,
+        };
+        self.skip_whitespace();
+        if !self.eat(']') {
+            return Err(self.error("Expected ']'"));
+        }
+
+        if attribute {
+            return Ok(Filter::Attribute { name, op, value });
+        }
+        let (Some(op), Some(value)) = (op, value) else {
+            return Err(self.error(format!(
+                "[{name}] needs a comparison; use [@{name}] to test for an attribute"
+            )));
+        };
+        let text = || match &value {
+            JsonValue::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        match name.as_str() {
+            "Tag" | "Name" | "ClassName" if op != Comparison::Eq => {
+                Err(self.error(format!("[{name}] only supports '='")))
+            }
+            "Tag" => Ok(Filter::Tag { tag: text() }),
+            "Name" => Ok(Filter::Name { pattern: text() }),
+            "ClassName" => {
+                let class_name = text();
+                check_class(&class_name).map_err(|message| self.error(message))?;
+                Ok(Filter::ExactClass { class_name })
+            }
+            _ => Ok(Filter::Property { name, op, value }),
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn check_class(class_name: &str) -> Result<(), String> {
+    if rbx_reflection_database::get()
+        .classes
+        .contains_key(class_name)
+    {
+        Ok(())
+    } else {
+        Err(format!("Unknown class '{class_name}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(source: &str) -> Selector {
+        Selector::parse(source).expect("selector parses")
+    }
+
+    /// The filters of a selector with a single step.
+    fn filters(source: &str) -> Vec<Filter> {
+        let mut selector = parse(source);
+        assert_eq!(selector.alternatives.len(), 1);
+        assert_eq!(selector.alternatives[0].len(), 1);
+        selector.alternatives.remove(0).remove(0).filters
+    }
+
+    fn class(class_name: &str) -> Filter {
+        Filter::Class {
+            class_name: class_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_classes_and_wildcards() {
+        assert_eq!(filters("Part"), vec![class("Part")]);
+        assert_eq!(filters("*"), vec![class("*")]);
+    }
+
+    #[test]
+    fn parses_name_globs_and_quoted_names() {
+        let name = |pattern: &str| Filter::Name {
+            pattern: pattern.to_string(),
+        };
+        assert_eq!(filters("Model#Door*"), vec![class("Model"), name("Door*")]);
+        assert_eq!(filters("#\"Spawn Pad\""), vec![name("Spawn Pad")]);
+        assert_eq!(filters("[Name=D?or*]"), vec![name("D?or*")]);
+    }
+
+    #[test]
+    fn parses_tags_and_exact_classes() {
+        assert_eq!(
+            filters("[Tag=Enemy][ClassName=Part]"),
+            vec![
+                Filter::Tag {
+                    tag: "Enemy".to_string()
+                },
+                Filter::ExactClass {
+                    class_name: "Part".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_attribute_presence_and_comparisons() {
+        assert_eq!(
+            filters("[@Health][@Team!=\"Red team\"]"),
+            vec![
+                Filter::Attribute {
+                    name: "Health".to_string(),
+                    op: None,
+                    value: None,
+                },
+                Filter::Attribute {
+                    name: "Team".to_string(),
+                    op: Some(Comparison::Ne),
+                    value: Some(json!("Red team")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_property_comparisons_in_both_forms() {
+        let property = |op, value| Filter::Property {
+            name: "Transparency".to_string(),
+            op,
+            value,
+        };
+        assert_eq!(
+            filters(".Transparency<0.5"),
+            vec![property(Comparison::Lt, json!(0.5))]
+        );
+        assert_eq!(
+            filters("[Transparency >= 1]"),
+            vec![property(Comparison::Ge, json!(1.0))]
+        );
+    }
+
+    #[test]
+    fn reads_bare_values_as_json() {
+        let value = |source: &str| match filters(source).remove(0) {
+            Filter::Property { value, .. } => value,
+            other => panic!("expected a property filter, got {other:?}"),
+        };
+        assert_eq!(value(".Anchored=false"), json!(false));
+        assert_eq!(value(".PrimaryPart=nil"), JsonValue::Null);
+        assert_eq!(value(".Material=Neon"), json!("Neon"));
+        assert_eq!(
+            value("[Texture=rbxassetid://123]"),
+            json!("rbxassetid://123")
+        );
+    }
+
+    #[test]
+    fn parses_combinators() {
+        let selector = parse("Workspace > Model Part");
+        let combinators: Vec<Combinator> = selector.alternatives[0]
+            .iter()
+            .map(|step| step.combinator)
+            .collect();
+        assert_eq!(
+            combinators,
+            vec![
+                Combinator::Descendant,
+                Combinator::Child,
+                Combinator::Descendant
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_alternatives() {
+        let selector = parse("Part, Model#Door*");
+        assert_eq!(selector.alternatives.len(), 2);
+        assert_eq!(selector.alternatives[1][0].filters[0], class("Model"));
+    }
+
+    #[test]
+    fn serializes_the_parsed_form() {
+        assert_eq!(
+            serde_json::to_value(parse("Workspace > Model[Tag=Enemy]")).expect("serializes"),
+            json!({
+                "selector": "Workspace > Model[Tag=Enemy]",
+                "alternatives": [[
+                    { "combinator": "descendant", "filters": [{ "kind": "class", "className": "Workspace" }] },
+                    { "combinator": "child", "filters": [
+                        { "kind": "class", "className": "Model" },
+                        { "kind": "tag", "tag": "Enemy" },
+                    ] },
+                ]],
+            })
+        );
+    }
+
+    #[test]
+    fn reads_targets_as_paths_or_selectors() {
+        let path: InstanceTarget =
+            serde_json::from_value(json!(["Workspace", "Map"])).expect("path target");
+        assert_eq!(
+            path.path(),
+            Some(["Workspace".to_string(), "Map".to_string()].as_slice())
+        );
+
+        let selector: InstanceTarget =
+            serde_json::from_value(json!("Model#Door*")).expect("selector target");
+        assert_eq!(selector.to_string(), "Model#Door*");
+        assert_eq!(selector.path(), None);
+    }
+
+    #[test]
+    fn reads_back_the_parsed_form() {
+        let target: InstanceTarget =
+            serde_json::from_value(json!("Model#Door*")).expect("selector target");
+        let round_trip: InstanceTarget =
+            serde_json::from_value(serde_json::to_value(&target).expect("serializes"))
+                .expect("parsed form reads back");
+        assert_eq!(round_trip, target);
+    }
+
+    #[test]
+    fn rejects_unknown_classes() {
+        assert_eq!(
+            Selector::parse("Workspace > Prat").unwrap_err(),
+            "Unknown class 'Prat' at column 17 of selector 'Workspace > Prat'"
+        );
+        assert_eq!(
+            Selector::parse("[ClassName=Prat]").unwrap_err(),
+            "Unknown class 'Prat' at column 17 of selector '[ClassName=Prat]'"
+        );
+    }
+
+    #[test]
+    fn rejects_unclosed_brackets() {
+        assert_eq!(
+            Selector::parse("Part[Tag=Enemy").unwrap_err(),
+            "Expected ']' at column 15 of selector 'Part[Tag=Enemy'"
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_combinators() {
+        assert_eq!(
+            Selector::parse("Part >").unwrap_err(),
+            "Expected a class name, '*', '#', '[', or '.' but found the end at column 7 of selector 'Part >'"
+        );
+    }
+
+    #[test]
+    fn rejects_properties_without_comparisons() {
+        assert_eq!(
+            Selector::parse("Part[Anchored]").unwrap_err(),
+            "[Anchored] needs a comparison; use [@Anchored] to test for an attribute at column 15 of selector 'Part[Anchored]'"
+        );
+        assert_eq!(
+            Selector::parse("Part.Anchored").unwrap_err(),
+            "Expected a comparison after .Anchored at column 14 of selector 'Part.Anchored'"
+        );
+    }
+
+    #[test]
+    fn rejects_ordering_on_names_and_tags() {
+        assert_eq!(
+            Selector::parse("[Tag>Enemy]").unwrap_err(),
+            "[Tag] only supports '=' at column 12 of selector '[Tag>Enemy]'"
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        assert_eq!(
+            Selector::parse("#\"Spawn").unwrap_err(),
+            "Unterminated string at column 8 of selector '#\"Spawn'"
+        );
+    }
+
+    #[test]
+    fn rejects_unexpected_characters() {
+        assert_eq!(
+            Selector::parse("Part)").unwrap_err(),
+            "Unexpected ')' at column 5 of selector 'Part)'"
+        );
+    }
+
+    #[test]
+    fn rejects_targets_of_other_shapes() {
+        for target in [json!(42), json!({ "name": "Map" }), json!([1, 2])] {
+            assert!(
+                serde_json::from_value::<InstanceTarget>(target.clone()).is_err(),
+                "{target} should not be an instance target"
+            );
+        }
+        assert!(serde_json::from_value::<InstanceTarget>(json!("Part[")).is_err());
+    }
+
+    #[test]
+    fn schema_lists_paths_ids_and_selectors() {
+        let schema = schemars::schema_for!(InstanceTarget);
+        let forms = schema
+            .get("anyOf")
+            .and_then(JsonValue::as_array)
+            .expect("anyOf");
+        let types: Vec<&str> = forms
+            .iter()
+            .filter_map(|form| form.get("type").and_then(JsonValue::as_str))
+            .collect();
+        assert_eq!(types, vec!["array", "object", "string"]);
+    }
+
+    #[test]
+    fn matches_name_globs() {
+        assert!(glob_matches("Door*", "DoorFrame"));
+        assert!(glob_matches("D?or*e", "DoorFrame"));
+        assert!(!glob_matches("Door", "DoorFrame"));
+    }
+
+    #[test]
+    fn compares_enum_items_by_name() {
+        assert!(Comparison::Eq.matches(
+            &json!({ "type": "EnumItem", "value": "Enum.Material.Neon" }),
+            &json!("Neon")
+        ));
+        assert!(Comparison::Lt.matches(&json!(0.25), &json!(0.5)));
+        assert!(!Comparison::Lt.matches(&json!("a"), &json!("b")));
+    }
+}
@@ -0,0 +1,378 @@
+//! Snapshot handles for `data_model_snapshot`. Every complete snapshot is cached under a handle, and
+//! a later call with `since: <handle>` retakes it with the same options and reports only the
+//! instances added, removed, or changed in between, so agents can check the effect of their edits
+//! without reading the whole tree again.
+
+use super::{DataModelSnapshotInstance, DataModelSnapshotRequest, RobloxValue};
+use color_eyre::eyre::{eyre, Result};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Snapshots kept for `since`; older handles are forgotten.
+const CACHED_SNAPSHOTS: usize = 16;
+
+#[derive(Debug, Clone)]
+struct CachedSnapshot {
+    handle: String,
+    request: DataModelSnapshotRequest,
+    entries: Vec<DataModelSnapshotInstance>,
+}
+
+#[derive(Debug, Default)]
+struct Snapshots {
+    taken: u64,
+    cached: VecDeque<CachedSnapshot>,
+}
+
+/// The most recent complete snapshots, by handle.
+#[derive(Debug, Default)]
+pub(super) struct SnapshotCache {
+    snapshots: Mutex<Snapshots>,
+}
+
+impl SnapshotCache {
+    /// Caches a snapshot taken with `request` and returns its handle.
+    pub(super) fn store(
+        &self,
+        request: DataModelSnapshotRequest,
+        entries: Vec<DataModelSnapshotInstance>,
+    ) -> String {
+        let mut snapshots = self.snapshots.lock().expect("snapshot cache poisoned");
+        snapshots.taken += 1;
+        let handle = format!("snapshot-{}", snapshots.taken);
+        if snapshots.cached.len() == CACHED_SNAPSHOTS {
+            snapshots.cached.pop_front();
+        }
+        snapshots.cached.push_back(CachedSnapshot {
+            handle: handle.clone(),
+            request,
+            entries,
+        });
+        handle
+    }
+
+    /// The request and entries of the snapshot cached under `handle`.
+    pub(super) fn get(
+        &self,
+        handle: &str,
+    ) -> Result<(DataModelSnapshotRequest, Vec<DataModelSnapshotInstance>)> {
+        let snapshots = self.snapshots.lock().expect("snapshot cache poisoned");
+        snapshots
+            .cached
+            .iter()
+            .find(|snapshot| snapshot.handle == handle)
+            .map(|snapshot| (snapshot.request.clone(), snapshot.entries.clone()))
+            .ok_or_else(|| {
+                eyre!(
+                    "Unknown snapshot handle '{handle}'; the server keeps the last {CACHED_SNAPSHOTS} snapshots, so take a new one"
+                )
+            })
+    }
+
+    /// Adds a `handle` to a `data_model_snapshot` response that holds the whole snapshot. Pages of
+    /// a paginated snapshot are returned unchanged.
+    pub(super) fn annotate(&self, request: DataModelSnapshotRequest, response: String) -> String {
+        if request.page_cursor.is_some() {
+            return response;
+        }
+        let Ok(mut parsed) = serde_json::from_str::<JsonValue>(&response) else {
+            return response;
+        };
+        let Some(object) = parsed.as_object_mut() else {
+            return response;
+        };
+        if object.get("truncated").and_then(JsonValue::as_bool) == Some(true) {
+            return response;
+        }
+        let Some(Ok(entries)) = object
+            .get("entries")
+            .map(|entries| serde_json::from_value(entries.clone()))
+        else {
+            return response;
+        };
+        let handle = self.store(request, entries);
+        object.insert("handle".into(), JsonValue::String(handle));
+        parsed.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum SnapshotChangeKind {
+    /// The instance was renamed or moved; `before` and `after` are its paths.
+    Path,
+    Property,
+    Attribute,
+}
+
+/// One difference in an instance between two snapshots. `before` or `after` is omitted when the
+/// value was not captured on that side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SnapshotChange {
+    pub(super) kind: SnapshotChangeKind,
+    /// Property or attribute name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) before: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) after: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RemovedInstance {
+    /// Path of the instance in the earlier snapshot.
+    pub(super) path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) id: Option<String>,
+    pub(super) name: String,
+    pub(super) class_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ChangedInstance {
+    pub(super) path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) id: Option<String>,
+    pub(super) class_name: String,
+    pub(super) changes: Vec<SnapshotChange>,
+}
+
+/// Response to `data_model_snapshot` with `since`.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SnapshotDelta {
+    /// Handle of the snapshot just taken, for the next `since`.
+    pub(super) handle: String,
+    pub(super) since: String,
+    /// Entries for instances that were not in the earlier snapshot.
+    pub(super) added: Vec<DataModelSnapshotInstance>,
+    pub(super) removed: Vec<RemovedInstance>,
+    pub(super) changed: Vec<ChangedInstance>,
+    /// Number of instances found in both snapshots without differences.
+    pub(super) unchanged: usize,
+    pub(super) summary: String,
+}
+
+/// Instances are matched by id, or by path when a snapshot has no ids.
+fn key(entry: &DataModelSnapshotInstance) -> String {
+    entry.id.clone().unwrap_or_else(|| entry.path.join("."))
+}
+
+fn value_changes(
+    kind: SnapshotChangeKind,
+    before: &HashMap<String, RobloxValue>,
+    after: &HashMap<String, RobloxValue>,
+    changes: &mut Vec<SnapshotChange>,
+) {
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    for name in names {
+        let (old, new) = (before.get(name), after.get(name));
+        if old != new {
+            changes.push(SnapshotChange {
+                kind,
+                name: Some(name.clone()),
+                before: old.map(|value| serde_json::to_value(value).unwrap_or(JsonValue::Null)),
+                after: new.map(|value| serde_json::to_value(value).unwrap_or(JsonValue::Null)),
+            });
+        }
+    }
+}
+
+/// Compares two snapshots taken with the same request.
+pub(super) fn delta(
+    since: &str,
+    handle: String,
+    before: &[DataModelSnapshotInstance],
+    after: &[DataModelSnapshotInstance],
+) -> SnapshotDelta {
+    let earlier: HashMap<String, &DataModelSnapshotInstance> =
+        before.iter().map(|entry| (key(entry), entry)).collect();
+    let later: BTreeSet<String> = after.iter().map(key).collect();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+    for entry in after {
+        let Some(previous) = earlier.get(&key(entry)) else {
+            added.push(entry.clone());
+            continue;
+        };
+        let mut changes = Vec::new();
+        if previous.path != entry.path {
+            changes.push(SnapshotChange {
+                kind: SnapshotChangeKind::Path,
+                name: None,
+                before: Some(serde_json::json!(previous.path)),
+                after: Some(serde_json::json!(entry.path)),
+            });
+        }
+        value_changes(
+            SnapshotChangeKind::Property,
+            &previous.properties,
+            &entry.properties,
+            &mut changes,
+        );
+        value_changes(
+            SnapshotChangeKind::Attribute,
+            &previous.attributes,
+            &entry.attributes,
+            &mut changes,
+        );
+        if changes.is_empty() {
+            unchanged += 1;
+        } else {
+            changed.push(ChangedInstance {
+                path: entry.path.clone(),
+                id: entry.id.clone(),
+                class_name: entry.class_name.clone(),
+                changes,
+            });
+        }
+    }
+    let removed: Vec<RemovedInstance> = before
+        .iter()
+        .filter(|entry| !later.contains(&key(entry)))
+        .map(|entry| RemovedInstance {
+            path: entry.path.clone(),
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            class_name: entry.class_name.clone(),
+        })
+        .collect();
+
+    SnapshotDelta {
+        summary: format!(
+            "{} added, {} removed, {} changed since {since} ({unchanged} unchanged)",
+            added.len(),
+            removed.len(),
+            changed.len()
+        ),
+        handle,
+        since: since.to_string(),
+        added,
+        removed,
+        changed,
+        unchanged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(id: &str, path: &[&str], class_name: &str, properties: JsonValue) -> JsonValue {
+        json!({
+            "path": path,
+            "id": id,
+            "name": path.last(),
+            "className": class_name,
+            "depth": path.len() - 1,
+            "properties": properties,
+        })
+    }
+
+    fn instances(entries: JsonValue) -> Vec<DataModelSnapshotInstance> {
+        serde_json::from_value(entries).expect("entries")
+    }
+
+    /// A delta in which Door is renamed Gate and made half transparent, Old is removed, and New
+    /// is added.
+    fn sample_delta() -> SnapshotDelta {
+        let before = instances(json!([
+            entry("1", &["Workspace"], "Workspace", json!({})),
+            entry(
+                "2",
+                &["Workspace", "Door"],
+                "Part",
+                json!({ "Transparency": 0 })
+            ),
+            entry("3", &["Workspace", "Old"], "Folder", json!({})),
+        ]));
+        let after = instances(json!([
+            entry("1", &["Workspace"], "Workspace", json!({})),
+            entry(
+                "2",
+                &["Workspace", "Gate"],
+                "Part",
+                json!({ "Transparency": 0.5 })
+            ),
+            entry("4", &["Workspace", "New"], "Model", json!({})),
+        ]));
+        delta("snapshot-1", "snapshot-2".to_string(), &before, &after)
+    }
+
+    #[test]
+    fn caches_complete_snapshots_under_a_handle() {
+        let cache = SnapshotCache::default();
+        let request = DataModelSnapshotRequest {
+            include_properties: Some(true),
+            ..Default::default()
+        };
+        let response = json!({
+            "entries": [entry("1", &["Workspace"], "Workspace", json!({}))],
+            "totalMatched": 1,
+            "totalVisited": 1,
+            "truncated": false,
+        });
+        let annotated: JsonValue =
+            serde_json::from_str(&cache.annotate(request, response.to_string())).unwrap();
+        let handle = annotated["handle"].as_str().expect("handle");
+
+        let (request, entries) = cache.get(handle).expect("cached");
+        assert_eq!(request.include_properties, Some(true));
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn does_not_cache_truncated_snapshots() {
+        let cache = SnapshotCache::default();
+        let response = json!({
+            "entries": [entry("1", &["Workspace"], "Workspace", json!({}))],
+            "truncated": true,
+        })
+        .to_string();
+        assert_eq!(
+            cache.annotate(DataModelSnapshotRequest::default(), response.clone()),
+            response
+        );
+    }
+
+    #[test]
+    fn unknown_handles_are_errors() {
+        let cache = SnapshotCache::default();
+        cache.store(DataModelSnapshotRequest::default(), Vec::new());
+        assert!(cache.get("snapshot-1").is_ok());
+        assert!(cache.get("snapshot-99").is_err());
+    }
+
+    #[test]
+    fn reports_added_and_removed_instances() {
+        let delta = sample_delta();
+        assert_eq!(delta.added.len(), 1);
+        assert_eq!(delta.added[0].name, "New");
+        assert_eq!(delta.removed.len(), 1);
+        assert_eq!(delta.removed[0].path, vec!["Workspace", "Old"]);
+        assert_eq!(delta.unchanged, 1);
+    }
+
+    #[test]
+    fn reports_moves_and_property_changes() {
+        let delta = sample_delta();
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&delta.changed[0].changes).unwrap(),
+            json!([
+                { "kind": "path", "before": ["Workspace", "Door"], "after": ["Workspace", "Gate"] },
+                { "kind": "property", "name": "Transparency", "before": 0.0, "after": 0.5 },
+            ])
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Named instance templates for `instantiate_template`. Each template is a binary model
+//! `<name>.rbxm` with a single root, saved from the place or copied from a local model file, and a
+//! `<name>.json` description beside it. Instance names and string attribute values in a template
+//! can hold `{{parameter}}` placeholders that are filled in every time it is instantiated.
+
+use super::place_file::{inspect_template, TemplateContents};
+use super::{InstancePropertyMap, PathOrId};
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const MODEL_EXTENSION: &str = "rbxm";
+const INFO_EXTENSION: &str = "json";
+
+/// Default location of the store: the platform's local data directory.
+pub fn default_templates_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("rbx-studio-mcp").join("templates"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum TemplateAction {
+    /// Save `instancePath` from the place, or the model file at `filePath`, as template `name`.
+    Save,
+    /// List every saved template.
+    List,
+    /// Describe template `name`, including the parameters it takes.
+    Get,
+    /// Delete template `name`.
+    Delete,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ManageTemplatesRequest {
+    pub(super) action: TemplateAction,
+    #[serde(default)]
+    #[schemars(description = "Template name: letters, digits, '_' and '-'")]
+    pub(super) name: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Instance saved by save, with everything under it")]
+    #[serde(deserialize_with = "super::instance_ids::optional_path")]
+    #[schemars(with = "Option<PathOrId>")]
+    pub(super) instance_path: Option<Vec<String>>,
+    #[serde(default)]
+    #[schemars(
+        description = "Absolute path of a .rbxm or .rbxmx file with a single root saved by save"
+    )]
+    pub(super) file_path: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "What the template is for, shown by list and get")]
+    pub(super) description: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Replace a template that already has this name")]
+    pub(super) overwrite: bool,
+}
+
+impl ManageTemplatesRequest {
+    pub(super) fn validate(&self) -> Result<()> {
+        if self.action != TemplateAction::List && self.name.is_none() {
+            bail!("{:?} requires a template name", self.action);
+        }
+        match (self.action, &self.instance_path, &self.file_path) {
+            (TemplateAction::Save, Some(_), Some(_)) => {
+                bail!("save takes instancePath or filePath, not both")
+            }
+            (TemplateAction::Save, None, None) => bail!("save requires instancePath or filePath"),
+            (TemplateAction::Save, _, _) | (_, None, None) => Ok(()),
+            (action, _, _) => {
+                bail!("instancePath and filePath are only used by save, not {action:?}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct InstantiateTemplateRequest {
+    #[schemars(description = "Name of the saved template")]
+    pub(super) name: String,
+    #[schemars(description = "Instance the copy is parented to, e.g. [\"Workspace\", \"Map\"]")]
+    #[serde(deserialize_with = "super::instance_ids::path")]
+    #[schemars(with = "PathOrId")]
+    pub(super) parent_path: Vec<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Name of the new instance. Defaults to the template root's name; a numbered suffix is added when the parent already has a child with that name."
+    )]
+    pub(super) instance_name: Option<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Values for the {{parameter}} placeholders in the template's instance names and string attributes, as listed by manage_templates"
+    )]
+    pub(super) parameters: BTreeMap<String, String>,
+    #[serde(default)]
+    #[schemars(description = "Attributes set on the new instance. Use JSON null to remove one.")]
+    pub(super) attributes: InstancePropertyMap,
+    #[serde(default)]
+    #[schemars(
+        description = "Pivot of the new instance as 12 numbers: position followed by the rotation matrix rows"
+    )]
+    pub(super) cframe: Option<[f64; 12]>,
+    #[serde(default)]
+    #[schemars(
+        description = "Position of the new instance's pivot as [x, y, z], keeping the template's rotation"
+    )]
+    pub(super) position: Option<[f64; 3]>,
+}
+
+impl InstantiateTemplateRequest {
+    pub(super) fn validate(&self) -> Result<()> {
+        if self.cframe.is_some() && self.position.is_some() {
+            bail!("instantiate_template takes cframe or position, not both");
+        }
+        Ok(())
+    }
+}
+
+/// Request forwarded to the plugin's `InstantiateTemplate` tool: a template with its parameters,
+/// attributes, and pivot already applied, as a base64 encoded binary model.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct InstantiateTemplatePayload {
+    pub(super) template: String,
+    pub(super) parent_path: Vec<String>,
+    pub(super) model: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct InstantiateTemplateResponse {
+    pub(super) template: String,
+    pub(super) path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) id: Option<String>,
+    pub(super) summary: String,
+    pub(super) write_occurred: bool,
+}
+
+/// The `<name>.json` description of a template.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TemplateInfo {
+    pub(super) name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) description: Option<String>,
+    pub(super) class_name: String,
+    pub(super) root_name: String,
+    pub(super) instance_count: usize,
+    pub(super) parameters: Vec<String>,
+    /// The instance path or file the template was saved from.
+    pub(super) source: String,
+    pub(super) saved_at: String,
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        bail!("Template names may only contain letters, digits, '_' and '-', not '{name}'");
+    }
+    Ok(())
+}
+
+/// Reads a local `.rbxm` or `.rbxmx` file as a binary model.
+pub(super) fn read_model_file(path: &Path) -> Result<Vec<u8>> {
+    if !path.is_absolute() {
+        bail!("filePath must be an absolute path");
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let bytes = fs::read(path).wrap_err_with(|| format!("Unable to read {}", path.display()))?;
+    match extension.as_str() {
+        "rbxm" => Ok(bytes),
+        "rbxmx" => {
+            let dom = rbx_xml::from_reader_default(bytes.as_slice())
+                .wrap_err_with(|| format!("Unable to decode {}", path.display()))?;
+            let mut buffer = Vec::new();
+            rbx_binary::to_writer(&mut buffer, &dom, dom.root().children())
+                .wrap_err("Unable to encode model")?;
+            Ok(buffer)
+        }
+        _ => bail!("filePath must end in .rbxm or .rbxmx"),
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct TemplateStore {
+    dir: PathBuf,
+}
+
+impl TemplateStore {
+    pub(super) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn file(&self, name: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{name}.{extension}"))
+    }
+
+    /// Checks that `name` can be saved, before the model is fetched from the place.
+    pub(super) fn check_save(&self, name: &str, overwrite: bool) -> Result<()> {
+        validate_name(name)?;
+        if !overwrite && self.file(name, INFO_EXTENSION).exists() {
+            bail!("A template named '{name}' already exists; set overwrite to replace it");
+        }
+        Ok(())
+    }
+
+    pub(super) fn save(
+        &self,
+        name: &str,
+        description: Option<String>,
+        source: String,
+        model: &[u8],
+        overwrite: bool,
+    ) -> Result<TemplateInfo> {
+        self.check_save(name, overwrite)?;
+        let TemplateContents {
+            class_name,
+            root_name,
+            instance_count,
+            parameters,
+        } = inspect_template(model)?;
+        let info = TemplateInfo {
+            name: name.to_string(),
+            description,
+            class_name,
+            root_name,
+            instance_count,
+            parameters,
+            source,
+            saved_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        };
+        fs::create_dir_all(&self.dir)
+            .wrap_err_with(|| format!("failed to create {}", self.dir.display()))?;
+        let model_path = self.file(name, MODEL_EXTENSION);
+        fs::write(&model_path, model)
+            .wrap_err_with(|| format!("failed to write {}", model_path.display()))?;
+        let info_path = self.file(name, INFO_EXTENSION);
+        fs::write(&info_path, serde_json::to_string_pretty(&info)?)
+            .wrap_err_with(|| format!("failed to write {}", info_path.display()))?;
+        Ok(info)
+    }
+
+    /// Every saved template, by name.
+    pub(super) fn list(&self) -> Result<Vec<TemplateInfo>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("failed to read {}", self.dir.display()))
+            }
+        };
+        let mut templates = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(INFO_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                templates.push(self.get(name)?);
+            }
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    pub(super) fn get(&self, name: &str) -> Result<TemplateInfo> {
+        validate_name(name)?;
+        let path = self.file(name, INFO_EXTENSION);
+        let contents = fs::read_to_string(&path)
+            .map_err(|_| eyre!("No template is named '{name}'; see manage_templates list"))?;
+        serde_json::from_str(&contents).wrap_err_with(|| format!("Invalid {}", path.display()))
+    }
+
+    /// The description and binary model of template `name`.
+    pub(super) fn load(&self, name: &str) -> Result<(TemplateInfo, Vec<u8>)> {
+        let info = self.get(name)?;
+        let path = self.file(name, MODEL_EXTENSION);
+        let model =
+            fs::read(&path).wrap_err_with(|| format!("Unable to read {}", path.display()))?;
+        Ok((info, model))
+    }
+
+    pub(super) fn delete(&self, name: &str) -> Result<TemplateInfo> {
+        let info = self.get(name)?;
+        for extension in [MODEL_EXTENSION, INFO_EXTENSION] {
+            let path = self.file(name, extension);
+            fs::remove_file(&path)
+                .wrap_err_with(|| format!("failed to delete {}", path.display()))?;
+        }
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::{types::Variant, InstanceBuilder, WeakDom};
+
+    /// A template of a `Crate_{{id}}` model with a `{{team}}` attribute and one child part.
+    fn sample_template() -> Vec<u8> {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root = dom.root_ref();
+        let mut attributes = rbx_dom_weak::types::Attributes::new();
+        attributes.insert("Team".to_string(), Variant::String("{{team}}".to_string()));
+        dom.insert(
+            root,
+            InstanceBuilder::new("Model")
+                .with_name("Crate_{{id}}")
+                .with_property("Attributes", Variant::Attributes(attributes))
+                .with_child(InstanceBuilder::new("Part").with_name("Lid")),
+        );
+        let mut model = Vec::new();
+        rbx_binary::to_writer(&mut model, &dom, dom.root().children()).expect("encode");
+        model
+    }
+
+    #[test]
+    fn saves_and_lists_templates() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = TemplateStore::new(dir.path().join("templates"));
+        assert!(store.list().expect("list").is_empty());
+
+        let model = sample_template();
+        let info = store
+            .save("crate", None, "Workspace.Crate".into(), &model, false)
+            .expect("save");
+        assert_eq!(info.class_name, "Model");
+        assert_eq!(info.instance_count, 2);
+        assert_eq!(info.parameters, vec!["id".to_string(), "team".to_string()]);
+        assert_eq!(store.list().expect("list"), vec![info]);
+        assert_eq!(store.load("crate").expect("load").1, model);
+    }
+
+    #[test]
+    fn refuses_to_overwrite_without_asking() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = TemplateStore::new(dir.path().join("templates"));
+        let model = sample_template();
+        store
+            .save("crate", None, "Workspace.Crate".into(), &model, false)
+            .expect("save");
+        assert!(store
+            .save("crate", None, "Workspace.Crate".into(), &model, false)
+            .is_err());
+        assert!(store
+            .save("crate", None, "Workspace.Crate".into(), &model, true)
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_names_outside_the_template_directory() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = TemplateStore::new(dir.path().join("templates"));
+        assert!(store.check_save("../crate", true).is_err());
+    }
+
+    #[test]
+    fn deletes_templates() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = TemplateStore::new(dir.path().join("templates"));
+        let info = store
+            .save(
+                "crate",
+                None,
+                "Workspace.Crate".into(),
+                &sample_template(),
+                false,
+            )
+            .expect("save");
+        assert_eq!(store.delete("crate").expect("delete"), info);
+        assert!(store.get("crate").is_err());
+        assert!(store.list().expect("list").is_empty());
+    }
+}